@@ -792,6 +792,15 @@ pub struct ConcreteDepFiles {
     contents: HashMap<Arc<str>, DirectorySelector>,
 }
 
+impl ConcreteDepFiles {
+    /// Whether any of the dep files lists `path`, or a file below it.
+    pub fn selects_within(&self, path: &ProjectRelativePath) -> bool {
+        self.contents
+            .values()
+            .any(|selector| selector.selects_within(path.as_forward_relative_path()))
+    }
+}
+
 /// A command line visitor to collect inputs and outputs in a form relevant for dep files
 /// computations.
 pub(crate) struct DepFilesCommandLineVisitor<'a> {
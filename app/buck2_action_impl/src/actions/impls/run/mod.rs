@@ -9,6 +9,7 @@
 
 use std::borrow::Cow;
 use std::fmt::Display;
+use std::time::Duration;
 
 use allocative::Allocative;
 use async_trait::async_trait;
@@ -34,6 +35,7 @@ use buck2_core::category::Category;
 use buck2_core::directory::FingerprintedDirectory;
 use buck2_core::fs::buck_out_path::BuckOutPath;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_events::dispatch::span_async;
 use buck2_execute::artifact::fs::ExecutorFs;
 use buck2_execute::execute::environment_inheritance::EnvironmentInheritance;
@@ -42,7 +44,10 @@ use buck2_execute::execute::request::CommandExecutionInput;
 use buck2_execute::execute::request::CommandExecutionOutput;
 use buck2_execute::execute::request::CommandExecutionPaths;
 use buck2_execute::execute::request::CommandExecutionRequest;
+use buck2_execute::execute::request::CommandStdRedirects;
 use buck2_execute::execute::request::ExecutorPreference;
+use buck2_execute::execute::request::WorkerProtocol;
+use buck2_execute::execute::request::WorkerSpec;
 use dupe::Dupe;
 use gazebo::prelude::*;
 use host_sharing::HostSharingRequirements;
@@ -72,6 +77,8 @@ mod metadata;
 enum RunActionValidationError {
     #[error("Expected command line value, got {0}")]
     ContentsNotCommandLineValue(String),
+    #[error("`stdin` must expand to a single path, got `{0:?}`")]
+    StdinNotSinglePath(Vec<String>),
 }
 
 #[derive(Debug, Allocative)]
@@ -144,6 +151,12 @@ pub(crate) struct UnregisteredRunAction {
     pub(crate) no_outputs_cleanup: bool,
     pub(crate) allow_cache_upload: bool,
     pub(crate) force_full_hybrid_if_capable: bool,
+    /// The protocol of the persistent worker, if the action has one.
+    pub(crate) worker_protocol: WorkerProtocol,
+    pub(crate) timeout: Option<Duration>,
+    /// Indices in the outputs of the action of the outputs the stdout and stderr are written to.
+    pub(crate) stdout: Option<usize>,
+    pub(crate) stderr: Option<usize>,
 }
 
 impl UnregisteredAction for UnregisteredRunAction {
@@ -166,61 +179,125 @@ pub(crate) struct RunAction {
     outputs: BoxSliceSet<BuildArtifact>,
 }
 
+/// The unpacked Starlark values of a `RunAction`: the command line, the environment, the worker
+/// startup command, the worker environment and the stdin.
+type RunActionValues<'a> = (
+    &'a dyn CommandLineArgLike,
+    Vec<(&'a str, &'a dyn CommandLineArgLike)>,
+    Option<&'a dyn CommandLineArgLike>,
+    Vec<(&'a str, &'a dyn CommandLineArgLike)>,
+    Option<&'a dyn CommandLineArgLike>,
+);
+
 impl RunAction {
-    fn unpack(
-        args: &OwnedFrozenValue,
-    ) -> Option<(
-        &dyn CommandLineArgLike,
-        Vec<(&str, &dyn CommandLineArgLike)>,
-    )> {
-        // We expect (CmdArgs, Option<Dict<String, CmdArgs>>) in the Starlark value
-        let (cli, env) = match TupleRef::from_value(args.value())?.content() {
-            [cli, env] => (*cli, *env),
-            _ => return None,
-        };
+    fn unpack(args: &OwnedFrozenValue) -> Option<RunActionValues<'_>> {
+        // We expect (CmdArgs, Option<Dict<String, CmdArgs>>, Option<CmdArgs>,
+        // Option<Dict<String, CmdArgs>>, Option<CmdArgs>) in the Starlark value
+        let (cli, env, worker, worker_env, stdin) =
+            match TupleRef::from_value(args.value())?.content() {
+                [cli, env, worker, worker_env, stdin] => (*cli, *env, *worker, *worker_env, *stdin),
+                _ => return None,
+            };
         let cli = cli.as_command_line()?;
-        let env = if env.is_none() {
-            Vec::new()
+        let worker = if worker.is_none() {
+            None
         } else {
-            let d = DictRef::from_value(env)?;
-            let mut res = Vec::with_capacity(d.len());
-            for (k, v) in d.iter() {
-                res.push((k.unpack_str()?, v.as_command_line()?));
+            Some(worker.as_command_line()?)
+        };
+        let stdin = if stdin.is_none() {
+            None
+        } else {
+            Some(stdin.as_command_line()?)
+        };
+        let unpack_env = |env: Value<'_>| {
+            if env.is_none() {
+                Some(Vec::new())
+            } else {
+                let d = DictRef::from_value(env)?;
+                let mut res = Vec::with_capacity(d.len());
+                for (k, v) in d.iter() {
+                    res.push((k.unpack_str()?, v.as_command_line()?));
+                }
+                Some(res)
             }
-            res
         };
-        Some((cli, env))
+        Some((
+            cli,
+            unpack_env(env)?,
+            worker,
+            unpack_env(worker_env)?,
+            stdin,
+        ))
     }
 
-    /// Get the command line expansion for this RunAction.
+    /// Get the command line expansion for this RunAction, along with the expanded worker startup
+    /// command, which the command line starts with, and environment, and the expanded stdin path.
+    /// The worker environment is also part of the environment of the command line, for when it
+    /// does not run in a worker.
     fn expand_command_line(
         &self,
         fs: &ExecutorFs,
         artifact_visitor: &mut impl CommandLineArtifactVisitor,
-    ) -> anyhow::Result<ExpandedCommandLine> {
+    ) -> anyhow::Result<(
+        ExpandedCommandLine,
+        Option<(Vec<String>, Vec<(String, String)>)>,
+        Option<String>,
+    )> {
         let mut cli_rendered = Vec::<String>::new();
         let mut ctx = DefaultCommandLineContext::new(fs);
 
-        let (cli, env) = Self::unpack(&self.starlark_cli).unwrap();
+        let (cli, env, worker, worker_env, stdin) = Self::unpack(&self.starlark_cli).unwrap();
+        let stdin_rendered = match stdin {
+            Some(stdin) => {
+                let mut stdin_rendered = Vec::<String>::new();
+                stdin.add_to_command_line(&mut stdin_rendered, &mut ctx)?;
+                stdin.visit_artifacts(artifact_visitor)?;
+                match <[String; 1]>::try_from(stdin_rendered) {
+                    Ok([path]) => Some(path),
+                    Err(rendered) => {
+                        return Err(RunActionValidationError::StdinNotSinglePath(rendered).into());
+                    }
+                }
+            }
+            None => None,
+        };
+        let worker_rendered = match worker {
+            Some(worker) => {
+                worker.add_to_command_line(&mut cli_rendered, &mut ctx)?;
+                worker.visit_artifacts(artifact_visitor)?;
+                Some(cli_rendered.clone())
+            }
+            None => None,
+        };
         cli.add_to_command_line(&mut cli_rendered, &mut ctx)?;
         cli.visit_artifacts(artifact_visitor)?;
 
-        let cli_env: anyhow::Result<SortedVectorMap<_, _>> = env
-            .into_iter()
-            .map(|(k, v)| {
-                let mut env = Vec::<String>::new(); // TODO (torozco): Use a String.
-                let mut ctx = DefaultCommandLineContext::new(fs);
-                v.add_to_command_line(&mut env, &mut ctx)?;
-                v.visit_artifacts(artifact_visitor)?;
-                let var = env.join(" ");
-                Ok((k.to_owned(), var))
-            })
-            .collect();
+        let mut expand_env = |env: Vec<(&str, &dyn CommandLineArgLike)>| {
+            env.into_iter()
+                .map(|(k, v)| {
+                    let mut env = Vec::<String>::new(); // TODO (torozco): Use a String.
+                    let mut ctx = DefaultCommandLineContext::new(fs);
+                    v.add_to_command_line(&mut env, &mut ctx)?;
+                    v.visit_artifacts(artifact_visitor)?;
+                    let var = env.join(" ");
+                    Ok((k.to_owned(), var))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+        let mut cli_env: SortedVectorMap<_, _> = expand_env(env)?.into_iter().collect();
+        let worker_env = expand_env(worker_env)?;
+        for (k, v) in &worker_env {
+            cli_env.insert(k.clone(), v.clone());
+        }
 
-        Ok(ExpandedCommandLine {
-            cli: cli_rendered,
-            env: cli_env?,
-        })
+        Ok((
+            ExpandedCommandLine {
+                cli: cli_rendered,
+                env: cli_env,
+            },
+            worker_rendered.map(|exe| (exe, worker_env)),
+            stdin_rendered,
+        ))
     }
 
     pub(crate) fn new(
@@ -250,7 +327,7 @@ impl RunAction {
     ) -> anyhow::Result<PreparedRunAction> {
         let fs = ctx.fs();
 
-        let expanded = self.expand_command_line(&ctx.executor_fs(), visitor)?;
+        let (expanded, worker, stdin) = self.expand_command_line(&ctx.executor_fs(), visitor)?;
 
         // TODO (@torozco): At this point, might as well just receive the list already. Finding
         // those things in a HashMap is just not very useful.
@@ -297,10 +374,31 @@ impl RunAction {
             ctx.digest_config(),
         )?;
 
+        let output = |index: Option<usize>| {
+            index.map(|i| {
+                let b = &self.outputs.as_slice()[i];
+                CommandExecutionOutput::BuildArtifact {
+                    path: b.get_path().dupe(),
+                    output_type: b.output_type(),
+                }
+            })
+        };
+        let std_redirects = CommandStdRedirects {
+            stdin: stdin.map(ProjectRelativePathBuf::try_from).transpose()?,
+            stdout: output(self.inner.stdout),
+            stderr: output(self.inner.stderr),
+        };
+
         Ok(PreparedRunAction {
             expanded,
             extra_env,
             paths,
+            worker: worker.map(|(exe, env)| WorkerSpec {
+                exe,
+                env,
+                protocol: self.inner.worker_protocol,
+            }),
+            std_redirects,
         })
     }
 }
@@ -309,6 +407,8 @@ struct PreparedRunAction {
     expanded: ExpandedCommandLine,
     extra_env: Option<(String, String)>,
     paths: CommandExecutionPaths,
+    worker: Option<WorkerSpec>,
+    std_redirects: CommandStdRedirects,
 }
 
 impl PreparedRunAction {
@@ -317,6 +417,8 @@ impl PreparedRunAction {
             expanded: ExpandedCommandLine { cli, mut env },
             extra_env,
             paths,
+            worker,
+            std_redirects,
         } = self;
 
         for (k, v) in extra_env.into_iter() {
@@ -324,6 +426,8 @@ impl PreparedRunAction {
         }
 
         CommandExecutionRequest::new(cli, paths, env)
+            .with_worker(worker)
+            .with_std_redirects(std_redirects)
     }
 }
 
@@ -358,10 +462,13 @@ impl Action for RunAction {
     }
 
     fn inputs(&self) -> anyhow::Result<Cow<'_, [ArtifactGroup]>> {
-        let (cli, env) = Self::unpack(&self.starlark_cli).unwrap();
+        let (cli, env, worker, worker_env, stdin) = Self::unpack(&self.starlark_cli).unwrap();
         let mut artifact_visitor = SimpleCommandLineArtifactVisitor::new();
         cli.visit_artifacts(&mut artifact_visitor)?;
-        for (_, v) in env.iter() {
+        for (_, v) in env.iter().chain(worker_env.iter()) {
+            v.visit_artifacts(&mut artifact_visitor)?;
+        }
+        for v in worker.into_iter().chain(stdin) {
             v.visit_artifacts(&mut artifact_visitor)?;
         }
         Ok(Cow::Owned(artifact_visitor.inputs.into_iter().collect()))
@@ -371,6 +478,24 @@ impl Action for RunAction {
         Ok(Cow::Borrowed(self.outputs.as_slice()))
     }
 
+    fn dep_file_inputs(&self) -> anyhow::Result<Option<Vec<ArtifactGroup>>> {
+        if self.inner.dep_files.labels.is_empty() {
+            return Ok(None);
+        }
+        let (cli, env, worker, worker_env, stdin) = Self::unpack(&self.starlark_cli).unwrap();
+        let mut visitor = DepFilesCommandLineVisitor::new(&self.inner.dep_files);
+        cli.visit_artifacts(&mut visitor)?;
+        for (_, v) in env.iter().chain(worker_env.iter()) {
+            v.visit_artifacts(&mut visitor)?;
+        }
+        for v in worker.into_iter().chain(stdin) {
+            v.visit_artifacts(&mut visitor)?;
+        }
+        Ok(Some(
+            visitor.inputs.tagged.into_values().flatten().collect(),
+        ))
+    }
+
     fn as_executable(&self) -> ActionExecutable<'_> {
         ActionExecutable::Incremental(self)
     }
@@ -390,10 +515,51 @@ impl Action for RunAction {
     fn aquery_attributes(&self, fs: &ExecutorFs) -> indexmap::IndexMap<String, String> {
         let mut cli_rendered = Vec::<String>::new();
         let mut ctx = DefaultCommandLineContext::new(fs);
-        let (cli, _env) = Self::unpack(&self.starlark_cli).unwrap();
+        let (cli, _env, worker, worker_env, stdin) = Self::unpack(&self.starlark_cli).unwrap();
         cli.add_to_command_line(&mut cli_rendered, &mut ctx)
             .unwrap();
         let cmd = format!("[{}]", cli_rendered.iter().join(", "));
+        let worker = match worker {
+            None => "None".to_owned(),
+            Some(worker) => {
+                let mut worker_rendered = Vec::<String>::new();
+                worker
+                    .add_to_command_line(&mut worker_rendered, &mut ctx)
+                    .unwrap();
+                let env: serde_json::Map<_, _> = worker_env
+                    .iter()
+                    .map(|(k, v)| {
+                        let mut rendered = Vec::<String>::new();
+                        v.add_to_command_line(&mut rendered, &mut ctx).unwrap();
+                        ((*k).to_owned(), rendered.join(" ").into())
+                    })
+                    .collect();
+                json!({
+                    "exe": worker_rendered,
+                    "env": env,
+                    "protocol": self.inner.worker_protocol.to_string(),
+                })
+                .to_string()
+            }
+        };
+        let stdin = match stdin {
+            None => "None".to_owned(),
+            Some(stdin) => {
+                let mut stdin_rendered = Vec::<String>::new();
+                stdin
+                    .add_to_command_line(&mut stdin_rendered, &mut ctx)
+                    .unwrap();
+                stdin_rendered.join(" ")
+            }
+        };
+        let std_output = |index: Option<usize>| match index {
+            None => "None".to_owned(),
+            Some(i) => fs
+                .fs()
+                .buck_out_path_resolver()
+                .resolve_gen(self.outputs.as_slice()[i].get_path())
+                .to_string(),
+        };
         indexmap! {
             "cmd".to_owned() => cmd,
             "executor_preference".to_owned() => self.inner.executor_preference.to_string(),
@@ -405,6 +571,14 @@ impl Action for RunAction {
                 Some(x) => x.to_string(),
             },
             "no_outputs_cleanup".to_owned() => self.inner.no_outputs_cleanup.to_string(),
+            "worker".to_owned() => worker,
+            "timeout".to_owned() => match self.inner.timeout {
+                None => "None".to_owned(),
+                Some(timeout) => format!("{}s", timeout.as_secs()),
+            },
+            "stdin".to_owned() => stdin,
+            "stdout".to_owned() => std_output(self.inner.stdout),
+            "stderr".to_owned() => std_output(self.inner.stderr),
         }
     }
 }
@@ -490,6 +664,10 @@ impl IncrementalActionExecutable for RunAction {
             .with_local_environment_inheritance(EnvironmentInheritance::local_command_exclusions())
             .with_force_full_hybrid_if_capable(self.inner.force_full_hybrid_if_capable)
             .with_custom_tmpdir(ctx.target().custom_tmpdir());
+        let req = match self.inner.timeout {
+            Some(timeout) => req.with_timeout(timeout),
+            None => req,
+        };
 
         let (outputs, meta) = ctx.exec_cmd(&req).await?;
 
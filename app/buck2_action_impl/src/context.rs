@@ -10,6 +10,7 @@
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Context;
 use buck2_build_api::actions::artifact::artifact_type::OutputArtifact;
@@ -35,7 +36,9 @@ use buck2_common::executor_config::RemoteExecutorUseCase;
 use buck2_core::category::Category;
 use buck2_core::collections::ordered_set::OrderedSet;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_execute::execute::request::ExecutorPreference;
 use buck2_execute::execute::request::OutputType;
+use buck2_execute::execute::request::WorkerProtocol;
 use buck2_execute::materialize::http::Checksum;
 use buck2_interpreter::starlark_promise::StarlarkPromise;
 use buck2_interpreter_for_build::rule::FrozenRuleCallable;
@@ -131,6 +134,24 @@ enum RunActionError {
         "Recursion limit exceeded when visiting artifacts: do you have a cycle in your inputs or outputs?"
     )]
     ArtifactVisitRecursionLimitExceeded,
+    #[error("`worker_protocol` must be `json` or `proto`, got `{0}`")]
+    InvalidWorkerProtocol(String),
+    #[error("`timeout` must be a positive number of seconds, got `{0}`")]
+    InvalidTimeout(i32),
+    #[error("`stdin` cannot be used with `worker`, which reads work requests from its stdin")]
+    StdinWithWorker,
+    #[error("`worker_env` cannot be used without `worker`")]
+    WorkerEnvWithoutWorker,
+    #[error("`{0}` is set in both `env` and `worker_env`")]
+    WorkerEnvConflict(String),
+    #[error(
+        "`{0}` cannot be used with `worker`, which writes work responses to its stdout and reports a single output"
+    )]
+    StdStreamWithWorker(&'static str),
+    #[error("`{0}` must be an output artifact, not a string")]
+    StdStreamNotArtifact(&'static str),
+    #[error("The `{0}` artifact is also an output of the command line or of another stream")]
+    StdStreamOutputConflict(&'static str),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -193,6 +214,7 @@ fn copy_file<'v>(
 // Type literals that we use
 const TYPE_INPUT_ARTIFACT: &str = "[str.type, \"output_artifact\", \"artifact\"]";
 const TYPE_ARTIFACT: &str = "\"artifact\"";
+const TYPE_OUTPUT_ARTIFACT: &str = "[\"output_artifact\", \"artifact\"]";
 const TYPE_CMD_ARG_LIKE: &str = "\"_arglike\"";
 
 /// Functions to allow users to interact with the Actions registry.
@@ -558,6 +580,12 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
     ///     * `metadata_path`: defines a path relative to the result directory for a file with action metadata, which will be created right before the command will be run.
     ///     * Metadata contains the path relative to the Buck2 project root and hash digest for every action input (this excludes symlinks as they could be resolved by a user script if needed). The resolved path relative to the Buck2 project for the metadata file will be passed to command from arguments, via the environment variable, with its name set by `metadata_env_var`
     ///     * Both `metadata_env_var` and `metadata_path` are useful when making actions behave in an incremental manner (for details, see [Incremental Actions](https://buck2.build/docs/rule_authors/incremental_actions/))
+    /// * `worker`: the startup command of a persistent worker, which, when running locally, is started once with an extra `--persistent_worker` flag and then runs many actions, with `arguments` sent to it in work requests of the [Bazel worker protocol](https://bazel.build/remote/persistent). Arguments `@path` and `--flagfile=path` are expanded into the lines of the file, so a per-request flagfile works as with Bazel. When running remotely, or if persistent workers are disabled, the command is the worker startup command followed by `arguments`
+    /// * `worker_protocol`: the protocol spoken by the worker, `"json"` (the default) or `"proto"`
+    /// * `worker_env`: the environment the worker is started with. Workers are shared by the actions with the same startup command, `worker_env` and protocol, so they do not see the `env` of the actions they run. Each worker gets its own `$TMPDIR`. When the command does not run in a worker, `worker_env` is added to `env`
+    /// * `timeout`: the number of seconds after which the command is killed and the action fails with a timeout error, both when running locally and remotely
+    /// * `stdin`: an artifact the command reads its stdin from, instead of an empty stdin. Remote execution cannot provide a stdin, so such commands always run locally, and `stdin` cannot be used with `worker`
+    /// * `stdout` and `stderr`: output artifacts (bound by this action, like the outputs of `arguments`) the stdout and stderr of the command are written to. They are still reported as usual, e.g. when the command fails. They cannot be used with `worker`
     fn run<'v>(
         this: &AnalysisActions<'v>,
         #[starlark(require = pos, type = TYPE_CMD_ARG_LIKE)] arguments: Value<'v>,
@@ -579,6 +607,13 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
         #[starlark(require = named, default = false)] no_outputs_cleanup: bool,
         #[starlark(require = named, default = false)] allow_cache_upload: bool,
         #[starlark(require = named, default = false)] force_full_hybrid_if_capable: bool,
+        #[starlark(require = named, type = TYPE_CMD_ARG_LIKE)] worker: Option<Value<'v>>,
+        #[starlark(require = named, default = "json")] worker_protocol: &str,
+        #[starlark(require = named)] worker_env: Option<ValueOf<'v, SmallMap<&'v str, Value<'v>>>>,
+        #[starlark(require = named)] timeout: Option<i32>,
+        #[starlark(require = named, type = TYPE_CMD_ARG_LIKE)] stdin: Option<Value<'v>>,
+        #[starlark(require = named, type = TYPE_OUTPUT_ARTIFACT)] stdout: Option<Value<'v>>,
+        #[starlark(require = named, type = TYPE_OUTPUT_ARTIFACT)] stderr: Option<Value<'v>>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<NoneType> {
         struct RunCommandArtifactVisitor {
@@ -629,7 +664,8 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
             }
         }
 
-        let executor_preference = new_executor_preference(local_only, prefer_local, prefer_remote)?;
+        let mut executor_preference =
+            new_executor_preference(local_only, prefer_local, prefer_remote)?;
 
         let mut artifact_visitor = RunCommandArtifactVisitor::new();
 
@@ -653,23 +689,96 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
             }
         };
 
-        let starlark_env = match env {
+        let starlark_worker = match worker {
             None => Value::new_none(),
+            Some(worker) => {
+                let worker = StarlarkCommandLine::try_from_value(worker)?;
+                worker.visit_artifacts(&mut artifact_visitor)?;
+                eval.heap().alloc(worker)
+            }
+        };
+        let worker_protocol = match worker_protocol {
+            "json" => WorkerProtocol::Json,
+            "proto" => WorkerProtocol::Proto,
+            _ => {
+                return Err(
+                    RunActionError::InvalidWorkerProtocol(worker_protocol.to_owned()).into(),
+                );
+            }
+        };
+
+        let timeout = match timeout {
+            None => None,
+            Some(t) if t > 0 => Some(Duration::from_secs(t as u64)),
+            Some(t) => return Err(RunActionError::InvalidTimeout(t).into()),
+        };
+
+        let starlark_stdin = match stdin {
+            None => Value::new_none(),
+            Some(_) if worker.is_some() => return Err(RunActionError::StdinWithWorker.into()),
+            Some(stdin) => {
+                let stdin = StarlarkCommandLine::try_from_value(stdin)?;
+                stdin.visit_artifacts(&mut artifact_visitor)?;
+                // Remote execution has no way to provide a stdin.
+                executor_preference = executor_preference.and(ExecutorPreference::LocalRequired)?;
+                eval.heap().alloc(stdin)
+            }
+        };
+
+        if let (Some(env), Some(worker_env)) = (&env, &worker_env) {
+            if let Some(k) = env.typed.keys().find(|k| worker_env.typed.contains_key(*k)) {
+                return Err(RunActionError::WorkerEnvConflict((*k).to_owned()).into());
+            }
+        }
+        let mut visit_env = |env: Option<ValueOf<'v, SmallMap<&'v str, Value<'v>>>>| match env {
+            None => anyhow::Ok(Value::new_none()),
             Some(env) => {
                 for v in env.typed.values() {
                     v.as_command_line_err()?
                         .visit_artifacts(&mut artifact_visitor)?;
                 }
-                env.value
+                Ok(env.value)
+            }
+        };
+        let starlark_worker_env = match worker_env {
+            Some(_) if worker.is_none() => {
+                return Err(RunActionError::WorkerEnvWithoutWorker.into());
             }
+            worker_env => visit_env(worker_env)?,
         };
+        let starlark_env = visit_env(env)?;
 
         let RunCommandArtifactVisitor {
-            inner: artifacts,
+            inner: mut artifacts,
             tagged_outputs,
             depth: _,
         } = artifact_visitor;
 
+        let mut std_output = |value: Option<Value<'v>>,
+                              name: &'static str|
+         -> anyhow::Result<Option<usize>> {
+            match value {
+                None => Ok(None),
+                Some(_) if worker.is_some() => {
+                    Err(RunActionError::StdStreamWithWorker(name).into())
+                }
+                Some(value) if value.unpack_str().is_some() => {
+                    Err(RunActionError::StdStreamNotArtifact(name).into())
+                }
+                Some(value) => {
+                    let (_, output) =
+                        this.state()
+                            .get_or_declare_output(eval, value, name, OutputType::File)?;
+                    match artifacts.outputs.insert_full(output) {
+                        (index, true) => Ok(Some(index)),
+                        (_, false) => Err(RunActionError::StdStreamOutputConflict(name).into()),
+                    }
+                }
+            }
+        };
+        let stdout = std_output(stdout, "stdout")?;
+        let stderr = std_output(stderr, "stderr")?;
+
         let mut dep_files_configuration = RunActionDepFiles::new();
 
         if let Some(dep_files) = dep_files {
@@ -724,7 +833,13 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
         if artifacts.outputs.is_empty() {
             return Err(RunActionError::NoOutputsSpecified.into());
         }
-        let starlark = eval.heap().alloc((starlark_cli, starlark_env));
+        let starlark = eval.heap().alloc((
+            starlark_cli,
+            starlark_env,
+            starlark_worker,
+            starlark_worker_env,
+            starlark_stdin,
+        ));
 
         let action = UnregisteredRunAction {
             category,
@@ -737,6 +852,10 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
             no_outputs_cleanup,
             allow_cache_upload,
             force_full_hybrid_if_capable,
+            worker_protocol,
+            timeout,
+            stdout,
+            stderr,
         };
         this.state().register_action(
             artifacts.inputs,
@@ -13,6 +13,7 @@ use async_trait::async_trait;
 use buck2_cli_proto::ClientContext;
 use buck2_client_ctx::common::CommonCommandOptions;
 use buck2_core::configuration::bound_id::BoundConfigurationId;
+use buck2_core::configuration::cfg_diff::cfg_diff;
 use buck2_core::configuration::data::ConfigurationData;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
@@ -20,6 +21,12 @@ use itertools::Itertools;
 
 use crate::AuditSubcommand;
 
+#[derive(Debug, thiserror::Error)]
+enum AuditConfigurationsCommandError {
+    #[error("`--diff` requires exactly two configurations, got {0}")]
+    DiffRequiresTwoConfigurations(usize),
+}
+
 #[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
 #[clap(
     name = "audit-configuration",
@@ -35,6 +42,12 @@ pub struct AuditConfigurationsCommand {
         help = "configurations to audit (example: `cell//package:target-105fe3389fc7e436`). If none provided, will print information about all known configurations."
     )]
     configs: Vec<String>,
+
+    #[clap(
+        long,
+        help = "print the differences between the two given configurations instead of their constraints"
+    )]
+    diff: bool,
 }
 
 #[async_trait]
@@ -47,7 +60,25 @@ impl AuditSubcommand for AuditConfigurationsCommand {
     ) -> anyhow::Result<()> {
         let mut stdout = stdout.as_writer();
 
-        if self.configs.is_empty() {
+        if self.diff {
+            let (a, b) = match self.configs.as_slice() {
+                [a, b] => (lookup_cfg(a)?, lookup_cfg(b)?),
+                configs => {
+                    return Err(
+                        AuditConfigurationsCommandError::DiffRequiresTwoConfigurations(
+                            configs.len(),
+                        )
+                        .into(),
+                    );
+                }
+            };
+            writeln!(stdout, "--- {}", a.full_name())?;
+            writeln!(stdout, "+++ {}", b.full_name())?;
+            match cfg_diff(&a, &b) {
+                Ok(()) => writeln!(stdout, "Configurations are equal")?,
+                Err(diff) => write!(stdout, "{}", diff)?,
+            }
+        } else if self.configs.is_empty() {
             for cfg in ConfigurationData::iter_existing()
                 .filter(|c| c.is_bound())
                 .sorted_by_cached_key(|c| c.full_name().to_owned())
@@ -56,9 +87,7 @@ impl AuditSubcommand for AuditConfigurationsCommand {
             }
         } else {
             for cfg in &self.configs {
-                let cfg = BoundConfigurationId::parse(cfg)?;
-                let cfg = ConfigurationData::lookup_bound(cfg)?;
-                print_cfg(&mut stdout, &cfg)?;
+                print_cfg(&mut stdout, &lookup_cfg(cfg)?)?;
             }
         }
 
@@ -70,6 +99,10 @@ impl AuditSubcommand for AuditConfigurationsCommand {
     }
 }
 
+fn lookup_cfg(cfg: &str) -> anyhow::Result<ConfigurationData> {
+    ConfigurationData::lookup_bound(BoundConfigurationId::parse(cfg)?)
+}
+
 fn print_cfg(stdout: &mut impl Write, cfg: &ConfigurationData) -> anyhow::Result<()> {
     writeln!(stdout, "{}:", cfg.full_name())?;
     let data = cfg.data()?;
@@ -0,0 +1,125 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_build_api::analysis::dependency_rules::dependency_rule_violations;
+use buck2_build_api::calculation::load_patterns;
+use buck2_build_api::calculation::Calculation;
+use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_build_api::nodes::calculation::NodeCalculation;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_query::query::compatibility::MaybeCompatible;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dupe::Dupe;
+use gazebo::prelude::*;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-dependency-rules",
+    about = "prints the violations of `PACKAGE` dependency rules in the build of the given \
+    patterns, without failing on them"
+)]
+pub struct AuditDependencyRulesCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(
+        name = "TARGET_PATTERNS",
+        required = true,
+        help = "Patterns of the build to check"
+    )]
+    patterns: Vec<String>,
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditDependencyRulesCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                let loaded_patterns =
+                    load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
+                let target_platform =
+                    target_platform_from_client_context(&client_ctx, server_ctx, &ctx).await?;
+
+                let mut seen = HashSet::new();
+                let mut work = Vec::new();
+                for (_, targets) in loaded_patterns.into_iter() {
+                    for (_, node) in targets? {
+                        let label = ctx
+                            .get_configured_target(node.label(), target_platform.as_ref())
+                            .await?;
+                        match ctx.get_configured_target_node(&label).await? {
+                            MaybeCompatible::Compatible(node) => {
+                                if seen.insert(label) {
+                                    work.push(node);
+                                }
+                            }
+                            MaybeCompatible::Incompatible(_) => {}
+                        }
+                    }
+                }
+
+                let mut violations = Vec::new();
+                while let Some(node) = work.pop() {
+                    let node_violations = dependency_rule_violations(&ctx, &node).await?;
+                    if !node_violations.is_empty() {
+                        violations.push((node.label().dupe(), node_violations));
+                    }
+                    for dep in node.deps() {
+                        if seen.insert(dep.label().dupe()) {
+                            work.push(dep.dupe());
+                        }
+                    }
+                }
+                violations.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+                let mut stdout = stdout.as_writer();
+                if violations.is_empty() {
+                    writeln!(stdout, "No dependency rule violations")?;
+                }
+                for (label, node_violations) in &violations {
+                    writeln!(stdout, "{}:", label)?;
+                    for violation in node_violations {
+                        writeln!(stdout, "  {}", violation)?;
+                    }
+                }
+
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
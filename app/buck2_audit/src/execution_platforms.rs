@@ -0,0 +1,282 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_build_api::calculation::load_patterns;
+use buck2_build_api::calculation::Calculation;
+use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_build_api::nodes::calculation::NodeCalculation;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_common::executor_config::Executor;
+use buck2_common::executor_config::RemoteEnabledExecutor;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_node::configuration::execution::ExecutionPlatform;
+use buck2_node::configuration::execution::ExecutionPlatformIncompatibleReason;
+use buck2_node::configuration::execution::ExecutionPlatformResolution;
+use buck2_query::query::compatibility::MaybeCompatible;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dupe::Dupe;
+use gazebo::prelude::*;
+use serde::Serialize;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-execution-platforms",
+    about = "prints a JSON report of execution platform resolution for every target in the \
+    build of the given patterns"
+)]
+pub struct AuditExecutionPlatformsCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(
+        name = "TARGET_PATTERNS",
+        required = true,
+        help = "Patterns of the build to report on"
+    )]
+    patterns: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Skipped {
+    platform: String,
+    reason: String,
+}
+
+impl Skipped {
+    fn new((platform, reason): &(String, ExecutionPlatformIncompatibleReason)) -> Self {
+        Self {
+            platform: platform.clone(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PlatformSummary {
+    /// How the platform executes commands, e.g. `local` or `remote`.
+    executor: &'static str,
+    /// Number of targets that resolved to this platform.
+    targets: u64,
+    /// Number of those targets that only got this platform after skipping other ones.
+    fallbacks: u64,
+}
+
+#[derive(Serialize)]
+struct Fallback {
+    target: String,
+    platform: String,
+    skipped: Vec<Skipped>,
+}
+
+#[derive(Serialize)]
+struct Unspecified {
+    target: String,
+    skipped: Vec<Skipped>,
+}
+
+#[derive(Serialize, Default)]
+struct Report {
+    /// Number of configured targets in the build.
+    targets: u64,
+    platforms: BTreeMap<String, PlatformSummary>,
+    /// Targets that did not get the highest priority execution platform.
+    fallbacks: Vec<Fallback>,
+    /// Targets that did not get an execution platform, either because they can't execute
+    /// (e.g. configuration nodes) or because the platforms fall back to an unspecified one
+    /// once all of them are skipped. Targets for which every platform is incompatible
+    /// without such a fallback fail to configure, so they never show up here.
+    unspecified: Vec<Unspecified>,
+    /// For each skipped platform, how often each reason made it incompatible.
+    incompatibility_reasons: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+fn executor_kind(platform: &ExecutionPlatform) -> &'static str {
+    match &platform.executor_config().executor {
+        Executor::Local(_) => "local",
+        Executor::RemoteEnabled { executor, .. } => match executor {
+            RemoteEnabledExecutor::Local(_) => "local_with_remote_cache",
+            RemoteEnabledExecutor::Remote(_) => "remote",
+            RemoteEnabledExecutor::Hybrid { .. } => "hybrid",
+        },
+    }
+}
+
+impl Report {
+    fn add(&mut self, target: String, resolution: &ExecutionPlatformResolution) {
+        self.targets += 1;
+
+        for (platform, reason) in resolution.skipped() {
+            *self
+                .incompatibility_reasons
+                .entry(platform.clone())
+                .or_default()
+                .entry(reason.to_string())
+                .or_default() += 1;
+        }
+
+        match resolution.platform() {
+            Ok(platform) => {
+                let summary =
+                    self.platforms
+                        .entry(platform.id())
+                        .or_insert_with(|| PlatformSummary {
+                            executor: executor_kind(platform),
+                            targets: 0,
+                            fallbacks: 0,
+                        });
+                summary.targets += 1;
+                if !resolution.skipped().is_empty() {
+                    summary.fallbacks += 1;
+                    self.fallbacks.push(Fallback {
+                        target: target.clone(),
+                        platform: platform.id(),
+                        skipped: resolution.skipped().map(Skipped::new),
+                    });
+                }
+            }
+            Err(_) => self.unspecified.push(Unspecified {
+                target,
+                skipped: resolution.skipped().map(Skipped::new),
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditExecutionPlatformsCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                let loaded_patterns =
+                    load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
+                let target_platform =
+                    target_platform_from_client_context(&client_ctx, server_ctx, &ctx).await?;
+
+                let mut seen = HashSet::new();
+                let mut work = Vec::new();
+                for (_, targets) in loaded_patterns.into_iter() {
+                    for (_, node) in targets? {
+                        let label = ctx
+                            .get_configured_target(node.label(), target_platform.as_ref())
+                            .await?;
+                        match ctx.get_configured_target_node(&label).await? {
+                            MaybeCompatible::Compatible(node) => {
+                                if seen.insert(label) {
+                                    work.push(node);
+                                }
+                            }
+                            MaybeCompatible::Incompatible(_) => {}
+                        }
+                    }
+                }
+
+                let mut report = Report::default();
+                while let Some(node) = work.pop() {
+                    // Forward nodes share the resolution of the node they forward to, which is
+                    // one of their deps, so only count the latter.
+                    if node.forward_target().is_none() {
+                        report.add(
+                            node.label().to_string(),
+                            node.execution_platform_resolution(),
+                        );
+                    }
+                    for dep in node.deps() {
+                        if seen.insert(dep.label().dupe()) {
+                            work.push(dep.dupe());
+                        }
+                    }
+                }
+                report.fallbacks.sort_by(|a, b| a.target.cmp(&b.target));
+                report.unspecified.sort_by(|a, b| a.target.cmp(&b.target));
+
+                let mut stdout = stdout.as_writer();
+                writeln!(stdout, "{}", serde_json::to_string_pretty(&report)?)?;
+
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::target::label::TargetLabel;
+
+    use super::*;
+
+    #[test]
+    fn test_report_unspecified() {
+        let mut report = Report::default();
+        report.add(
+            "root//config:setting".to_owned(),
+            &ExecutionPlatformResolution::unspecified(),
+        );
+        report.add(
+            "root//app:lib".to_owned(),
+            &ExecutionPlatformResolution::new(
+                None,
+                vec![(
+                    "root//platforms:linux".to_owned(),
+                    ExecutionPlatformIncompatibleReason::ConstraintNotSatisfied(
+                        TargetLabel::testing_parse("root//constraints:mac"),
+                    ),
+                )],
+            ),
+        );
+
+        assert_eq!(2, report.targets);
+        assert!(report.platforms.is_empty());
+        assert!(report.fallbacks.is_empty());
+        assert_eq!(
+            vec!["root//config:setting", "root//app:lib"],
+            report.unspecified.map(|u| u.target.as_str())
+        );
+        assert!(report.unspecified[0].skipped.is_empty());
+        assert_eq!(
+            vec!["root//platforms:linux"],
+            report.unspecified[1].skipped.map(|s| s.platform.as_str())
+        );
+        assert_eq!(
+            Some(&1),
+            report
+                .incompatibility_reasons
+                .get("root//platforms:linux")
+                .and_then(|reasons| reasons.values().next())
+        );
+    }
+}
@@ -33,12 +33,18 @@ use crate::config::AuditConfigCommand;
 use crate::configurations::AuditConfigurationsCommand;
 use crate::deferred_materializer::DeferredMaterializerCommand;
 use crate::dep_files::AuditDepFilesCommand;
+use crate::dependency_rules::AuditDependencyRulesCommand;
 use crate::execution_platform_resolution::AuditExecutionPlatformResolutionCommand;
+use crate::execution_platforms::AuditExecutionPlatformsCommand;
 use crate::includes::AuditIncludesCommand;
 use crate::output::command::AuditOutputCommand;
+use crate::package_values::AuditPackageValuesCommand;
 use crate::prelude::AuditPreludeCommand;
 use crate::providers::AuditProvidersCommand;
+use crate::select_resolution::AuditSelectResolutionCommand;
 use crate::starlark::StarlarkCommand;
+use crate::target_configurations::AuditTargetConfigurationsCommand;
+use crate::unused_deps::AuditUnusedDepsCommand;
 use crate::visibility::AuditVisibilityCommand;
 
 mod analysis_queries;
@@ -48,13 +54,19 @@ mod config;
 mod configurations;
 pub mod deferred_materializer;
 mod dep_files;
+mod dependency_rules;
 mod execution_platform_resolution;
+mod execution_platforms;
 mod includes;
 pub mod output;
+mod package_values;
 mod prelude;
 mod providers;
+mod select_resolution;
 pub mod server;
 mod starlark;
+mod target_configurations;
+mod unused_deps;
 mod visibility;
 
 #[derive(Debug, clap::Subcommand, serde::Serialize, serde::Deserialize)]
@@ -69,12 +81,18 @@ pub enum AuditCommand {
     Providers(AuditProvidersCommand),
     AnalysisQueries(AuditAnalysisQueriesCommand),
     ExecutionPlatformResolution(AuditExecutionPlatformResolutionCommand),
+    ExecutionPlatforms(AuditExecutionPlatformsCommand),
     Visibility(AuditVisibilityCommand),
     #[clap(subcommand)]
     Starlark(StarlarkCommand),
     DepFiles(AuditDepFilesCommand),
     DeferredMaterializer(DeferredMaterializerCommand),
     Output(AuditOutputCommand),
+    TargetConfigurations(AuditTargetConfigurationsCommand),
+    PackageValues(AuditPackageValuesCommand),
+    DependencyRules(AuditDependencyRulesCommand),
+    UnusedDeps(AuditUnusedDepsCommand),
+    SelectResolution(AuditSelectResolutionCommand),
 }
 
 /// `buck2 audit` subcommands have a somewhat unique approach to make it really easy to
@@ -119,11 +137,17 @@ impl AuditCommand {
             AuditCommand::Providers(cmd) => cmd,
             AuditCommand::AnalysisQueries(cmd) => cmd,
             AuditCommand::ExecutionPlatformResolution(cmd) => cmd,
+            AuditCommand::ExecutionPlatforms(cmd) => cmd,
             AuditCommand::Starlark(cmd) => cmd,
             AuditCommand::DepFiles(cmd) => cmd,
             AuditCommand::DeferredMaterializer(cmd) => cmd,
             AuditCommand::Visibility(cmd) => cmd,
             AuditCommand::Output(cmd) => cmd,
+            AuditCommand::TargetConfigurations(cmd) => cmd,
+            AuditCommand::PackageValues(cmd) => cmd,
+            AuditCommand::DependencyRules(cmd) => cmd,
+            AuditCommand::UnusedDeps(cmd) => cmd,
+            AuditCommand::SelectResolution(cmd) => cmd,
         }
     }
 }
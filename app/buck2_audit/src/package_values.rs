@@ -0,0 +1,133 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_core::cells::build_file_cell::BuildFileCell;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::ParsedPattern;
+use buck2_interpreter_for_build::interpreter::dice_calculation_delegate::HasCalculationDelegate;
+use buck2_interpreter_for_build::super_package::data::SuperPackage;
+use buck2_node::deprecation::DeprecationLevel;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::PatternParser;
+use dupe::Dupe;
+use serde_json::json;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, thiserror::Error)]
+enum AuditPackageValuesCommandError {
+    #[error("Recursive patterns are not supported, pass packages instead: `{0}`")]
+    RecursivePattern(String),
+}
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-package-values",
+    about = "prints the effective values set by `PACKAGE` files for the given packages as JSON"
+)]
+pub struct AuditPackageValuesCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(
+        name = "PACKAGES",
+        required = true,
+        help = "Packages to print the values of, e.g. `//foo:`"
+    )]
+    packages: Vec<String>,
+}
+
+fn super_package_to_json(super_package: &SuperPackage) -> anyhow::Result<serde_json::Value> {
+    let package_values = super_package
+        .package_values()
+        .iter()
+        .map(|(k, v)| Ok((k.clone(), serde_json::to_value(v.value())?)))
+        .collect::<anyhow::Result<serde_json::Map<_, _>>>()?;
+    let deprecation = super_package.deprecation().map(|d| {
+        json!({
+            "message": d.message,
+            "allowlist": d.allowlist.iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+            "error": d.level == DeprecationLevel::Error,
+        })
+    });
+    Ok(json!({
+        "visibility": super_package.visibility().to_json(),
+        "within_view": super_package.within_view().to_json(),
+        "deprecation": deprecation,
+        "default_attrs": super_package.default_attrs().to_json(),
+        "dependency_rules": super_package
+            .dependency_rules()
+            .iter()
+            .map(|r| {
+                json!({
+                    "declared_in": r.declared_in,
+                    "kind": r.kind.to_string(),
+                    "patterns": r.patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+                })
+            })
+            .collect::<Vec<_>>(),
+        "package_values": package_values,
+    }))
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditPackageValuesCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        _client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let pattern_parser = PatternParser::new(&ctx, server_ctx.working_dir()).await?;
+
+                let mut result = serde_json::Map::new();
+                for package in &self.packages {
+                    let package =
+                        match pattern_parser.parse_pattern::<TargetPatternExtra>(package)? {
+                            ParsedPattern::Package(package) => package,
+                            ParsedPattern::Target(package, ..) => package,
+                            ParsedPattern::Recursive(_) => {
+                                return Err(AuditPackageValuesCommandError::RecursivePattern(
+                                    package.clone(),
+                                )
+                                .into());
+                            }
+                        };
+                    let super_package = ctx
+                        .get_interpreter_calculator(
+                            package.cell_name(),
+                            BuildFileCell::new(package.cell_name()),
+                        )
+                        .await?
+                        .eval_super_package(package.dupe())
+                        .await?;
+                    result.insert(package.to_string(), super_package_to_json(&super_package)?);
+                }
+
+                let mut stdout = stdout.as_writer();
+                writeln!(stdout, "{}", serde_json::to_string_pretty(&result)?)?;
+
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
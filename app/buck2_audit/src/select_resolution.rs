@@ -0,0 +1,319 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::io::Write;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use buck2_build_api::calculation::load_patterns;
+use buck2_build_api::calculation::Calculation;
+use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_build_api::configuration::calculation::ConfigurationCalculation;
+use buck2_build_api::interpreter::rule_defs::transition::calculation_apply_transition::ApplyTransition;
+use buck2_build_api::nodes::calculation::NodeCalculation;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_core::collections::ordered_map::OrderedMap;
+use buck2_core::configuration::config_setting::ConfigSettingData;
+use buck2_core::configuration::data::ConfigurationData;
+use buck2_core::configuration::pair::ConfigurationNoExec;
+use buck2_core::configuration::pair::ConfigurationWithExec;
+use buck2_core::configuration::transition::applied::TransitionApplied;
+use buck2_core::configuration::transition::id::TransitionId;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::target::label::TargetLabel;
+use buck2_node::attrs::configuration_context::AttrConfigurationContext;
+use buck2_node::attrs::display::AttrDisplayWithContextExt;
+use buck2_node::attrs::fmt_context::AttrFmtContext;
+use buck2_node::attrs::inspect_options::AttrInspectOptions;
+use buck2_node::configuration::resolved::ResolvedConfiguration;
+use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_query::query::compatibility::MaybeCompatible;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use dice::DiceComputations;
+use dupe::Dupe;
+use gazebo::prelude::*;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-select-resolution",
+    about = "prints the values the attributes of targets resolve to under each of the given \
+    target platforms, marking with `*` the attributes whose value differs between platforms. \
+    Dependencies are shown in the unbound configuration, so that values only differ by what \
+    their `select()`s pick"
+)]
+pub struct AuditSelectResolutionCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(
+        long = "platform",
+        required = true,
+        help = "Target platform to resolve `select()`s for, can be specified multiple times"
+    )]
+    platforms: Vec<String>,
+
+    #[clap(
+        long,
+        help = "Only print the attributes whose value differs between platforms"
+    )]
+    differing_only: bool,
+
+    #[clap(
+        name = "TARGET_PATTERNS",
+        required = true,
+        help = "Patterns of the targets to resolve"
+    )]
+    patterns: Vec<String>,
+}
+
+/// Resolves `select()`s as per the configuration of a platform, but configures dependencies in
+/// the unbound configuration rather than in the configuration of the platform.
+struct PlatformSelectContext<'a> {
+    resolved_cfg: &'a ResolvedConfiguration,
+    resolved_transitions: &'a OrderedMap<Arc<TransitionId>, Arc<TransitionApplied>>,
+}
+
+impl AttrConfigurationContext for PlatformSelectContext<'_> {
+    fn matches<'a>(&'a self, label: &TargetLabel) -> Option<&'a ConfigSettingData> {
+        self.resolved_cfg.matches(label)
+    }
+
+    fn cfg(&self) -> ConfigurationNoExec {
+        ConfigurationNoExec::unbound()
+    }
+
+    fn exec_cfg(&self) -> ConfigurationNoExec {
+        ConfigurationNoExec::unbound()
+    }
+
+    fn toolchain_cfg(&self) -> ConfigurationWithExec {
+        ConfigurationNoExec::unbound().make_toolchain(&ConfigurationNoExec::unbound())
+    }
+
+    fn platform_cfg(&self, _label: &TargetLabel) -> anyhow::Result<ConfigurationData> {
+        Ok(ConfigurationData::unbound())
+    }
+
+    fn resolved_transitions(&self) -> &OrderedMap<Arc<TransitionId>, Arc<TransitionApplied>> {
+        self.resolved_transitions
+    }
+}
+
+/// The attributes of `node` resolved for `platform`, as name and displayed value, or `None` if
+/// the target is incompatible with the platform.
+async fn resolve_for_platform(
+    ctx: &DiceComputations,
+    node: &TargetNode,
+    platform: &TargetLabel,
+) -> anyhow::Result<Option<Vec<(String, String)>>> {
+    let label = ctx
+        .get_configured_target(node.label(), Some(platform))
+        .await?;
+    let configured = match ctx.get_configured_target_node(&label).await? {
+        MaybeCompatible::Compatible(configured) => configured,
+        MaybeCompatible::Incompatible(_) => return Ok(None),
+    };
+    // With an incoming transition, the attributes are configured in the transitioned
+    // configuration.
+    let cfg = match configured.forward_target() {
+        Some(forward) => forward.label().cfg().dupe(),
+        None => label.cfg().dupe(),
+    };
+
+    let resolved_cfg = ctx
+        .get_resolved_configuration(
+            &cfg,
+            node.label().pkg().cell_name(),
+            node.get_configuration_deps(),
+        )
+        .await?;
+    let mut resolved_transitions = OrderedMap::new();
+    for (_dep, tr) in node.transition_deps() {
+        resolved_transitions.insert(tr.dupe(), ctx.apply_transition(node, &cfg, tr).await?);
+    }
+
+    let cfg_ctx = PlatformSelectContext {
+        resolved_cfg: &resolved_cfg,
+        resolved_transitions: &resolved_transitions,
+    };
+    let fmt_ctx = AttrFmtContext {
+        package: Some(node.label().pkg()),
+    };
+    let mut attrs = Vec::new();
+    for attr in node.attrs(AttrInspectOptions::All) {
+        let value = attr.value.configure(&cfg_ctx)?;
+        attrs.push((attr.name.to_owned(), value.as_display(&fmt_ctx).to_string()));
+    }
+    Ok(Some(attrs))
+}
+
+/// Write the attributes of `target` resolved for each platform, or that it is incompatible.
+fn write_select_resolution<'a>(
+    mut stdout: impl Write,
+    target: &TargetLabel,
+    resolved: impl IntoIterator<Item = (&'a TargetLabel, Option<Vec<(String, String)>>)>,
+    differing_only: bool,
+) -> anyhow::Result<()> {
+    let mut compatible = Vec::new();
+    let mut incompatible = Vec::new();
+    for (platform, attrs) in resolved {
+        match attrs {
+            Some(attrs) => compatible.push((platform, attrs)),
+            None => incompatible.push(platform),
+        }
+    }
+
+    writeln!(stdout, "{}:", target)?;
+    for platform in incompatible {
+        writeln!(stdout, "  incompatible with {}", platform)?;
+    }
+    let Some((_, first)) = compatible.first() else {
+        return Ok(());
+    };
+    // All platforms resolve the same attributes, in the same order.
+    for (i, (name, value)) in first.iter().enumerate() {
+        let differs = compatible.iter().any(|(_, attrs)| &attrs[i].1 != value);
+        if differs {
+            writeln!(stdout, "* {}:", name)?;
+            for (platform, attrs) in &compatible {
+                writeln!(stdout, "    {}: {}", platform, attrs[i].1)?;
+            }
+        } else if !differing_only {
+            writeln!(stdout, "  {} = {}", name, value)?;
+        }
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditSelectResolutionCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        _client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let platforms = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &ctx,
+                    &self
+                        .platforms
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?
+                .into_iter()
+                .zip(&self.platforms)
+                .map(|(pattern, platform)| pattern.as_target_label(platform))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                let loaded_patterns =
+                    load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
+                let mut nodes = Vec::new();
+                for (_, targets) in loaded_patterns.into_iter() {
+                    nodes.extend(targets?.into_iter().map(|(_, node)| node));
+                }
+                nodes.sort_by(|a, b| a.label().cmp(b.label()));
+
+                let mut stdout = stdout.as_writer();
+                for node in &nodes {
+                    let resolved = futures::future::try_join_all(
+                        platforms
+                            .iter()
+                            .map(|platform| resolve_for_platform(&ctx, node, platform)),
+                    )
+                    .await?;
+                    write_select_resolution(
+                        &mut stdout,
+                        node.label(),
+                        platforms.iter().zip(resolved),
+                        self.differing_only,
+                    )?;
+                }
+
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attrs(attrs: &[(&str, &str)]) -> Option<Vec<(String, String)>> {
+        Some(
+            attrs
+                .iter()
+                .map(|(name, value)| ((*name).to_owned(), (*value).to_owned()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_write_select_resolution() -> anyhow::Result<()> {
+        let linux = TargetLabel::testing_parse("root//platforms:linux");
+        let mac = TargetLabel::testing_parse("root//platforms:mac");
+        let windows = TargetLabel::testing_parse("root//platforms:windows");
+        let resolved = || {
+            vec![
+                (
+                    &linux,
+                    attrs(&[("name", "\"lib\""), ("srcs", "[\"linux.c\"]")]),
+                ),
+                (&mac, attrs(&[("name", "\"lib\""), ("srcs", "[\"mac.c\"]")])),
+                (&windows, None),
+            ]
+        };
+        let target = TargetLabel::testing_parse("root//app:lib");
+
+        let mut out = Vec::new();
+        write_select_resolution(&mut out, &target, resolved(), false)?;
+        assert_eq!(
+            "root//app:lib:\n  incompatible with root//platforms:windows\n  name = \"lib\"\n\
+            * srcs:\n    root//platforms:linux: [\"linux.c\"]\n    root//platforms:mac: [\"mac.c\"]\n",
+            String::from_utf8(out)?
+        );
+
+        let mut out = Vec::new();
+        write_select_resolution(&mut out, &target, resolved(), true)?;
+        assert_eq!(
+            "root//app:lib:\n  incompatible with root//platforms:windows\n\
+            * srcs:\n    root//platforms:linux: [\"linux.c\"]\n    root//platforms:mac: [\"mac.c\"]\n",
+            String::from_utf8(out)?
+        );
+
+        let mut out = Vec::new();
+        write_select_resolution(&mut out, &target, vec![(&windows, None)], false)?;
+        assert_eq!(
+            "root//app:lib:\n  incompatible with root//platforms:windows\n",
+            String::from_utf8(out)?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,223 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::io::Write;
+
+use anyhow::Context as _;
+use async_trait::async_trait;
+use buck2_build_api::calculation::load_patterns;
+use buck2_build_api::calculation::Calculation;
+use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_build_api::nodes::calculation::NodeCalculation;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_core::configuration::cfg_diff::cfg_diff;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_core::target::label::ConfiguredTargetLabel;
+use buck2_node::attrs::configured_traversal::ConfiguredAttrTraversal;
+use buck2_node::attrs::inspect_options::AttrInspectOptions;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_query::query::compatibility::MaybeCompatible;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dupe::Dupe;
+use gazebo::prelude::*;
+use indent_write::io::IndentWriter;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-target-configurations",
+    about = "prints the configurations a target is built in when building the given patterns, \
+    along with the dependency path that introduced each configuration and the configuration \
+    changes along that path"
+)]
+pub struct AuditTargetConfigurationsCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(help = "Target to find the configurations of")]
+    target: String,
+
+    #[clap(
+        name = "TARGET_PATTERNS",
+        required = true,
+        help = "Patterns of the build the target is part of"
+    )]
+    patterns: Vec<String>,
+}
+
+/// How a dependency edge of the configured graph configures the dependency.
+fn describe_edge(parent: &ConfiguredTargetNode, child: &ConfiguredTargetNode) -> String {
+    struct DepFinder<'a> {
+        target: &'a ConfiguredTargetLabel,
+        found: bool,
+    }
+
+    impl ConfiguredAttrTraversal for DepFinder<'_> {
+        fn dep(&mut self, dep: &ConfiguredProvidersLabel) -> anyhow::Result<()> {
+            self.found |= dep.target() == self.target;
+            Ok(())
+        }
+    }
+
+    if parent.forward_target().map(|t| t.label()) == Some(child.label()) {
+        return "incoming transition of the rule".to_owned();
+    }
+
+    let kind = if parent.exec_deps().any(|d| d.label() == child.label()) {
+        "exec dep"
+    } else if parent.label().cfg() != child.label().cfg() {
+        "transition"
+    } else {
+        "dep"
+    };
+
+    let attrs: Vec<_> = parent
+        .attrs(AttrInspectOptions::All)
+        .filter(|a| {
+            let mut finder = DepFinder {
+                target: child.label(),
+                found: false,
+            };
+            a.value.traverse(parent.label().pkg(), &mut finder).is_ok() && finder.found
+        })
+        .map(|a| format!("`{}`", a.name))
+        .collect();
+
+    if attrs.is_empty() {
+        kind.to_owned()
+    } else {
+        format!("{} of attribute {}", kind, attrs.join(", "))
+    }
+}
+
+/// Prints the dependency path from a root to `node`, with the configuration differences on each
+/// edge that changes the configuration.
+fn print_path(
+    stdout: &mut impl Write,
+    parents: &HashMap<ConfiguredTargetLabel, Option<ConfiguredTargetNode>>,
+    node: &ConfiguredTargetNode,
+) -> anyhow::Result<()> {
+    let mut path = vec![node.dupe()];
+    while let Some(Some(parent)) = parents.get(path.last().unwrap().label()) {
+        path.push(parent.dupe());
+    }
+    path.reverse();
+
+    writeln!(stdout, "{}:", node.label())?;
+    writeln!(stdout, "  {}", path[0].label())?;
+    for (parent, child) in path.iter().zip(path.iter().skip(1)) {
+        writeln!(
+            stdout,
+            "  -> {} ({})",
+            child.label(),
+            describe_edge(parent, child)
+        )?;
+        if let Err(diff) = cfg_diff(parent.label().cfg(), child.label().cfg()) {
+            write!(IndentWriter::new("       ", &mut *stdout), "{}", diff)?;
+        }
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditTargetConfigurationsCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let target = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &ctx,
+                    &[buck2_data::TargetPattern {
+                        value: self.target.clone(),
+                    }],
+                    server_ctx.working_dir(),
+                )
+                .await?
+                .into_iter()
+                .next()
+                .context("Parsing patterns returned nothing")?
+                .as_target_label(&self.target)?;
+
+                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                let loaded_patterns =
+                    load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
+                let target_platform =
+                    target_platform_from_client_context(&client_ctx, server_ctx, &ctx).await?;
+
+                // Breadth-first search, so that the path to each configuration is a shortest one.
+                let mut parents: HashMap<ConfiguredTargetLabel, Option<ConfiguredTargetNode>> =
+                    HashMap::new();
+                let mut queue = VecDeque::new();
+                for (_, targets) in loaded_patterns.into_iter() {
+                    for (_, node) in targets? {
+                        let label = ctx
+                            .get_configured_target(node.label(), target_platform.as_ref())
+                            .await?;
+                        match ctx.get_configured_target_node(&label).await? {
+                            MaybeCompatible::Compatible(node) => {
+                                if parents.insert(label, None).is_none() {
+                                    queue.push_back(node);
+                                }
+                            }
+                            MaybeCompatible::Incompatible(_) => {}
+                        }
+                    }
+                }
+
+                let mut found = Vec::new();
+                while let Some(node) = queue.pop_front() {
+                    if node.label().unconfigured() == &target {
+                        found.push(node.dupe());
+                    }
+                    for dep in node.deps() {
+                        if !parents.contains_key(dep.label()) {
+                            parents.insert(dep.label().dupe(), Some(node.dupe()));
+                            queue.push_back(dep.dupe());
+                        }
+                    }
+                }
+
+                let mut stdout = stdout.as_writer();
+                if found.is_empty() {
+                    writeln!(stdout, "{} is not part of the build", target)?;
+                }
+                found.sort_by(|a, b| a.label().cmp(b.label()));
+                for node in &found {
+                    print_path(&mut stdout, &parents, node)?;
+                }
+
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
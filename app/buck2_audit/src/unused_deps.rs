@@ -0,0 +1,373 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::any;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashSet;
+use std::io::Write;
+
+use anyhow::Context as _;
+use async_trait::async_trait;
+use buck2_action_impl::actions::impls::run::dep_files::get_dep_files;
+use buck2_action_impl::actions::impls::run::dep_files::DepFilesKey;
+use buck2_build_api::actions::artifact::artifact_type::Artifact;
+use buck2_build_api::actions::RegisteredAction;
+use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
+use buck2_build_api::artifact_groups::ArtifactGroup;
+use buck2_build_api::calculation::Calculation;
+use buck2_build_api::deferred::base_deferred_key::BaseDeferredKey;
+use buck2_build_api::deferred::calculation::DeferredCalculation;
+use buck2_build_api::interpreter::rule_defs::cmd_args::SimpleCommandLineArtifactVisitor;
+use buck2_build_api::nodes::calculation::NodeCalculation;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_core::target::label::ConfiguredTargetLabel;
+use buck2_execute::materialize::materializer::HasMaterializer;
+use buck2_node::attrs::configured_traversal::ConfiguredAttrTraversal;
+use buck2_node::attrs::inspect_options::AttrInspectOptions;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dice::DiceComputations;
+use dupe::Dupe;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-unused-deps",
+    about = "prints the dependencies of a target whose artifacts are never inputs of the \
+    actions registered by the target. Actions registered by `dynamic_output` are not considered"
+)]
+pub struct AuditUnusedDepsCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(help = "Target to find the unused dependencies of")]
+    target: String,
+
+    #[clap(
+        long,
+        help = "Use the dep files of the last build of the target's actions (e.g. C++ headers \
+        actually included) to exclude inputs the actions did not read"
+    )]
+    dep_files: bool,
+}
+
+/// Artifacts of a set of artifact groups, with transitive set projections expanded.
+async fn collect_artifacts(
+    ctx: &DiceComputations,
+    groups: impl IntoIterator<Item = ArtifactGroup>,
+) -> anyhow::Result<HashSet<Artifact>> {
+    let mut artifacts = HashSet::new();
+    let mut projections = HashSet::new();
+    let mut queue: Vec<ArtifactGroup> = groups.into_iter().collect();
+    while let Some(group) = queue.pop() {
+        match group {
+            ArtifactGroup::Artifact(artifact) => {
+                artifacts.insert(artifact);
+            }
+            ArtifactGroup::TransitiveSetProjection(key) => {
+                if projections.insert(key.dupe()) {
+                    let set = ctx
+                        .compute_deferred_data(&key.key)
+                        .await
+                        .context("Failed to compute deferred")?;
+                    queue.extend(
+                        set.as_transitive_set()?
+                            .get_projection_sub_inputs(key.projection)?,
+                    );
+                }
+            }
+        }
+    }
+    Ok(artifacts)
+}
+
+/// Inputs of `action` which the last execution of the action did not read according to its dep
+/// files, or nothing if that is unknown.
+async fn inputs_not_read(
+    ctx: &DiceComputations,
+    artifact_fs: &ArtifactFs,
+    label: &ConfiguredTargetLabel,
+    action: &RegisteredAction,
+) -> anyhow::Result<HashSet<Artifact>> {
+    let tagged = match action.dep_file_inputs()? {
+        Some(tagged) => tagged,
+        None => return Ok(HashSet::new()),
+    };
+    let key = DepFilesKey::new(
+        BaseDeferredKey::TargetLabel(label.dupe()),
+        action.category().clone(),
+        action.identifier().map(|i| i.to_owned()),
+    );
+    let state = match get_dep_files(&key) {
+        Some(state) => state,
+        None => return Ok(HashSet::new()),
+    };
+    let dep_files = match state
+        .read_dep_files(
+            artifact_fs,
+            ctx.per_transaction_data().get_materializer().as_ref(),
+        )
+        .await?
+    {
+        Some(dep_files) => dep_files,
+        None => return Ok(HashSet::new()),
+    };
+
+    let mut not_read = HashSet::new();
+    for artifact in collect_artifacts(ctx, tagged).await? {
+        if !dep_files.selects_within(&artifact.get_path().resolve(artifact_fs)?) {
+            not_read.insert(artifact);
+        }
+    }
+    Ok(not_read)
+}
+
+/// Names of the attributes through which `node` depends on each of its deps.
+fn dep_attrs(
+    node: &ConfiguredTargetNode,
+) -> anyhow::Result<BTreeMap<ConfiguredTargetLabel, BTreeSet<String>>> {
+    struct DepCollector {
+        deps: Vec<ConfiguredTargetLabel>,
+    }
+
+    impl ConfiguredAttrTraversal for DepCollector {
+        fn dep(&mut self, dep: &ConfiguredProvidersLabel) -> anyhow::Result<()> {
+            self.deps.push(dep.target().dupe());
+            Ok(())
+        }
+    }
+
+    let mut result: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+    for attr in node.attrs(AttrInspectOptions::All) {
+        let mut collector = DepCollector { deps: Vec::new() };
+        attr.value
+            .traverse(node.label().pkg(), &mut collector)
+            .with_context(|| format!("Collecting the deps of attribute `{}`", attr.name))?;
+        for dep in collector.deps {
+            result.entry(dep).or_default().insert(attr.name.to_owned());
+        }
+    }
+    Ok(result)
+}
+
+/// Writes the deps of `target` providing artifacts none of which are `consumed`, and the deps
+/// providing no artifacts at all, which cannot be checked.
+fn write_unused_deps(
+    mut stdout: impl Write,
+    target: &ConfiguredTargetLabel,
+    consumed: &HashSet<Artifact>,
+    deps: Vec<(ConfiguredTargetLabel, BTreeSet<String>, HashSet<Artifact>)>,
+) -> anyhow::Result<()> {
+    let mut unused = Vec::new();
+    let mut unchecked = Vec::new();
+    for (dep, attrs, artifacts) in deps {
+        if artifacts.is_empty() {
+            unchecked.push((dep, attrs));
+        } else if artifacts.is_disjoint(consumed) {
+            unused.push((dep, attrs));
+        }
+    }
+
+    let attrs_display = |attrs: &BTreeSet<String>| {
+        attrs
+            .iter()
+            .map(|a| format!("`{}`", a))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    if unused.is_empty() {
+        writeln!(stdout, "No unused dependencies of {}", target)?;
+    } else {
+        writeln!(stdout, "Unused dependencies of {}:", target)?;
+        for (dep, attrs) in &unused {
+            writeln!(stdout, "  {} (attribute {})", dep, attrs_display(attrs))?;
+        }
+    }
+    if !unchecked.is_empty() {
+        writeln!(stdout, "Dependencies providing no artifacts, not checked:")?;
+        for (dep, attrs) in &unchecked {
+            writeln!(stdout, "  {} (attribute {})", dep, attrs_display(attrs))?;
+        }
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditUnusedDepsCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let target = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &ctx,
+                    &[buck2_data::TargetPattern {
+                        value: self.target.clone(),
+                    }],
+                    server_ctx.working_dir(),
+                )
+                .await?
+                .into_iter()
+                .next()
+                .context("Parsing patterns returned nothing")?
+                .as_target_label(&self.target)?;
+                let target_platform =
+                    target_platform_from_client_context(&client_ctx, server_ctx, &ctx).await?;
+                let label = ctx
+                    .get_configured_target(&target, target_platform.as_ref())
+                    .await?;
+                let mut node = ctx
+                    .get_configured_target_node(&label)
+                    .await?
+                    .require_compatible()?;
+                // Forward nodes register no actions, the node they forward to does.
+                if let Some(forward) = node.forward_target() {
+                    node = forward.dupe();
+                }
+
+                let artifact_fs = ctx.get_artifact_fs().await?;
+
+                let analysis = ctx
+                    .get_analysis_result(node.label())
+                    .await?
+                    .require_compatible()?;
+                let mut consumed = HashSet::new();
+                for entry in analysis.iter_deferreds() {
+                    let action = match any::request_ref::<RegisteredAction>(entry.as_complex()) {
+                        Some(action) => action,
+                        None => continue,
+                    };
+                    let mut inputs =
+                        collect_artifacts(&ctx, action.inputs()?.iter().cloned()).await?;
+                    if self.dep_files {
+                        for artifact in
+                            inputs_not_read(&ctx, &artifact_fs, node.label(), action).await?
+                        {
+                            inputs.remove(&artifact);
+                        }
+                    }
+                    consumed.extend(inputs);
+                }
+
+                let mut deps = Vec::new();
+                for (dep, attrs) in dep_attrs(&node)? {
+                    let providers = ctx
+                        .get_analysis_result(&dep)
+                        .await?
+                        .require_compatible()?
+                        .providers()
+                        .dupe();
+                    let mut visitor = SimpleCommandLineArtifactVisitor::new();
+                    providers
+                        .provider_collection()
+                        .visit_artifacts(&mut visitor)?;
+                    let artifacts = collect_artifacts(&ctx, visitor.inputs).await?;
+                    deps.push((dep, attrs, artifacts));
+                }
+
+                write_unused_deps(&mut stdout.as_writer(), node.label(), &consumed, deps)?;
+
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_build_api::actions::artifact::source_artifact::SourceArtifact;
+    use buck2_core::buck_path::path::BuckPath;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::package::package_relative_path::PackageRelativePath;
+    use buck2_core::package::PackageLabel;
+
+    use super::*;
+
+    fn label(label: &str) -> ConfiguredTargetLabel {
+        ConfiguredTargetLabel::testing_parse(label, ConfigurationData::testing_new())
+    }
+
+    fn source(package: &str, path: &str) -> Artifact {
+        SourceArtifact::new(BuckPath::testing_new(
+            PackageLabel::testing_parse(package),
+            PackageRelativePath::new(path).unwrap(),
+        ))
+        .into()
+    }
+
+    #[test]
+    fn test_write_unused_deps() -> anyhow::Result<()> {
+        let used = source("root//used", "used.h");
+        let deps = vec![
+            (
+                label("root//used:used"),
+                BTreeSet::from(["deps".to_owned()]),
+                HashSet::from([used.dupe()]),
+            ),
+            (
+                label("root//unused:unused"),
+                BTreeSet::from(["deps".to_owned(), "exported_deps".to_owned()]),
+                HashSet::from([source("root//unused", "unused.h")]),
+            ),
+            (
+                label("root//config:config"),
+                BTreeSet::from(["deps".to_owned()]),
+                HashSet::new(),
+            ),
+        ];
+
+        let mut out = Vec::new();
+        write_unused_deps(
+            &mut out,
+            &label("root//app:app"),
+            &HashSet::from([used]),
+            deps,
+        )?;
+        assert_eq!(
+            format!(
+                "Unused dependencies of {}:\n  {} (attribute `deps`, `exported_deps`)\n\
+                Dependencies providing no artifacts, not checked:\n  {} (attribute `deps`)\n",
+                label("root//app:app"),
+                label("root//unused:unused"),
+                label("root//config:config"),
+            ),
+            String::from_utf8(out)?
+        );
+
+        let mut out = Vec::new();
+        write_unused_deps(
+            &mut out,
+            &label("root//app:app"),
+            &HashSet::new(),
+            Vec::new(),
+        )?;
+        assert_eq!(
+            format!("No unused dependencies of {}\n", label("root//app:app")),
+            String::from_utf8(out)?
+        );
+        Ok(())
+    }
+}
@@ -58,6 +58,7 @@ use more_futures::cancellation::CancellationContext;
 use crate::actions::artifact::build_artifact::BuildArtifact;
 use crate::actions::execute::action_execution_target::ActionExecutionTarget;
 use crate::actions::execute::error::CommandExecutionErrorMarker;
+use crate::actions::execute::error::CommandTimedOutMarker;
 use crate::actions::execute::error::ExecuteError;
 use crate::actions::impls::run_action_knobs::HasRunActionKnobs;
 use crate::actions::impls::run_action_knobs::RunActionKnobs;
@@ -381,6 +382,11 @@ impl ActionExecutionCtx for BuckActionExecutionContext<'_> {
                 },
             )),
 
+            CommandExecutionStatus::TimedOut { duration, .. } => Err(CommandTimedOutMarker {
+                duration: *duration,
+            }
+            .into()),
+
             _ => Err(CommandExecutionErrorMarker.into()),
         };
 
@@ -9,6 +9,7 @@
 
 use std::fmt::Display;
 use std::fmt::Write;
+use std::time::Duration;
 
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_execute::execute::request::OutputType;
@@ -32,6 +33,9 @@ pub enum ExecuteError {
         error: anyhow::Error,
     },
     CommandExecutionError,
+    CommandTimedOut {
+        duration: Duration,
+    },
 }
 
 impl ExecuteError {
@@ -57,6 +61,10 @@ impl ExecuteError {
             .into(),
             ExecuteError::Error { error } => format!("{:#}", error).into(),
             ExecuteError::CommandExecutionError => buck2_data::CommandExecutionError {}.into(),
+            ExecuteError::CommandTimedOut { duration } => buck2_data::CommandTimedOut {
+                message: format!("Command timed out after {:.3}s", duration.as_secs_f64()),
+            }
+            .into(),
         }
     }
 }
@@ -80,6 +88,11 @@ impl From<anyhow::Error> for ExecuteError {
         if error.is::<CommandExecutionErrorMarker>() {
             return Self::CommandExecutionError;
         }
+        if let Some(CommandTimedOutMarker { duration }) = error.downcast_ref() {
+            return Self::CommandTimedOut {
+                duration: *duration,
+            };
+        }
         Self::Error { error }
     }
 }
@@ -87,3 +100,9 @@ impl From<anyhow::Error> for ExecuteError {
 #[derive(Error, Debug)]
 #[error("Command execution failed. Details are in the command report.")]
 pub struct CommandExecutionErrorMarker;
+
+#[derive(Error, Debug)]
+#[error("Command timed out. Details are in the command report.")]
+pub struct CommandTimedOutMarker {
+    pub duration: Duration,
+}
@@ -41,7 +41,9 @@ use crate::interpreter::rule_defs::cmd_args::FrozenStarlarkCommandLine;
 use crate::interpreter::rule_defs::cmd_args::StarlarkCommandLine;
 use crate::interpreter::rule_defs::provider::ProviderLike;
 use crate::interpreter::rule_defs::provider::ValueAsProviderLike;
+use crate::interpreter::rule_defs::transitive_set::TransitiveSet;
 use crate::interpreter::rule_defs::transitive_set::TransitiveSetJsonProjection;
+use crate::interpreter::rule_defs::transitive_set::TransitiveSetOrdering;
 
 /// A wrapper with a Serialize instance so we can pass down the necessary context.
 struct SerializeValue<'a, 'v> {
@@ -254,6 +256,36 @@ pub fn write_json(x: Value, fs: Option<&ExecutorFs>, writer: impl Write) -> anyh
 pub fn visit_json_artifacts(
     v: Value,
     visitor: &mut dyn CommandLineArtifactVisitor,
+) -> anyhow::Result<()> {
+    visit_artifacts(v, visitor, |v, _visitor| {
+        Err(anyhow::anyhow!(
+            "Type `{}` is not supported by `write_json` (this should be unreachable)",
+            v.get_type()
+        ))
+    })
+}
+
+/// Visits the artifacts of any value held by a provider. Unlike `visit_json_artifacts`,
+/// transitive sets are visited through their values, and values which cannot hold artifacts
+/// usable by actions (such as functions) are skipped.
+pub fn visit_provider_value_artifacts(
+    v: Value,
+    visitor: &mut dyn CommandLineArtifactVisitor,
+) -> anyhow::Result<()> {
+    visit_artifacts(v, visitor, |v, visitor| {
+        if let Some(set) = TransitiveSet::from_value(v) {
+            for v in set.iter_values(TransitiveSetOrdering::Preorder)? {
+                visit_provider_value_artifacts(v, visitor)?;
+            }
+        }
+        Ok(())
+    })
+}
+
+fn visit_artifacts(
+    v: Value,
+    visitor: &mut dyn CommandLineArtifactVisitor,
+    unsupported: fn(Value, &mut dyn CommandLineArtifactVisitor) -> anyhow::Result<()>,
 ) -> anyhow::Result<()> {
     match unpack(v) {
         JsonUnpack::None
@@ -266,28 +298,28 @@ pub fn visit_json_artifacts(
 
         JsonUnpack::List(x) => {
             for x in x.iter() {
-                visit_json_artifacts(x, visitor)?;
+                visit_artifacts(x, visitor, unsupported)?;
             }
         }
         JsonUnpack::Tuple(x) => {
             for x in x.iter() {
-                visit_json_artifacts(x, visitor)?;
+                visit_artifacts(x, visitor, unsupported)?;
             }
         }
         JsonUnpack::Dict(x) => {
             for (k, v) in x.iter() {
-                visit_json_artifacts(k, visitor)?;
-                visit_json_artifacts(v, visitor)?;
+                visit_artifacts(k, visitor, unsupported)?;
+                visit_artifacts(v, visitor, unsupported)?;
             }
         }
         JsonUnpack::Struct(x) => {
             for (_k, v) in x.iter() {
-                visit_json_artifacts(v, visitor)?;
+                visit_artifacts(v, visitor, unsupported)?;
             }
         }
         JsonUnpack::Record(x) => {
             for (_k, v) in x.iter() {
-                visit_json_artifacts(v, visitor)?;
+                visit_artifacts(v, visitor, unsupported)?;
             }
         }
         JsonUnpack::TransitiveSetJsonProjection(x) => visitor.visit_input(
@@ -301,20 +333,15 @@ pub fn visit_json_artifacts(
             v.as_command_line_err()?.visit_artifacts(visitor)?;
         }
         JsonUnpack::CommandLine(x) => x.visit_artifacts(visitor)?,
-        JsonUnpack::Unsupported => {
-            return Err(anyhow::anyhow!(
-                "Type `{}` is not supported by `write_json` (this should be unreachable)",
-                v.get_type()
-            ));
-        }
+        JsonUnpack::Unsupported => unsupported(v, visitor)?,
         JsonUnpack::Provider(x) => {
             for (_, v) in x.items() {
-                visit_json_artifacts(v, visitor)?;
+                visit_artifacts(v, visitor, unsupported)?;
             }
         }
         JsonUnpack::TaggedValue(v) => {
             let mut visitor = v.wrap_visitor(visitor);
-            visit_json_artifacts(*v.value(), &mut visitor)?;
+            visit_artifacts(*v.value(), &mut visitor, unsupported)?;
         }
     }
     Ok(())
@@ -108,6 +108,12 @@ pub trait Action: Allocative + Debug + Send + Sync + 'static {
     /// function.
     fn outputs(&self) -> anyhow::Result<Cow<'_, [BuildArtifact]>>;
 
+    /// The inputs which are only relevant to the action if listed in one of its dep files, or
+    /// `None` if the action has no dep files.
+    fn dep_file_inputs(&self) -> anyhow::Result<Option<Vec<ArtifactGroup>>> {
+        Ok(None)
+    }
+
     /// Obtains an executable for this action.
     fn as_executable(&self) -> ActionExecutable<'_>;
 
@@ -264,6 +270,7 @@ impl TrivialDeferred for Arc<RegisteredAction> {
     }
 
     fn provide<'a>(&'a self, demand: &mut Demand<'a>) {
+        demand.provide_ref::<RegisteredAction>(self);
         demand.provide_value_with(|| {
             ProvideOutputs(
                 self.action
@@ -53,6 +53,7 @@ use crate::actions::build_listener::NodeDuration;
 use crate::analysis::calculation::keys::AnalysisKey;
 use crate::analysis::configured_graph::AnalysisConfiguredGraphQueryDelegate;
 use crate::analysis::configured_graph::AnalysisDiceQueryDelegate;
+use crate::analysis::dependency_rules::check_dependency_rules;
 use crate::analysis::get_user_defined_rule_impl;
 use crate::analysis::run_analysis;
 use crate::analysis::AnalysisResult;
@@ -268,6 +269,9 @@ async fn get_analysis_result(
         MaybeCompatible::Compatible(configured_node) => configured_node,
     };
 
+    // Checked here rather than when configuring, so that queries still work on the graph.
+    check_dependency_rules(ctx, &configured_node).await?;
+
     let mut dep_analysis = get_dep_analysis(&configured_node, ctx).await?;
 
     let now = Instant::now();
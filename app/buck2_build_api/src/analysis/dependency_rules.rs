@@ -0,0 +1,109 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Dice calculations checking `PACKAGE` dependency rules.
+
+use std::sync::Arc;
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use buck2_common::result::SharedResult;
+use buck2_core::target::label::ConfiguredTargetLabel;
+use buck2_node::dependency_rules::direct_dependency_rule_violations;
+use buck2_node::dependency_rules::transitive_forbidden_rules;
+use buck2_node::dependency_rules::DependencyRule;
+use buck2_node::dependency_rules::DependencyRuleViolation;
+use buck2_node::dependency_rules::DependencyRulesError;
+use buck2_node::dependency_rules::ForbiddenReachable;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use derive_more::Display;
+use dice::DiceComputations;
+use dice::Key;
+use dupe::Dupe;
+use more_futures::cancellation::CancellationContext;
+
+use crate::nodes::calculation::NodeCalculation;
+
+/// Targets matching `rule` reachable from `target`, computed once per target and rule from the
+/// results of the target deps, rather than by a traversal for every target checking the rule.
+#[derive(Clone, Display, Debug, Eq, Hash, PartialEq, Allocative)]
+#[display(fmt = "{} ({} from {})", target, "rule.kind", "rule.declared_in")]
+struct ForbiddenReachableKey {
+    target: ConfiguredTargetLabel,
+    rule: Arc<DependencyRule>,
+}
+
+#[async_trait]
+impl Key for ForbiddenReachableKey {
+    type Value = SharedResult<Arc<ForbiddenReachable>>;
+
+    async fn compute(
+        &self,
+        ctx: &DiceComputations,
+        _cancellation: &CancellationContext,
+    ) -> Self::Value {
+        let node = ctx
+            .get_configured_target_node(&self.target)
+            .await?
+            .require_compatible()?;
+        let deps = futures::future::try_join_all(
+            node.target_deps()
+                .map(|dep| forbidden_reachable(ctx, dep.label().dupe(), self.rule.dupe())),
+        )
+        .await?;
+        Ok(Arc::new(ForbiddenReachable::new(
+            &self.rule,
+            node.target_deps()
+                .zip(deps.iter().map(|reachable| &**reachable)),
+        )))
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        match (x, y) {
+            (Ok(x), Ok(y)) => x == y,
+            _ => false,
+        }
+    }
+}
+
+async fn forbidden_reachable(
+    ctx: &DiceComputations,
+    target: ConfiguredTargetLabel,
+    rule: Arc<DependencyRule>,
+) -> anyhow::Result<Arc<ForbiddenReachable>> {
+    Ok(ctx
+        .compute(&ForbiddenReachableKey { target, rule })
+        .await??)
+}
+
+/// Returns the dependencies of `node` forbidden by the rules of its package.
+pub async fn dependency_rule_violations(
+    ctx: &DiceComputations,
+    node: &ConfiguredTargetNode,
+) -> anyhow::Result<Vec<DependencyRuleViolation>> {
+    let mut violations = direct_dependency_rule_violations(node);
+    for rule in transitive_forbidden_rules(node) {
+        let rule = Arc::new(rule.clone());
+        let reachable = forbidden_reachable(ctx, node.label().dupe(), rule.dupe()).await?;
+        violations.extend(reachable.violations(node, &rule));
+    }
+    Ok(violations)
+}
+
+pub async fn check_dependency_rules(
+    ctx: &DiceComputations,
+    node: &ConfiguredTargetNode,
+) -> anyhow::Result<()> {
+    let violations = dependency_rule_violations(ctx, node).await?;
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(DependencyRulesError::Violations(node.label().dupe(), violations).into())
+    }
+}
@@ -57,6 +57,29 @@ pub async fn configure_dice_for_buck(
     dice.set_io_provider(io);
     dice.set_digest_config(digest_config);
 
+    if let Some(budget_mb) = root_config
+        .map(|c| c.parse::<usize>("buck2", "dice_memory_budget_mb"))
+        .transpose()?
+        .flatten()
+    {
+        match which_dice {
+            WhichDice::Legacy => tracing::warn!(
+                "Ignoring `buck2.dice_memory_budget_mb`: values are only evicted with `buck2.dice = modern`"
+            ),
+            WhichDice::Modern => dice.memory_budget(budget_mb * 1024 * 1024),
+        }
+    }
+
+    // Kept for `buck2 debug dice explain`.
+    if root_config
+        .map(|c| c.parse::<bool>("buck2", "dice_record_invalidations"))
+        .transpose()?
+        .flatten()
+        .unwrap_or(false)
+    {
+        dice.record_invalidations();
+    }
+
     let dice = dice.build(detect_cycles);
     let mut dice_ctx = dice.updater();
     dice_ctx.set_none_cell_resolver()?;
@@ -280,6 +280,7 @@ mod tests {
                         "__type__": "root//include.bzl:export_file",
                         "compatible_with": [],
                         "default_target_platform": null,
+                        "deprecation": null,
                         "exec_compatible_with": [],
                         "name": "DEFAULT",
                         "target_compatible_with": [],
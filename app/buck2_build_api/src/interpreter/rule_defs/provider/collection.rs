@@ -12,6 +12,7 @@ use std::fmt::Display;
 use std::sync::Arc;
 
 use allocative::Allocative;
+use anyhow::Context as _;
 use buck2_core::provider::id::ProviderId;
 use buck2_core::provider::id::ProviderIdWithType;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
@@ -45,6 +46,8 @@ use starlark::values::Tracer;
 use starlark::values::Value;
 use starlark::values::ValueLike;
 
+use crate::actions::impls::json::visit_provider_value_artifacts;
+use crate::interpreter::rule_defs::cmd_args::CommandLineArtifactVisitor;
 use crate::interpreter::rule_defs::provider::DefaultInfo;
 use crate::interpreter::rule_defs::provider::DefaultInfoCallable;
 use crate::interpreter::rule_defs::provider::FrozenDefaultInfo;
@@ -371,6 +374,27 @@ impl FrozenProviderCollection {
     pub fn provider_ids(&self) -> Vec<&ProviderId> {
         self.providers.keys().map(|k| &**k).collect()
     }
+
+    /// Visits the artifacts held by the providers of this collection and of its sub-targets,
+    /// including the values of transitive sets.
+    pub fn visit_artifacts(
+        &self,
+        visitor: &mut dyn CommandLineArtifactVisitor,
+    ) -> anyhow::Result<()> {
+        for provider in self.providers.values() {
+            let provider = provider
+                .to_value()
+                .as_provider()
+                .context("Provider collection contains a non-provider value")?;
+            for (_, value) in provider.items() {
+                visit_provider_value_artifacts(value, visitor)?;
+            }
+        }
+        for sub_target in self.default_info().sub_targets().values() {
+            sub_target.visit_artifacts(visitor)?;
+        }
+        Ok(())
+    }
 }
 
 /// Thin wrapper around `FrozenValue` that can only be constructed if that value is a `FrozenProviderCollection`
@@ -467,6 +491,9 @@ pub(crate) mod tester {
     use starlark::values::Value;
     use starlark::values::ValueLike;
 
+    use crate::artifact_groups::ArtifactGroup;
+    use crate::interpreter::rule_defs::artifact::StarlarkArtifact;
+    use crate::interpreter::rule_defs::cmd_args::SimpleCommandLineArtifactVisitor;
     use crate::interpreter::rule_defs::provider::collection::FrozenProviderCollection;
     use crate::interpreter::rule_defs::provider::ProviderCollection;
 
@@ -533,6 +560,26 @@ pub(crate) mod tester {
                 })?
                 .provider_names())
         }
+
+        fn provider_artifacts<'v>(collection: Value<'v>) -> anyhow::Result<Vec<StarlarkArtifact>> {
+            let mut visitor = SimpleCommandLineArtifactVisitor::new();
+            collection
+                .unpack_frozen()
+                .expect("a frozen value")
+                .downcast_ref::<FrozenProviderCollection>()
+                .ok_or_else(|| {
+                    anyhow::anyhow!("{:?} was not a FrozenProviderCollection", collection)
+                })?
+                .visit_artifacts(&mut visitor)?;
+            visitor
+                .inputs
+                .into_iter()
+                .map(|input| match input {
+                    ArtifactGroup::Artifact(artifact) => Ok(StarlarkArtifact::new(artifact)),
+                    input => Err(anyhow::anyhow!("unexpected input {}", input)),
+                })
+                .collect()
+        }
     }
 }
 
@@ -545,9 +592,11 @@ mod tests {
     use indoc::indoc;
 
     use crate::interpreter::build_defs::register_provider;
+    use crate::interpreter::build_defs::register_transitive_set;
     use crate::interpreter::rule_defs::artifact::testing::artifactory;
     use crate::interpreter::rule_defs::provider::collection::tester::collection_creator;
     use crate::interpreter::rule_defs::register_rule_defs;
+    use crate::interpreter::rule_defs::transitive_set::testing::tset_factory;
 
     fn provider_collection_tester() -> SharedResult<Tester> {
         let mut tester = Tester::new()?;
@@ -737,4 +786,29 @@ mod tests {
             "#
         ))
     }
+
+    #[test]
+    fn provider_collection_visit_artifacts() -> SharedResult<()> {
+        let mut tester = provider_collection_tester()?;
+        tester.additional_globals(register_transitive_set);
+        tester.additional_globals(tset_factory);
+        tester.run_starlark_bzl_test(indoc!(
+            r#"
+            load("//provider:defs1.bzl", "FooInfo", "BarInfo")
+            FooSet = transitive_set()
+            a = source_artifact("foo", "a.cpp")
+            b = source_artifact("foo", "b.cpp")
+            c = source_artifact("foo", "c.cpp")
+            d = source_artifact("foo", "d.cpp")
+            frozen_collection = create_collection([
+                FooInfo(foo = {"srcs": [a], "callback": lambda: None}),
+                BarInfo(bar = make_tset(FooSet, value = b, children = [make_tset(FooSet, value = c)])),
+                DefaultInfo(sub_targets = {"sub": [DefaultInfo(default_outputs = [d])]}),
+            ])
+            def test():
+                artifacts = provider_artifacts(frozen_collection)
+                assert_eq([a, b, c, d], sorted(artifacts, key = lambda x: x.short_path))
+            "#
+        ))
+    }
 }
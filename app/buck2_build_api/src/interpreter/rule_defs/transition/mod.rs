@@ -7,6 +7,6 @@
  * of this source tree.
  */
 
-pub(crate) mod calculation_apply_transition;
+pub mod calculation_apply_transition;
 pub(crate) mod calculation_fetch_transition;
 pub(crate) mod starlark;
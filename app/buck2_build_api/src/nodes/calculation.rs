@@ -46,6 +46,8 @@ use buck2_node::configuration::execution::ExecutionPlatformResolution;
 use buck2_node::configuration::resolved::ConfigurationSettingKeyRef;
 use buck2_node::configuration::resolved::ResolvedConfiguration;
 use buck2_node::configuration::toolchain_constraints::ToolchainConstraints;
+use buck2_node::deprecation::DeprecationError;
+use buck2_node::deprecation::DeprecationLevel;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_node::nodes::unconfigured::TargetNode;
 use buck2_node::visibility::VisibilityError;
@@ -526,6 +528,31 @@ fn check_compatible(
     )))
 }
 
+/// Fail if `target_label` depends on a target deprecated with `DeprecationLevel::Error`.
+///
+/// Warnings are not emitted here: this runs in a cached computation, so they would only show up
+/// the first time the node is computed. See `crate::nodes::deprecation` instead.
+fn check_deprecation(
+    target_label: &ConfiguredTargetLabel,
+    dep: &ConfiguredTargetNode,
+) -> anyhow::Result<()> {
+    let deprecation = match dep.deprecation() {
+        Some(deprecation) => deprecation,
+        None => return Ok(()),
+    };
+    if deprecation.level != DeprecationLevel::Error
+        || !deprecation.applies_to(dep.label().unconfigured(), target_label.unconfigured())
+    {
+        return Ok(());
+    }
+    Err(DeprecationError::DeprecatedDependency(
+        target_label.unconfigured().dupe(),
+        dep.label().unconfigured().dupe(),
+        deprecation.message.to_owned(),
+    )
+    .into())
+}
+
 /// Compute configured target node ignoring transition for this node.
 async fn compute_configured_target_node_no_transition(
     target_label: &ConfiguredTargetLabel,
@@ -668,6 +695,8 @@ async fn compute_configured_target_node_no_transition(
                             target_label.unconfigured().dupe(),
                         ))),
                     )
+                } else if let Err(e) = check_deprecation(target_label, &dep) {
+                    ControlFlow::Break(Err(e))
                 } else {
                     ControlFlow::Continue(dep)
                 }
@@ -0,0 +1,136 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Warnings for dependencies on deprecated targets.
+//!
+//! Configured nodes are cached, so warnings emitted while computing them would only be shown by
+//! the first command computing them. Instead the warnings reachable from a target are computed
+//! with Dice, and emitted by the commands building the target.
+
+use std::sync::Arc;
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use buck2_common::result::SharedResult;
+use buck2_core::target::label::ConfiguredTargetLabel;
+use buck2_events::dispatch::get_dispatcher;
+use buck2_node::deprecation::DeprecationLevel;
+use buck2_query::query::compatibility::MaybeCompatible;
+use derive_more::Display;
+use dice::DiceComputations;
+use dice::Key;
+use dupe::Dupe;
+use more_futures::cancellation::CancellationContext;
+use starlark::collections::SmallSet;
+
+use crate::nodes::calculation::NodeCalculation;
+
+/// A dependency edge on a target deprecated with `DeprecationLevel::Warn`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Allocative)]
+pub struct DeprecatedDependency {
+    pub target: ConfiguredTargetLabel,
+    pub dependency: ConfiguredTargetLabel,
+    pub message: String,
+}
+
+/// Deprecated dependency edges reachable from a target, including its own.
+#[derive(Clone, Display, Debug, Eq, Hash, PartialEq, Allocative)]
+struct DeprecatedDependenciesKey(ConfiguredTargetLabel);
+
+#[async_trait]
+impl Key for DeprecatedDependenciesKey {
+    type Value = SharedResult<Arc<SmallSet<DeprecatedDependency>>>;
+
+    async fn compute(
+        &self,
+        ctx: &DiceComputations,
+        _cancellation: &CancellationContext,
+    ) -> Self::Value {
+        let node = match ctx.get_configured_target_node(&self.0).await? {
+            MaybeCompatible::Compatible(node) => node,
+            MaybeCompatible::Incompatible(_) => return Ok(Arc::new(SmallSet::new())),
+        };
+        let deps = futures::future::try_join_all(
+            node.deps()
+                .map(|dep| deprecated_dependencies(ctx, dep.label().dupe())),
+        )
+        .await?;
+
+        let own: SmallSet<_> = node
+            .deps()
+            .filter_map(|dep| {
+                let deprecation = dep.deprecation()?;
+                (deprecation.level == DeprecationLevel::Warn
+                    && deprecation.applies_to(dep.label().unconfigured(), self.0.unconfigured()))
+                .then(|| DeprecatedDependency {
+                    target: self.0.dupe(),
+                    dependency: dep.label().dupe(),
+                    message: deprecation.message.to_owned(),
+                })
+            })
+            .collect();
+
+        // Share the set of the only dependency reaching deprecated targets, if any.
+        let mut non_empty = deps.into_iter().filter(|deps| !deps.is_empty());
+        match (own.is_empty(), non_empty.next(), non_empty.next()) {
+            (true, None, _) => Ok(Arc::new(SmallSet::new())),
+            (true, Some(only), None) => Ok(only),
+            (_, first, second) => {
+                let mut all = own;
+                for deps in first.into_iter().chain(second).chain(non_empty) {
+                    all.extend(deps.iter().cloned());
+                }
+                Ok(Arc::new(all))
+            }
+        }
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        match (x, y) {
+            (Ok(x), Ok(y)) => x == y,
+            _ => false,
+        }
+    }
+}
+
+async fn deprecated_dependencies(
+    ctx: &DiceComputations,
+    target: ConfiguredTargetLabel,
+) -> anyhow::Result<Arc<SmallSet<DeprecatedDependency>>> {
+    Ok(ctx.compute(&DeprecatedDependenciesKey(target)).await??)
+}
+
+/// Emit a `DeprecatedDependency` event for each deprecated dependency edge reachable from
+/// `targets`. Errors are ignored: they are reported when building the targets.
+pub async fn emit_deprecation_warnings(
+    ctx: &DiceComputations,
+    targets: impl IntoIterator<Item = ConfiguredTargetLabel>,
+) {
+    let all: Vec<_> = futures::future::join_all(
+        targets
+            .into_iter()
+            .map(|target| deprecated_dependencies(ctx, target)),
+    )
+    .await
+    .into_iter()
+    .filter_map(Result::ok)
+    .collect();
+    let mut emitted = SmallSet::new();
+    for deps in &all {
+        for dep in deps.iter() {
+            if emitted.insert(dep) {
+                get_dispatcher().instant_event(buck2_data::DeprecatedDependency {
+                    target: dep.target.to_string(),
+                    dependency: dep.dependency.to_string(),
+                    message: dep.message.clone(),
+                });
+            }
+        }
+    }
+}
@@ -8,6 +8,7 @@
  */
 
 pub mod calculation;
+pub mod deprecation;
 pub mod lookup;
 
 use buck2_node::attrs::coerced_attr::CoercedAttr;
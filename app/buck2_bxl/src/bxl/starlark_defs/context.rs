@@ -37,14 +37,12 @@ use buck2_core::provider::label::ConfiguredProvidersLabel;
 use buck2_core::target::label::TargetLabel;
 use buck2_execute::digest_config::DigestConfig;
 use buck2_interpreter::starlark_promise::StarlarkPromise;
-use buck2_interpreter::types::label::Label;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_node::nodes::unconfigured::TargetNode;
 use dashmap::DashMap;
 use derivative::Derivative;
 use derive_more::Display;
 use dupe::Dupe;
-use either::Either;
 use indexmap::IndexSet;
 use itertools::Itertools;
 use starlark::any::ProvidesStaticType;
@@ -75,6 +73,8 @@ use crate::bxl::starlark_defs::audit::StarlarkAuditCtx;
 use crate::bxl::starlark_defs::context::actions::validate_action_instantiation;
 use crate::bxl::starlark_defs::context::actions::BxlActionsCtx;
 use crate::bxl::starlark_defs::context::fs::BxlFilesystem;
+use crate::bxl::starlark_defs::context::lazy::LazyOperation;
+use crate::bxl::starlark_defs::context::lazy::StarlarkBxlLazy;
 use crate::bxl::starlark_defs::context::output::EnsuredArtifactOrGroup;
 use crate::bxl::starlark_defs::context::output::OutputStream;
 use crate::bxl::starlark_defs::context::starlark_async::BxlSafeDiceComputations;
@@ -83,6 +83,8 @@ use crate::bxl::starlark_defs::providers_expr::ProvidersExpr;
 use crate::bxl::starlark_defs::target_expr::filter_incompatible;
 use crate::bxl::starlark_defs::target_expr::TargetExpr;
 use crate::bxl::starlark_defs::targetset::StarlarkTargetSet;
+use crate::bxl::starlark_defs::testing::BxlTestMocks;
+use crate::bxl::starlark_defs::testing::MockedFileSystem;
 use crate::bxl::starlark_defs::uquery::get_uquery_env;
 use crate::bxl::starlark_defs::uquery::StarlarkUQueryCtx;
 use crate::bxl::value_as_starlark_target_label::ValueAsStarlarkTargetLabel;
@@ -90,7 +92,9 @@ use crate::bxl::value_as_starlark_target_label::ValueAsStarlarkTargetLabel;
 pub mod actions;
 pub mod analysis;
 pub mod build;
+pub mod build_file_edits;
 pub mod fs;
+pub mod lazy;
 pub mod output;
 pub mod starlark_async;
 
@@ -127,6 +131,9 @@ pub struct BxlContext<'v> {
     /// Use a RefCell/Option so when we are done with it, without obtaining exclusive access,
     /// we can take the internal state without having to clone it.
     pub(crate) materializations: Arc<DashMap<BuildArtifact, ()>>,
+    /// The mocked results of this context when running a `bxl_test`
+    #[trace(unsafe_ignore)]
+    pub(crate) test_mocks: Option<Arc<BxlTestMocks>>,
 }
 
 impl<'v> BxlContext<'v> {
@@ -144,6 +151,7 @@ impl<'v> BxlContext<'v> {
         error_sink: RefCell<Box<dyn Write>>,
         digest_config: DigestConfig,
         global_target_platform: Option<TargetLabel>,
+        test_mocks: Option<Arc<BxlTestMocks>>,
     ) -> Self {
         Self {
             current_bxl,
@@ -171,6 +179,15 @@ impl<'v> BxlContext<'v> {
             )),
             global_target_platform,
             materializations: Arc::new(DashMap::new()),
+            test_mocks,
+        }
+    }
+
+    /// The query to evaluate for `query`, which is mocked when running some `bxl_test`s.
+    pub(crate) fn mocked_query<'a>(&'a self, query: &'a str) -> &'a str {
+        match &self.test_mocks {
+            Some(mocks) => mocks.query(query),
+            None => query,
         }
     }
 
@@ -283,6 +300,11 @@ impl<'v> UnpackValue<'v> for &'v BxlContext<'v> {
     }
 }
 
+fn parse_materializations(materializations: &str) -> anyhow::Result<Materializations> {
+    Materializations::from_str_name(&materializations.to_uppercase())
+        .ok_or_else(|| anyhow::anyhow!("Unknown materialization setting `{}`", materializations))
+}
+
 /// The bxl context that the top level bxl implementation receives as parameter.
 /// This context contains all the core bxl functions to query, build, create actions, etc.
 #[starlark_module]
@@ -482,21 +504,9 @@ fn register_context(builder: &mut MethodsBuilder) {
 
         let res: anyhow::Result<_> = this
             .async_ctx
-            .via_dice(|ctx| async { analysis::analysis(ctx, providers, skip_incompatible).await });
-
-        Ok(match res? {
-            Either::Left(single) => eval.heap().alloc(single),
-            Either::Right(many) => eval.heap().alloc(Dict::new(
-                many.into_iter()
-                    .map(|(t, v)| {
-                        Ok((
-                            eval.heap().alloc(Label::new(t)).get_hashed()?,
-                            eval.heap().alloc(v),
-                        ))
-                    })
-                    .collect::<anyhow::Result<_>>()?,
-            )),
-        })
+            .via_dice(|ctx| async { analysis::analysis(ctx, &providers, skip_incompatible).await });
+
+        analysis::alloc_analysis_result(res?, eval.heap())
     }
 
     /// Runs a build on the given `labels`, accepting an optional `target_platform` which is the
@@ -523,13 +533,70 @@ fn register_context(builder: &mut MethodsBuilder) {
             this,
             spec,
             target_platform,
-            Materializations::from_str_name(&materializations.to_uppercase()).ok_or_else(|| {
-                anyhow::anyhow!("Unknown materialization setting `{}`", materializations)
-            })?,
+            parse_materializations(materializations)?,
             eval,
         )?)))
     }
 
+    /// Like `analysis()`, but returns a [`StarlarkBxlLazy`] that runs the analysis when resolved,
+    /// so that many analyses can run concurrently with `join_lazy()`.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl(ctx):
+    ///     targets = ctx.configured_targets("//foo/...")
+    ///     lazies = [ctx.lazy_analysis(target) for target in targets]
+    ///     for result in ctx.join_lazy(lazies):
+    ///         ctx.output.print(result.providers())
+    /// ```
+    fn lazy_analysis<'v>(
+        this: &'v BxlContext<'v>,
+        labels: Value<'v>,
+        #[starlark(default = NoneType)] target_platform: Value<'v>,
+        #[starlark(default = true)] skip_incompatible: bool,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<StarlarkBxlLazy<'v>> {
+        let providers =
+            ProvidersExpr::<ConfiguredProvidersLabel>::unpack(labels, target_platform, this, eval)?;
+        Ok(StarlarkBxlLazy::new(
+            this,
+            LazyOperation::Analysis {
+                providers,
+                skip_incompatible,
+            },
+        ))
+    }
+
+    /// Like `build()`, but returns a [`StarlarkBxlLazy`] that runs the build when resolved,
+    /// so that many builds can run concurrently with `join_lazy()`.
+    fn lazy_build<'v>(
+        this: &'v BxlContext<'v>,
+        spec: Value<'v>,
+        #[starlark(default = NoneType)] target_platform: Value<'v>,
+        #[starlark(require = named, default = "default")] materializations: &str,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<StarlarkBxlLazy<'v>> {
+        let providers =
+            ProvidersExpr::<ConfiguredProvidersLabel>::unpack(spec, target_platform, this, eval)?;
+        Ok(StarlarkBxlLazy::new(
+            this,
+            LazyOperation::Build {
+                providers,
+                materializations: parse_materializations(materializations)?,
+            },
+        ))
+    }
+
+    /// Resolves the given list of [`StarlarkBxlLazy`] concurrently, returning the list of their
+    /// results in the same order.
+    fn join_lazy<'v>(
+        this: &'v BxlContext<'v>,
+        lazies: Vec<&'v StarlarkBxlLazy<'v>>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<Vec<Value<'v>>> {
+        lazy::join_lazy(this, &lazies, eval.heap())
+    }
+
     /// A struct of the command line args as declared using the [`cli_args`] module.
     /// These command lines are resolved per the users input on the cli when invoking the bxl script.
     #[starlark(attribute)]
@@ -540,11 +607,21 @@ fn register_context(builder: &mut MethodsBuilder) {
     /// Returns the [`BxlFilesystem`] for performing a basic set of filesystem operations within bxl
     #[starlark(attribute)]
     fn fs<'v>(this: &BxlContext<'v>) -> anyhow::Result<BxlFilesystem<'v>> {
+        let cell = this.cell_resolver.get(this.cell_name)?;
+        let mocked = match this.test_mocks.as_ref().and_then(|m| m.files.as_ref()) {
+            Some(files) => Some(Arc::new(MockedFileSystem::new(
+                files,
+                &this.async_ctx,
+                cell,
+            )?)),
+            None => None,
+        };
         Ok(BxlFilesystem::new(
             &this.async_ctx,
             &this.output_stream.project_fs,
             &this.output_stream.artifact_fs,
-            this.cell_resolver.get(this.cell_name)?,
+            cell,
+            mocked,
         ))
     }
 
@@ -9,16 +9,20 @@
 
 use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_interpreter::types::label::Label;
 use dice::DiceComputations;
 use either::Either;
 use gazebo::prelude::*;
+use starlark::values::dict::Dict;
+use starlark::values::Heap;
+use starlark::values::Value;
 
 use crate::bxl::starlark_defs::analysis_result::StarlarkAnalysisResult;
 use crate::bxl::starlark_defs::providers_expr::ProvidersExpr;
 
 pub(crate) async fn analysis(
     ctx: &DiceComputations,
-    expr: ProvidersExpr<ConfiguredProvidersLabel>,
+    expr: &ProvidersExpr<ConfiguredProvidersLabel>,
     skip_incompatible: bool,
 ) -> anyhow::Result<
     Either<StarlarkAnalysisResult, Vec<(ConfiguredProvidersLabel, StarlarkAnalysisResult)>>,
@@ -61,3 +65,19 @@ pub(crate) async fn analysis(
         ProvidersExpr::Iterable(_) => Ok(Either::Right(analysis)),
     }
 }
+
+/// Allocates the result of `analysis`, either a single analysis result or a dict keyed by the
+/// analyzed labels.
+pub(crate) fn alloc_analysis_result<'v>(
+    result: Either<StarlarkAnalysisResult, Vec<(ConfiguredProvidersLabel, StarlarkAnalysisResult)>>,
+    heap: &'v Heap,
+) -> anyhow::Result<Value<'v>> {
+    Ok(match result {
+        Either::Left(single) => heap.alloc(single),
+        Either::Right(many) => heap.alloc(Dict::new(
+            many.into_iter()
+                .map(|(t, v)| Ok((heap.alloc(Label::new(t)).get_hashed()?, heap.alloc(v))))
+                .collect::<anyhow::Result<_>>()?,
+        )),
+    })
+}
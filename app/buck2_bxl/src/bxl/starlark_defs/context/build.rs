@@ -9,6 +9,8 @@
 
 //!
 //! Implements the ability for bxl to build targets
+use std::collections::BTreeMap;
+
 use allocative::Allocative;
 use buck2_build_api::build::build_configured_label;
 use buck2_build_api::build::BuildTargetResult;
@@ -20,6 +22,7 @@ use buck2_cli_proto::build_request::Materializations;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
 use buck2_interpreter::types::label::Label;
 use derive_more::Display;
+use dice::DiceComputations;
 use dupe::Dupe;
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
@@ -137,49 +140,65 @@ pub(crate) fn build<'v>(
     let build_spec =
         ProvidersExpr::<ConfiguredProvidersLabel>::unpack(spec, target_platform, ctx, eval)?;
 
+    let build_result = ctx
+        .async_ctx
+        .via_dice(async move |dice| build_labels(dice, ctx, &build_spec, materializations).await)?;
+
+    alloc_build_result(build_result, eval.heap())
+}
+
+/// Builds the given labels, materializing their outputs as configured.
+pub(crate) async fn build_labels(
+    dice: &DiceComputations,
+    ctx: &BxlContext<'_>,
+    build_spec: &ProvidersExpr<ConfiguredProvidersLabel>,
+    materializations: Materializations,
+) -> anyhow::Result<BTreeMap<ConfiguredProvidersLabel, Option<BuildTargetResult>>> {
     let materializations =
         ConvertMaterializationContext::with_existing_map(materializations, &ctx.materializations);
+    let materializations = &materializations;
+
+    let stream = build_spec
+        .labels()
+        .unique()
+        .map(|target| async move {
+            let res = build_configured_label(
+                dice,
+                materializations,
+                target.clone(),
+                &ProvidersToBuild {
+                    default: true,
+                    default_other: true,
+                    run: true,
+                    tests: true,
+                }, // TODO support skipping/configuring?
+                false,
+            )
+            .await;
+
+            match res {
+                Ok(stream) => stream.map(Ok).left_stream(),
+                Err(e) => futures::stream::once(futures::future::ready(Err(e))).right_stream(),
+            }
+        })
+        .collect::<FuturesUnordered<_>>()
+        .flatten_unordered(None);
 
-    let build_result = ctx.async_ctx.via_dice(async move |dice| {
-        let materializations = &materializations;
-
-        let stream = build_spec
-            .labels()
-            .unique()
-            .map(|target| async move {
-                let res = build_configured_label(
-                    dice,
-                    materializations,
-                    target.clone(),
-                    &ProvidersToBuild {
-                        default: true,
-                        default_other: true,
-                        run: true,
-                        tests: true,
-                    }, // TODO support skipping/configuring?
-                    false,
-                )
-                .await;
-
-                match res {
-                    Ok(stream) => stream.map(Ok).left_stream(),
-                    Err(e) => futures::stream::once(futures::future::ready(Err(e))).right_stream(),
-                }
-            })
-            .collect::<FuturesUnordered<_>>()
-            .flatten_unordered(None);
-
-        // TODO (torozco): support --fail-fast in BXL.
-        BuildTargetResult::collect_stream(stream, false).await
-    })?;
+    // TODO (torozco): support --fail-fast in BXL.
+    BuildTargetResult::collect_stream(stream, false).await
+}
 
+/// Allocates the result of `build_labels` as a dict keyed by the built labels.
+pub(crate) fn alloc_build_result<'v>(
+    build_result: BTreeMap<ConfiguredProvidersLabel, Option<BuildTargetResult>>,
+    heap: &'v Heap,
+) -> anyhow::Result<SmallMap<Value<'v>, Value<'v>>> {
     build_result
         .into_iter()
         .map(|(target, result)| {
             Ok((
-                eval.heap().alloc(Label::new(target)).get_hashed().unwrap(),
-                eval.heap()
-                    .alloc(StarlarkBxlBuildResult(BxlBuildResult::new(result))),
+                heap.alloc(Label::new(target)).get_hashed().unwrap(),
+                heap.alloc(StarlarkBxlBuildResult(BxlBuildResult::new(result))),
             ))
         })
         .collect()
@@ -0,0 +1,882 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Programmatic edits of build files, for codemods.
+//!
+//! Edits are made on the source text of the build files rather than on a reformatted AST, so
+//! everything that is not edited, including formatting and comments, is kept as is.
+
+use std::cell::RefCell;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use allocative::Allocative;
+use buck2_common::dice::file_ops::HasFileOps;
+use buck2_common::file_ops::FileOps;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_node::nodes::unconfigured::TargetNode;
+use derivative::Derivative;
+use derive_more::Display;
+use starlark::any::ProvidesStaticType;
+use starlark::collections::SmallMap;
+use starlark::environment::Methods;
+use starlark::environment::MethodsBuilder;
+use starlark::environment::MethodsStatic;
+use starlark::starlark_module;
+use starlark::starlark_type;
+use starlark::syntax::AstModule;
+use starlark::syntax::AstTopLevelCall;
+use starlark::syntax::Dialect;
+use starlark::values::none::NoneType;
+use starlark::values::type_repr::StarlarkTypeRepr;
+use starlark::values::AllocValue;
+use starlark::values::Heap;
+use starlark::values::NoSerialize;
+use starlark::values::StarlarkValue;
+use starlark::values::Trace;
+use starlark::values::UnpackValue;
+use starlark::values::Value;
+use starlark::values::ValueLike;
+use starlark::StarlarkDocs;
+
+use crate::bxl::starlark_defs::context::starlark_async::BxlSafeDiceComputations;
+use crate::bxl::starlark_defs::nodes::unconfigured::StarlarkTargetNode;
+
+/// Lines of unchanged context around the changes in patches.
+const PATCH_CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, thiserror::Error)]
+enum BuildFileEditsError {
+    #[error("Could not find the declaration of target `{0}` in `{1}`")]
+    TargetNotFound(String, String),
+    #[error("Attribute `{0}` of target `{1}` is not a list literal")]
+    NotAList(String, String),
+    #[error("A target named `{0}` is already declared in `{1}`")]
+    TargetAlreadyExists(String, String),
+    #[error("New targets must have a string `name` attribute")]
+    MissingName,
+}
+
+/// What is known about where a target is declared before reading its build file.
+struct TargetDeclaration {
+    name: String,
+    /// The 0-based line of the top level call in the build file that declared the target, from
+    /// the call stack recorded on the target node, if any.
+    line: Option<usize>,
+}
+
+impl TargetDeclaration {
+    fn new(node: &TargetNode) -> Self {
+        Self {
+            name: node.label().name().to_string(),
+            line: node.call_stack().and_then(|s| call_stack_line(&s)),
+        }
+    }
+}
+
+/// The line of the outermost frame of a call stack, formatted like `  * path/BUCK:12, in <module>`,
+/// which is the call at the top level of the build file.
+fn call_stack_line(call_stack: &str) -> Option<usize> {
+    let frame = call_stack
+        .lines()
+        .find_map(|l| l.trim_start().strip_prefix("* "))?;
+    let location = frame.rsplit_once(", in ")?.0;
+    let line: usize = location.rsplit_once(':')?.1.parse().ok()?;
+    line.checked_sub(1)
+}
+
+fn line_start(text: &str, pos: usize) -> usize {
+    text[..pos].rfind('\n').map_or(0, |i| i + 1)
+}
+
+fn line_end(text: &str, pos: usize) -> usize {
+    text[pos..].find('\n').map_or(text.len(), |i| pos + i)
+}
+
+fn line_of(text: &str, pos: usize) -> usize {
+    text[..pos].matches('\n').count()
+}
+
+fn indent_at(text: &str, pos: usize) -> &str {
+    let start = line_start(text, pos);
+    let line = &text[start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+fn is_blank_or_comment(s: &str) -> bool {
+    let s = s.trim();
+    s.is_empty() || s.starts_with('#')
+}
+
+/// The edit inserting `new` as the last element of a comma separated sequence, like arguments
+/// or list items, following the layout of the existing elements.
+///
+/// `open` is just after the opening bracket, and `close` is the closing bracket.
+fn insert_element(
+    text: &str,
+    open: usize,
+    close: usize,
+    elements: &[Range<usize>],
+    new: &str,
+) -> (Range<usize>, String) {
+    let last = match elements.last() {
+        None => return (close..close, new.to_owned()),
+        Some(last) => last,
+    };
+    if !text[open..last.start].contains('\n') {
+        return (last.end..last.end, format!(", {}", new));
+    }
+
+    // One element per line: add a line with the same indentation after the last element.
+    let indent = indent_at(text, last.start);
+    let after = &text[last.end..close];
+    let trimmed = after.trim_start();
+    if trimmed.starts_with(',') {
+        let comma = last.end + (after.len() - trimmed.len()) + 1;
+        let end = line_end(text, comma);
+        // Keep a comment after the last element on its line.
+        let pos = if end <= close && is_blank_or_comment(&text[comma..end]) {
+            end
+        } else {
+            comma
+        };
+        (pos..pos, format!("\n{}{},", indent, new))
+    } else {
+        (last.end..last.end, format!(",\n{}{}", indent, new))
+    }
+}
+
+/// The range to delete to remove an element of a comma separated sequence, along with its
+/// separator, and its line if it is alone on it.
+fn remove_element(text: &str, elements: &[Range<usize>], index: usize) -> Range<usize> {
+    let element = &elements[index];
+    let after = &text[element.end..];
+    let trimmed = after.trim_start_matches([' ', '\t']);
+    let comma_end = trimmed
+        .starts_with(',')
+        .then(|| element.end + (after.len() - trimmed.len()) + 1);
+    let end = comma_end.unwrap_or(element.end);
+
+    let start_of_line = line_start(text, element.start);
+    let end_of_line = line_end(text, end);
+    if text[start_of_line..element.start].trim().is_empty()
+        && is_blank_or_comment(&text[end..end_of_line])
+    {
+        return start_of_line..std::cmp::min(end_of_line + 1, text.len());
+    }
+
+    match comma_end {
+        Some(end) => {
+            let rest = &text[end..];
+            element.start..end + rest.len() - rest.trim_start_matches([' ', '\t']).len()
+        }
+        None if index > 0 => elements[index - 1].end..element.end,
+        None => element.clone(),
+    }
+}
+
+fn find_by_name(calls: &[AstTopLevelCall], name: &str) -> Option<usize> {
+    calls
+        .iter()
+        .position(|call| call.argument("name").and_then(|a| a.value.string) == Some(name))
+}
+
+fn open_paren(text: &str, call: &AstTopLevelCall) -> usize {
+    call.span.start + text[call.span.start..].find('(').map_or(0, |i| i + 1)
+}
+
+/// The pending edits of a single build file.
+pub(crate) struct BuildFileEditor {
+    /// The project relative path of the file, used in errors and patches.
+    path: String,
+    /// The content the patch is computed against.
+    original: String,
+    current: String,
+}
+
+impl BuildFileEditor {
+    pub(crate) fn new(path: String, content: String) -> Self {
+        Self {
+            path,
+            original: content.clone(),
+            current: content,
+        }
+    }
+
+    fn parse(&self, content: &str) -> anyhow::Result<AstModule> {
+        AstModule::parse(&self.path, content.to_owned(), &Dialect::Extended)
+    }
+
+    /// The index of the call declaring the target among the top level calls of the current
+    /// content.
+    fn find_call(
+        &self,
+        calls: &[AstTopLevelCall],
+        target: &TargetDeclaration,
+    ) -> anyhow::Result<usize> {
+        if let Some(i) = find_by_name(calls, &target.name) {
+            return Ok(i);
+        }
+
+        // The target was declared by a macro, so look for the top level call recorded in its
+        // call stack. The call stack refers to the file before any edit.
+        if let Some(line) = target.line {
+            let original = self.parse(&self.original)?;
+            let original_calls = original.top_level_calls();
+            let call = original_calls.iter().position(|c| {
+                line_of(&self.original, c.span.start) <= line
+                    && line <= line_of(&self.original, c.span.end)
+            });
+            if let Some(i) = call {
+                match original_calls[i]
+                    .argument("name")
+                    .and_then(|a| a.value.string)
+                {
+                    Some(name) => {
+                        if let Some(i) = find_by_name(calls, name) {
+                            return Ok(i);
+                        }
+                    }
+                    None if self.original == self.current => return Ok(i),
+                    None => {}
+                }
+            }
+        }
+
+        Err(BuildFileEditsError::TargetNotFound(target.name.clone(), self.path.clone()).into())
+    }
+
+    /// Replaces the current content with the result of `edit`, which is given the current
+    /// content, all the top level calls, and the call declaring `target`.
+    fn edit(
+        &mut self,
+        target: &TargetDeclaration,
+        edit: impl FnOnce(
+            &str,
+            &[AstTopLevelCall],
+            &AstTopLevelCall,
+        ) -> anyhow::Result<Option<(Range<usize>, String)>>,
+    ) -> anyhow::Result<()> {
+        let module = self.parse(&self.current)?;
+        let calls = module.top_level_calls();
+        let call = &calls[self.find_call(&calls, target)?];
+        if let Some((range, replacement)) = edit(module.source(), &calls, call)? {
+            self.current.replace_range(range, &replacement);
+        }
+        Ok(())
+    }
+
+    /// The 0-based line of the call declaring the target in the current content.
+    fn locate(&self, target: &TargetDeclaration) -> anyhow::Result<usize> {
+        let module = self.parse(&self.current)?;
+        let calls = module.top_level_calls();
+        let call = &calls[self.find_call(&calls, target)?];
+        Ok(line_of(&self.current, call.span.start))
+    }
+
+    fn set_attr(
+        &mut self,
+        target: &TargetDeclaration,
+        attr: &str,
+        value: &str,
+    ) -> anyhow::Result<()> {
+        self.edit(target, |text, _, call| {
+            Ok(Some(match call.argument(attr) {
+                Some(arg) => (arg.value.span.clone(), value.to_owned()),
+                None => insert_element(
+                    text,
+                    open_paren(text, call),
+                    call.span.end - 1,
+                    &call
+                        .arguments
+                        .iter()
+                        .map(|a| a.span.clone())
+                        .collect::<Vec<_>>(),
+                    &format!("{} = {}", attr, value),
+                ),
+            }))
+        })
+    }
+
+    fn remove_attr(&mut self, target: &TargetDeclaration, attr: &str) -> anyhow::Result<()> {
+        self.edit(target, |text, _, call| {
+            let arguments: Vec<_> = call.arguments.iter().map(|a| a.span.clone()).collect();
+            Ok(call
+                .arguments
+                .iter()
+                .position(|a| a.name == Some(attr))
+                .map(|i| (remove_element(text, &arguments, i), String::new())))
+        })
+    }
+
+    /// Adds `value` to the list attribute `attr`, unless it is a string already in the list.
+    fn add_attr_value(
+        &mut self,
+        target: &TargetDeclaration,
+        attr: &str,
+        value: &str,
+        string: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let list = match self.list_items(target, attr)? {
+            None => return self.set_attr(target, attr, &format!("[{}]", value)),
+            Some(list) => list,
+        };
+        if string.is_some() && list.iter().any(|(_, s)| s.as_deref() == string) {
+            return Ok(());
+        }
+        self.edit(target, |text, _, call| {
+            let list = &call.argument(attr).unwrap().value.span;
+            Ok(Some(insert_element(
+                text,
+                list.start + 1,
+                list.end - 1,
+                &list_ranges(call, attr),
+                value,
+            )))
+        })
+    }
+
+    /// Removes the string `value` from the list attribute `attr`, if it is there.
+    fn remove_attr_value(
+        &mut self,
+        target: &TargetDeclaration,
+        attr: &str,
+        value: &str,
+    ) -> anyhow::Result<()> {
+        let index = match self.list_items(target, attr)? {
+            None => return Ok(()),
+            Some(list) => match list.iter().position(|(_, s)| s.as_deref() == Some(value)) {
+                None => return Ok(()),
+                Some(index) => index,
+            },
+        };
+        self.edit(target, |text, _, call| {
+            Ok(Some((
+                remove_element(text, &list_ranges(call, attr), index),
+                String::new(),
+            )))
+        })
+    }
+
+    /// The items of the list attribute `attr` and their values if they are strings, or `None` if
+    /// the attribute is not set.
+    fn list_items(
+        &self,
+        target: &TargetDeclaration,
+        attr: &str,
+    ) -> anyhow::Result<Option<Vec<(Range<usize>, Option<String>)>>> {
+        let module = self.parse(&self.current)?;
+        let calls = module.top_level_calls();
+        let call = &calls[self.find_call(&calls, target)?];
+        match call.argument(attr) {
+            None => Ok(None),
+            Some(arg) => match &arg.value.list {
+                None => {
+                    Err(BuildFileEditsError::NotAList(attr.to_owned(), target.name.clone()).into())
+                }
+                Some(items) => Ok(Some(
+                    items
+                        .iter()
+                        .map(|i| (i.span.clone(), i.string.map(str::to_owned)))
+                        .collect(),
+                )),
+            },
+        }
+    }
+
+    /// Inserts a call to `rule` declaring the target `name` with the given attributes, which
+    /// include `name`, after the call declaring `after`.
+    fn insert_target(
+        &mut self,
+        after: &TargetDeclaration,
+        rule: &str,
+        name: &str,
+        attrs: &[(String, String)],
+    ) -> anyhow::Result<()> {
+        let path = self.path.clone();
+        self.edit(after, |text, calls, call| {
+            if find_by_name(calls, name).is_some() {
+                return Err(BuildFileEditsError::TargetAlreadyExists(name.to_owned(), path).into());
+            }
+            let pos = line_end(text, call.span.end);
+            let mut new = format!("\n\n{}(\n", rule);
+            for (attr, value) in attrs {
+                new.push_str(&format!("    {} = {},\n", attr, value));
+            }
+            new.push(')');
+            Ok(Some((pos..pos, new)))
+        })
+    }
+
+    fn patch(&self) -> String {
+        unified_diff(&self.path, &self.original, &self.current)
+    }
+}
+
+fn list_ranges(call: &AstTopLevelCall, attr: &str) -> Vec<Range<usize>> {
+    call.argument(attr)
+        .and_then(|a| a.value.list.as_ref())
+        .map(|items| items.iter().map(|i| i.span.clone()).collect())
+        .unwrap_or_default()
+}
+
+fn hunk_range(start: usize, len: usize) -> String {
+    if len == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, len)
+    }
+}
+
+/// A patch in the unified format, with a single hunk covering all the changed lines.
+fn unified_diff(path: &str, old: &str, new: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let prefix = old_lines
+        .iter()
+        .zip(&new_lines)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let start = prefix - std::cmp::min(prefix, PATCH_CONTEXT_LINES);
+    let old_end = old_lines.len() - suffix;
+    let new_end = new_lines.len() - suffix;
+    let context_end = old_end + std::cmp::min(suffix, PATCH_CONTEXT_LINES);
+
+    let mut patch = format!(
+        "--- a/{path}\n+++ b/{path}\n@@ -{} +{} @@\n",
+        hunk_range(start, context_end - start),
+        hunk_range(start, new_end + (context_end - old_end) - start),
+        path = path,
+    );
+    let mut push = |sign: char, line: &str| {
+        patch.push(sign);
+        patch.push_str(line);
+        if !line.ends_with('\n') {
+            patch.push_str("\n\\ No newline at end of file\n");
+        }
+    };
+    old_lines[start..prefix].iter().for_each(|l| push(' ', l));
+    old_lines[prefix..old_end].iter().for_each(|l| push('-', l));
+    new_lines[prefix..new_end].iter().for_each(|l| push('+', l));
+    old_lines[old_end..context_end]
+        .iter()
+        .for_each(|l| push(' ', l));
+    patch
+}
+
+/// Renders a Starlark value as source code, along with its value if it is a string.
+fn render<'v>(value: Value<'v>) -> (String, Option<&'v str>) {
+    (value.to_repr(), value.unpack_str())
+}
+
+#[derive(
+    ProvidesStaticType,
+    Derivative,
+    Display,
+    Trace,
+    NoSerialize,
+    StarlarkDocs,
+    Allocative
+)]
+#[derivative(Debug)]
+#[starlark_docs(directory = "bxl")]
+#[display(fmt = "build_file_edits")]
+#[allocative(skip)]
+pub struct StarlarkBuildFileEdits<'v> {
+    #[trace(unsafe_ignore)]
+    #[derivative(Debug = "ignore")]
+    dice: &'v BxlSafeDiceComputations<'v>,
+    #[trace(unsafe_ignore)]
+    #[derivative(Debug = "ignore")]
+    project_fs: &'v ProjectRoot,
+    #[trace(unsafe_ignore)]
+    #[derivative(Debug = "ignore")]
+    artifact_fs: &'v ArtifactFs,
+    #[trace(unsafe_ignore)]
+    #[derivative(Debug = "ignore")]
+    files: RefCell<BTreeMap<ProjectRelativePathBuf, BuildFileEditor>>,
+}
+
+impl<'v> StarlarkBuildFileEdits<'v> {
+    pub(crate) fn new(
+        dice: &'v BxlSafeDiceComputations<'v>,
+        project_fs: &'v ProjectRoot,
+        artifact_fs: &'v ArtifactFs,
+    ) -> Self {
+        Self {
+            dice,
+            project_fs,
+            artifact_fs,
+            files: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// Runs `f` with the editor of the build file of the target, reading the file if it was not
+    /// edited yet.
+    fn with_editor<R>(
+        &self,
+        target: &TargetNode,
+        f: impl FnOnce(&mut BuildFileEditor, &TargetDeclaration) -> anyhow::Result<R>,
+    ) -> anyhow::Result<R> {
+        let cell_path = target.buildfile_path().path();
+        let path = self.artifact_fs.resolve_cell_path(cell_path.as_ref())?;
+        let mut files = self.files.borrow_mut();
+        let editor = match files.entry(path) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => {
+                let content = self.dice.via_dice(async move |ctx| {
+                    <dyn FileOps>::read_file(&ctx.file_ops(), cell_path.as_ref()).await
+                })?;
+                let path = e.key().to_string();
+                e.insert(BuildFileEditor::new(path, content))
+            }
+        };
+        f(editor, &TargetDeclaration::new(target))
+    }
+}
+
+impl<'v> StarlarkValue<'v> for StarlarkBuildFileEdits<'v> {
+    starlark_type!("build_file_edits");
+
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(build_file_edits_methods)
+    }
+}
+
+impl<'v> AllocValue<'v> for StarlarkBuildFileEdits<'v> {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc_complex_no_freeze(self)
+    }
+}
+
+impl<'v> StarlarkTypeRepr for &'v StarlarkBuildFileEdits<'v> {
+    fn starlark_type_repr() -> String {
+        StarlarkBuildFileEdits::get_type_starlark_repr()
+    }
+}
+
+impl<'v> UnpackValue<'v> for &'v StarlarkBuildFileEdits<'v> {
+    fn unpack_value(x: Value<'v>) -> Option<&'v StarlarkBuildFileEdits<'v>> {
+        x.downcast_ref()
+    }
+}
+
+/// Edits of build files, made on their source so that formatting and comments are kept.
+///
+/// Targets are located in their build file by their `name` attribute, or for targets declared by
+/// macros, by the call stack recorded when the build file was evaluated (see
+/// `buck2.record_target_call_stacks`). For the latter, the edits apply to the macro call.
+///
+/// Attribute values are given as Starlark values, written to the build file as their `repr`.
+/// Edits are kept in memory until `apply` is called.
+#[starlark_module]
+fn build_file_edits_methods(builder: &mut MethodsBuilder) {
+    /// Returns the location, as `path:line`, of the call declaring the unconfigured target node
+    /// in its build file, including the pending edits.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_locate(ctx):
+    ///     edits = ctx.fs.edit_build_files()
+    ///     ctx.output.print(edits.locate(ctx.uquery().eval("//foo:bar")[0]))
+    /// ```
+    fn locate<'v>(
+        this: &StarlarkBuildFileEdits<'v>,
+        target: StarlarkTargetNode,
+    ) -> anyhow::Result<String> {
+        this.with_editor(&target.0, |editor, target| {
+            Ok(format!("{}:{}", editor.path, editor.locate(target)? + 1))
+        })
+    }
+
+    /// Sets the attribute of the target to the value, adding the attribute if it is not set.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_set_attr(ctx):
+    ///     edits = ctx.fs.edit_build_files()
+    ///     edits.set_attr(ctx.uquery().eval("//foo:bar")[0], "visibility", ["PUBLIC"])
+    ///     ctx.output.print(edits.patch())
+    /// ```
+    fn set_attr<'v>(
+        this: &StarlarkBuildFileEdits<'v>,
+        target: StarlarkTargetNode,
+        attr: &str,
+        value: Value<'v>,
+    ) -> anyhow::Result<NoneType> {
+        let (value, _) = render(value);
+        this.with_editor(&target.0, |editor, target| {
+            editor.set_attr(target, attr, &value)
+        })?;
+        Ok(NoneType)
+    }
+
+    /// Removes the attribute from the target. Does nothing if the attribute is not set.
+    fn remove_attr<'v>(
+        this: &StarlarkBuildFileEdits<'v>,
+        target: StarlarkTargetNode,
+        attr: &str,
+    ) -> anyhow::Result<NoneType> {
+        this.with_editor(&target.0, |editor, target| editor.remove_attr(target, attr))?;
+        Ok(NoneType)
+    }
+
+    /// Appends the value to the list attribute of the target, setting the attribute to a list of
+    /// the value if it is not set. Strings already in the list are not added again.
+    ///
+    /// Errors if the attribute is not a list literal, e.g. a `select` or a concatenation.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_add_attr_value(ctx):
+    ///     edits = ctx.fs.edit_build_files()
+    ///     for target in ctx.uquery().eval("rdeps(//..., //old:lib, 1)"):
+    ///         edits.add_attr_value(target, "deps", "//new:lib")
+    ///         edits.remove_attr_value(target, "deps", "//old:lib")
+    ///     edits.apply()
+    /// ```
+    fn add_attr_value<'v>(
+        this: &StarlarkBuildFileEdits<'v>,
+        target: StarlarkTargetNode,
+        attr: &str,
+        value: Value<'v>,
+    ) -> anyhow::Result<NoneType> {
+        let (rendered, string) = render(value);
+        this.with_editor(&target.0, |editor, target| {
+            editor.add_attr_value(target, attr, &rendered, string)
+        })?;
+        Ok(NoneType)
+    }
+
+    /// Removes the string from the list attribute of the target. Does nothing if the string is
+    /// not in the list.
+    ///
+    /// Errors if the attribute is not a list literal, e.g. a `select` or a concatenation.
+    fn remove_attr_value<'v>(
+        this: &StarlarkBuildFileEdits<'v>,
+        target: StarlarkTargetNode,
+        attr: &str,
+        value: &str,
+    ) -> anyhow::Result<NoneType> {
+        this.with_editor(&target.0, |editor, target| {
+            editor.remove_attr_value(target, attr, value)
+        })?;
+        Ok(NoneType)
+    }
+
+    /// Inserts a call to `rule` declaring a new target after the call declaring `after`, in the
+    /// same build file. The keyword arguments are the attributes of the new target, and must
+    /// include `name`.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_insert_target(ctx):
+    ///     edits = ctx.fs.edit_build_files()
+    ///     lib = ctx.uquery().eval("//foo:lib")[0]
+    ///     edits.insert_target(lib, "cxx_test", name = "lib_test", deps = [":lib"])
+    ///     ctx.output.print(edits.patch())
+    /// ```
+    fn insert_target<'v>(
+        this: &StarlarkBuildFileEdits<'v>,
+        after: StarlarkTargetNode,
+        rule: &str,
+        #[starlark(kwargs)] attrs: SmallMap<String, Value<'v>>,
+    ) -> anyhow::Result<NoneType> {
+        let name = attrs
+            .get("name")
+            .and_then(|v| v.unpack_str())
+            .ok_or(BuildFileEditsError::MissingName)?;
+        let attrs: Vec<_> = attrs
+            .iter()
+            .map(|(k, v)| (k.clone(), render(*v).0))
+            .collect();
+        this.with_editor(&after.0, |editor, after| {
+            editor.insert_target(after, rule, name, &attrs)
+        })?;
+        Ok(NoneType)
+    }
+
+    /// Returns the pending edits of all the build files as a patch in the unified format, which
+    /// can be applied with `patch -p1` from the project root.
+    fn patch<'v>(this: &StarlarkBuildFileEdits<'v>) -> anyhow::Result<String> {
+        Ok(this
+            .files
+            .borrow()
+            .values()
+            .map(BuildFileEditor::patch)
+            .collect())
+    }
+
+    /// Writes the pending edits to the build files. Later patches only include later edits.
+    fn apply<'v>(this: &StarlarkBuildFileEdits<'v>) -> anyhow::Result<NoneType> {
+        for (path, editor) in this.files.borrow_mut().iter_mut() {
+            if editor.original != editor.current {
+                fs_util::write(this.project_fs.resolve(path), &editor.current)?;
+                editor.original = editor.current.clone();
+            }
+        }
+        Ok(NoneType)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(name: &str) -> TargetDeclaration {
+        TargetDeclaration {
+            name: name.to_owned(),
+            line: None,
+        }
+    }
+
+    const BUCK: &str = r#"load(":defs.bzl", "my_macro")
+
+# The library.
+cxx_library(
+    name = "lib",
+    srcs = [
+        "a.cpp",  # Comment on a.
+        "b.cpp",
+    ],
+    deps = [":base"],
+    visibility = ["PUBLIC"],  # Comment on visibility.
+)
+
+my_macro(name = "macro", x = 1)
+"#;
+
+    #[test]
+    fn test_call_stack_line() {
+        let call_stack = "Traceback (most recent call last):\n  * root//foo/BUCK:14, in <module>\n    my_macro(name = \"macro\")\n  * root//foo/defs.bzl:3, in my_macro\n    cxx_library(\n";
+        assert_eq!(Some(13), call_stack_line(call_stack));
+        assert_eq!(None, call_stack_line(""));
+    }
+
+    #[test]
+    fn test_locate() -> anyhow::Result<()> {
+        let editor = BuildFileEditor::new("foo/BUCK".to_owned(), BUCK.to_owned());
+        assert_eq!(3, editor.locate(&target("lib"))?);
+        let generated = TargetDeclaration {
+            name: "macro_gen".to_owned(),
+            line: Some(13),
+        };
+        assert_eq!(13, editor.locate(&generated)?);
+        assert!(editor.locate(&target("missing")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_and_remove_attr() -> anyhow::Result<()> {
+        let mut editor = BuildFileEditor::new("foo/BUCK".to_owned(), BUCK.to_owned());
+        editor.set_attr(&target("lib"), "deps", "[]")?;
+        editor.set_attr(&target("lib"), "link_style", "\"static\"")?;
+        editor.set_attr(&target("macro"), "y", "2")?;
+        editor.remove_attr(&target("lib"), "srcs")?;
+        editor.remove_attr(&target("macro"), "x")?;
+        editor.remove_attr(&target("macro"), "missing")?;
+        assert_eq!(
+            r#"load(":defs.bzl", "my_macro")
+
+# The library.
+cxx_library(
+    name = "lib",
+    deps = [],
+    visibility = ["PUBLIC"],  # Comment on visibility.
+    link_style = "static",
+)
+
+my_macro(name = "macro", y = 2)
+"#,
+            editor.current
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_values() -> anyhow::Result<()> {
+        let mut editor = BuildFileEditor::new("foo/BUCK".to_owned(), BUCK.to_owned());
+        editor.add_attr_value(&target("lib"), "srcs", "\"c.cpp\"", Some("c.cpp"))?;
+        editor.add_attr_value(&target("lib"), "srcs", "\"b.cpp\"", Some("b.cpp"))?;
+        editor.remove_attr_value(&target("lib"), "srcs", "a.cpp")?;
+        editor.add_attr_value(&target("lib"), "deps", "\":other\"", Some(":other"))?;
+        editor.remove_attr_value(&target("lib"), "deps", ":base")?;
+        editor.add_attr_value(&target("macro"), "tags", "\"t\"", Some("t"))?;
+        assert!(
+            editor
+                .add_attr_value(&target("macro"), "x", "\"t\"", Some("t"))
+                .is_err()
+        );
+        assert_eq!(
+            r#"load(":defs.bzl", "my_macro")
+
+# The library.
+cxx_library(
+    name = "lib",
+    srcs = [
+        "b.cpp",
+        "c.cpp",
+    ],
+    deps = [":other"],
+    visibility = ["PUBLIC"],  # Comment on visibility.
+)
+
+my_macro(name = "macro", x = 1, tags = ["t"])
+"#,
+            editor.current
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_target_and_patch() -> anyhow::Result<()> {
+        let mut editor = BuildFileEditor::new("foo/BUCK".to_owned(), BUCK.to_owned());
+        let attrs = vec![
+            ("name".to_owned(), "\"lib_test\"".to_owned()),
+            ("deps".to_owned(), "[\":lib\"]".to_owned()),
+        ];
+        editor.insert_target(&target("lib"), "cxx_test", "lib_test", &attrs)?;
+        assert!(
+            editor
+                .insert_target(&target("lib"), "cxx_test", "lib_test", &attrs)
+                .is_err()
+        );
+        let expected = [
+            "--- a/foo/BUCK",
+            "+++ b/foo/BUCK",
+            "@@ -11,4 +11,9 @@",
+            "     visibility = [\"PUBLIC\"],  # Comment on visibility.",
+            " )",
+            " ",
+            "+cxx_test(",
+            "+    name = \"lib_test\",",
+            "+    deps = [\":lib\"],",
+            "+)",
+            "+",
+            " my_macro(name = \"macro\", x = 1)",
+            "",
+        ];
+        assert_eq!(expected.join("\n"), editor.patch());
+        Ok(())
+    }
+}
@@ -7,15 +7,27 @@
  * of this source tree.
  */
 
+use std::sync::Arc;
+
 use allocative::Allocative;
+use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::dice::file_ops::HasFileOps;
+use buck2_common::file_ops::FileDigest;
 use buck2_common::file_ops::FileOps;
+use buck2_common::file_ops::RawPathMetadata;
+use buck2_common::find_buildfile::find_buildfile;
+use buck2_common::package_boundary::HasPackageBoundaryExceptions;
+use buck2_core::cells::cell_path::CellPath;
 use buck2_core::cells::instance::CellInstance;
 use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::project::ProjectRoot;
+use buck2_execute::digest_config::HasDigestConfig;
+use buck2_interpreter::globspec::GlobSpec;
 use derivative::Derivative;
 use derive_more::Display;
+use dice::DiceComputations;
+use futures::future;
 use starlark::any::ProvidesStaticType;
 use starlark::environment::Methods;
 use starlark::environment::MethodsBuilder;
@@ -33,9 +45,18 @@ use starlark::values::Value;
 use starlark::values::ValueLike;
 use starlark::StarlarkDocs;
 
+use crate::bxl::starlark_defs::context::build_file_edits::StarlarkBuildFileEdits;
 use crate::bxl::starlark_defs::context::starlark_async::BxlSafeDiceComputations;
 use crate::bxl::starlark_defs::file_expr::FileExpr;
+use crate::bxl::starlark_defs::file_set::StarlarkFileNode;
 use crate::bxl::starlark_defs::file_set::StarlarkReadDirSet;
+use crate::bxl::starlark_defs::testing::MockedFileSystem;
+
+#[derive(Debug, thiserror::Error)]
+enum BxlFilesystemError {
+    #[error("Expected `{0}` to be a file")]
+    NotAFile(CellPath),
+}
 
 #[derive(
     ProvidesStaticType,
@@ -62,6 +83,10 @@ pub struct BxlFilesystem<'v> {
     artifact_fs: &'v ArtifactFs,
     #[trace(unsafe_ignore)]
     cell: &'v CellInstance,
+    /// Set when running a `bxl_test` that mocks the file system.
+    #[trace(unsafe_ignore)]
+    #[derivative(Debug = "ignore")]
+    mocked: Option<Arc<MockedFileSystem>>,
 }
 
 impl<'v> BxlFilesystem<'v> {
@@ -70,12 +95,14 @@ impl<'v> BxlFilesystem<'v> {
         project_fs: &'v ProjectRoot,
         artifact_fs: &'v ArtifactFs,
         cell: &'v CellInstance,
+        mocked: Option<Arc<MockedFileSystem>>,
     ) -> Self {
         Self {
             dice,
             project_fs,
             artifact_fs,
             cell,
+            mocked,
         }
     }
 }
@@ -122,11 +149,12 @@ fn fs_operations(builder: &mut MethodsBuilder) {
     fn exists<'v>(this: &BxlFilesystem<'v>, expr: FileExpr<'v>) -> anyhow::Result<bool> {
         let path = expr.get(this.dice, this.cell);
 
-        match path {
-            Ok(p) => this.dice.via_dice(async move |ctx| {
+        match (path, &this.mocked) {
+            (Ok(p), Some(mocked)) => Ok(mocked.exists(&p)),
+            (Ok(p), None) => this.dice.via_dice(async move |ctx| {
                 <dyn FileOps>::try_exists(&ctx.file_ops(), p.as_ref()).await
             }),
-            Err(e) => Err(e),
+            (Err(e), _) => Err(e),
         }
     }
 
@@ -149,8 +177,13 @@ fn fs_operations(builder: &mut MethodsBuilder) {
     ) -> anyhow::Result<StarlarkReadDirSet> {
         let path = expr.get(this.dice, this.cell);
 
-        match path {
-            Ok(path) => this.dice.via_dice(async move |ctx| {
+        match (path, &this.mocked) {
+            (Ok(path), Some(mocked)) => Ok(StarlarkReadDirSet {
+                included: mocked.list(&path)?,
+                cell_path: path,
+                dirs_only,
+            }),
+            (Ok(path), None) => this.dice.via_dice(async move |ctx| {
                 let read_dir_output = ctx.file_ops().read_dir(path.as_ref()).await?;
                 Ok(StarlarkReadDirSet {
                     cell_path: path,
@@ -158,7 +191,7 @@ fn fs_operations(builder: &mut MethodsBuilder) {
                     dirs_only,
                 })
             }),
-            Err(e) => Err(e),
+            (Err(e), _) => Err(e),
         }
     }
 
@@ -171,6 +204,9 @@ fn fs_operations(builder: &mut MethodsBuilder) {
     ///     ctx.output.print(ctx.fs.is_dir("bin"))
     /// ```
     fn is_dir<'v>(this: &BxlFilesystem<'v>, expr: FileExpr<'v>) -> anyhow::Result<bool> {
+        if let Some(mocked) = &this.mocked {
+            return Ok(mocked.is_dir(&expr.get(this.dice, this.cell)?));
+        }
         Ok(std::path::Path::is_dir(resolve(this, expr)?.as_ref()))
     }
 
@@ -183,8 +219,159 @@ fn fs_operations(builder: &mut MethodsBuilder) {
     ///     ctx.output.print(ctx.fs.is_dir("bin"))
     /// ```
     fn is_file<'v>(this: &BxlFilesystem<'v>, expr: FileExpr<'v>) -> anyhow::Result<bool> {
+        if let Some(mocked) = &this.mocked {
+            return Ok(mocked.is_file(&expr.get(this.dice, this.cell)?));
+        }
         Ok(std::path::Path::is_file(resolve(this, expr)?.as_ref()))
     }
+
+    /// Returns the contents of the given file as a string. The file is tracked like the sources
+    /// of the build, so a cached result of the bxl function is invalidated when it changes.
+    /// In a `bxl_test` with `mock_files`, returns the mocked contents.
+    /// The input is a either a literal, a source artifact (via `[StarlarkArtifact]`), or a `[StarlarkFileNode]`.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_read(ctx):
+    ///     ctx.output.print(ctx.fs.read("foo/Cargo.toml"))
+    /// ```
+    fn read<'v>(this: &BxlFilesystem<'v>, expr: FileExpr<'v>) -> anyhow::Result<String> {
+        let path = expr.get(this.dice, this.cell)?;
+        if let Some(mocked) = &this.mocked {
+            return Ok(mocked.read(&path)?.to_owned());
+        }
+        this.dice.via_dice(async move |ctx| {
+            <dyn FileOps>::read_file(&ctx.file_ops(), path.as_ref()).await
+        })
+    }
+
+    /// Returns the files under the directory `root` matching the `include` glob patterns and
+    /// none of the `exclude` ones, e.g. `**/Cargo.toml`, as a sorted list of `[StarlarkFileNode]`.
+    /// Like globs in build files, files ignored by the buckconfig are skipped and the glob does
+    /// not cross into other packages, except for the `project.package_boundary_exceptions`.
+    /// The input is a either a literal, a source artifact (via `[StarlarkArtifact]`), or a `[StarlarkFileNode]`.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_glob(ctx):
+    ///     for manifest in ctx.fs.glob("foo", ["**/package.json"], exclude = ["node_modules/**"]):
+    ///         ctx.output.print(ctx.fs.read(manifest))
+    /// ```
+    fn glob<'v>(
+        this: &BxlFilesystem<'v>,
+        root: FileExpr<'v>,
+        include: Vec<&str>,
+        #[starlark(require = named, default = Vec::new())] exclude: Vec<&str>,
+    ) -> anyhow::Result<Vec<StarlarkFileNode>> {
+        let root = root.get(this.dice, this.cell)?;
+        let spec = GlobSpec::new(&include, &exclude)?;
+        let files = match &this.mocked {
+            Some(mocked) => mocked.glob(&root, &spec)?,
+            None => this
+                .dice
+                .via_dice(async move |ctx| glob_files(ctx, root, &spec).await)?,
+        };
+        Ok(files.into_iter().map(StarlarkFileNode).collect())
+    }
+
+    /// Returns the hex-encoded digest of the contents of the given file, as used to store it in
+    /// the CAS. Like `read`, the file is tracked for invalidation.
+    /// The input is a either a literal, a source artifact (via `[StarlarkArtifact]`), or a `[StarlarkFileNode]`.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_digest(ctx):
+    ///     ctx.output.print(ctx.fs.digest("foo/Cargo.lock"))
+    /// ```
+    fn digest<'v>(this: &BxlFilesystem<'v>, expr: FileExpr<'v>) -> anyhow::Result<String> {
+        let path = expr.get(this.dice, this.cell)?;
+        if let Some(mocked) = &this.mocked {
+            let content = mocked.read(&path)?;
+            let digest_config = this
+                .dice
+                .via_dice(async move |ctx| Ok(ctx.global_data().get_digest_config()))?;
+            return Ok(FileDigest::from_content(
+                content.as_bytes(),
+                digest_config.cas_digest_config(),
+            )
+            .raw_digest()
+            .to_string());
+        }
+        this.dice.via_dice(async move |ctx| {
+            match <dyn FileOps>::read_path_metadata(&ctx.file_ops(), path.as_ref()).await? {
+                RawPathMetadata::File(metadata) => Ok(metadata.digest.raw_digest().to_string()),
+                _ => Err(BxlFilesystemError::NotAFile(path).into()),
+            }
+        })
+    }
+
+    /// Returns a new `build_file_edits` object to programmatically edit the build files
+    /// declaring targets, e.g. for codemods. Edits preserve the formatting and comments of the
+    /// build files, and can be emitted as a patch or applied.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_edit_build_files(ctx):
+    ///     edits = ctx.fs.edit_build_files()
+    ///     for target in ctx.uquery().eval("kind(cxx_library, //foo/...)"):
+    ///         edits.set_attr(target, "visibility", ["PUBLIC"])
+    ///     ctx.output.print(edits.patch())
+    /// ```
+    fn edit_build_files<'v>(
+        this: &BxlFilesystem<'v>,
+    ) -> anyhow::Result<StarlarkBuildFileEdits<'v>> {
+        Ok(StarlarkBuildFileEdits::new(
+            this.dice,
+            this.project_fs,
+            this.artifact_fs,
+        ))
+    }
+}
+
+/// Walks `root` to find the files matching `spec`, tracking the read directories in DICE. Only
+/// the directories which the literal prefixes of the patterns lead to are walked.
+async fn glob_files(
+    ctx: &DiceComputations,
+    root: CellPath,
+    spec: &GlobSpec,
+) -> anyhow::Result<Vec<CellPath>> {
+    let cell_resolver = ctx.get_cell_resolver().await?;
+    let buildfiles = cell_resolver.get(root.cell())?.buildfiles();
+    let file_ops = ctx.file_ops();
+
+    let mut files = Vec::new();
+    let mut dirs = vec![root.clone()];
+    while !dirs.is_empty() {
+        let listings = future::try_join_all(
+            dirs.iter()
+                .map(|dir| <dyn FileOps>::read_dir(&file_ops, dir.as_ref())),
+        )
+        .await?;
+
+        let mut subdirs = Vec::new();
+        for (dir, listing) in dirs.iter().zip(listings) {
+            if dir != &root
+                && find_buildfile(buildfiles, &listing.included).is_some()
+                && !ctx.get_package_boundary_exception(dir.as_ref()).await?
+            {
+                continue;
+            }
+            for entry in listing.included.iter() {
+                let path = dir.join(&entry.file_name);
+                if entry.file_type.is_dir() {
+                    if spec.may_match_in_dir(path.strip_prefix(root.as_ref())?.as_str()) {
+                        subdirs.push(path);
+                    }
+                } else if spec.matches(path.strip_prefix(root.as_ref())?.as_str()) {
+                    files.push(path);
+                }
+            }
+        }
+        dirs = subdirs;
+    }
+
+    files.sort();
+    Ok(files)
 }
 
 /// Returns the absolute path for a FileExpr.
@@ -0,0 +1,200 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Deferred analysis and builds, so that bxl functions can run many of them concurrently while
+//! keeping a synchronous style.
+
+use std::collections::BTreeMap;
+
+use allocative::Allocative;
+use buck2_build_api::build::BuildTargetResult;
+use buck2_cli_proto::build_request::Materializations;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
+use derivative::Derivative;
+use derive_more::Display;
+use dice::DiceComputations;
+use either::Either;
+use starlark::any::ProvidesStaticType;
+use starlark::environment::Methods;
+use starlark::environment::MethodsBuilder;
+use starlark::environment::MethodsStatic;
+use starlark::eval::Evaluator;
+use starlark::starlark_module;
+use starlark::starlark_type;
+use starlark::values::dict::Dict;
+use starlark::values::type_repr::StarlarkTypeRepr;
+use starlark::values::AllocValue;
+use starlark::values::Heap;
+use starlark::values::NoSerialize;
+use starlark::values::StarlarkValue;
+use starlark::values::Trace;
+use starlark::values::UnpackValue;
+use starlark::values::Value;
+use starlark::values::ValueLike;
+use starlark::StarlarkDocs;
+
+use crate::bxl::starlark_defs::analysis_result::StarlarkAnalysisResult;
+use crate::bxl::starlark_defs::context::analysis;
+use crate::bxl::starlark_defs::context::build;
+use crate::bxl::starlark_defs::context::BxlContext;
+use crate::bxl::starlark_defs::providers_expr::ProvidersExpr;
+
+/// A `ctx.analysis()` or `ctx.build()` call that has not run yet.
+pub(crate) enum LazyOperation {
+    Analysis {
+        providers: ProvidersExpr<ConfiguredProvidersLabel>,
+        skip_incompatible: bool,
+    },
+    Build {
+        providers: ProvidersExpr<ConfiguredProvidersLabel>,
+        materializations: Materializations,
+    },
+}
+
+enum LazyResult {
+    Analysis(
+        Either<StarlarkAnalysisResult, Vec<(ConfiguredProvidersLabel, StarlarkAnalysisResult)>>,
+    ),
+    Build(BTreeMap<ConfiguredProvidersLabel, Option<BuildTargetResult>>),
+}
+
+impl LazyOperation {
+    fn kind(&self) -> &'static str {
+        match self {
+            LazyOperation::Analysis { .. } => "analysis",
+            LazyOperation::Build { .. } => "build",
+        }
+    }
+
+    async fn compute(
+        &self,
+        dice: &DiceComputations,
+        ctx: &BxlContext<'_>,
+    ) -> anyhow::Result<LazyResult> {
+        Ok(match self {
+            LazyOperation::Analysis {
+                providers,
+                skip_incompatible,
+            } => {
+                LazyResult::Analysis(analysis::analysis(dice, providers, *skip_incompatible).await?)
+            }
+            LazyOperation::Build {
+                providers,
+                materializations,
+            } => LazyResult::Build(
+                build::build_labels(dice, ctx, providers, *materializations).await?,
+            ),
+        })
+    }
+}
+
+impl LazyResult {
+    fn alloc<'v>(self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match self {
+            LazyResult::Analysis(result) => analysis::alloc_analysis_result(result, heap),
+            LazyResult::Build(result) => {
+                Ok(heap.alloc(Dict::new(build::alloc_build_result(result, heap)?)))
+            }
+        }
+    }
+}
+
+/// A deferred `ctx.analysis()` or `ctx.build()`, created by `ctx.lazy_analysis()` and
+/// `ctx.lazy_build()`. Nothing runs until it is resolved, either alone with `resolve()` or
+/// together with other lazy values with `ctx.join_lazy()`, which runs them concurrently.
+#[derive(
+    ProvidesStaticType,
+    Derivative,
+    Display,
+    Trace,
+    NoSerialize,
+    Allocative,
+    StarlarkDocs
+)]
+#[starlark_docs(directory = "bxl")]
+#[derivative(Debug)]
+#[display(fmt = "<lazy {}>", "operation.kind()")]
+#[allocative(skip)]
+pub struct StarlarkBxlLazy<'v> {
+    #[trace(unsafe_ignore)]
+    #[derivative(Debug = "ignore")]
+    ctx: &'v BxlContext<'v>,
+    #[trace(unsafe_ignore)]
+    #[derivative(Debug = "ignore")]
+    operation: LazyOperation,
+}
+
+impl<'v> StarlarkBxlLazy<'v> {
+    pub(crate) fn new(ctx: &'v BxlContext<'v>, operation: LazyOperation) -> Self {
+        Self { ctx, operation }
+    }
+}
+
+impl<'v> StarlarkValue<'v> for StarlarkBxlLazy<'v> {
+    starlark_type!("bxl_lazy");
+
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(lazy_methods)
+    }
+}
+
+impl<'v> AllocValue<'v> for StarlarkBxlLazy<'v> {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc_complex_no_freeze(self)
+    }
+}
+
+impl<'v> StarlarkTypeRepr for &'v StarlarkBxlLazy<'v> {
+    fn starlark_type_repr() -> String {
+        StarlarkBxlLazy::get_type_starlark_repr()
+    }
+}
+
+impl<'v> UnpackValue<'v> for &'v StarlarkBxlLazy<'v> {
+    fn unpack_value(x: Value<'v>) -> Option<&'v StarlarkBxlLazy<'v>> {
+        x.downcast_ref()
+    }
+}
+
+/// Runs all the lazy operations concurrently, returning their results in order.
+pub(crate) fn join_lazy<'v>(
+    ctx: &'v BxlContext<'v>,
+    lazies: &[&'v StarlarkBxlLazy<'v>],
+    heap: &'v Heap,
+) -> anyhow::Result<Vec<Value<'v>>> {
+    let results = ctx.async_ctx.via_dice(|dice| async move {
+        futures::future::try_join_all(lazies.iter().map(|lazy| lazy.operation.compute(dice, ctx)))
+            .await
+    })?;
+
+    results
+        .into_iter()
+        .map(|result| result.alloc(heap))
+        .collect()
+}
+
+#[starlark_module]
+fn lazy_methods(builder: &mut MethodsBuilder) {
+    /// Runs the operation, returning what `ctx.analysis()` or `ctx.build()` would have returned.
+    /// To run several operations concurrently, use `ctx.join_lazy()` instead.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl(ctx):
+    ///     lazy = ctx.lazy_analysis("//foo:bar")
+    ///     ctx.output.print(lazy.resolve().providers())
+    /// ```
+    fn resolve<'v>(
+        this: &'v StarlarkBxlLazy<'v>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<Value<'v>> {
+        Ok(join_lazy(this.ctx, &[this], eval.heap())?.remove(0))
+    }
+}
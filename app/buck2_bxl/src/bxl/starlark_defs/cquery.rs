@@ -650,6 +650,7 @@ fn register_cquery(builder: &mut MethodsBuilder) {
                 return err;
             }
         };
+        let query = this.ctx.mocked_query(query);
 
         this.ctx.async_ctx.via_dice(|ctx| async {
             match get_cquery_evaluator(
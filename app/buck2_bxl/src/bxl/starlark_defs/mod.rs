@@ -56,6 +56,9 @@ use crate::bxl::starlark_defs::functions::register_error_handling_function;
 use crate::bxl::starlark_defs::functions::register_instant_function;
 use crate::bxl::starlark_defs::functions::register_label_function;
 use crate::bxl::starlark_defs::functions::register_target_function;
+use crate::bxl::starlark_defs::testing::register_asserts_module;
+use crate::bxl::starlark_defs::testing::register_bxl_test_function;
+use crate::bxl::starlark_defs::testing::BxlTestSpec;
 pub mod alloc_node;
 pub mod analysis_result;
 pub mod artifacts;
@@ -72,6 +75,7 @@ pub mod providers_expr;
 mod query_util;
 pub mod target_expr;
 pub mod targetset;
+pub mod testing;
 pub mod time;
 pub mod uquery;
 use starlark::starlark_module;
@@ -88,12 +92,7 @@ pub fn register_bxl_function(builder: &mut GlobalsBuilder) {
     ) -> anyhow::Result<Value<'v>> {
         let implementation = r#impl;
 
-        let build_context = BuildContext::from_context(eval)?;
-        let bxl_path = (*build_context
-            .starlark_path()
-            .unpack_bxl_file()
-            .ok_or_else(|| anyhow::anyhow!("`bxl` can only be declared in bxl files"))?)
-        .clone();
+        let bxl_path = current_bxl_path(eval)?;
 
         let mut unresolved_cli_args = SmallMap::new();
         let mut short_args = SmallSet::new();
@@ -115,13 +114,26 @@ pub fn register_bxl_function(builder: &mut GlobalsBuilder) {
             implementation,
             cli_args: unresolved_cli_args,
             docs: Some(doc.to_owned()),
+            test: None,
         }))
     }
 }
 
+/// The bxl file being evaluated, in which `bxl` functions are declared.
+fn current_bxl_path(eval: &Evaluator) -> anyhow::Result<BxlFilePath> {
+    let build_context = BuildContext::from_context(eval)?;
+    Ok((*build_context
+        .starlark_path()
+        .unpack_bxl_file()
+        .ok_or_else(|| anyhow::anyhow!("`bxl` can only be declared in bxl files"))?)
+    .clone())
+}
+
 fn register_bxl_defs(globals: &mut GlobalsBuilder) {
     globals.struct_("cli_args", cli_args::register_cli_args_module);
+    globals.struct_("asserts", register_asserts_module);
     register_bxl_function(globals);
+    register_bxl_test_function(globals);
     register_artifact_function(globals);
     register_label_function(globals);
     register_target_function(globals);
@@ -164,6 +176,9 @@ pub(crate) struct BxlFunction<'v> {
     /// the cli args to this bxl function
     cli_args: SmallMap<String, CliArgs>,
     docs: Option<String>,
+    /// Set for tests declared with `bxl_test()`
+    #[trace(unsafe_ignore)]
+    test: Option<Arc<BxlTestSpec>>,
 }
 
 impl<'v> Display for BxlFunction<'v> {
@@ -208,6 +223,7 @@ impl<'v> Freeze for BxlFunction<'v> {
             cli_args: self.cli_args,
             bxl_id,
             docs,
+            test: self.test,
         })
     }
 }
@@ -219,6 +235,7 @@ pub struct FrozenBxlFunction {
     cli_args: SmallMap<String, CliArgs>,
     bxl_id: Arc<BxlFunctionLabel>,
     docs: Option<String>,
+    test: Option<Arc<BxlTestSpec>>,
 }
 starlark_simple_value!(FrozenBxlFunction);
 
@@ -231,6 +248,11 @@ impl FrozenBxlFunction {
         self.implementation
     }
 
+    /// How to run this function if it is a test declared with `bxl_test()`
+    pub(crate) fn test(&self) -> Option<&Arc<BxlTestSpec>> {
+        self.test.as_ref()
+    }
+
     pub fn to_clap<'v>(&'v self, mut clap: clap::Command<'v>) -> clap::Command<'v> {
         if let Some(docs) = self.docs.as_ref() {
             clap = clap.about(docs.as_str())
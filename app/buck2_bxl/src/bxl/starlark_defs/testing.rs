@@ -0,0 +1,382 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Unit testing of bxl scripts: `bxl_test` declares a test run by `buck2 bxl --test`, with
+//! optionally mocked file system and query results and a golden output, and `asserts` provides
+//! assertion helpers for the test implementations.
+
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use buck2_common::file_ops::FileType;
+use buck2_common::file_ops::SimpleDirEntry;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::instance::CellInstance;
+use buck2_interpreter::globspec::GlobSpec;
+use either::Either;
+use starlark::collections::SmallMap;
+use starlark::environment::GlobalsBuilder;
+use starlark::eval::Evaluator;
+use starlark::starlark_module;
+use starlark::values::dict::DictOf;
+use starlark::values::none::NoneType;
+use starlark::values::Value;
+use thiserror::Error;
+
+use crate::bxl::starlark_defs::context::starlark_async::BxlSafeDiceComputations;
+use crate::bxl::starlark_defs::current_bxl_path;
+use crate::bxl::starlark_defs::file_expr::FileExpr;
+use crate::bxl::starlark_defs::BxlFunction;
+
+#[derive(Debug, Error)]
+enum BxlTestError {
+    #[error("Path `{0}` is not a directory in the mocked file system")]
+    NotADirectory(CellPath),
+    #[error("Path `{0}` is not a file in the mocked file system")]
+    NotAFile(CellPath),
+    #[error(
+        "The contents of `{0}` are not mocked, pass `mock_files` as a dict from paths to contents to read it"
+    )]
+    NoContents(CellPath),
+}
+
+#[derive(Debug, Error)]
+enum BxlAssertionError {
+    #[error("{0}Expected `{1}`, got `{2}`")]
+    NotEqual(String, String, String),
+    #[error("{0}Expected the condition to be {1}")]
+    Condition(String, bool),
+    #[error("{0}Expected `{1}` to contain `{2}`")]
+    NotContained(String, String, String),
+}
+
+/// How a test declared with `bxl_test` is run.
+#[derive(Debug, Allocative)]
+pub(crate) struct BxlTestSpec {
+    /// The path of the file with the expected output of the test, relative to the directory of
+    /// the bxl file.
+    pub(crate) golden: Option<String>,
+    pub(crate) mocks: Arc<BxlTestMocks>,
+}
+
+/// Results of the bxl context operations that are replaced in tests.
+#[derive(Debug, Default, Allocative)]
+pub(crate) struct BxlTestMocks {
+    /// If set, `ctx.fs` only sees these files and their parent directories, instead of the
+    /// files in the repo. The files are mapped to their contents, if mocked.
+    pub(crate) files: Option<SmallMap<String, Option<String>>>,
+    /// Queries evaluated by `ctx.uquery().eval()` and `ctx.cquery().eval()` instead of the query
+    /// they are keyed by, typically returning targets from fixture packages. Only queries equal
+    /// to a key, as passed to `eval()` before the substitution of `query_args`, are replaced.
+    pub(crate) queries: SmallMap<String, String>,
+}
+
+impl BxlTestMocks {
+    /// The query to evaluate for `query`: its mock if `query` is exactly a mocked query, or
+    /// `query` itself. Queries are matched as strings, not parsed, so an equivalent query written
+    /// differently, e.g. with other whitespace, is not mocked.
+    pub(crate) fn query<'a>(&'a self, query: &'a str) -> &'a str {
+        self.queries.get(query).map_or(query, |q| q.as_str())
+    }
+}
+
+/// The file system seen by `ctx.fs` in tests that mock it.
+pub(crate) struct MockedFileSystem {
+    files: Vec<CellPath>,
+    contents: SmallMap<CellPath, String>,
+}
+
+impl MockedFileSystem {
+    /// Resolves the mocked files like the paths passed to `ctx.fs` are.
+    pub(crate) fn new(
+        files: &SmallMap<String, Option<String>>,
+        dice: &BxlSafeDiceComputations,
+        cell: &CellInstance,
+    ) -> anyhow::Result<Self> {
+        let mut paths = Vec::with_capacity(files.len());
+        let mut contents = SmallMap::new();
+        for (file, content) in files {
+            let path = FileExpr::Literal(file).get(dice, cell)?;
+            if let Some(content) = content {
+                contents.insert(path.clone(), content.clone());
+            }
+            paths.push(path);
+        }
+        Ok(Self {
+            files: paths,
+            contents,
+        })
+    }
+
+    pub(crate) fn is_file(&self, path: &CellPath) -> bool {
+        self.files.contains(path)
+    }
+
+    pub(crate) fn is_dir(&self, path: &CellPath) -> bool {
+        self.files
+            .iter()
+            .any(|f| f != path && f.starts_with(path.as_ref()))
+    }
+
+    pub(crate) fn exists(&self, path: &CellPath) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+
+    /// The mocked contents of the file.
+    pub(crate) fn read(&self, path: &CellPath) -> anyhow::Result<&str> {
+        match self.contents.get(path) {
+            Some(content) => Ok(content),
+            None if self.is_file(path) => Err(BxlTestError::NoContents(path.clone()).into()),
+            None => Err(BxlTestError::NotAFile(path.clone()).into()),
+        }
+    }
+
+    /// The sorted entries of the directory.
+    pub(crate) fn list(&self, path: &CellPath) -> anyhow::Result<Arc<[SimpleDirEntry]>> {
+        if !self.is_dir(path) {
+            return Err(BxlTestError::NotADirectory(path.clone()).into());
+        }
+        let mut entries: Vec<SimpleDirEntry> = self
+            .files
+            .iter()
+            .filter_map(|f| {
+                let (name, rest) = f.strip_prefix(path.as_ref()).ok()?.split_first()?;
+                Some(SimpleDirEntry {
+                    file_name: name.to_owned(),
+                    file_type: if rest.is_empty() {
+                        FileType::File
+                    } else {
+                        FileType::Directory
+                    },
+                })
+            })
+            .collect();
+        entries.sort();
+        entries.dedup_by(|a, b| a.file_name == b.file_name);
+        Ok(entries.into())
+    }
+
+    /// The sorted files under `root` matching `spec`.
+    pub(crate) fn glob(&self, root: &CellPath, spec: &GlobSpec) -> anyhow::Result<Vec<CellPath>> {
+        let mut files = Vec::new();
+        for f in &self.files {
+            if let Ok(path) = f.strip_prefix(root.as_ref()) {
+                if spec.matches(path.as_str()) {
+                    files.push(f.clone());
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+#[starlark_module]
+pub fn register_bxl_test_function(builder: &mut GlobalsBuilder) {
+    /// Declares a test of bxl code, run with `buck2 bxl --test path/to/file.bxl`. The `impl`
+    /// takes a bxl context like the implementation of a `bxl` function, and the test fails if it
+    /// fails, e.g. with `fail()` or the `asserts` helpers.
+    ///
+    /// `golden` is the path, relative to the directory of the bxl file, of the expected content of
+    /// `ctx.output`. `buck2 bxl --test --update-goldens` writes it instead of comparing it.
+    ///
+    /// `mock_files` replaces the files seen by `ctx.fs` with the given files and their parent
+    /// directories. It is either a list of paths, or a dict from paths to the contents returned by
+    /// `ctx.fs.read()` and digested by `ctx.fs.digest()`, which fail for files without contents.
+    /// `mock_queries` maps queries passed to `ctx.uquery().eval()` and
+    /// `ctx.cquery().eval()` to the queries to evaluate instead, typically returning targets of
+    /// fixture packages. A query is only replaced if it is passed to `eval()` exactly as written
+    /// in the dict, before its `query_args` are substituted. Equivalent queries written
+    /// differently, and the other query functions, e.g. `ctx.uquery().deps()`, are not mocked.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _test_owners_impl(ctx):
+    ///     owners = ctx.uquery().eval("owner('foo/lib.cpp')")
+    ///     asserts.equals(1, len(owners))
+    ///     ctx.output.print(owners)
+    ///
+    /// test_owners = bxl_test(
+    ///     impl = _test_owners_impl,
+    ///     golden = "test_owners.golden",
+    ///     mock_queries = {"owner('foo/lib.cpp')": "//fixtures:lib"},
+    /// )
+    /// ```
+    fn bxl_test<'v>(
+        #[starlark(require = named)] r#impl: Value<'v>,
+        #[starlark(require = named, default = "")] doc: &str,
+        #[starlark(require = named)] golden: Option<&str>,
+        #[starlark(require = named)] mock_files: Option<Either<Vec<String>, SmallMap<&str, &str>>>,
+        #[starlark(require = named)] mock_queries: Option<DictOf<'v, &'v str, &'v str>>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<Value<'v>> {
+        let bxl_path = current_bxl_path(eval)?;
+
+        let files = mock_files.map(|files| match files {
+            Either::Left(paths) => paths.into_iter().map(|p| (p, None)).collect(),
+            Either::Right(contents) => contents
+                .into_iter()
+                .map(|(p, c)| (p.to_owned(), Some(c.to_owned())))
+                .collect(),
+        });
+
+        let queries = mock_queries
+            .map(|q| {
+                q.to_dict()
+                    .into_iter()
+                    .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(eval.heap().alloc(BxlFunction {
+            bxl_path,
+            id: RefCell::new(None),
+            implementation: r#impl,
+            cli_args: SmallMap::new(),
+            docs: Some(doc.to_owned()),
+            test: Some(Arc::new(BxlTestSpec {
+                golden: golden.map(str::to_owned),
+                mocks: Arc::new(BxlTestMocks { files, queries }),
+            })),
+        }))
+    }
+}
+
+fn message_prefix(msg: Option<&str>) -> String {
+    msg.map_or_else(String::new, |m| format!("{}: ", m))
+}
+
+/// Assertion helpers for `bxl_test` implementations, failing the test with a descriptive error.
+#[starlark_module]
+pub(crate) fn register_asserts_module(builder: &mut GlobalsBuilder) {
+    /// Fails if `actual` is not equal to `expected`.
+    fn equals<'v>(
+        expected: Value<'v>,
+        actual: Value<'v>,
+        #[starlark(require = named)] msg: Option<&str>,
+    ) -> anyhow::Result<NoneType> {
+        if expected.equals(actual)? {
+            Ok(NoneType)
+        } else {
+            Err(BxlAssertionError::NotEqual(
+                message_prefix(msg),
+                expected.to_repr(),
+                actual.to_repr(),
+            )
+            .into())
+        }
+    }
+
+    /// Fails if the condition is false.
+    fn r#true(
+        condition: bool,
+        #[starlark(require = named)] msg: Option<&str>,
+    ) -> anyhow::Result<NoneType> {
+        if condition {
+            Ok(NoneType)
+        } else {
+            Err(BxlAssertionError::Condition(message_prefix(msg), true).into())
+        }
+    }
+
+    /// Fails if the condition is true.
+    fn r#false(
+        condition: bool,
+        #[starlark(require = named)] msg: Option<&str>,
+    ) -> anyhow::Result<NoneType> {
+        if condition {
+            Err(BxlAssertionError::Condition(message_prefix(msg), false).into())
+        } else {
+            Ok(NoneType)
+        }
+    }
+
+    /// Fails if `item` is not in `collection`, as tested by the `in` operator.
+    fn contains<'v>(
+        collection: Value<'v>,
+        item: Value<'v>,
+        #[starlark(require = named)] msg: Option<&str>,
+    ) -> anyhow::Result<NoneType> {
+        if collection.is_in(item)? {
+            Ok(NoneType)
+        } else {
+            Err(BxlAssertionError::NotContained(
+                message_prefix(msg),
+                collection.to_repr(),
+                item.to_repr(),
+            )
+            .into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_common::file_ops::FileType;
+    use buck2_core::cells::cell_path::CellPath;
+    use buck2_interpreter::globspec::GlobSpec;
+    use starlark::collections::SmallMap;
+
+    use super::MockedFileSystem;
+
+    #[test]
+    fn test_mocked_file_system() -> anyhow::Result<()> {
+        let fs = MockedFileSystem {
+            files: vec![
+                CellPath::testing_new("root//foo/a.txt"),
+                CellPath::testing_new("root//foo/bar/b.txt"),
+                CellPath::testing_new("root//foo/bar/c.txt"),
+            ],
+            contents: SmallMap::from_iter([(
+                CellPath::testing_new("root//foo/a.txt"),
+                "a".to_owned(),
+            )]),
+        };
+        assert!(fs.is_file(&CellPath::testing_new("root//foo/a.txt")));
+        assert!(!fs.is_dir(&CellPath::testing_new("root//foo/a.txt")));
+        assert!(fs.is_dir(&CellPath::testing_new("root//foo/bar")));
+        assert!(fs.exists(&CellPath::testing_new("root//foo")));
+        assert!(!fs.exists(&CellPath::testing_new("root//baz")));
+
+        assert_eq!("a", fs.read(&CellPath::testing_new("root//foo/a.txt"))?);
+        assert!(
+            fs.read(&CellPath::testing_new("root//foo/bar/b.txt"))
+                .is_err()
+        );
+        assert!(fs.read(&CellPath::testing_new("root//foo/bar")).is_err());
+
+        let entries: Vec<_> = fs
+            .list(&CellPath::testing_new("root//foo"))?
+            .iter()
+            .map(|e| (e.file_name.to_string(), e.file_type.clone()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("a.txt".to_owned(), FileType::File),
+                ("bar".to_owned(), FileType::Directory),
+            ],
+            entries
+        );
+        assert!(fs.list(&CellPath::testing_new("root//foo/a.txt")).is_err());
+
+        assert_eq!(
+            vec![
+                CellPath::testing_new("root//foo/bar/b.txt"),
+                CellPath::testing_new("root//foo/bar/c.txt"),
+            ],
+            fs.glob(
+                &CellPath::testing_new("root//foo"),
+                &GlobSpec::new(&["**/*.txt"], &["a.txt"])?
+            )?
+        );
+        Ok(())
+    }
+}
@@ -503,6 +503,7 @@ fn register_uquery(builder: &mut MethodsBuilder) {
                 .into());
             }
         };
+        let query = this.ctx.mocked_query(query);
 
         this.ctx.async_ctx.via_dice(|ctx| async {
             match get_uquery_evaluator(
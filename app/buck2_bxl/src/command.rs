@@ -64,6 +64,7 @@ use crate::bxl::eval::resolve_cli_args;
 use crate::bxl::eval::BxlResolvedCliArgs;
 use crate::bxl::eval::CliResolutionCtx;
 use crate::bxl::starlark_defs::functions::BxlErrorWithoutStacktrace;
+use crate::test_command::bxl_test;
 
 pub async fn bxl_command(
     ctx: &dyn ServerCommandContextTrait,
@@ -120,6 +121,10 @@ async fn bxl(
     ctx: DiceTransaction,
     request: &BxlRequest,
 ) -> anyhow::Result<buck2_cli_proto::BxlResponse> {
+    if request.test {
+        return bxl_test(server_ctx, ctx, request).await;
+    }
+
     let cwd = server_ctx.working_dir();
     let cell_resolver = ctx.get_cell_resolver().await?;
     let bxl_label = parse_bxl_label_from_cli(cwd, &request.bxl_label, &cell_resolver)?;
@@ -229,7 +234,7 @@ pub(crate) async fn get_bxl_cli_args(
     resolve_cli_args(bxl_label, &cli_ctx, bxl_args, &frozen_callable).await
 }
 
-async fn copy_output<W: Write>(
+pub(crate) async fn copy_output<W: Write>(
     mut output: W,
     dice: &DiceComputations,
     output_loc: &BuckOutPath,
@@ -255,7 +260,7 @@ async fn copy_output<W: Write>(
     Ok(())
 }
 
-async fn ensure_artifacts(
+pub(crate) async fn ensure_artifacts(
     ctx: &DiceComputations,
     materialization_ctx: &MaterializationContext,
     bxl_result: &buck2_build_api::bxl::result::BxlResult,
@@ -331,6 +336,22 @@ pub(crate) fn parse_bxl_label_from_cli(
     bxl_label: &str,
     cell_resolver: &CellResolver,
 ) -> anyhow::Result<BxlFunctionLabel> {
+    let (bxl_path, bxl_fn) = bxl_label
+        .rsplit_once(':')
+        .ok_or_else(|| BxlLabelError::Format(bxl_label.to_owned()))?;
+
+    Ok(BxlFunctionLabel {
+        bxl_path: parse_bxl_path_from_cli(cwd, bxl_path, cell_resolver)?,
+        name: bxl_fn.to_owned(),
+    })
+}
+
+/// Parse the path of a bxl file out of cli pattern
+pub(crate) fn parse_bxl_path_from_cli(
+    cwd: &ProjectRelativePath,
+    bxl_path: &str,
+    cell_resolver: &CellResolver,
+) -> anyhow::Result<BxlFilePath> {
     let current_cell = cell_resolver.get_cell_path(cwd)?;
 
     // Targets with cell aliases should be resolved against the cell mapping
@@ -340,10 +361,6 @@ pub(crate) fn parse_bxl_label_from_cli(
         .unwrap()
         .cell_alias_resolver();
 
-    let (bxl_path, bxl_fn) = bxl_label
-        .rsplit_once(':')
-        .ok_or_else(|| BxlLabelError::Format(bxl_label.to_owned()))?;
-
     const OPTS: ParseImportOptions = ParseImportOptions {
         allow_missing_at_symbol: true,
         allow_relative_imports: true,
@@ -364,8 +381,5 @@ pub(crate) fn parse_bxl_label_from_cli(
         )?;
     }
 
-    Ok(BxlFunctionLabel {
-        bxl_path: BxlFilePath::new(import_path)?,
-        name: bxl_fn.to_owned(),
-    })
+    BxlFilePath::new(import_path)
 }
@@ -15,3 +15,4 @@
 pub mod bxl;
 pub mod command;
 pub mod profile_command;
+mod test_command;
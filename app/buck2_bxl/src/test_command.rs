@@ -0,0 +1,284 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! `buck2 bxl --test`: runs the tests declared with `bxl_test()` in a bxl file.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::Context;
+use buck2_build_api::build::ConvertMaterializationContext;
+use buck2_build_api::bxl::calculation::BxlCalculation;
+use buck2_build_api::bxl::calculation::BxlComputeResult;
+use buck2_build_api::bxl::types::BxlFunctionLabel;
+use buck2_build_api::bxl::types::BxlKey;
+use buck2_cli_proto::build_request::Materializations;
+use buck2_cli_proto::BxlRequest;
+use buck2_cli_proto::BxlResponse;
+use buck2_cli_proto::HasClientContext;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_core::collections::ordered_map::OrderedMap;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::target::label::TargetLabel;
+use buck2_data::test_result::OptionalMsg;
+use buck2_data::TestStatus;
+use buck2_events::dispatch::get_dispatcher;
+use buck2_interpreter::path::BxlFilePath;
+use buck2_interpreter::path::StarlarkModulePath;
+use buck2_interpreter_for_build::interpreter::calculation::InterpreterCalculation;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dice::DiceTransaction;
+use dupe::Dupe;
+use itertools::Itertools;
+
+use crate::bxl::starlark_defs::FrozenBxlFunction;
+use crate::command::copy_output;
+use crate::command::ensure_artifacts;
+use crate::command::parse_bxl_label_from_cli;
+use crate::command::parse_bxl_path_from_cli;
+
+#[derive(Debug, thiserror::Error)]
+enum BxlTestCommandError {
+    #[error("`{0}` is not a test declared with `bxl_test()`")]
+    NotATest(BxlFunctionLabel),
+    #[error("No tests declared with `bxl_test()` in `{0}`")]
+    NoTests(BxlFilePath),
+    #[error("The golden file `{0}` does not exist. Re-run with `--update-goldens` to create it")]
+    MissingGolden(String),
+    #[error(
+        "The output does not match the golden file `{0}`. Re-run with `--update-goldens` to update it"
+    )]
+    GoldenMismatch(String),
+}
+
+/// Runs either the given test, `path/to/file.bxl:test_name`, or all the tests of the given file,
+/// reporting each of them as a test result.
+pub(crate) async fn bxl_test(
+    server_ctx: &dyn ServerCommandContextTrait,
+    ctx: DiceTransaction,
+    request: &BxlRequest,
+) -> anyhow::Result<BxlResponse> {
+    let cwd = server_ctx.working_dir();
+    let cell_resolver = ctx.get_cell_resolver().await?;
+    let project_root = server_ctx.project_root().to_string();
+
+    let client_ctx = request.client_context()?;
+    let global_target_platform =
+        target_platform_from_client_context(client_ctx, server_ctx, &ctx).await?;
+
+    let final_artifact_materializations =
+        Materializations::from_i32(request.final_artifact_materializations)
+            .with_context(|| "Invalid final_artifact_materializations")
+            .unwrap();
+
+    let (bxl_path, name) = if request.bxl_label.contains(':') {
+        let label = parse_bxl_label_from_cli(cwd, &request.bxl_label, &cell_resolver)?;
+        (label.bxl_path, Some(label.name))
+    } else {
+        (
+            parse_bxl_path_from_cli(cwd, &request.bxl_label, &cell_resolver)?,
+            None,
+        )
+    };
+
+    let bxl_module = ctx
+        .get_loaded_module(StarlarkModulePath::BxlFile(&bxl_path))
+        .await?;
+
+    let mut tests = Vec::new();
+    for test_name in bxl_module.env().names().map(|n| n.as_str().to_owned()) {
+        if name.as_ref().map_or(false, |name| name != &test_name) {
+            continue;
+        }
+        let label = BxlFunctionLabel {
+            bxl_path: bxl_path.clone(),
+            name: test_name,
+        };
+        let callable = bxl_module.env().get_any_visibility(&label.name)?.0;
+        match callable
+            .downcast::<FrozenBxlFunction>()
+            .ok()
+            .and_then(|f| f.test().cloned())
+        {
+            Some(spec) => tests.push((label, spec)),
+            None if name.is_some() => return Err(BxlTestCommandError::NotATest(label).into()),
+            None => {}
+        }
+    }
+    if tests.is_empty() {
+        return Err(BxlTestCommandError::NoTests(bxl_path).into());
+    }
+    tests.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+
+    let golden_dir = bxl_path
+        .path()
+        .parent()
+        .context("bxl file should have a parent directory")?
+        .to_owned();
+
+    let mut error_messages = Vec::new();
+    for (label, spec) in tests {
+        let test_name = label.to_string();
+        let start = Instant::now();
+        let result = async {
+            let golden = match &spec.golden {
+                Some(golden) => Some((
+                    golden.as_str(),
+                    server_ctx
+                        .project_root()
+                        .resolve(&cell_resolver.resolve_path(
+                            golden_dir.join(ForwardRelativePath::new(golden)?).as_ref(),
+                        )?),
+                )),
+                None => None,
+            };
+            run_test(
+                server_ctx,
+                &ctx,
+                label,
+                global_target_platform.dupe(),
+                final_artifact_materializations,
+                golden,
+                request.update_goldens,
+            )
+            .await
+        }
+        .await;
+
+        let (status, msg) = match result {
+            Ok(()) => (TestStatus::Pass, None),
+            Err(e) => {
+                let msg = format!("{:#}", e);
+                error_messages.push(format!("{}: {}", test_name, msg));
+                (TestStatus::Fail, Some(OptionalMsg { msg }))
+            }
+        };
+        get_dispatcher().instant_event(buck2_data::instant_event::Data::TestResult(
+            buck2_data::TestResult {
+                name: test_name,
+                status: status as i32,
+                msg,
+                duration: start.elapsed().try_into().ok(),
+                details: String::new(),
+                target_label: None,
+            },
+        ));
+    }
+
+    Ok(BxlResponse {
+        project_root,
+        error_messages: error_messages.into_iter().unique().collect(),
+    })
+}
+
+async fn run_test(
+    server_ctx: &dyn ServerCommandContextTrait,
+    ctx: &DiceTransaction,
+    label: BxlFunctionLabel,
+    global_target_platform: Option<TargetLabel>,
+    final_artifact_materializations: Materializations,
+    golden: Option<(&str, AbsNormPathBuf)>,
+    update_goldens: bool,
+) -> anyhow::Result<()> {
+    let bxl_key = BxlKey::new(label, Arc::new(OrderedMap::new()), global_target_platform);
+
+    let BxlComputeResult {
+        bxl_result,
+        materializations,
+    } = ctx.eval_bxl(bxl_key).await?;
+
+    let materialization_context = ConvertMaterializationContext::with_existing_map(
+        final_artifact_materializations,
+        &Arc::new((*materializations).clone()),
+    );
+    if let Err(errors) = ensure_artifacts(ctx, &materialization_context, &bxl_result).await {
+        return Err(anyhow::anyhow!(
+            "{}",
+            errors
+                .iter()
+                .map(|e| format!("{:#}", e))
+                .unique()
+                .join("\n")
+        ));
+    }
+
+    let mut output = Vec::new();
+    copy_output(&mut output, ctx, bxl_result.get_output_loc()).await?;
+    copy_output(server_ctx.stderr()?, ctx, bxl_result.get_error_loc()).await?;
+
+    match golden {
+        Some((golden, path)) => check_golden(golden, &path, &output, update_goldens),
+        None => Ok(()),
+    }
+}
+
+/// Compares the output of a test to its golden file at `path`, or writes it there when updating
+/// the goldens.
+fn check_golden(
+    golden: &str,
+    path: &AbsNormPath,
+    output: &[u8],
+    update_goldens: bool,
+) -> anyhow::Result<()> {
+    if update_goldens {
+        if let Some(parent) = path.parent() {
+            fs_util::create_dir_all(parent)?;
+        }
+        fs_util::write(path, output)?;
+        return Ok(());
+    }
+    match fs_util::read_to_string_opt(path)? {
+        None => Err(BxlTestCommandError::MissingGolden(golden.to_owned()).into()),
+        Some(expected) if expected.as_bytes() != output => {
+            Err(BxlTestCommandError::GoldenMismatch(golden.to_owned()).into())
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_core::fs::project_rel_path::ProjectRelativePath;
+
+    use crate::test_command::check_golden;
+
+    #[test]
+    fn test_check_golden() -> anyhow::Result<()> {
+        let fs = ProjectRootTemp::new()?;
+        let path = fs
+            .path()
+            .resolve(ProjectRelativePath::new("goldens/test.golden")?);
+
+        let missing = check_golden("goldens/test.golden", &path, b"out\n", false).unwrap_err();
+        assert!(
+            missing.to_string().contains("does not exist"),
+            "{:#}",
+            missing
+        );
+
+        check_golden("goldens/test.golden", &path, b"out\n", true)?;
+        check_golden("goldens/test.golden", &path, b"out\n", false)?;
+
+        let mismatch = check_golden("goldens/test.golden", &path, b"other\n", false).unwrap_err();
+        assert!(
+            mismatch.to_string().contains("does not match"),
+            "{:#}",
+            mismatch
+        );
+
+        check_golden("goldens/test.golden", &path, b"other\n", true)?;
+        check_golden("goldens/test.golden", &path, b"other\n", false)?;
+        Ok(())
+    }
+}
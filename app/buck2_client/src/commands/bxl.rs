@@ -48,6 +48,19 @@ pub struct BxlCommandOptions {
     )]
     materializations: Option<FinalArtifactMaterializations>,
 
+    #[clap(
+        long = "test",
+        help = "Run the tests declared with `bxl_test()` instead of a bxl function. The label may omit `:<function>` to run all the tests of the file."
+    )]
+    test: bool,
+
+    #[clap(
+        long = "update-goldens",
+        help = "Write the golden files of the tests instead of comparing their output to them.",
+        requires = "test"
+    )]
+    update_goldens: bool,
+
     #[clap(
         name = "BXL label",
         help = "The bxl function to execute as defined by the label of form `<cell>//path/file.bxl:<function>`"
@@ -85,6 +98,8 @@ impl StreamingCommand for BxlCommand {
                     final_artifact_materializations: self.bxl_opts.materializations.to_proto()
                         as i32,
                     print_stacktrace: ctx.verbosity.print_success_stderr(),
+                    test: self.bxl_opts.test,
+                    update_goldens: self.bxl_opts.update_goldens,
                 },
                 ctx.stdin()
                     .console_interaction_stream(&self.common_ops.console_opts),
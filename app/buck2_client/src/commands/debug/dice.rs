@@ -0,0 +1,159 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use bincode::Options;
+use buck2_cli_proto::unstable_dice_dump_request::DiceDumpFormat;
+use buck2_cli_proto::UnstableDiceDumpRequest;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonConsoleOptions;
+use buck2_client_ctx::common::CommonDaemonCommandOptions;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::streaming::StreamingCommand;
+use buck2_core::fs::fs_util::create_dir_all;
+use buck2_core::fs::fs_util::remove_all;
+use dice::introspection::explain::InvalidationExplanation;
+use dice::introspection::explain::InvalidationGraph;
+use dice::introspection::graph::SerializedGraphNodesForKey;
+use flate2::read::GzDecoder;
+
+#[derive(Debug, thiserror::Error)]
+enum DiceCommandError {
+    #[error("No DICE key matches `{0}`")]
+    NoMatchingKey(String),
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct DiceCommand {
+    #[clap(subcommand)]
+    action: DiceSubcommand,
+}
+
+/// Inspect the DICE graph of the running daemon.
+#[derive(Debug, clap::Subcommand)]
+enum DiceSubcommand {
+    /// Explain why keys were recomputed, by printing the chain of invalidations, as recorded when
+    /// they happened, from a changed key (e.g. a modified file) to each key matching the pattern.
+    /// Invalidations are only recorded when `buck2.dice_record_invalidations` is set.
+    Explain {
+        /// Substring of the keys or key type names to explain.
+        #[clap(value_name = "KEY_PATTERN")]
+        pattern: String,
+    },
+}
+
+#[async_trait]
+impl StreamingCommand for DiceCommand {
+    const COMMAND_NAME: &'static str = "dice";
+
+    fn existing_only() -> bool {
+        true
+    }
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        _matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        match self.action {
+            DiceSubcommand::Explain { pattern } => {
+                let dump_dir = ctx.paths()?.dice_dump_dir();
+                create_dir_all(&dump_dir)?;
+                let dump_path = dump_dir
+                    .as_path()
+                    .join(format!("explain-{}", std::process::id()));
+
+                buckd
+                    .with_flushing()
+                    .unstable_dice_dump(UnstableDiceDumpRequest {
+                        destination_path: dump_path.to_str().unwrap().to_owned(),
+                        format: DiceDumpFormat::Bincode.into(),
+                    })
+                    .await?;
+                let nodes = read_dump(&dump_path);
+                remove_all(&dump_path)?;
+                let nodes = nodes?;
+                if nodes.iter().all(|n| n.invalidated.is_none()) {
+                    buck2_client_ctx::eprintln!(
+                        "No invalidation was recorded. Set `buck2.dice_record_invalidations = true` \
+                        and restart the daemon to record them."
+                    )?;
+                }
+
+                let graph = InvalidationGraph::new(&nodes);
+                let mut keys: Vec<_> = graph.matching(&pattern).collect();
+                if keys.is_empty() {
+                    return ExitResult::err(DiceCommandError::NoMatchingKey(pattern).into());
+                }
+                keys.sort_by(|a, b| a.key.cmp(&b.key));
+
+                for key in keys {
+                    let explanation = graph.explain(key);
+                    print_explanation(&explanation)?;
+                }
+            }
+        }
+        ExitResult::success()
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        CommonConsoleOptions::none_ref()
+    }
+
+    fn event_log_opts(&self) -> &CommonDaemonCommandOptions {
+        CommonDaemonCommandOptions::default_ref()
+    }
+
+    fn common_opts(&self) -> &CommonBuildConfigurationOptions {
+        CommonBuildConfigurationOptions::default_ref()
+    }
+}
+
+/// Reads a dump written in the `Bincode` format.
+fn read_dump(path: &Path) -> anyhow::Result<Vec<SerializedGraphNodesForKey>> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open DICE dump `{}`", path.display()))?;
+    bincode::config::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .deserialize_from(GzDecoder::new(BufReader::new(file)))
+        .with_context(|| format!("Failed to read DICE dump `{}`", path.display()))
+}
+
+fn print_explanation(explanation: &InvalidationExplanation) -> anyhow::Result<()> {
+    let key = explanation.key;
+    match explanation.version {
+        None => {
+            buck2_client_ctx::println!("{} ({}): never invalidated", key.key, key.type_name)?;
+        }
+        Some(v) => {
+            buck2_client_ctx::println!("{} ({}): invalidated at v{}", key.key, key.type_name, v)?;
+            if !explanation.complete {
+                buck2_client_ctx::println!("  ... (earlier keys were invalidated again since)")?;
+            }
+            for (i, step) in explanation.path.iter().enumerate() {
+                let prefix = if i == 0 && explanation.complete {
+                    "changed"
+                } else {
+                    "     ->"
+                };
+                buck2_client_ctx::println!("  {} {} ({})", prefix, step.key, step.type_name)?;
+            }
+        }
+    }
+    Ok(())
+}
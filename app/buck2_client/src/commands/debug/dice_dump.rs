@@ -28,6 +28,9 @@ pub struct DiceDumpCommand {
     serde: bool,
     #[clap(long, group = "dice_dump_format")]
     serde_pretty: bool,
+    /// Write a compact dump that can be inspected offline with `buck2 debug dice-query`.
+    #[clap(long, group = "dice_dump_format")]
+    compact: bool,
 }
 
 #[async_trait]
@@ -48,6 +51,8 @@ impl StreamingCommand for DiceDumpCommand {
             DiceDumpFormat::Bincode
         } else if self.serde_pretty {
             DiceDumpFormat::JsonPretty
+        } else if self.compact {
+            DiceDumpFormat::Compact
         } else {
             DiceDumpFormat::Tsv
         };
@@ -0,0 +1,150 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::cmp::Reverse;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use anyhow::Context;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
+use dice::introspection::compact::CompactGraph;
+use flate2::read::GzDecoder;
+
+#[derive(Debug, thiserror::Error)]
+enum DiceQueryError {
+    #[error("No DICE key matches `{0}`")]
+    NoMatchingKey(String),
+}
+
+/// Query a DICE dump written by `buck2 debug dice-dump --compact`, without a running daemon.
+#[derive(Debug, clap::Parser)]
+pub struct DiceQueryCommand {
+    /// The path of the dump.
+    #[clap(value_name = "PATH")]
+    path: PathArg,
+    #[clap(subcommand)]
+    query: DiceQuery,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum DiceQuery {
+    /// Print the key types with the most keys, or using the most memory.
+    TopTypes {
+        /// Order by the memory used by the values instead of by the number of keys.
+        #[clap(long)]
+        by_memory: bool,
+        /// Number of types to print.
+        #[clap(long, default_value = "20")]
+        limit: usize,
+    },
+    /// Print the keys that depend on the keys matching the pattern.
+    Rdeps {
+        /// Substring of the keys or key type names.
+        #[clap(value_name = "KEY_PATTERN")]
+        pattern: String,
+        /// Also print the keys that depend on them indirectly.
+        #[clap(long)]
+        transitive: bool,
+    },
+    /// Print the keys that were invalidated and not recomputed since.
+    Dirty {
+        /// Only print the number of such keys of each type.
+        #[clap(long)]
+        count_by_type: bool,
+    },
+}
+
+impl DiceQueryCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let path = self.path.resolve(&ctx.working_dir);
+        let graph = read_dump(&path)?;
+
+        match self.query {
+            DiceQuery::TopTypes { by_memory, limit } => {
+                let mut stats = graph.type_stats();
+                if by_memory {
+                    stats.sort_by_key(|s| Reverse(s.value_bytes));
+                } else {
+                    stats.sort_by_key(|s| Reverse(s.count));
+                }
+                buck2_client_ctx::println!("{:>10}  {:>14}  TYPE", "KEYS", "VALUE BYTES")?;
+                for s in stats.iter().take(limit) {
+                    buck2_client_ctx::println!(
+                        "{:>10}  {:>14}  {}",
+                        s.count,
+                        s.value_bytes,
+                        s.type_name
+                    )?;
+                }
+            }
+            DiceQuery::Rdeps {
+                pattern,
+                transitive,
+            } => {
+                let roots: Vec<u32> = graph.matching(&pattern).collect();
+                if roots.is_empty() {
+                    return ExitResult::err(DiceQueryError::NoMatchingKey(pattern).into());
+                }
+                let mut rdeps = if transitive {
+                    graph.transitive_rdeps(&roots)
+                } else {
+                    let all_rdeps = graph.rdeps();
+                    roots
+                        .iter()
+                        .flat_map(|r| all_rdeps[*r as usize].iter().copied())
+                        .collect()
+                };
+                rdeps.sort_unstable();
+                rdeps.dedup();
+                for rdep in rdeps {
+                    let key = &graph.keys[rdep as usize];
+                    buck2_client_ctx::println!("{}\t{}", graph.type_name(key), key.key)?;
+                }
+            }
+            DiceQuery::Dirty { count_by_type } => {
+                let dirty = graph.keys.iter().filter(|k| k.is_dirty());
+                if count_by_type {
+                    let mut counts = vec![0usize; graph.type_names.len()];
+                    for key in dirty {
+                        counts[key.type_name as usize] += 1;
+                    }
+                    let mut counts: Vec<_> = counts
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(_, c)| *c > 0)
+                        .collect();
+                    counts.sort_by_key(|(_, count)| Reverse(*count));
+                    for (type_name, count) in counts {
+                        buck2_client_ctx::println!(
+                            "{:>10}  {}",
+                            count,
+                            graph.type_names[type_name]
+                        )?;
+                    }
+                } else {
+                    for key in dirty {
+                        buck2_client_ctx::println!("{}\t{}", graph.type_name(key), key.key)?;
+                    }
+                }
+            }
+        }
+
+        ExitResult::success()
+    }
+}
+
+fn read_dump(path: &Path) -> anyhow::Result<CompactGraph> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open DICE dump `{}`", path.display()))?;
+    CompactGraph::read(GzDecoder::new(BufReader::new(file)))
+        .with_context(|| format!("Failed to read DICE dump `{}`", path.display()))
+}
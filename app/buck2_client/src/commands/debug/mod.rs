@@ -23,6 +23,8 @@ use replay::ReplayCommand;
 
 use crate::commands::debug::allocative::AllocativeCommand;
 use crate::commands::debug::daemon_dir::DaemonDirCommand;
+use crate::commands::debug::dice::DiceCommand;
+use crate::commands::debug::dice_query::DiceQueryCommand;
 use crate::commands::debug::exe::ExeCommand;
 use crate::commands::debug::log_perf::LogPerfCommand;
 use crate::commands::debug::persist_event_logs::PersistEventLogsCommand;
@@ -38,7 +40,9 @@ mod allocator_stats;
 mod chrome_trace;
 mod crash;
 mod daemon_dir;
+mod dice;
 mod dice_dump;
+mod dice_query;
 mod exe;
 mod file_status;
 mod flush_dep_files;
@@ -68,6 +72,10 @@ pub enum DebugCommand {
     AllocatorStats(AllocatorStatsCommand),
     /// Dump the DICE graph to a file and saves it to disk.
     DiceDump(DiceDumpCommand),
+    /// Inspect the DICE graph, e.g. to explain why keys were recomputed.
+    Dice(DiceCommand),
+    /// Query a compact DICE dump offline, without a running daemon.
+    DiceQuery(DiceQueryCommand),
     /// Replay a previous command by reading off from an event log.
     ///
     /// This does not interact (or even launch) a daemon.
@@ -110,6 +118,8 @@ impl DebugCommand {
         let matches = matches.subcommand().expect("subcommand not found").1;
         match self {
             DebugCommand::DiceDump(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::Dice(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::DiceQuery(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Crash(cmd) => cmd.exec(matches, ctx),
             DebugCommand::HeapDump(cmd) => cmd.exec(matches, ctx),
             DebugCommand::AllocatorStats(cmd) => cmd.exec(matches, ctx),
@@ -11,6 +11,7 @@ use std::time::Duration;
 
 use anyhow::Context as _;
 use async_trait::async_trait;
+use buck2_cli_proto::profile_request::ProfileFormat;
 use buck2_cli_proto::profile_request::ProfileOpts;
 use buck2_cli_proto::profile_request::Profiler;
 use buck2_cli_proto::target_profile::Action;
@@ -96,6 +97,14 @@ enum BuckProfileMode {
     Bytecode,
     BytecodePairs,
     Typecheck,
+    Coverage,
+}
+
+#[derive(clap::ValueEnum, Dupe, Clone, Debug)]
+enum BuckProfileFormat {
+    Default,
+    Pprof,
+    ChromeTrace,
 }
 
 #[derive(Debug, clap::Parser)]
@@ -135,6 +144,8 @@ pub struct ProfileCommonOptions {
     /// Output file path for profile data.
     ///
     /// File will be created if it does not exist, and overwritten if it does.
+    ///
+    /// For flame graph and coverage modes this is a directory.
     #[clap(long, short = 'o', value_name = "PATH")]
     output: PathArg,
 
@@ -148,8 +159,21 @@ pub struct ProfileCommonOptions {
     /// This is probably what you want when profiling analysis.
     ///
     /// `-allocated` means allocated memory, including memory which is later garbage collected.
+    ///
+    /// `coverage` writes line coverage of all evaluated files
+    /// in LCOV (`lcov.info`) and Cobertura (`cobertura.xml`) formats.
     #[clap(long, short = 'm', value_enum)]
     mode: BuckProfileMode,
+
+    /// Output format.
+    ///
+    /// `default` is flame graph for flame modes, CSV for summary modes, and so on.
+    ///
+    /// `pprof` (protobuf, for `pprof` tools) and `chrome-trace`
+    /// (JSON, for `chrome://tracing` or Perfetto) are available
+    /// for `time-flame` and `heap-flame-*` modes, and write a single file.
+    #[clap(long, value_enum, default_value = "default")]
+    format: BuckProfileFormat,
 }
 
 pub struct ProfileSubcommand {
@@ -157,6 +181,14 @@ pub struct ProfileSubcommand {
     profile_common_opts: ProfileCommonOptions,
 }
 
+fn profile_format_to_proto(format: &BuckProfileFormat) -> ProfileFormat {
+    match format {
+        BuckProfileFormat::Default => ProfileFormat::Default,
+        BuckProfileFormat::Pprof => ProfileFormat::Pprof,
+        BuckProfileFormat::ChromeTrace => ProfileFormat::ChromeTrace,
+    }
+}
+
 fn profile_mode_to_profile(mode: &BuckProfileMode) -> Profiler {
     match mode {
         BuckProfileMode::TimeFlame => Profiler::TimeFlame,
@@ -168,6 +200,7 @@ fn profile_mode_to_profile(mode: &BuckProfileMode) -> Profiler {
         BuckProfileMode::Bytecode => Profiler::Bytecode,
         BuckProfileMode::BytecodePairs => Profiler::BytecodePairs,
         BuckProfileMode::Typecheck => Profiler::Typecheck,
+        BuckProfileMode::Coverage => Profiler::Coverage,
     }
 }
 
@@ -190,6 +223,7 @@ impl StreamingCommand for ProfileSubcommand {
         let destination_path = self.profile_common_opts.output.resolve(&ctx.working_dir);
 
         let profile_mode = &self.profile_common_opts.mode;
+        let profile_format = &self.profile_common_opts.format;
 
         let destination_path = destination_path.into_string()?;
 
@@ -213,6 +247,7 @@ impl StreamingCommand for ProfileSubcommand {
                             profile_opts: Some(ProfileOpts::TargetProfile(target_opts)),
                             destination_path,
                             profiler: profile_mode_to_profile(profile_mode).into(),
+                            format: profile_format_to_proto(profile_format).into(),
                         },
                         console_opts,
                         &mut NoPartialResultHandler,
@@ -233,6 +268,7 @@ impl StreamingCommand for ProfileSubcommand {
                             profile_opts: Some(ProfileOpts::BxlProfile(bxl_opts)),
                             destination_path,
                             profiler: profile_mode_to_profile(profile_mode).into(),
+                            format: profile_format_to_proto(profile_format).into(),
                         },
                         console_opts,
                         &mut NoPartialResultHandler,
@@ -21,6 +21,7 @@ use async_trait::async_trait;
 use buck2_core::fs::paths::file_name::FileNameBuf;
 use buck2_data::CommandExecutionDetails;
 use buck2_event_observer::display;
+use buck2_event_observer::display::display_deprecated_dependency;
 use buck2_event_observer::display::display_file_watcher_end;
 use buck2_event_observer::display::TargetDisplayOptions;
 use buck2_event_observer::event_observer::EventObserver;
@@ -340,6 +341,16 @@ where
         Ok(())
     }
 
+    async fn handle_deprecated_dependency(
+        &mut self,
+        deprecated: &buck2_data::DeprecatedDependency,
+        _event: &BuckEvent,
+    ) -> anyhow::Result<()> {
+        echo!("{}", display_deprecated_dependency(deprecated))?;
+        self.notify_printed();
+        Ok(())
+    }
+
     async fn handle_file_watcher_end(
         &mut self,
         file_watcher: &buck2_data::FileWatcherEnd,
@@ -20,6 +20,7 @@ use async_trait::async_trait;
 use buck2_core::fs::paths::file_name::FileNameBuf;
 use buck2_data::CommandExecutionDetails;
 use buck2_event_observer::display;
+use buck2_event_observer::display::display_deprecated_dependency;
 use buck2_event_observer::display::display_file_watcher_end;
 use buck2_event_observer::display::TargetDisplayOptions;
 use buck2_event_observer::event_observer::DebugEventObserverExtra;
@@ -466,6 +467,28 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
         }
     }
 
+    async fn handle_deprecated_dependency(
+        &mut self,
+        deprecated: &buck2_data::DeprecatedDependency,
+        event: &BuckEvent,
+    ) -> anyhow::Result<()> {
+        match &mut self.super_console {
+            Some(super_console) => {
+                super_console.emit(Lines(vec![Line::from_iter([Span::new_colored_lossy(
+                    &display_deprecated_dependency(deprecated),
+                    Color::DarkYellow,
+                )])]));
+                Ok(())
+            }
+            None => {
+                self.state
+                    .simple_console
+                    .handle_deprecated_dependency(deprecated, event)
+                    .await
+            }
+        }
+    }
+
     async fn handle_file_watcher_end(
         &mut self,
         file_watcher: &buck2_data::FileWatcherEnd,
@@ -145,6 +145,12 @@ impl InvocationPaths {
             .join(ForwardRelativePath::unchecked_new("tmp"))
     }
 
+    /// Logs of the persistent workers, receiving their stderr, and their temporary directories.
+    pub fn workers_dir(&self) -> AbsNormPathBuf {
+        self.buck_out_path()
+            .join(ForwardRelativePath::unchecked_new("workers"))
+    }
+
     pub fn re_logs_dir(&self) -> AbsNormPathBuf {
         self.buck_out_path()
             .join(ForwardRelativePath::unchecked_new("re_logs"))
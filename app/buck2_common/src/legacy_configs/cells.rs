@@ -27,6 +27,7 @@ use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use gazebo::prelude::*;
 use once_cell::unsync::OnceCell;
 
+use crate::legacy_configs::external_cells::ExternalCells;
 use crate::legacy_configs::path::BuckConfigFile;
 use crate::legacy_configs::path::DEFAULT_BUCK_CONFIG_FILES;
 use crate::legacy_configs::push_all_files_from_a_directory;
@@ -67,6 +68,7 @@ impl BuckConfigBasedCells {
     /// Performs a parse of the root `.buckconfig` for the cell _only_ without following includes
     /// and without parsing any configs for any referenced cells. This means this function might return
     /// an empty mapping if the root `.buckconfig` does not contain the cell definitions.
+    /// External cells are not included, as their roots are in the buck-out of the daemon.
     pub fn parse_immediate_cell_mapping(project_fs: &ProjectRoot) -> anyhow::Result<CellResolver> {
         Self::parse_immediate_cell_mapping_with_file_ops(
             project_fs,
@@ -87,6 +89,7 @@ impl BuckConfigBasedCells {
             file_ops,
             &[],
             ProjectRelativePath::empty(),
+            None,
             opts,
         )?;
         Ok(cells.cell_resolver)
@@ -101,16 +104,24 @@ impl BuckConfigBasedCells {
         )
     }
 
+    /// Parses the cells and their configs, including the external cells materialized in
+    /// `external_cells_dir` whose pin is known. This does not fetch external cells.
     pub fn parse_with_config_args(
         project_fs: &ProjectRoot,
         config_args: &[LegacyConfigCmdArg],
         cwd: &ProjectRelativePath,
+        external_cells_dir: &ProjectRelativePath,
     ) -> anyhow::Result<Self> {
-        Self::parse_with_file_ops(
+        let opts = BuckConfigParseOptions {
+            follow_includes: true,
+        };
+        Self::parse_with_file_ops_and_options(
             project_fs,
             &mut DefaultConfigParserFileOps {},
             config_args,
             cwd,
+            Some(external_cells_dir),
+            opts,
         )
     }
 
@@ -123,7 +134,7 @@ impl BuckConfigBasedCells {
         let opts = BuckConfigParseOptions {
             follow_includes: true,
         };
-        Self::parse_with_file_ops_and_options(project_fs, file_ops, config_args, cwd, opts)
+        Self::parse_with_file_ops_and_options(project_fs, file_ops, config_args, cwd, None, opts)
     }
 
     fn parse_with_file_ops_and_options(
@@ -131,6 +142,7 @@ impl BuckConfigBasedCells {
         file_ops: &mut dyn ConfigParserFileOps,
         config_args: &[LegacyConfigCmdArg],
         cwd: &ProjectRelativePath,
+        external_cells_dir: Option<&ProjectRelativePath>,
         options: BuckConfigParseOptions,
     ) -> anyhow::Result<Self> {
         // Tracing file ops to record config file accesses on command invocation.
@@ -334,6 +346,18 @@ impl BuckConfigBasedCells {
                 return Err(CellsError::MissingRootCellName.into());
             }
 
+            if let Some(external_cells_dir) = external_cells_dir {
+                if is_root && config.get_section("external_cells").is_some() {
+                    let external_cells = ExternalCells::load(project_fs, external_cells_dir)?;
+                    for (name, cell_root) in external_cells.roots(&config)? {
+                        let alias = NonEmptyCellAlias::new(name)?;
+                        root_aliases.insert(alias.clone(), cell_root.clone());
+                        cells_aggregator.add_cell_entry(path.clone(), alias, cell_root.clone())?;
+                        work.push(cell_root);
+                    }
+                }
+            }
+
             if let Some(aliases) = config.get_section("repository_aliases") {
                 for (alias, destination) in aliases.iter() {
                     let alias = NonEmptyCellAlias::new(alias.to_owned())?;
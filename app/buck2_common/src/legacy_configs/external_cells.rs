@@ -0,0 +1,578 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! External cells are cells whose contents are fetched from a git revision or an archive instead
+//! of being part of the repo. They are declared in the root `.buckconfig`:
+//!
+//! ```text
+//! [external_cells]
+//!   foo = git
+//!   bar = archive
+//!
+//! [external_cell_foo]
+//!   git_origin = file:///path/to/foo.git
+//!   rev = main
+//!
+//! [external_cell_bar]
+//!   url = https://example.com/bar-1.0.tar.gz
+//!   sha256 = <hex digest of the archive>
+//!   strip_prefix = bar-1.0
+//! ```
+//!
+//! A cell is materialized under `external_cells/<name>/<pin>` in the buck-out of the isolation dir,
+//! where the pin is the git commit or the digest of the archive, so a cell that was fetched once is
+//! used without network access, and changing the pin changes the paths of all the files of the
+//! cell. Pins are recorded in `external_cells.lock` at the project root: a git revision is resolved
+//! to a commit on the first fetch and stays locked to it until the origin or the revision changes
+//! in the config.
+//!
+//! Only the daemon fetches cells, before reading the cells and configs of a command. Parsing the
+//! cells elsewhere (e.g. in the client) only reads the lockfile, and leaves out the cells whose pin
+//! is not known yet.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use anyhow::Context;
+use buck2_core::cells::cell_root_path::CellRootPathBuf;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_util::process::background_command;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::legacy_configs::LegacyBuckConfig;
+use crate::temp_path::TempPath;
+
+/// The lockfile, relative to the project root.
+pub const EXTERNAL_CELLS_LOCKFILE: &str = "external_cells.lock";
+
+/// The directory external cells are materialized in, given the buck-out of the isolation dir.
+pub fn external_cells_dir(buck_out: &ProjectRelativePath) -> ProjectRelativePathBuf {
+    buck_out.join(ForwardRelativePath::unchecked_new("external_cells"))
+}
+
+#[derive(Debug, thiserror::Error)]
+enum ExternalCellsError {
+    #[error("Unknown kind `{1}` for external cell `{0}`, expected `git` or `archive`")]
+    UnknownKind(String, String),
+    #[error("External cell `{0}` is missing `{2}` in section `[{1}]`")]
+    MissingKey(String, String, &'static str),
+    #[error("External cell `{0}` is also defined in `[repositories]`")]
+    AlsoARepository(String),
+    #[error("Archive of external cell `{0}` has digest `{2}`, expected `{1}`")]
+    DigestMismatch(String, String, String),
+    #[error("Archive `{0}` is not a `.tar`, `.tar.gz` or `.tgz` archive")]
+    UnsupportedArchive(String),
+    #[error("Archive of external cell `{0}` does not contain `{1}`")]
+    MissingStripPrefix(String, String),
+    #[error("Command `{0}` failed: {1}")]
+    CommandFailed(String, String),
+}
+
+/// Where the contents of an external cell come from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExternalCellOrigin {
+    /// A git revision (commit, branch or tag) of a repository.
+    Git { origin: String, rev: String },
+    /// A `.tar`, `.tar.gz` or `.tgz` archive, at an `http(s)://` or `file://` url.
+    Archive {
+        url: String,
+        sha256: String,
+        /// The directory of the archive that is the root of the cell.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        strip_prefix: Option<String>,
+    },
+}
+
+impl ExternalCellOrigin {
+    /// Parses the origin of the external cell `name` declared with `kind`.
+    fn parse(config: &LegacyBuckConfig, name: &str, kind: &str) -> anyhow::Result<Self> {
+        let section = format!("external_cell_{}", name);
+        let get = |key: &'static str| {
+            config.get(&section, key).map(str::to_owned).ok_or_else(|| {
+                ExternalCellsError::MissingKey(name.to_owned(), section.clone(), key)
+            })
+        };
+        match kind {
+            "git" => Ok(ExternalCellOrigin::Git {
+                origin: get("git_origin")?,
+                rev: get("rev")?,
+            }),
+            "archive" => Ok(ExternalCellOrigin::Archive {
+                url: get("url")?,
+                sha256: get("sha256")?.to_lowercase(),
+                strip_prefix: get("strip_prefix").ok(),
+            }),
+            _ => Err(ExternalCellsError::UnknownKind(name.to_owned(), kind.to_owned()).into()),
+        }
+    }
+
+    /// The pin of the cell, if it is known without fetching it.
+    fn pin(&self) -> Option<String> {
+        match self {
+            ExternalCellOrigin::Git { rev, .. } => {
+                let is_commit = rev.len() == 40 && rev.chars().all(|c| c.is_ascii_hexdigit());
+                is_commit.then(|| rev.to_lowercase())
+            }
+            ExternalCellOrigin::Archive { sha256, .. } => Some(sha256.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct LockedExternalCell {
+    /// The git commit or the digest of the archive. This comes first because TOML requires values
+    /// to be emitted before tables.
+    pin: String,
+    origin: ExternalCellOrigin,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct ExternalCellsLock {
+    #[serde(default)]
+    cells: BTreeMap<String, LockedExternalCell>,
+}
+
+/// The external cells of a project, materialized in `dir`, and its lockfile.
+pub struct ExternalCells<'a> {
+    project_fs: &'a ProjectRoot,
+    dir: ProjectRelativePathBuf,
+    lock: ExternalCellsLock,
+    new_lock: ExternalCellsLock,
+}
+
+impl<'a> ExternalCells<'a> {
+    pub fn load(project_fs: &'a ProjectRoot, dir: &ProjectRelativePath) -> anyhow::Result<Self> {
+        let lockfile =
+            project_fs.resolve(ProjectRelativePath::unchecked_new(EXTERNAL_CELLS_LOCKFILE));
+        let lock = match fs_util::read_to_string_opt(&lockfile)? {
+            Some(lock) => toml::from_str(&lock)
+                .with_context(|| format!("Error parsing lockfile `{}`", lockfile))?,
+            None => ExternalCellsLock::default(),
+        };
+        Ok(Self {
+            project_fs,
+            dir: dir.to_buf(),
+            lock,
+            new_lock: ExternalCellsLock::default(),
+        })
+    }
+
+    /// The external cells declared in the `[external_cells]` section of the root config.
+    fn declared(config: &LegacyBuckConfig) -> anyhow::Result<Vec<(String, ExternalCellOrigin)>> {
+        let section = match config.get_section("external_cells") {
+            Some(section) => section,
+            None => return Ok(Vec::new()),
+        };
+        section
+            .iter()
+            .map(|(name, kind)| {
+                if config.get("repositories", name).is_some() {
+                    return Err(ExternalCellsError::AlsoARepository(name.to_owned()).into());
+                }
+                Ok((
+                    name.to_owned(),
+                    ExternalCellOrigin::parse(config, name, kind.as_str())?,
+                ))
+            })
+            .collect()
+    }
+
+    /// The pin of the cell according to the lockfile or its origin, if it is known without
+    /// fetching the cell.
+    fn known_pin(&self, name: &str, origin: &ExternalCellOrigin) -> Option<String> {
+        match self.lock.cells.get(name) {
+            Some(locked) if &locked.origin == origin => Some(locked.pin.clone()),
+            _ => origin.pin(),
+        }
+    }
+
+    /// The roots of the external cells of the root config whose pin is known, without fetching
+    /// them. The roots of the cells which are not fetched yet do not exist.
+    pub(crate) fn roots(
+        &self,
+        config: &LegacyBuckConfig,
+    ) -> anyhow::Result<Vec<(String, CellRootPathBuf)>> {
+        let mut roots = Vec::new();
+        for (name, origin) in Self::declared(config)? {
+            if let Some(pin) = self.known_pin(&name, &origin) {
+                let root = CellRootPathBuf::new(self.cell_path(&name, &pin)?);
+                roots.push((name, root));
+            }
+        }
+        Ok(roots)
+    }
+
+    /// Fetches the external cells of the root config which are not cached, returning whether any
+    /// cell was fetched.
+    pub fn materialize_all(&mut self, config: &LegacyBuckConfig) -> anyhow::Result<bool> {
+        let mut fetched = false;
+        for (name, origin) in Self::declared(config)? {
+            fetched |= self
+                .materialize(&name, origin)
+                .with_context(|| format!("Error materializing external cell `{}`", name))?;
+        }
+        Ok(fetched)
+    }
+
+    /// Fetches the cell if it is not cached, returning whether it was fetched.
+    fn materialize(&mut self, name: &str, origin: ExternalCellOrigin) -> anyhow::Result<bool> {
+        let pin = self.known_pin(name, &origin);
+        let cell_dir = self.dir.join(ForwardRelativePath::new(name)?);
+
+        let (pin, fetched) = match pin {
+            Some(pin)
+                if fs_util::try_exists(self.project_fs.resolve(&self.cell_path(name, &pin)?))? =>
+            {
+                (pin, false)
+            }
+            pin => {
+                let abs_cell_dir = self.project_fs.resolve(&cell_dir);
+                fs_util::create_dir_all(&abs_cell_dir)?;
+                let temp = TempPath::new_in(&abs_cell_dir)?;
+                fs_util::create_dir_all(temp.path())?;
+
+                let (fetched, fetched_pin) = match &origin {
+                    ExternalCellOrigin::Git { origin, rev } => {
+                        fetch_git(temp.path(), origin, pin.as_deref().unwrap_or(rev))?
+                    }
+                    ExternalCellOrigin::Archive {
+                        url,
+                        sha256,
+                        strip_prefix,
+                    } => fetch_archive(temp.path(), name, url, sha256, strip_prefix.as_deref())?,
+                };
+
+                let dest = self
+                    .project_fs
+                    .resolve(&self.cell_path(name, &fetched_pin)?);
+                // Another invocation may have fetched the same pin concurrently.
+                if !fs_util::try_exists(&dest)? {
+                    fs_util::rename(&fetched, &dest)?;
+                }
+                temp.close()?;
+                (fetched_pin, true)
+            }
+        };
+
+        self.new_lock
+            .cells
+            .insert(name.to_owned(), LockedExternalCell { pin, origin });
+        Ok(fetched)
+    }
+
+    fn cell_path(&self, name: &str, pin: &str) -> anyhow::Result<ProjectRelativePathBuf> {
+        Ok(self
+            .dir
+            .join(ForwardRelativePath::new(name)?)
+            .join(ForwardRelativePath::new(pin)?))
+    }
+
+    /// Writes the lockfile if the pins of the external cells changed, returning whether it did.
+    pub fn write_lock(self) -> anyhow::Result<bool> {
+        if self.new_lock == self.lock {
+            return Ok(false);
+        }
+        let lockfile = self
+            .project_fs
+            .resolve(ProjectRelativePath::unchecked_new(EXTERNAL_CELLS_LOCKFILE));
+        if self.new_lock.cells.is_empty() {
+            fs_util::remove_file(&lockfile)?;
+            return Ok(true);
+        }
+        let content = format!(
+            "# Generated by buck2 from the `[external_cells]` of `.buckconfig`, do not edit.\n{}",
+            toml::to_string(&self.new_lock)?
+        );
+        fs_util::write(&lockfile, content)
+            .with_context(|| format!("Error writing lockfile `{}`", lockfile))?;
+        Ok(true)
+    }
+}
+
+fn run_command(command: &mut std::process::Command) -> anyhow::Result<String> {
+    let description = format!("{:?}", command);
+    let output = command
+        .output()
+        .with_context(|| format!("Error running `{}`", description))?;
+    if !output.status.success() {
+        return Err(ExternalCellsError::CommandFailed(
+            description,
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        )
+        .into());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Checks out the revision of the repository in `dir/checkout`, returning that directory and
+/// the commit that was checked out.
+fn fetch_git(
+    dir: &AbsNormPath,
+    origin: &str,
+    rev: &str,
+) -> anyhow::Result<(AbsNormPathBuf, String)> {
+    let checkout = dir.join(ForwardRelativePath::new("checkout")?);
+    let git = |args: &[&str]| {
+        let mut command = background_command("git");
+        command.arg("-C").arg(checkout.as_path()).args(args);
+        run_command(&mut command)
+    };
+
+    fs_util::create_dir_all(&checkout)?;
+    git(&["init", "--quiet"])?;
+    git(&["fetch", "--quiet", "--depth", "1", origin, rev])?;
+    git(&["checkout", "--quiet", "FETCH_HEAD"])?;
+    let commit = git(&["rev-parse", "HEAD"])?.trim().to_owned();
+    fs_util::remove_all(checkout.join(ForwardRelativePath::new(".git")?))?;
+
+    Ok((checkout, commit))
+}
+
+/// Downloads the archive, checks its digest and extracts it in `dir/archive`, returning the
+/// directory that is the root of the cell and the digest.
+fn fetch_archive(
+    dir: &AbsNormPath,
+    name: &str,
+    url: &str,
+    sha256: &str,
+    strip_prefix: Option<&str>,
+) -> anyhow::Result<(AbsNormPathBuf, String)> {
+    let content = match url.strip_prefix("file://") {
+        Some(path) => fs_util::read(path)?,
+        None => {
+            let mut curl = background_command("curl");
+            curl.args(["--fail", "--silent", "--show-error", "--location", url]);
+            let output = curl.output().context("Error running `curl`")?;
+            if !output.status.success() {
+                return Err(ExternalCellsError::CommandFailed(
+                    format!("curl {}", url),
+                    String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+                )
+                .into());
+            }
+            output.stdout
+        }
+    };
+
+    let digest = hex::encode(Sha256::digest(&content));
+    if digest != sha256 {
+        return Err(
+            ExternalCellsError::DigestMismatch(name.to_owned(), sha256.to_owned(), digest).into(),
+        );
+    }
+
+    let reader: Box<dyn Read> = if url.ends_with(".tar.gz") || url.ends_with(".tgz") {
+        Box::new(flate2::read::GzDecoder::new(content.as_slice()))
+    } else if url.ends_with(".tar") {
+        Box::new(content.as_slice())
+    } else {
+        return Err(ExternalCellsError::UnsupportedArchive(url.to_owned()).into());
+    };
+    let archive = dir.join(ForwardRelativePath::new("archive")?);
+    tar::Archive::new(reader)
+        .unpack(&archive)
+        .with_context(|| format!("Error extracting `{}`", url))?;
+
+    let root = match strip_prefix {
+        Some(prefix) => {
+            let root = archive.join(ForwardRelativePath::new(prefix)?);
+            if !fs_util::try_exists(&root)? {
+                return Err(ExternalCellsError::MissingStripPrefix(
+                    name.to_owned(),
+                    prefix.to_owned(),
+                )
+                .into());
+            }
+            root
+        }
+        None => archive,
+    };
+    Ok((root, digest))
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::fs::project::ProjectRootTemp;
+    use indoc::indoc;
+
+    use super::*;
+    use crate::legacy_configs::testing::parse;
+
+    fn dir() -> ProjectRelativePathBuf {
+        external_cells_dir(ProjectRelativePath::unchecked_new("buck-out/v2"))
+    }
+
+    fn write_archive(path: &AbsNormPath) -> anyhow::Result<String> {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        let content = b"[repositories]\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, "lib-1.0/.buckconfig", &content[..])?;
+        let archive = builder.into_inner()?.finish()?;
+        fs_util::write(path, &archive)?;
+        Ok(hex::encode(Sha256::digest(&archive)))
+    }
+
+    #[test]
+    fn test_archive_cell() -> anyhow::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let archive_path = temp
+            .path()
+            .root()
+            .join(ForwardRelativePath::new("lib.tar.gz")?);
+        let sha256 = write_archive(&archive_path)?;
+
+        let config = parse(
+            &[(
+                "/config",
+                &format!(
+                    indoc!(
+                        r#"
+                        [external_cells]
+                          lib = archive
+                        [external_cell_lib]
+                          url = file://{}
+                          sha256 = {}
+                          strip_prefix = lib-1.0
+                        "#
+                    ),
+                    archive_path, sha256
+                ),
+            )],
+            "/config",
+        )?;
+
+        // The digest is the pin, so the root is known before fetching the cell.
+        let mut cells = ExternalCells::load(temp.path(), &dir())?;
+        let root = format!("buck-out/v2/external_cells/lib/{}", sha256);
+        let roots = cells.roots(&config)?;
+        assert_eq!(
+            vec![("lib".to_owned(), CellRootPathBuf::testing_new(&root))],
+            roots
+        );
+        assert!(cells.materialize_all(&config)?);
+        assert!(cells.write_lock()?);
+        assert!(fs_util::try_exists(temp.path().resolve(
+            ProjectRelativePath::new(&format!("{}/.buckconfig", root))?
+        ))?);
+
+        // The cell is now cached, so it does not need the archive anymore.
+        fs_util::remove_file(&archive_path)?;
+        let mut cells = ExternalCells::load(temp.path(), &dir())?;
+        assert_eq!(roots, cells.roots(&config)?);
+        assert!(!cells.materialize_all(&config)?);
+        assert!(!cells.write_lock()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive_digest_mismatch() -> anyhow::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let archive_path = temp
+            .path()
+            .root()
+            .join(ForwardRelativePath::new("lib.tar.gz")?);
+        write_archive(&archive_path)?;
+
+        let mut cells = ExternalCells::load(temp.path(), &dir())?;
+        let err = cells
+            .materialize(
+                "lib",
+                ExternalCellOrigin::Archive {
+                    url: format!("file://{}", archive_path),
+                    sha256: "0".repeat(64),
+                    strip_prefix: None,
+                },
+            )
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("expected `0000"), "{:#}", err);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_git_cell() -> anyhow::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let root = temp.path().root();
+        let work = root.join(ForwardRelativePath::new("work")?);
+        let bare = root.join(ForwardRelativePath::new("repo.git")?);
+        fs_util::create_dir_all(&work)?;
+        fs_util::write(work.join(ForwardRelativePath::new("BUCK")?), "")?;
+        for args in [
+            &["init", "--quiet"][..],
+            &["add", "BUCK"],
+            &[
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "--quiet",
+                "-m",
+                "init",
+            ],
+        ] {
+            run_command(
+                background_command("git")
+                    .arg("-C")
+                    .arg(work.as_path())
+                    .args(args),
+            )?;
+        }
+        run_command(
+            background_command("git")
+                .args(["clone", "--quiet", "--bare"])
+                .arg(work.as_path())
+                .arg(bare.as_path()),
+        )?;
+        let commit = run_command(
+            background_command("git")
+                .arg("-C")
+                .arg(bare.as_path())
+                .args(["rev-parse", "HEAD"]),
+        )?
+        .trim()
+        .to_owned();
+
+        let origin = ExternalCellOrigin::Git {
+            origin: format!("file://{}", bare),
+            rev: "HEAD".to_owned(),
+        };
+        // The commit of the revision is not known before fetching it.
+        let mut cells = ExternalCells::load(temp.path(), &dir())?;
+        assert_eq!(None, cells.known_pin("lib", &origin));
+        assert!(cells.materialize("lib", origin.clone())?);
+        assert!(cells.write_lock()?);
+        assert!(fs_util::try_exists(temp.path().resolve(
+            ProjectRelativePath::new(&format!("buck-out/v2/external_cells/lib/{}/BUCK", commit))?
+        ))?);
+
+        // The revision is locked to the commit, and the cell is cached.
+        fs_util::remove_all(&bare)?;
+        let mut cells = ExternalCells::load(temp.path(), &dir())?;
+        assert_eq!(Some(commit), cells.known_pin("lib", &origin));
+        assert!(!cells.materialize("lib", origin)?);
+        Ok(())
+    }
+}
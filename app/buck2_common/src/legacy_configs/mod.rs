@@ -12,6 +12,7 @@
 
 pub mod cells;
 pub mod dice;
+pub mod external_cells;
 pub(crate) mod path;
 pub mod view;
 
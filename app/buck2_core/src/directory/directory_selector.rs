@@ -96,6 +96,21 @@ impl DirectorySelector {
         }
     }
 
+    /// Whether this selects `path`, an entry below it, or a directory containing it.
+    pub fn selects_within(&self, path: &ForwardRelativePath) -> bool {
+        let mut selector = self;
+        for name in path.iter() {
+            match selector {
+                Self::Take => return true,
+                Self::Traverse(s) => match s.get(name) {
+                    Some(s) => selector = s,
+                    None => return false,
+                },
+            }
+        }
+        !selector.is_empty()
+    }
+
     /// Filter a DirectoryBuilder by only retaining matching entries.
     pub fn filter<L, H>(&self, dir: &mut DirectoryBuilder<L, H>) -> Result<(), DirectoryFilterError>
     where
@@ -424,6 +424,19 @@ fn test_search() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_selects_within() {
+    let mut selector = DirectorySelector::empty();
+    selector.select(path("a/b/c"));
+    selector.select(path("d"));
+
+    assert!(selector.selects_within(path("a")));
+    assert!(selector.selects_within(path("a/b/c")));
+    assert!(selector.selects_within(path("d/e")));
+    assert!(!selector.selects_within(path("a/b/cc")));
+    assert!(!selector.selects_within(path("b")));
+}
+
 #[test]
 fn test_filter() -> anyhow::Result<()> {
     let mut b = TestDirectoryBuilder::empty();
@@ -328,6 +328,13 @@ pub fn display_analysis_stage(stage: &buck2_data::analysis_stage_start::Stage) -
     }
 }
 
+pub fn display_deprecated_dependency(deprecated: &buck2_data::DeprecatedDependency) -> String {
+    format!(
+        "Warning: `{}` depends on deprecated target `{}`: {}",
+        deprecated.target, deprecated.dependency, deprecated.message
+    )
+}
+
 pub fn display_file_watcher_end(file_watcher_end: &buck2_data::FileWatcherEnd) -> Vec<String> {
     const MAX_PRINT_MESSAGES: usize = 3;
     let mut res = Vec::new();
@@ -520,6 +527,7 @@ pub fn display_action_error<'a>(
                 None => "Unexpected command status".to_owned(),
             }
         }
+        Error::CommandTimedOut(timed_out) => timed_out.message.clone(),
     };
 
     Ok(ActionErrorDisplay {
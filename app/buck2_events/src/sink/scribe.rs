@@ -369,6 +369,7 @@ mod fbcode {
                     Some(Data::ReSession(..)) => true,
                     Some(Data::StructuredError(..)) => true,
                     Some(Data::TestResult(..)) => true,
+                    Some(Data::DeprecatedDependency(..)) => true,
                     None => false,
                     _ => false,
                 }
@@ -223,6 +223,12 @@ impl CommandExecutionPaths {
         &self.output_paths
     }
 
+    /// The resolved path of the output, if it is one of the outputs.
+    pub fn output_path(&self, output: &CommandExecutionOutput) -> Option<&ProjectRelativePath> {
+        let index = self.outputs.get_index_of(output)?;
+        Some(&self.output_paths[index].0)
+    }
+
     pub fn input_files_bytes(&self) -> u64 {
         self.input_files_bytes
     }
@@ -256,6 +262,10 @@ pub struct CommandExecutionRequest {
     /// Whether to disable capturing performance counters for this execution.
     disable_miniperf: bool,
     required_local_resources: SortedSet<LocalResourceState>,
+    /// If set, the command can run in a persistent worker when executed locally.
+    worker: Option<WorkerSpec>,
+    /// Files the standard streams of the command are redirected from and to.
+    std_redirects: CommandStdRedirects,
 }
 
 impl CommandExecutionRequest {
@@ -280,6 +290,8 @@ impl CommandExecutionRequest {
             force_full_hybrid_if_capable: false,
             disable_miniperf: false,
             required_local_resources: SortedSet::new(),
+            worker: None,
+            std_redirects: CommandStdRedirects::default(),
         }
     }
 
@@ -425,6 +437,72 @@ impl CommandExecutionRequest {
     pub fn required_local_resources(&self) -> &SortedSet<LocalResourceState> {
         &self.required_local_resources
     }
+
+    pub fn with_worker(mut self, worker: Option<WorkerSpec>) -> Self {
+        self.worker = worker;
+        self
+    }
+
+    pub fn worker(&self) -> Option<&WorkerSpec> {
+        self.worker.as_ref()
+    }
+
+    pub fn with_std_redirects(mut self, std_redirects: CommandStdRedirects) -> Self {
+        self.std_redirects = std_redirects;
+        self
+    }
+
+    pub fn std_redirects(&self) -> &CommandStdRedirects {
+        &self.std_redirects
+    }
+}
+
+/// Redirections of the standard streams of a command. The stdout and stderr of the command are
+/// still captured and reported as usual, but are also written to the given outputs, which must be
+/// outputs of the request.
+#[derive(Debug, Default)]
+pub struct CommandStdRedirects {
+    /// File the command reads its stdin from. Without it, the stdin of the command is empty.
+    pub stdin: Option<ProjectRelativePathBuf>,
+    pub stdout: Option<CommandExecutionOutput>,
+    pub stderr: Option<CommandExecutionOutput>,
+}
+
+impl CommandStdRedirects {
+    /// The outputs the stdout and stderr are written to, along with the stream they get.
+    pub fn outputs<'a, T>(
+        &'a self,
+        stdout: &'a T,
+        stderr: &'a T,
+    ) -> impl Iterator<Item = (&'a CommandExecutionOutput, &'a T)> + 'a {
+        self.stdout
+            .iter()
+            .map(move |o| (o, stdout))
+            .chain(self.stderr.iter().map(move |o| (o, stderr)))
+    }
+}
+
+/// The protocol spoken by a persistent worker over its stdin and stdout, as defined by Bazel:
+/// either newline-delimited JSON or length-delimited protobuf `WorkRequest` and `WorkResponse`
+/// messages.
+#[derive(Copy, Clone, Dupe, Debug, Display, PartialEq, Eq, Hash, Allocative)]
+pub enum WorkerProtocol {
+    #[display(fmt = "json")]
+    Json,
+    #[display(fmt = "proto")]
+    Proto,
+}
+
+/// How to run a command in a persistent worker. The args of the request start with the worker
+/// startup command, and the remaining args are sent to the worker in a work request. Executors
+/// that do not support workers run the args as a regular command.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct WorkerSpec {
+    /// The command starting the worker, without the `--persistent_worker` flag.
+    pub exe: Vec<String>,
+    /// The environment the worker is started with, which the environment of the request includes.
+    pub env: Vec<(String, String)>,
+    pub protocol: WorkerProtocol,
 }
 
 /// Is an output a file or a directory
@@ -57,7 +57,7 @@ use buck2_execute::knobs::ExecutorGlobalKnobs;
 use buck2_execute::materialize::materializer::MaterializationError;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_forkserver::client::ForkserverClient;
-use buck2_forkserver::run::gather_output;
+use buck2_forkserver::run::gather_output_with_stdin;
 use buck2_forkserver::run::timeout_into_cancellation;
 use buck2_forkserver::run::GatherOutputStatus;
 use buck2_util::process::background_command;
@@ -75,6 +75,8 @@ use more_futures::cancellation::CancellationContext;
 use thiserror::Error;
 use tracing::info;
 
+use crate::executors::worker::WorkerPool;
+
 #[derive(Debug, Error)]
 enum LocalExecutionError {
     #[error("Args list was empty")]
@@ -95,6 +97,7 @@ pub struct LocalExecutor {
     forkserver: Option<ForkserverClient>,
     #[allow(unused)]
     knobs: ExecutorGlobalKnobs,
+    worker_pool: Arc<WorkerPool>,
 }
 
 impl LocalExecutor {
@@ -106,6 +109,7 @@ impl LocalExecutor {
         root: AbsNormPathBuf,
         forkserver: Option<ForkserverClient>,
         knobs: ExecutorGlobalKnobs,
+        worker_pool: Arc<WorkerPool>,
     ) -> Self {
         Self {
             artifact_fs,
@@ -115,6 +119,7 @@ impl LocalExecutor {
             root,
             forkserver,
             knobs,
+            worker_pool,
         }
     }
 
@@ -126,6 +131,7 @@ impl LocalExecutor {
         args: impl IntoIterator<Item = impl AsRef<OsStr> + Send> + Send + 'a,
         env: impl IntoIterator<Item = (impl AsRef<OsStr> + Send, impl AsRef<OsStr> + Send)> + Send + 'a,
         working_directory: Option<&'a ProjectRelativePath>,
+        stdin: Option<&'a ProjectRelativePath>,
         timeout: Option<Duration>,
         env_inheritance: Option<&'a EnvironmentInheritance>,
         liveliness_observer: impl LivelinessObserver + 'static,
@@ -141,6 +147,7 @@ impl LocalExecutor {
             };
 
             let working_directory: &Path = working_directory.as_ref();
+            let stdin = stdin.map(|stdin| self.root.join(stdin));
 
             match &self.forkserver {
                 Some(forkserver) => {
@@ -152,6 +159,7 @@ impl LocalExecutor {
                             args,
                             env,
                             working_directory,
+                            stdin.as_deref().map(|stdin| stdin.as_path()),
                             timeout,
                             env_inheritance,
                             liveliness_observer,
@@ -162,12 +170,18 @@ impl LocalExecutor {
 
                     #[cfg(not(unix))]
                     {
-                        let _unused = (forkserver, disable_miniperf);
+                        let _unused = (forkserver, disable_miniperf, stdin);
                         Err(anyhow::anyhow!("Forkserver is not supported off-UNIX"))
                     }
                 }
 
-                None => {
+                None => async {
+                    let stdin = stdin
+                        .map(|stdin| {
+                            std::fs::File::open(stdin.as_path())
+                                .with_context(|| format!("Error opening stdin `{}`", stdin))
+                        })
+                        .transpose()?;
                     let mut cmd = background_command(exe);
                     cmd.current_dir(working_directory);
                     cmd.args(args);
@@ -186,8 +200,9 @@ impl LocalExecutor {
                     let cancellation =
                         select(timeout.boxed(), alive.boxed()).map(|r| r.factor_first().0);
 
-                    gather_output(cmd, cancellation).await
+                    gather_output_with_stdin(cmd, stdin, cancellation).await
                 }
+                .await
                 .with_context(|| format!("Failed to gather output from command: {}", exe)),
             }
         }
@@ -371,19 +386,42 @@ impl LocalExecutor {
                 let execution_start = Instant::now();
                 let start_time = SystemTime::now();
 
-                let env = iter_env().map(|(k, v)| (k, v.into_os_str()));
-                let r = self
-                    .exec(
-                        &args[0],
-                        &args[1..],
-                        env,
-                        request.working_directory(),
-                        request.timeout(),
-                        request.local_environment_inheritance(),
-                        liveliness_observer,
-                        request.disable_miniperf(),
-                    )
-                    .await;
+                // Workers run in the project root, so commands with another working directory
+                // run as regular commands.
+                let worker_request = request
+                    .worker()
+                    .filter(|_| self.worker_pool.enabled() && request.working_directory().is_none())
+                    .and_then(|worker| Some((worker, args.strip_prefix(worker.exe.as_slice())?)));
+
+                let r = match worker_request {
+                    Some((worker, worker_args)) => {
+                        self.worker_pool
+                            .exec(
+                                worker,
+                                worker_args,
+                                self.root.as_path(),
+                                request.timeout(),
+                                request.local_environment_inheritance(),
+                                liveliness_observer,
+                            )
+                            .await
+                    }
+                    None => {
+                        let env = iter_env().map(|(k, v)| (k, v.into_os_str()));
+                        self.exec(
+                            &args[0],
+                            &args[1..],
+                            env,
+                            request.working_directory(),
+                            request.std_redirects().stdin.as_deref(),
+                            request.timeout(),
+                            request.local_environment_inheritance(),
+                            liveliness_observer,
+                            request.disable_miniperf(),
+                        )
+                        .await
+                    }
+                };
 
                 let execution_time = execution_start.elapsed();
 
@@ -418,6 +456,10 @@ impl LocalExecutor {
                 exit_code,
                 execution_stats,
             } => {
+                if let Err(e) = self.write_std_redirects(request, &std_streams) {
+                    return manager.error("write_std_redirects_failed", e);
+                }
+
                 let outputs = match self
                     .calculate_and_declare_output_values(request, digest_config)
                     .await
@@ -479,6 +521,24 @@ impl LocalExecutor {
         }
     }
 
+    /// Write the stdout and stderr of the command to the outputs they are redirected to.
+    fn write_std_redirects(
+        &self,
+        request: &CommandExecutionRequest,
+        std_streams: &CommandStdStreams,
+    ) -> anyhow::Result<()> {
+        let (stdout, stderr) = match std_streams {
+            CommandStdStreams::Local { stdout, stderr } => (stdout, stderr),
+            _ => return Ok(()),
+        };
+        for (output, bytes) in request.std_redirects().outputs(stdout, stderr) {
+            let path = output.as_ref().resolve(&self.artifact_fs).into_path();
+            fs_util::write(self.root.join(&path), bytes)
+                .with_context(|| format!("writing std stream to {:?}", path))?;
+        }
+        Ok(())
+    }
+
     async fn calculate_and_declare_output_values(
         &self,
         request: &CommandExecutionRequest,
@@ -874,6 +934,7 @@ mod unix {
         args: impl IntoIterator<Item = impl AsRef<OsStr>>,
         env: impl IntoIterator<Item = (impl AsRef<OsStr>, impl AsRef<OsStr>)>,
         working_directory: &Path,
+        stdin: Option<&Path>,
         command_timeout: Option<Duration>,
         env_inheritance: Option<&EnvironmentInheritance>,
         liveliness_observer: impl LivelinessObserver + 'static,
@@ -893,6 +954,7 @@ mod unix {
             env: vec![],
             timeout: command_timeout.try_map(|d| d.try_into())?,
             enable_miniperf,
+            stdin: stdin.map_or_else(Vec::new, |p| p.as_os_str().as_bytes().to_vec()),
         };
         apply_local_execution_environment(&mut req, working_directory, env, env_inheritance);
         forkserver
@@ -958,14 +1020,23 @@ mod tests {
     use buck2_core::cells::CellResolver;
     use buck2_core::fs::artifact_path_resolver::ArtifactFs;
     use buck2_core::fs::buck_out_path::BuckOutPathResolver;
+    use buck2_core::fs::buck_out_path::BuckOutTestPath;
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
     use buck2_core::fs::project::ProjectRoot;
     use buck2_core::fs::project::ProjectRootTemp;
     use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
     use buck2_execute::execute::blocking::testing::DummyBlockingExecutor;
+    use buck2_execute::execute::request::CommandExecutionPaths;
+    use buck2_execute::execute::request::CommandStdRedirects;
+    use buck2_execute::execute::request::OutputCreationBehavior;
     use buck2_execute::materialize::nodisk::NoDiskMaterializer;
+    use buck2_forkserver::run::gather_output;
     use host_sharing::HostSharingStrategy;
+    use indexmap::IndexSet;
 
     use super::*;
+    use crate::executors::worker::WorkerPoolConfig;
 
     #[tokio::test]
     async fn test_gather_output() -> anyhow::Result<()> {
@@ -1077,6 +1148,14 @@ mod tests {
             temp.path().root().to_buf(),
             None,
             ExecutorGlobalKnobs::default(),
+            Arc::new(WorkerPool::new(
+                WorkerPoolConfig {
+                    max_workers_per_key: 0,
+                    max_workers: 0,
+                    idle_timeout: Duration::ZERO,
+                },
+                temp.path().root().to_buf(),
+            )),
         );
 
         Ok((executor, temp.path().root().to_buf(), temp))
@@ -1095,6 +1174,7 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
                 NoopLivelinessObserver::create(),
                 false,
             )
@@ -1130,6 +1210,7 @@ mod tests {
                 &HashMap::<String, String>::default(),
                 None,
                 None,
+                None,
                 Some(&EnvironmentInheritance::empty()),
                 NoopLivelinessObserver::create(),
                 false,
@@ -1140,4 +1221,109 @@ mod tests {
 
         Ok(())
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_exec_cmd_timeout_kills_command() -> anyhow::Result<()> {
+        let (executor, root, _tmpdir) = test_executor()?;
+
+        let (status, _, _) = executor
+            .exec(
+                "sh",
+                ["-c", "sleep 2; touch not_killed"],
+                &HashMap::<String, String>::default(),
+                None,
+                None,
+                Some(Duration::from_millis(500)),
+                None,
+                NoopLivelinessObserver::create(),
+                false,
+            )
+            .await?;
+        assert!(matches!(status, GatherOutputStatus::TimedOut(..)));
+
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        assert!(
+            !root
+                .join(ProjectRelativePath::unchecked_new("not_killed"))
+                .as_path()
+                .exists()
+        );
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_exec_cmd_stdin() -> anyhow::Result<()> {
+        let (executor, root, _tmpdir) = test_executor()?;
+        let stdin = ProjectRelativePath::unchecked_new("stdin");
+        fs_util::write(root.join(stdin), "hello")?;
+
+        for (stdin, expected) in [(Some(stdin), "hello"), (None, "")] {
+            let (status, stdout, _) = executor
+                .exec(
+                    "cat",
+                    Vec::<String>::new(),
+                    &HashMap::<String, String>::default(),
+                    None,
+                    stdin,
+                    None,
+                    None,
+                    NoopLivelinessObserver::create(),
+                    false,
+                )
+                .await?;
+            assert!(
+                matches!(status, GatherOutputStatus::Finished { exit_code, .. } if exit_code == 0)
+            );
+            assert_eq!(str::from_utf8(&stdout)?, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_std_redirects() -> anyhow::Result<()> {
+        let (executor, root, _tmpdir) = test_executor()?;
+
+        let output = |name: &str| CommandExecutionOutput::TestPath {
+            path: BuckOutTestPath::new(
+                ForwardRelativePathBuf::unchecked_new("t".to_owned()),
+                ForwardRelativePathBuf::unchecked_new(name.to_owned()),
+            ),
+            create: OutputCreationBehavior::Parent,
+        };
+        let paths = CommandExecutionPaths::new(
+            Vec::new(),
+            IndexSet::from([output("out"), output("err")]),
+            &executor.artifact_fs,
+            DigestConfig::testing_default(),
+        )?;
+        let request =
+            CommandExecutionRequest::new(vec!["true".to_owned()], paths, Default::default())
+                .with_std_redirects(CommandStdRedirects {
+                    stdin: None,
+                    stdout: Some(output("out")),
+                    stderr: Some(output("err")),
+                });
+
+        let dir = ProjectRelativePath::unchecked_new("buck_out/v2/test/t");
+        fs_util::create_dir_all(root.join(dir))?;
+        executor.write_std_redirects(
+            &request,
+            &CommandStdStreams::Local {
+                stdout: b"to stdout".to_vec(),
+                stderr: b"to stderr".to_vec(),
+            },
+        )?;
+
+        let read = |name: &str| {
+            fs_util::read_to_string(root.join(&dir.join(ForwardRelativePath::unchecked_new(name))))
+        };
+        assert_eq!(read("out")?, "to stdout");
+        assert_eq!(read("err")?, "to stderr");
+
+        Ok(())
+    }
 }
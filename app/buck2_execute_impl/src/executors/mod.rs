@@ -11,3 +11,4 @@ pub mod caching;
 pub mod hybrid;
 pub mod local;
 pub mod re;
+pub mod worker;
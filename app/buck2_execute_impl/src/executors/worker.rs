@@ -0,0 +1,645 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Persistent workers: long-lived processes that each run many actions, which they receive as
+//! work requests of the [Bazel worker protocol](https://bazel.build/remote/persistent) on their
+//! stdin, answering with work responses on their stdout.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::iter;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Once;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Context as _;
+use buck2_common::liveliness_observer::LivelinessObserver;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_execute::execute::environment_inheritance::EnvironmentInheritance;
+use buck2_execute::execute::request::WorkerProtocol;
+use buck2_execute::execute::request::WorkerSpec;
+use buck2_forkserver::run::timeout_into_cancellation;
+use buck2_forkserver::run::GatherOutputStatus;
+use buck2_util::process::background_command;
+use dupe::Dupe;
+use futures::future::select;
+use futures::future::FutureExt;
+use parking_lot::Mutex;
+use prost::Message;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::process::Child;
+use tokio::process::ChildStdin;
+use tokio::process::ChildStdout;
+use tokio::sync::Semaphore;
+
+use crate::executors::local::apply_local_execution_environment;
+
+#[derive(Debug, Error)]
+enum WorkerError {
+    #[error("Persistent worker exited unexpectedly, see its log in `{0}`")]
+    Exited(String),
+    #[error("Persistent worker sent an invalid work response, see its log in `{0}`")]
+    InvalidResponse(String),
+    #[error("Persistent worker work response length is not a valid varint")]
+    InvalidLength,
+}
+
+/// A request sent to a worker. Workers are singleplex, so the request id is always 0. Inputs are
+/// materialized before the request is sent, so workers read them from disk and `inputs` is not
+/// set.
+#[derive(Clone, PartialEq, Message, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WorkRequest {
+    #[prost(string, repeated, tag = "1")]
+    arguments: Vec<String>,
+    #[prost(int32, tag = "3")]
+    request_id: i32,
+}
+
+/// The response of a worker. Fields equal to their default value may be omitted in JSON.
+#[derive(Clone, PartialEq, Message, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct WorkResponse {
+    #[prost(int32, tag = "1")]
+    exit_code: i32,
+    #[prost(string, tag = "2")]
+    output: String,
+    #[prost(int32, tag = "3")]
+    request_id: i32,
+}
+
+/// Counts a worker in [`PoolInner::live`] until it is dropped.
+struct LiveWorker(Arc<AtomicUsize>);
+
+impl Drop for LiveWorker {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+struct Worker {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    protocol: WorkerProtocol,
+    log: PathBuf,
+    /// The `$TMPDIR` of the worker, deleted with it.
+    tmp_dir: PathBuf,
+    _live: LiveWorker,
+}
+
+impl Worker {
+    /// Starts a worker in the project root. Workers are shared between actions, so they get the
+    /// environment of the spec and their own `$TMPDIR`, not the one of an action.
+    fn spawn(
+        spec: &WorkerSpec,
+        root: &Path,
+        dir: &AbsNormPath,
+        id: u64,
+        env_inheritance: Option<&EnvironmentInheritance>,
+        live: LiveWorker,
+    ) -> anyhow::Result<Self> {
+        let (exe, args) = spec
+            .exe
+            .split_first()
+            .context("Persistent worker command is empty")?;
+
+        let log = dir.as_path().join(format!("worker-{}.log", id));
+        let tmp_dir = dir.as_path().join(format!("worker-{}.tmp", id));
+        fs_util::create_dir_all(&tmp_dir)?;
+        let log_file = std::fs::File::create(&log)
+            .with_context(|| format!("Error creating worker log `{}`", log.display()))?;
+
+        let tmp_vars: &[&str] = if cfg!(windows) {
+            &["TEMP", "TMP"]
+        } else {
+            &["TMPDIR"]
+        };
+        let daemon_uuid = buck2_events::daemon_id::DAEMON_UUID.to_string();
+        let env = tmp_vars
+            .iter()
+            .map(|k| (OsStr::new(k), tmp_dir.as_os_str()))
+            .chain(spec.env.iter().map(|(k, v)| (OsStr::new(k), OsStr::new(v))))
+            .chain(iter::once((
+                OsStr::new("BUCK2_DAEMON_UUID"),
+                OsStr::new(&daemon_uuid),
+            )));
+
+        let mut cmd = background_command(exe);
+        cmd.current_dir(root);
+        cmd.args(args);
+        cmd.arg("--persistent_worker");
+        apply_local_execution_environment(&mut cmd, root, env, env_inheritance);
+
+        let mut child = tokio::process::Command::from(cmd)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(log_file)
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Error spawning persistent worker `{}`", exe))?;
+
+        let stdin = child.stdin.take().context("Worker stdin is not piped")?;
+        let stdout = child.stdout.take().context("Worker stdout is not piped")?;
+
+        Ok(Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            protocol: spec.protocol,
+            log,
+            tmp_dir,
+            _live: live,
+        })
+    }
+
+    fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    async fn run(&mut self, request: &WorkRequest) -> anyhow::Result<WorkResponse> {
+        let log = self.log.display().to_string();
+        let exited = || WorkerError::Exited(log.clone());
+        let invalid = || WorkerError::InvalidResponse(log.clone());
+        let message = match self.protocol {
+            WorkerProtocol::Json => {
+                let mut message = serde_json::to_vec(request)?;
+                message.push(b'\n');
+                message
+            }
+            WorkerProtocol::Proto => request.encode_length_delimited_to_vec(),
+        };
+        self.stdin.write_all(&message).await.with_context(exited)?;
+        self.stdin.flush().await.with_context(exited)?;
+
+        match self.protocol {
+            WorkerProtocol::Json => {
+                let mut line = String::new();
+                if self
+                    .stdout
+                    .read_line(&mut line)
+                    .await
+                    .with_context(exited)?
+                    == 0
+                {
+                    return Err(exited().into());
+                }
+                serde_json::from_str(line.trim()).with_context(invalid)
+            }
+            WorkerProtocol::Proto => {
+                let len = read_varint(&mut self.stdout).await.with_context(exited)?;
+                let mut buf = vec![0; len];
+                self.stdout
+                    .read_exact(&mut buf)
+                    .await
+                    .with_context(exited)?;
+                WorkResponse::decode(buf.as_slice()).with_context(invalid)
+            }
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        // Cleanup is best effort: the directory of a worker is not reused by another worker of
+        // this daemon.
+        let _ignored = self.child.start_kill();
+        let _ignored = fs_util::remove_dir_all(&self.tmp_dir);
+    }
+}
+
+/// Reads the length prefix of a length-delimited protobuf message.
+async fn read_varint(reader: &mut (impl AsyncRead + Unpin)) -> anyhow::Result<usize> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = reader.read_u8().await?;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(WorkerError::InvalidLength.into())
+}
+
+/// Expands the flagfile arguments, `@path` and `--flagfile=path`, into the lines of the file,
+/// like Bazel does when sending a request to a worker.
+fn expand_flagfiles(args: &[String], working_directory: &Path) -> anyhow::Result<Vec<String>> {
+    let mut expanded = Vec::with_capacity(args.len());
+    for arg in args {
+        let flagfile = arg
+            .strip_prefix("--flagfile=")
+            .or_else(|| arg.strip_prefix('@').filter(|p| !p.starts_with('@')));
+        match flagfile {
+            Some(path) => {
+                let contents = fs_util::read_to_string(working_directory.join(path))?;
+                expanded.extend(contents.lines().map(str::to_owned));
+            }
+            None => expanded.push(arg.clone()),
+        }
+    }
+    Ok(expanded)
+}
+
+/// The limits of a [`WorkerPool`].
+#[derive(Clone, Copy, Debug)]
+pub struct WorkerPoolConfig {
+    /// The maximum number of workers with the same spec. Workers are disabled if it is 0.
+    pub max_workers_per_key: usize,
+    /// The maximum number of workers. Starting a worker beyond it stops the least recently used
+    /// idle worker.
+    pub max_workers: usize,
+    /// How long a worker is kept while idle.
+    pub idle_timeout: Duration,
+}
+
+enum Acquired {
+    Idle(Worker),
+    Spawn(LiveWorker),
+}
+
+struct PoolState {
+    /// The permits of the requests of each worker spec, at most `max_workers_per_key`.
+    permits: HashMap<WorkerSpec, Arc<Semaphore>>,
+    /// The idle workers with their spec and the time they became idle, least recently used
+    /// first.
+    idle: Vec<(WorkerSpec, Instant, Worker)>,
+}
+
+impl PoolState {
+    fn reap(&mut self, idle_timeout: Duration) {
+        self.idle
+            .retain_mut(|(_, since, worker)| since.elapsed() < idle_timeout && worker.is_alive());
+    }
+}
+
+struct PoolInner {
+    config: WorkerPoolConfig,
+    /// Directory of the logs of the workers, receiving their stderr, and of their `$TMPDIR`.
+    dir: AbsNormPathBuf,
+    next_worker_id: AtomicU64,
+    /// The permits of the requests running in workers, at most `max_workers`, so that when all
+    /// the workers are live, there is an idle one to stop for a request needing a new worker.
+    running: Semaphore,
+    /// The number of live workers, busy or idle.
+    live: Arc<AtomicUsize>,
+    state: Mutex<PoolState>,
+    reaper: Once,
+}
+
+impl PoolInner {
+    /// Takes an idle worker for the spec, or reserves a live worker to start, stopping the least
+    /// recently used idle worker if there are already `max_workers`.
+    fn acquire(&self, spec: &WorkerSpec) -> Acquired {
+        let mut state = self.state.lock();
+        state.reap(self.config.idle_timeout);
+        if let Some(i) = state.idle.iter().rposition(|(s, ..)| s == spec) {
+            return Acquired::Idle(state.idle.remove(i).2);
+        }
+        if self.live.load(Ordering::Relaxed) >= self.config.max_workers && !state.idle.is_empty() {
+            state.idle.remove(0);
+        }
+        self.live.fetch_add(1, Ordering::Relaxed);
+        Acquired::Spawn(LiveWorker(self.live.dupe()))
+    }
+
+    fn release(&self, spec: &WorkerSpec, worker: Worker) {
+        self.state
+            .lock()
+            .idle
+            .push((spec.clone(), Instant::now(), worker));
+    }
+}
+
+/// Stops the idle workers after `idle_timeout`, until the pool is dropped.
+fn start_reaper(inner: &Arc<PoolInner>) {
+    let inner = Arc::downgrade(inner);
+    tokio::spawn(async move {
+        loop {
+            let idle_timeout = match inner.upgrade() {
+                Some(inner) => inner.config.idle_timeout,
+                None => break,
+            };
+            tokio::time::sleep(idle_timeout.max(Duration::from_secs(1))).await;
+            match inner.upgrade() {
+                Some(inner) => inner.state.lock().reap(idle_timeout),
+                None => break,
+            }
+        }
+    });
+}
+
+fn is_exited(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<WorkerError>(),
+        Some(WorkerError::Exited(_))
+    )
+}
+
+/// The persistent workers of the daemon, started on demand and shared by the requests with the
+/// same [`WorkerSpec`]. A worker that exits is replaced by a new one on the next request.
+pub struct WorkerPool {
+    inner: Arc<PoolInner>,
+}
+
+impl WorkerPool {
+    pub fn new(config: WorkerPoolConfig, dir: AbsNormPathBuf) -> Self {
+        Self {
+            inner: Arc::new(PoolInner {
+                config,
+                dir,
+                next_worker_id: AtomicU64::new(0),
+                running: Semaphore::new(config.max_workers),
+                live: Arc::new(AtomicUsize::new(0)),
+                state: Mutex::new(PoolState {
+                    permits: HashMap::new(),
+                    idle: Vec::new(),
+                }),
+                reaper: Once::new(),
+            }),
+        }
+    }
+
+    /// Whether commands can run in workers, which is not the case if they are disabled by setting
+    /// a maximum number of workers to 0.
+    pub fn enabled(&self) -> bool {
+        self.inner.config.max_workers_per_key > 0 && self.inner.config.max_workers > 0
+    }
+
+    fn spawn(
+        &self,
+        spec: &WorkerSpec,
+        root: &Path,
+        env_inheritance: Option<&EnvironmentInheritance>,
+        live: LiveWorker,
+    ) -> anyhow::Result<Worker> {
+        self.inner.reaper.call_once(|| start_reaper(&self.inner));
+        fs_util::create_dir_all(&self.inner.dir)?;
+        let id = self.inner.next_worker_id.fetch_add(1, Ordering::Relaxed);
+        Worker::spawn(spec, root, &self.inner.dir, id, env_inheritance, live)
+    }
+
+    /// Runs a command in a worker started in the project root, returning its exit code and
+    /// output like a local command. `args` are the args of the request, without the worker
+    /// startup command.
+    pub async fn exec(
+        &self,
+        spec: &WorkerSpec,
+        args: &[String],
+        root: &Path,
+        timeout: Option<Duration>,
+        env_inheritance: Option<&EnvironmentInheritance>,
+        liveliness_observer: impl LivelinessObserver + 'static,
+    ) -> anyhow::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)> {
+        let request = WorkRequest {
+            arguments: expand_flagfiles(args, root)?,
+            request_id: 0,
+        };
+
+        let permits = self
+            .inner
+            .state
+            .lock()
+            .permits
+            .entry(spec.clone())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.inner.config.max_workers_per_key)))
+            .dupe();
+        let _permit = permits.acquire().await?;
+        let _running = self.inner.running.acquire().await?;
+
+        let run = async {
+            // A worker can die during a request for reasons unrelated to it, e.g. when killed by
+            // the OOM killer, so the request is retried once in a new worker.
+            let mut retried = false;
+            let response = loop {
+                let mut worker = match self.inner.acquire(spec) {
+                    Acquired::Idle(worker) => worker,
+                    Acquired::Spawn(live) => match self.spawn(spec, root, env_inheritance, live) {
+                        Ok(worker) => worker,
+                        Err(e) => {
+                            return Ok((
+                                GatherOutputStatus::SpawnFailed(format!("{:#}", e)),
+                                Vec::new(),
+                                Vec::new(),
+                            ));
+                        }
+                    },
+                };
+                match worker.run(&request).await {
+                    Ok(response) => {
+                        self.inner.release(spec, worker);
+                        break response;
+                    }
+                    Err(e) if !retried && is_exited(&e) => retried = true,
+                    Err(e) => return Err(e),
+                }
+            };
+
+            // Workers report a single output, which is mostly diagnostics, so it is reported as
+            // stderr.
+            Ok((
+                GatherOutputStatus::Finished {
+                    exit_code: response.exit_code,
+                    execution_stats: None,
+                },
+                Vec::new(),
+                response.output.into_bytes(),
+            ))
+        };
+
+        let alive = liveliness_observer
+            .while_alive()
+            .map(|()| anyhow::Ok(GatherOutputStatus::Cancelled));
+        let cancellation = select(timeout_into_cancellation(timeout).boxed(), alive.boxed())
+            .map(|r| anyhow::Ok((r.factor_first().0?, Vec::new(), Vec::new())));
+
+        // On cancellation, the worker running the request is dropped, which kills it.
+        select(run.boxed(), cancellation.boxed())
+            .await
+            .factor_first()
+            .0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use buck2_common::liveliness_observer::NoopLivelinessObserver;
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+    use buck2_core::fs::project::ProjectRootTemp;
+
+    use super::*;
+
+    /// A JSON worker answering each request with its pid. On a request containing `crash`, it
+    /// exits if `$MARKER` does not exist, after creating it.
+    const WORKER_SCRIPT: &str = r#"
+while read -r line; do
+    case "$line" in
+        *crash*) if [ ! -e "$MARKER" ]; then touch "$MARKER"; exit 1; fi ;;
+    esac
+    echo "{\"exitCode\":0,\"output\":\"$$\"}"
+done
+"#;
+
+    fn test_pool(temp: &ProjectRootTemp, max_workers: usize) -> anyhow::Result<WorkerPool> {
+        let root = temp.path().root();
+        fs_util::write(
+            root.join(ForwardRelativePath::new("worker.sh")?),
+            WORKER_SCRIPT,
+        )?;
+        Ok(WorkerPool::new(
+            WorkerPoolConfig {
+                max_workers_per_key: 1,
+                max_workers,
+                idle_timeout: Duration::from_secs(600),
+            },
+            root.join(ForwardRelativePath::new("workers")?),
+        ))
+    }
+
+    fn test_spec(temp: &ProjectRootTemp, marker: &str) -> anyhow::Result<WorkerSpec> {
+        let root = temp.path().root();
+        Ok(WorkerSpec {
+            exe: vec![
+                "sh".to_owned(),
+                root.join(ForwardRelativePath::new("worker.sh")?)
+                    .to_string(),
+            ],
+            env: vec![(
+                "MARKER".to_owned(),
+                root.join(ForwardRelativePath::new(marker)?).to_string(),
+            )],
+            protocol: WorkerProtocol::Json,
+        })
+    }
+
+    /// Runs a request in a worker, returning the pid of the worker.
+    async fn exec(
+        pool: &WorkerPool,
+        temp: &ProjectRootTemp,
+        spec: &WorkerSpec,
+        arg: &str,
+    ) -> anyhow::Result<String> {
+        let (status, _, stderr) = pool
+            .exec(
+                spec,
+                &[arg.to_owned()],
+                temp.path().root().as_path(),
+                None,
+                None,
+                NoopLivelinessObserver::create(),
+            )
+            .await?;
+        assert_matches!(status, GatherOutputStatus::Finished { exit_code: 0, .. });
+        Ok(String::from_utf8(stderr)?)
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pool_reuses_workers() -> anyhow::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let pool = test_pool(&temp, 4)?;
+        let a = test_spec(&temp, "a")?;
+        let b = test_spec(&temp, "b")?;
+
+        let first = exec(&pool, &temp, &a, "x").await?;
+        assert_eq!(first, exec(&pool, &temp, &a, "y").await?);
+        assert_ne!(first, exec(&pool, &temp, &b, "x").await?);
+        assert_eq!(2, pool.inner.live.load(Ordering::Relaxed));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pool_evicts_least_recently_used() -> anyhow::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let pool = test_pool(&temp, 2)?;
+        let a = test_spec(&temp, "a")?;
+        let b = test_spec(&temp, "b")?;
+        let c = test_spec(&temp, "c")?;
+
+        let a_pid = exec(&pool, &temp, &a, "x").await?;
+        let b_pid = exec(&pool, &temp, &b, "x").await?;
+        exec(&pool, &temp, &c, "x").await?;
+        assert_eq!(2, pool.inner.live.load(Ordering::Relaxed));
+        // The worker of `a` was stopped to start the one of `c`, but not the one of `b`.
+        assert_eq!(b_pid, exec(&pool, &temp, &b, "x").await?);
+        assert_ne!(a_pid, exec(&pool, &temp, &a, "x").await?);
+        assert_eq!(2, pool.inner.live.load(Ordering::Relaxed));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pool_retries_crashed_worker() -> anyhow::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let pool = test_pool(&temp, 4)?;
+        let a = test_spec(&temp, "a")?;
+
+        let first = exec(&pool, &temp, &a, "x").await?;
+        let retried = exec(&pool, &temp, &a, "crash").await?;
+        assert_ne!(first, retried);
+        assert_eq!(retried, exec(&pool, &temp, &a, "x").await?);
+        assert_eq!(1, pool.inner.live.load(Ordering::Relaxed));
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_protocol() -> anyhow::Result<()> {
+        let request = WorkRequest {
+            arguments: vec!["-d".to_owned(), "out".to_owned()],
+            request_id: 0,
+        };
+        assert_eq!(
+            r#"{"arguments":["-d","out"],"requestId":0}"#,
+            serde_json::to_string(&request)?
+        );
+
+        let response: WorkResponse = serde_json::from_str(r#"{"exitCode":1,"output":"error"}"#)?;
+        assert_eq!(
+            WorkResponse {
+                exit_code: 1,
+                output: "error".to_owned(),
+                request_id: 0,
+            },
+            response
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_proto_length() -> anyhow::Result<()> {
+        let response = WorkResponse {
+            exit_code: 0,
+            output: "x".repeat(300),
+            request_id: 0,
+        };
+        let encoded = response.encode_length_delimited_to_vec();
+        let mut reader = encoded.as_slice();
+        let len = read_varint(&mut reader).await?;
+        assert_eq!(reader.len(), len);
+        assert_eq!(response, WorkResponse::decode(reader)?);
+        Ok(())
+    }
+}
@@ -34,6 +34,7 @@ use buck2_execute::execute::manager::CommandExecutionManager;
 use buck2_execute::execute::manager::CommandExecutionManagerExt;
 use buck2_execute::execute::manager::CommandExecutionManagerWithClaim;
 use buck2_execute::execute::output::CommandStdStreams;
+use buck2_execute::execute::output::StdStreamPair;
 use buck2_execute::execute::request::CommandExecutionOutput;
 use buck2_execute::execute::request::CommandExecutionOutputRef;
 use buck2_execute::execute::request::CommandExecutionPaths;
@@ -41,6 +42,7 @@ use buck2_execute::execute::request::CommandExecutionRequest;
 use buck2_execute::execute::result::CommandExecutionResult;
 use buck2_execute::materialize::materializer::CasDownloadInfo;
 use buck2_execute::materialize::materializer::Materializer;
+use buck2_execute::materialize::materializer::WriteRequest;
 use buck2_execute::re::manager::ManagedRemoteExecutionClient;
 use buck2_execute::re::remote_action_result::RemoteActionResult;
 use chrono::DateTime;
@@ -96,16 +98,73 @@ pub async fn download_action_results<'a>(
     };
 
     let (download, std_streams) = future::join(download, std_streams).await;
-    let (manager, outputs) = download?;
+    let (manager, mut outputs) = download?;
+
+    let std_streams = CommandStdStreams::Remote(std_streams);
+    let std_redirects = request.std_redirects();
+    let std_streams = if std_redirects.stdout.is_some() || std_redirects.stderr.is_some() {
+        match write_std_redirects(request, paths, materializer, std_streams).await {
+            Ok((values, std_streams)) => {
+                outputs.extend(values);
+                std_streams
+            }
+            Err(e) => return DownloadResult::Result(manager.error("write_std_redirects", e)),
+        }
+    } else {
+        std_streams
+    };
 
     DownloadResult::Result(manager.success(
         response.execution_kind(action_digest.dupe()),
         outputs,
-        CommandStdStreams::Remote(std_streams),
+        std_streams,
         response.timing(),
     ))
 }
 
+/// Remote commands cannot write their std streams to files, so fetch them and declare the outputs
+/// they are redirected to. Returns the values of those outputs along with the fetched streams.
+async fn write_std_redirects(
+    request: &CommandExecutionRequest,
+    paths: &CommandExecutionPaths,
+    materializer: &dyn Materializer,
+    std_streams: CommandStdStreams,
+) -> anyhow::Result<(
+    Vec<(CommandExecutionOutput, ArtifactValue)>,
+    CommandStdStreams,
+)> {
+    let StdStreamPair { stdout, stderr } = std_streams.into_bytes().await?;
+
+    let (outputs, writes): (Vec<_>, Vec<_>) = request
+        .std_redirects()
+        .outputs(&stdout, &stderr)
+        .map(|(output, content)| {
+            let path = paths
+                .output_path(output)
+                .with_context(|| format!("Std stream redirected to a non-output: {:?}", output))?;
+            Ok((
+                output.as_ref().cloned(),
+                WriteRequest {
+                    path: path.to_buf(),
+                    content: content.clone(),
+                    is_executable: false,
+                },
+            ))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+
+    let values = materializer
+        .declare_write(Box::new(move || Ok(writes)))
+        .await?;
+
+    Ok((
+        outputs.into_iter().zip(values).collect(),
+        CommandStdStreams::Local { stdout, stderr },
+    ))
+}
+
 pub struct CasDownloader<'a> {
     pub materializer: &'a dyn Materializer,
     pub re_client: &'a ManagedRemoteExecutionClient,
@@ -283,7 +283,23 @@ pub async fn gather_output<T>(
 where
     T: Future<Output = anyhow::Result<GatherOutputStatus>> + Send,
 {
-    let cmd = prepare_command(cmd);
+    gather_output_with_stdin(cmd, None, cancellation).await
+}
+
+/// Like `gather_output`, but the command reads its stdin from the given file instead of an empty
+/// stdin.
+pub async fn gather_output_with_stdin<T>(
+    cmd: Command,
+    stdin: Option<std::fs::File>,
+    cancellation: T,
+) -> anyhow::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)>
+where
+    T: Future<Output = anyhow::Result<GatherOutputStatus>> + Send,
+{
+    let mut cmd = prepare_command(cmd);
+    if let Some(stdin) = stdin {
+        cmd.stdin(stdin);
+    }
 
     let child = spawn_retry_txt_busy(cmd, || tokio::time::sleep(Duration::from_millis(50))).await;
     let stream = stream_command_events(
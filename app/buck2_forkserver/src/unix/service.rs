@@ -109,6 +109,7 @@ impl Forkserver for UnixForkserverService {
                 cwd,
                 timeout,
                 enable_miniperf,
+                stdin,
             } = msg;
 
             let exe = OsStr::from_bytes(&exe);
@@ -154,6 +155,13 @@ impl Forkserver for UnixForkserverService {
             }
 
             let mut cmd = prepare_command(cmd);
+            if !stdin.is_empty() {
+                let stdin = OsStr::from_bytes(&stdin);
+                cmd.stdin(
+                    std::fs::File::open(stdin)
+                        .with_context(|| format!("Error opening stdin `{:?}`", stdin))?,
+                );
+            }
             let child = cmd.spawn();
 
             let timeout = timeout_into_cancellation(timeout);
@@ -81,6 +81,8 @@ impl GlobPattern {
 #[derivative(Debug)]
 pub struct GlobSpec {
     common_prefix: String,
+    /// The literal directory prefix of each include pattern, e.g. `foo/` for `foo/*/*.rs`.
+    dir_prefixes: Vec<String>,
     exact_matches: HashSet<String>,
     patterns: Vec<GlobPattern>,
     excludes: Vec<GlobPattern>,
@@ -148,8 +150,16 @@ impl GlobSpec {
             let pattern = pattern.as_ref();
             glob_excludes.push(GlobPattern::new(pattern)?);
         }
+        let dir_prefixes = patterns
+            .iter()
+            .map(|p| {
+                let prefix = longest_common_glob_prefix(std::slice::from_ref(p));
+                prefix[..prefix.rfind('/').map_or(0, |i| i + 1)].to_owned()
+            })
+            .collect();
         Ok(Self {
             common_prefix: longest_common_glob_prefix(patterns).to_owned(),
+            dir_prefixes,
             exact_matches,
             patterns: glob_patterns,
             excludes: glob_excludes,
@@ -175,6 +185,20 @@ impl GlobSpec {
                 .any(|p| p.0.matches_with(path, options))
     }
 
+    /// Whether files under the directory `dir`, relative to where the patterns are matched from,
+    /// may match. Used to skip walking directories off the literal prefixes of the patterns.
+    pub fn may_match_in_dir(&self, dir: &str) -> bool {
+        // Compare whole path components, and ignore case like `matches`.
+        fn starts_with(s: &[u8], prefix: &[u8]) -> bool {
+            s.len() >= prefix.len() && s[..prefix.len()].eq_ignore_ascii_case(prefix)
+        }
+        let dir = format!("{}/", dir);
+        self.dir_prefixes.iter().any(|prefix| {
+            starts_with(prefix.as_bytes(), dir.as_bytes())
+                || starts_with(dir.as_bytes(), prefix.as_bytes())
+        })
+    }
+
     pub fn resolve_glob<'a>(
         &'a self,
         spec: &'a PackageFileListing,
@@ -353,6 +377,23 @@ mod tests {
         assert!(GlobSpec::new(&["a/*//b"], &[""; 0]).is_err());
     }
 
+    #[test]
+    fn test_may_match_in_dir() -> anyhow::Result<()> {
+        let spec = GlobSpec::new(&["foo/bar/**/*.rs", "baz/*/BUCK", "qux/exact"], &[""; 0])?;
+        assert!(spec.may_match_in_dir("foo"));
+        assert!(spec.may_match_in_dir("FOO/bar"));
+        assert!(spec.may_match_in_dir("foo/bar/deep/er"));
+        assert!(spec.may_match_in_dir("baz/any"));
+        assert!(spec.may_match_in_dir("qux"));
+        assert!(!spec.may_match_in_dir("foo/barn"));
+        assert!(!spec.may_match_in_dir("foo/other"));
+        assert!(!spec.may_match_in_dir("other"));
+
+        let spec = GlobSpec::new(&["**/Cargo.toml"], &[""; 0])?;
+        assert!(spec.may_match_in_dir("any/dir"));
+        Ok(())
+    }
+
     #[test]
     fn test_glob_prefix() {
         assert_eq!("a/", longest_common_glob_prefix(&["a/1", "a/2"]));
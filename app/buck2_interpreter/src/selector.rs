@@ -87,7 +87,7 @@ impl<'v> StarlarkSelector<'v> {
     ///
     /// select_map([1, 2] + select({"c": [2]}}, increment_items) == [2, 3] + select({"c": [3]})
     /// ```
-    fn select_map<'a>(
+    pub fn select_map<'a>(
         val: Value<'a>,
         eval: &mut Evaluator<'a, '_>,
         func: Value<'a>,
@@ -50,6 +50,10 @@ impl AttributeAsStarlarkValue {
         self.0.clone()
     }
 
+    pub fn is_default_only(&self) -> bool {
+        self.0.is_default_only()
+    }
+
     /// Coercer to put into higher lever coercer (e. g. for `attrs.list(xxx)`).
     pub fn coercer_for_inner(&self) -> anyhow::Result<AttrType> {
         if self.0.is_default_only() {
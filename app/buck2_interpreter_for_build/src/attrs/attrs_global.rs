@@ -41,6 +41,7 @@ use tracing::error;
 use crate::attrs::attribute_as_starlark_value::AttributeAsStarlarkValue;
 use crate::attrs::coerce::attr_type::AttrTypeExt;
 use crate::attrs::coerce::ctx::BuildAttrCoercionContext;
+use crate::attrs::custom_attribute::CustomAttribute;
 use crate::interpreter::build_context::BuildContext;
 use crate::provider::callable::ValueAsProviderCallableLike;
 use crate::transition::transition_id_from_value;
@@ -56,6 +57,8 @@ enum AttrError {
     OptionDefaultNone(String),
     #[error("`attrs.default_only` argument must have a default")]
     DefaultOnlyMustHaveDefault,
+    #[error("`attrs.custom` cannot wrap `attrs.default_only`, which accepts no values")]
+    CustomOfDefaultOnly,
 }
 
 pub trait AttributeExt {
@@ -386,6 +389,31 @@ fn attr_module(registry: &mut MethodsBuilder) {
         )))
     }
 
+    /// Passes values from the user through the function `coerce`, then coerces the result as per
+    /// `inner`. `coerce` runs when the build file is evaluated, so its errors are reported at the
+    /// target definition, and `audit`/query output shows the value it returned. With `select()`,
+    /// `coerce` is applied to each branch. It is not applied to default values.
+    /// Can only be used as a rule attribute, not inside other attributes.
+    ///
+    /// ```python
+    /// def _version(v):
+    ///     if len(v.split(".")) != 3:
+    ///         fail("expected `major.minor.patch`, got `{}`".format(v))
+    ///     return v.removeprefix("v")
+    ///
+    /// attrs.custom(attrs.string(), coerce = _version)
+    /// ```
+    fn custom<'v>(
+        #[starlark(this)] _this: Value<'v>,
+        #[starlark(require = pos)] inner: &AttributeAsStarlarkValue,
+        #[starlark(require = named)] coerce: Value<'v>,
+    ) -> anyhow::Result<CustomAttribute<'v>> {
+        if inner.is_default_only() {
+            return Err(AttrError::CustomOfDefaultOnly.into());
+        }
+        Ok(CustomAttribute::new(inner.clone_attribute(), coerce))
+    }
+
     /// Takes a target (as per `deps`) and passes a `label` to the rule.
     /// Validates that the target exists, but does not introduce a dependency on it.
     fn label<'v>(
@@ -0,0 +1,65 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use allocative::Allocative;
+use buck2_node::attrs::attr::Attribute;
+use derive_more::Display;
+use starlark::any::ProvidesStaticType;
+use starlark::coerce::Coerce;
+use starlark::starlark_complex_value;
+use starlark::starlark_type;
+use starlark::values::Freeze;
+use starlark::values::NoSerialize;
+use starlark::values::StarlarkValue;
+use starlark::values::Trace;
+use starlark::values::ValueLike;
+
+/// An attribute created by `attrs.custom()`: user values are passed through a Starlark function
+/// before being coerced by the wrapped attribute.
+#[derive(
+    Debug,
+    Clone,
+    Trace,
+    Coerce,
+    Freeze,
+    Display,
+    ProvidesStaticType,
+    NoSerialize,
+    Allocative
+)]
+#[repr(C)]
+#[display(fmt = "attrs.custom({})", attr)]
+pub struct CustomAttributeGen<V> {
+    #[freeze(identity)]
+    attr: Attribute,
+    coerce: V,
+}
+
+starlark_complex_value!(pub CustomAttribute);
+
+impl<'v, V: ValueLike<'v> + 'v> StarlarkValue<'v> for CustomAttributeGen<V>
+where
+    Self: ProvidesStaticType,
+{
+    starlark_type!("custom_attribute");
+}
+
+impl<V> CustomAttributeGen<V> {
+    pub fn new(attr: Attribute, coerce: V) -> Self {
+        Self { attr, coerce }
+    }
+
+    pub fn clone_attribute(&self) -> Attribute {
+        self.attr.clone()
+    }
+
+    pub fn coerce(&self) -> &V {
+        &self.coerce
+    }
+}
@@ -21,6 +21,7 @@ use crate::attrs::coerce::attr_type::AttrTypeExt;
 pub mod attribute_as_starlark_value;
 pub mod attrs_global;
 pub mod coerce;
+pub mod custom_attribute;
 
 #[derive(Debug, thiserror::Error)]
 enum AttrCoerceError {
@@ -203,7 +203,10 @@ impl<'a> BuildContext<'a> {
 /// EvalResult at the end of interpreting
 impl ModuleInternals {
     /// Try to get this inner context from the `ctx.extra` property.
-    pub fn from_context<'a>(ctx: &'a Evaluator, function_name: &str) -> anyhow::Result<&'a Self> {
+    pub fn from_context<'v, 'a>(
+        ctx: &Evaluator<'v, 'a>,
+        function_name: &str,
+    ) -> anyhow::Result<&'a Self> {
         BuildContext::from_context(ctx)?
             .additional
             .require_build(function_name)
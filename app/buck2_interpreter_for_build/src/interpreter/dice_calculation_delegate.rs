@@ -440,6 +440,12 @@ impl<'c> DiceCalculationDelegate<'c> {
         }
     }
 
+    /// Contents of the `PACKAGE` files applying to the package, merged with their parents.
+    pub async fn eval_super_package(&self, package: PackageLabel) -> anyhow::Result<SuperPackage> {
+        self.eval_package_file(&PackageFilePath::for_dir(package.as_cell_path()))
+            .await
+    }
+
     async fn resolve_package_listing(
         &self,
         package: PackageLabel,
@@ -172,6 +172,8 @@ impl ModuleInternals {
                                 buildfile_path: self.buildfile_path.dupe(),
                                 oncall,
                                 default_visibility_to_public: self.default_visibility_to_public,
+                                deprecation: self.super_package.deprecation().cloned(),
+                                dependency_rules: self.super_package.dependency_rules().dupe(),
                             }),
                             recorder: TargetsRecorder::new(),
                         });
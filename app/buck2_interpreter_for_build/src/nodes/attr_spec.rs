@@ -11,21 +11,28 @@ use std::collections::HashMap;
 
 use anyhow::Context;
 use buck2_core::target::name::TargetName;
+use buck2_interpreter::selector::StarlarkSelector;
 use buck2_node::attrs::attr::CoercedValue;
 use buck2_node::attrs::attr_type::attr_literal::AttrLiteral;
 use buck2_node::attrs::attr_type::string::StringLiteral;
 use buck2_node::attrs::coerced_attr::CoercedAttr;
 use buck2_node::attrs::internal::attr_is_configurable;
+use buck2_node::attrs::internal::LEGACY_TARGET_COMPATIBLE_WITH_ATTRIBUTE_FIELD;
 use buck2_node::attrs::internal::NAME_ATTRIBUTE_FIELD;
+use buck2_node::attrs::internal::TARGET_COMPATIBLE_WITH_ATTRIBUTE_FIELD;
 use buck2_node::attrs::internal::VISIBILITY_ATTRIBUTE_FIELD;
 use buck2_node::attrs::spec::AttributeSpec;
 use buck2_node::attrs::values::AttrValues;
 use buck2_node::visibility::VisibilitySpecification;
 use buck2_util::arc_str::ArcStr;
 use starlark::docs::DocString;
+use starlark::eval::Evaluator;
 use starlark::eval::ParametersParser;
 use starlark::eval::ParametersSpec;
+use starlark::values::list::AllocList;
+use starlark::values::FrozenValue;
 use starlark::values::Value;
+use starlark_map::small_map::SmallMap;
 
 use crate::attrs::AttributeCoerceExt;
 use crate::interpreter::module_internals::ModuleInternals;
@@ -36,6 +43,8 @@ pub trait AttributeSpecExt {
         param_parser: ParametersParser<'v, '_>,
         arg_count: usize,
         internals: &ModuleInternals,
+        custom_coercers: &SmallMap<String, FrozenValue>,
+        eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<(TargetName, AttrValues)>;
 
     /// Returns a starlark Parameters for the rule callable.
@@ -48,13 +57,21 @@ pub trait AttributeSpecExt {
 
 impl AttributeSpecExt for AttributeSpec {
     /// Parses params extracting the TargetName and the attribute values to store in the TargetNode.
+    /// User values of attributes declared with `attrs.custom()` are first passed through their
+    /// `coerce` function, branch by branch for `select()`.
     fn parse_params<'v>(
         &self,
         mut param_parser: ParametersParser<'v, '_>,
         arg_count: usize,
         internals: &ModuleInternals,
+        custom_coercers: &SmallMap<String, FrozenValue>,
+        eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<(TargetName, AttrValues)> {
         let mut attr_values = AttrValues::with_capacity(arg_count);
+        // Values of attributes from the `PACKAGE` defaults, added after all the attributes
+        // set by the target are known.
+        let mut package_defaults = Vec::new();
+        let mut sets_legacy_target_compatible_with = false;
 
         let mut indices = self.attr_specs();
         let name = match indices.next() {
@@ -81,8 +98,27 @@ impl AttributeSpecExt for AttributeSpec {
                 None => Some(param_parser.next(attr_name)?),
             };
 
+            if attr_name == LEGACY_TARGET_COMPATIBLE_WITH_ATTRIBUTE_FIELD && user_value.is_some() {
+                sets_legacy_target_compatible_with = true;
+            }
+
             let is_visibility = attr_name == VISIBILITY_ATTRIBUTE_FIELD;
             if let Some(v) = user_value {
+                let v = match custom_coercers.get(attr_name) {
+                    Some(coerce) if !v.is_none() => {
+                        StarlarkSelector::select_map(v, eval, coerce.to_value()).with_context(
+                            || {
+                                format!(
+                                    "Error running `coerce` of attribute `{}` of `{}:{}`",
+                                    attr_name,
+                                    internals.buildfile_path().package(),
+                                    name,
+                                )
+                            },
+                        )?
+                    }
+                    _ => v,
+                };
                 let mut coerced = attribute
                     .coerce(
                         attr_name,
@@ -128,6 +164,43 @@ impl AttributeSpecExt for AttributeSpec {
                         )),
                     );
                 }
+            } else {
+                let package = internals.buildfile_path().package();
+                let default = internals
+                    .super_package
+                    .default_attrs()
+                    .attr_default(attr_name);
+                if let Some(default) = default {
+                    // The default is made of absolute labels, so it coerces to the value
+                    // declared in the `PACKAGE` file.
+                    let coerced = attribute
+                        .coerce(
+                            attr_name,
+                            configurable,
+                            internals.attr_coercion_context(),
+                            eval.heap()
+                                .alloc(AllocList(default.iter().map(|v| v.as_str()))),
+                        )
+                        .with_context(|| {
+                            format!(
+                                "Error coercing `PACKAGE` default of attribute `{}` of `{}:{}`",
+                                attr_name, package, name,
+                            )
+                        })?;
+                    if let CoercedValue::Custom(v) = coerced {
+                        package_defaults.push((attr_name, attr_idx, v));
+                    }
+                }
+            }
+        }
+
+        // The legacy `compatible_with` set by the target replaces the `PACKAGE` default of
+        // `target_compatible_with`, since a target cannot have both.
+        for (attr_name, attr_idx, v) in package_defaults {
+            if !(attr_name == TARGET_COMPATIBLE_WITH_ATTRIBUTE_FIELD
+                && sets_legacy_target_compatible_with)
+            {
+                attr_values.push_sorted(attr_idx, v);
             }
         }
 
@@ -27,8 +27,11 @@ use buck2_node::visibility::VisibilityPattern;
 use buck2_node::visibility::VisibilitySpecification;
 use dupe::Dupe;
 use starlark::eval::CallStack;
+use starlark::eval::Evaluator;
 use starlark::eval::ParametersParser;
+use starlark::values::FrozenValue;
 use starlark::values::Value;
+use starlark_map::small_map::SmallMap;
 
 use crate::interpreter::module_internals::ModuleInternals;
 use crate::nodes::attr_spec::AttributeSpecExt;
@@ -49,6 +52,8 @@ pub trait TargetNodeExt: Sized {
         arg_count: usize,
         ignore_attrs_for_profiling: bool,
         call_stack: Option<CallStack>,
+        custom_coercers: &SmallMap<String, FrozenValue>,
+        eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<Self>;
 }
 
@@ -90,6 +95,8 @@ impl TargetNodeExt for TargetNode {
         arg_count: usize,
         ignore_attrs_for_profiling: bool,
         call_stack: Option<CallStack>,
+        custom_coercers: &SmallMap<String, FrozenValue>,
+        eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<Self> {
         if ignore_attrs_for_profiling {
             return Self::from_params_ignore_attrs_for_profiling(
@@ -100,9 +107,13 @@ impl TargetNodeExt for TargetNode {
             );
         }
 
-        let (target_name, attr_values) =
-            rule.attributes
-                .parse_params(param_parser, arg_count, internals)?;
+        let (target_name, attr_values) = rule.attributes.parse_params(
+            param_parser,
+            arg_count,
+            internals,
+            custom_coercers,
+            eval,
+        )?;
         let package_name = internals.buildfile_path().package();
 
         let label = TargetLabel::new(package_name.dupe(), target_name.as_ref());
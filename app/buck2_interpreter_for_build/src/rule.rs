@@ -48,8 +48,10 @@ use starlark::values::NoSerialize;
 use starlark::values::StarlarkValue;
 use starlark::values::Trace;
 use starlark::values::Value;
+use starlark_map::small_map::SmallMap;
 
 use crate::attrs::attribute_as_starlark_value::AttributeAsStarlarkValue;
+use crate::attrs::custom_attribute::CustomAttribute;
 use crate::interpreter::build_context::BuildContext;
 use crate::interpreter::build_context::PerFileTypeContext;
 use crate::interpreter::module_internals::ModuleInternals;
@@ -74,6 +76,8 @@ struct RuleCallable<'v> {
     implementation: Value<'v>,
     // Field Name -> Attribute
     attributes: AttributeSpec,
+    /// The `coerce` functions of attributes declared with `attrs.custom()`.
+    custom_coercers: SmallMap<String, Value<'v>>,
     /// When specified, this transition will be applied to the target before configuring it.
     cfg: Option<Arc<TransitionId>>,
     /// This kind of the rule, e.g. whether it can be used in configuration context.
@@ -108,6 +112,8 @@ enum RuleError {
     IsConfigurationAndToolchain,
     #[error("`rule` can only be declared in bzl files")]
     RuleNonInBzl,
+    #[error("Attribute `{0}` must be an `attribute` or a `custom_attribute`, got `{1}`")]
+    InvalidAttribute(String, &'static str),
 }
 
 impl<'v> AllocValue<'v> for RuleCallable<'v> {
@@ -178,6 +184,11 @@ impl<'v> Freeze for RuleCallable<'v> {
         let rule_type = Arc::new(id);
         let rule_name = rule_type.name.to_owned();
         let signature = self.attributes.signature(rule_name).freeze(freezer)?;
+        let custom_coercers = self
+            .custom_coercers
+            .into_iter()
+            .map(|(name, coerce)| Ok((name, coerce.freeze(freezer)?)))
+            .collect::<anyhow::Result<_>>()?;
 
         Ok(FrozenRuleCallable {
             rule: Arc::new(Rule {
@@ -189,6 +200,7 @@ impl<'v> Freeze for RuleCallable<'v> {
             rule_type,
             implementation: frozen_impl,
             signature,
+            custom_coercers,
             rule_docs,
             ignore_attrs_for_profiling: self.ignore_attrs_for_profiling,
         })
@@ -203,6 +215,7 @@ pub struct FrozenRuleCallable {
     rule_type: Arc<StarlarkRuleType>,
     implementation: FrozenValue,
     signature: ParametersSpec<FrozenValue>,
+    custom_coercers: SmallMap<String, FrozenValue>,
     rule_docs: Option<DocItem>,
     ignore_attrs_for_profiling: bool,
 }
@@ -252,6 +265,8 @@ impl<'v> StarlarkValue<'v> for FrozenRuleCallable {
                 arg_count,
                 self.ignore_attrs_for_profiling,
                 call_stack,
+                &self.custom_coercers,
+                eval,
             )?;
             internals.record(target_node)?;
             Ok(Value::new_none())
@@ -279,7 +294,7 @@ pub fn register_rule_function(builder: &mut GlobalsBuilder) {
     /// ```
     fn rule<'v>(
         #[starlark(require = named)] r#impl: Value<'v>,
-        #[starlark(require = named)] attrs: DictOf<'v, &'v str, &'v AttributeAsStarlarkValue>,
+        #[starlark(require = named)] attrs: DictOf<'v, &'v str, Value<'v>>,
         #[starlark(require = named)] cfg: Option<Value>,
         #[starlark(require = named, default = "")] doc: &str,
         #[starlark(require = named, default = false)] is_configuration_rule: bool,
@@ -297,6 +312,7 @@ pub fn register_rule_function(builder: &mut GlobalsBuilder) {
             PerFileTypeContext::Bzl(bzl_path) => (*bzl_path).clone(),
             _ => return Err(RuleError::RuleNonInBzl.into()),
         };
+        let mut custom_coercers = SmallMap::new();
         let sorted_validated_attrs = attrs
             .to_dict()
             .into_iter()
@@ -304,8 +320,13 @@ pub fn register_rule_function(builder: &mut GlobalsBuilder) {
             .map(|(name, value)| {
                 if name == NAME_ATTRIBUTE_FIELD {
                     Err(RuleError::InvalidParameterName(NAME_ATTRIBUTE_FIELD.to_owned()).into())
+                } else if let Some(attr) = AttributeAsStarlarkValue::from_value(value) {
+                    Ok((name.to_owned(), attr.clone_attribute()))
+                } else if let Some(custom) = CustomAttribute::from_value(value) {
+                    custom_coercers.insert(name.to_owned(), *custom.coerce());
+                    Ok((name.to_owned(), custom.clone_attribute()))
                 } else {
-                    Ok((name.to_owned(), value.clone_attribute()))
+                    Err(RuleError::InvalidAttribute(name.to_owned(), value.get_type()).into())
                 }
             })
             .collect::<anyhow::Result<Vec<(String, Attribute)>>>()?;
@@ -324,6 +345,7 @@ pub fn register_rule_function(builder: &mut GlobalsBuilder) {
             id: RefCell::new(None),
             implementation,
             attributes: AttributeSpec::from(sorted_validated_attrs)?,
+            custom_coercers,
             cfg,
             rule_kind,
             docs: Some(doc.to_owned()),
@@ -10,8 +10,12 @@
 use std::sync::Arc;
 
 use allocative::Allocative;
+use buck2_node::dependency_rules::DependencyRule;
+use buck2_node::deprecation::DeprecationSpecification;
+use buck2_node::package_default_attrs::PackageDefaultAttrs;
 use buck2_node::visibility::VisibilitySpecification;
 use buck2_node::visibility::WithinViewSpecification;
+use buck2_util::arc_str::ThinArcSlice;
 use dupe::Dupe;
 use starlark::values::OwnedFrozenValue;
 use starlark_map::small_map::SmallMap;
@@ -21,37 +25,59 @@ pub(crate) struct SuperPackageData {
     package_values: SmallMap<String, OwnedFrozenValue>,
     visibility: VisibilitySpecification,
     within_view: WithinViewSpecification,
+    deprecation: Option<Arc<DeprecationSpecification>>,
+    default_attrs: PackageDefaultAttrs,
+    /// Rules of this `PACKAGE` file and of all containing `PACKAGE` files.
+    dependency_rules: ThinArcSlice<DependencyRule>,
 }
 
 /// Contents of a `PACKAGE` file merged with contents of containing `PACKAGE` files.
 /// This object exists even for non-existent `PACKAGE` files.
 #[derive(Default, Debug, Allocative, Clone, Dupe)]
-pub(crate) struct SuperPackage(Arc<SuperPackageData>);
+pub struct SuperPackage(Arc<SuperPackageData>);
 
 impl SuperPackage {
     pub(crate) fn new(
         package_values: SmallMap<String, OwnedFrozenValue>,
         visibility: VisibilitySpecification,
         within_view: WithinViewSpecification,
+        deprecation: Option<Arc<DeprecationSpecification>>,
+        default_attrs: PackageDefaultAttrs,
+        dependency_rules: ThinArcSlice<DependencyRule>,
     ) -> SuperPackage {
         SuperPackage(Arc::new(SuperPackageData {
             package_values,
             visibility,
             within_view,
+            deprecation,
+            default_attrs,
+            dependency_rules,
         }))
     }
 
-    pub(crate) fn package_values(&self) -> &SmallMap<String, OwnedFrozenValue> {
+    pub fn package_values(&self) -> &SmallMap<String, OwnedFrozenValue> {
         &self.0.package_values
     }
 
-    pub(crate) fn visibility(&self) -> &VisibilitySpecification {
+    pub fn visibility(&self) -> &VisibilitySpecification {
         &self.0.visibility
     }
 
-    pub(crate) fn within_view(&self) -> &WithinViewSpecification {
+    pub fn within_view(&self) -> &WithinViewSpecification {
         &self.0.within_view
     }
+
+    pub fn deprecation(&self) -> Option<&Arc<DeprecationSpecification>> {
+        self.0.deprecation.as_ref()
+    }
+
+    pub fn default_attrs(&self) -> &PackageDefaultAttrs {
+        &self.0.default_attrs
+    }
+
+    pub fn dependency_rules(&self) -> &ThinArcSlice<DependencyRule> {
+        &self.0.dependency_rules
+    }
 }
 
 impl PartialEq for SuperPackage {
@@ -60,13 +86,31 @@ impl PartialEq for SuperPackage {
             package_values: this_values,
             visibility: this_visibility,
             within_view: this_within_view,
+            deprecation: this_deprecation,
+            default_attrs: this_default_attrs,
+            dependency_rules: this_dependency_rules,
         } = &*self.0;
         let SuperPackageData {
             package_values: other_values,
             visibility: other_visibility,
             within_view: other_within_view,
+            deprecation: other_deprecation,
+            default_attrs: other_default_attrs,
+            dependency_rules: other_dependency_rules,
         } = &*other.0;
-        (this_visibility, this_within_view) == (other_visibility, other_within_view) && {
+        (
+            this_visibility,
+            this_within_view,
+            this_deprecation,
+            this_default_attrs,
+            this_dependency_rules,
+        ) == (
+            other_visibility,
+            other_within_view,
+            other_deprecation,
+            other_default_attrs,
+            other_dependency_rules,
+        ) && {
             // If either package values are not empty, we cannot compare them
             // because we cannot reliably compare arbitrary Starlark values.
             // So if either package values are not empty, we consider super package not equal.
@@ -8,7 +8,11 @@
  */
 
 use std::cell::RefCell;
+use std::sync::Arc;
 
+use buck2_node::dependency_rules::DependencyRule;
+use buck2_node::deprecation::DeprecationSpecification;
+use buck2_node::package_default_attrs::PackageDefaultAttrs;
 use buck2_node::visibility::VisibilitySpecification;
 use buck2_node::visibility::WithinViewSpecification;
 use starlark::values::OwnedFrozenValue;
@@ -21,6 +25,14 @@ pub(crate) struct PackageFileVisibilityFields {
     pub(crate) visibility: VisibilitySpecification,
     pub(crate) within_view: WithinViewSpecification,
     pub(crate) inherit: bool,
+    /// Deprecation set by this `PACKAGE` file, replacing the inherited one.
+    pub(crate) deprecation: Option<Arc<DeprecationSpecification>>,
+    /// Attribute defaults set by this `PACKAGE` file. They replace the inherited defaults
+    /// for the same attributes, or extend them when `inherit` is set.
+    pub(crate) default_attrs: PackageDefaultAttrs,
+    /// Dependency rules declared by this `PACKAGE` file. They are always added
+    /// to the inherited rules, regardless of `inherit`.
+    pub(crate) dependency_rules: Vec<DependencyRule>,
 }
 
 #[derive(Debug)]
@@ -43,8 +55,23 @@ impl PackageFileEvalCtx {
             visibility,
             within_view,
             inherit,
+            deprecation,
+            default_attrs,
+            dependency_rules,
         } = self.visibility.into_inner().unwrap_or_default();
 
+        let deprecation = deprecation.or_else(|| self.parent.deprecation().cloned());
+
+        let default_attrs = self.parent.default_attrs().merge(default_attrs, inherit);
+
+        let dependency_rules = self
+            .parent
+            .dependency_rules()
+            .iter()
+            .cloned()
+            .chain(dependency_rules)
+            .collect();
+
         let (visibility, within_view) = if inherit {
             (
                 self.parent.visibility().extend_with(&visibility),
@@ -54,6 +81,13 @@ impl PackageFileEvalCtx {
             (visibility, within_view)
         };
 
-        SuperPackage::new(merged_package_values, visibility, within_view)
+        SuperPackage::new(
+            merged_package_values,
+            visibility,
+            within_view,
+            deprecation,
+            default_attrs,
+            dependency_rules,
+        )
     }
 }
@@ -7,7 +7,7 @@
  * of this source tree.
  */
 
-pub(crate) mod data;
+pub mod data;
 pub mod defs;
 pub(crate) mod eval_ctx;
 pub(crate) mod package;
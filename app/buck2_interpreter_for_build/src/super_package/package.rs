@@ -7,9 +7,21 @@
  * of this source tree.
  */
 
+use std::sync::Arc;
+
+use buck2_core::cells::cell_path::CellPathRef;
 use buck2_core::cells::name::CellName;
 use buck2_core::cells::CellResolver;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
 use buck2_core::pattern::ParsedPattern;
+use buck2_core::target::label::TargetLabel;
+use buck2_interpreter::path::PackageFilePath;
+use buck2_node::dependency_rules::DependencyRule;
+use buck2_node::dependency_rules::DependencyRuleKind;
+use buck2_node::dependency_rules::DirectDependencyRuleKind;
+use buck2_node::deprecation::DeprecationLevel;
+use buck2_node::deprecation::DeprecationSpecification;
+use buck2_node::package_default_attrs::PackageDefaultAttrs;
 use buck2_node::visibility::VisibilityPattern;
 use buck2_node::visibility::VisibilitySpecification;
 use buck2_node::visibility::WithinViewSpecification;
@@ -32,6 +44,13 @@ enum PackageFileError {
     NotPackage,
     #[error("`package()` function can be used at most once per `PACKAGE` file")]
     AtMostOnce,
+    #[error("`deprecation_allowlist` and `deprecation_error` require `deprecation` to be set")]
+    DeprecationOptionsWithoutDeprecation,
+    #[error(
+        "`package()` license `{0}` is not a target label, \
+        use a target exporting the license file instead, e.g. `:LICENSE`"
+    )]
+    LicenseNotTarget(String),
 }
 
 fn parse_visibility(
@@ -82,18 +101,158 @@ fn parse_within_view(
     })
 }
 
+fn parse_deprecation(
+    deprecation: Option<String>,
+    allowlist: &[String],
+    error: bool,
+    cell_name: CellName,
+    cell_resolver: &CellResolver,
+) -> anyhow::Result<Option<Arc<DeprecationSpecification>>> {
+    let message = match deprecation {
+        Some(message) => message,
+        None if allowlist.is_empty() && !error => return Ok(None),
+        None => return Err(PackageFileError::DeprecationOptionsWithoutDeprecation.into()),
+    };
+    let allowlist = allowlist
+        .iter()
+        .map(|pattern| {
+            Ok(VisibilityPattern(ParsedPattern::parse_precise(
+                pattern,
+                cell_name,
+                cell_resolver,
+            )?))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    Ok(Some(Arc::new(DeprecationSpecification {
+        message,
+        allowlist,
+        level: if error {
+            DeprecationLevel::Error
+        } else {
+            DeprecationLevel::Warn
+        },
+    })))
+}
+
+fn parse_dependency_rules(
+    rules: [(DependencyRuleKind, Option<Vec<String>>); 3],
+    declared_in: &PackageFilePath,
+    cell_name: CellName,
+    cell_resolver: &CellResolver,
+) -> anyhow::Result<Vec<DependencyRule>> {
+    rules
+        .into_iter()
+        .filter_map(|(kind, patterns)| Some((kind, patterns?)))
+        .map(|(kind, patterns)| {
+            Ok(DependencyRule {
+                declared_in: declared_in.to_string(),
+                kind,
+                patterns: patterns
+                    .iter()
+                    .map(|pattern| {
+                        Ok(VisibilityPattern(ParsedPattern::parse_precise(
+                            pattern,
+                            cell_name,
+                            cell_resolver,
+                        )?))
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+            })
+        })
+        .collect()
+}
+
+/// Parse a target label, which may be relative to the directory of the `PACKAGE` file.
+fn parse_target_label(
+    label: &str,
+    dir: CellPathRef,
+    cell_resolver: &CellResolver,
+) -> anyhow::Result<TargetLabel> {
+    ParsedPattern::<TargetPatternExtra>::parsed_opt_absolute(
+        label,
+        Some(dir),
+        dir.cell(),
+        cell_resolver,
+    )?
+    .as_target_label(label)
+}
+
+fn parse_default_attrs(
+    target_compatible_with: Option<Vec<String>>,
+    labels: Option<Vec<String>>,
+    licenses: Option<Vec<String>>,
+    declared_in: &PackageFilePath,
+    cell_resolver: &CellResolver,
+) -> anyhow::Result<PackageDefaultAttrs> {
+    let dir = declared_in.dir();
+    let target_compatible_with = target_compatible_with
+        .map(|labels| {
+            labels
+                .iter()
+                .map(|label| parse_target_label(label, dir, cell_resolver))
+                .collect::<anyhow::Result<_>>()
+        })
+        .transpose()?;
+    // Unlike for `attrs.source()`, licenses must be targets: the targets of subpackages, which
+    // inherit the defaults, can't use the files of another package.
+    let licenses = licenses
+        .map(|licenses| {
+            licenses
+                .iter()
+                .map(|license| {
+                    if !license.contains(':') {
+                        return Err(PackageFileError::LicenseNotTarget(license.clone()).into());
+                    }
+                    parse_target_label(license, dir, cell_resolver)
+                })
+                .collect::<anyhow::Result<_>>()
+        })
+        .transpose()?;
+    Ok(PackageDefaultAttrs {
+        target_compatible_with,
+        labels,
+        licenses,
+    })
+}
+
 /// Globals for `PACKAGE` files and `bzl` files included from `PACKAGE` files.
 #[starlark_module]
 pub(crate) fn register_package_function(globals: &mut GlobalsBuilder) {
+    /// Set properties of the targets in the package and its subpackages.
+    ///
+    /// `default_target_compatible_with`, `labels` and `licenses` are used for the
+    /// `target_compatible_with`, `labels` and `licenses` attributes of targets which
+    /// do not set them (if the rule has such attribute). Licenses are targets providing the
+    /// license files, e.g. `export_file`. Target labels are relative to the `PACKAGE` file.
+    /// They are inherited by subpackages, and a `PACKAGE` file setting one of them replaces
+    /// the inherited value, or appends to it when `inherit = True`.
+    ///
+    /// `forbidden_deps`, `allowed_deps` and `transitive_forbidden_deps` restrict the
+    /// dependencies of targets in the package and its subpackages: direct dependencies
+    /// must not match `forbidden_deps` and must match `allowed_deps` (if set), and no
+    /// transitive dependency may match `transitive_forbidden_deps`. Dependencies within
+    /// a package are not checked. Rules of all containing `PACKAGE` files apply,
+    /// and violations fail the analysis of the target.
     fn package(
         #[starlark(require=named, default=false)] inherit: bool,
         #[starlark(require=named, default=Vec::new())] visibility: Vec<String>,
         #[starlark(require=named, default=Vec::new())] within_view: Vec<String>,
+        #[starlark(require=named)] deprecation: Option<String>,
+        #[starlark(require=named, default=Vec::new())] deprecation_allowlist: Vec<String>,
+        #[starlark(require=named, default=false)] deprecation_error: bool,
+        #[starlark(require=named)] default_target_compatible_with: Option<Vec<String>>,
+        #[starlark(require=named)] labels: Option<Vec<String>>,
+        #[starlark(require=named)] licenses: Option<Vec<String>>,
+        #[starlark(require=named)] forbidden_deps: Option<Vec<String>>,
+        #[starlark(require=named)] allowed_deps: Option<Vec<String>>,
+        #[starlark(require=named)] transitive_forbidden_deps: Option<Vec<String>>,
         eval: &mut Evaluator,
     ) -> anyhow::Result<NoneType> {
         let build_context = BuildContext::from_context(eval)?;
-        let package_file_eval_ctx = match &build_context.additional {
-            PerFileTypeContext::Package(_, package_file_eval_ctx) => package_file_eval_ctx,
+        let (package_file_path, package_file_eval_ctx) = match &build_context.additional {
+            PerFileTypeContext::Package(path, package_file_eval_ctx) => {
+                (path, package_file_eval_ctx)
+            }
             _ => return Err(PackageFileError::NotPackage.into()),
         };
         let visibility = parse_visibility(
@@ -106,6 +265,41 @@ pub(crate) fn register_package_function(globals: &mut GlobalsBuilder) {
             build_context.cell_info().name().name(),
             build_context.cell_info().cell_resolver(),
         )?;
+        let deprecation = parse_deprecation(
+            deprecation,
+            &deprecation_allowlist,
+            deprecation_error,
+            build_context.cell_info().name().name(),
+            build_context.cell_info().cell_resolver(),
+        )?;
+
+        let dependency_rules = parse_dependency_rules(
+            [
+                (
+                    DependencyRuleKind::Direct(DirectDependencyRuleKind::Forbidden),
+                    forbidden_deps,
+                ),
+                (
+                    DependencyRuleKind::Direct(DirectDependencyRuleKind::AllowedOnly),
+                    allowed_deps,
+                ),
+                (
+                    DependencyRuleKind::TransitiveForbidden,
+                    transitive_forbidden_deps,
+                ),
+            ],
+            package_file_path,
+            build_context.cell_info().name().name(),
+            build_context.cell_info().cell_resolver(),
+        )?;
+
+        let default_attrs = parse_default_attrs(
+            default_target_compatible_with,
+            labels,
+            licenses,
+            package_file_path,
+            build_context.cell_info().cell_resolver(),
+        )?;
 
         match &mut *package_file_eval_ctx.visibility.borrow_mut() {
             Some(_) => return Err(PackageFileError::AtMostOnce.into()),
@@ -114,6 +308,9 @@ pub(crate) fn register_package_function(globals: &mut GlobalsBuilder) {
                     visibility,
                     within_view,
                     inherit,
+                    deprecation,
+                    default_attrs,
+                    dependency_rules,
                 })
             }
         };
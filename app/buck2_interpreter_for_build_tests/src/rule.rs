@@ -165,6 +165,7 @@ fn udr_is_recorded() -> SharedResult<()> {
             "__type__": "root//some/package/defs.bzl:foo_binary",
             "compatible_with": [],
             "default_target_platform": null,
+            "deprecation": null,
             "mandatory": "m1",
             "optional": "o1",
             "other_optional": "some_default",
@@ -180,6 +181,7 @@ fn udr_is_recorded() -> SharedResult<()> {
             "__type__": "root//some/package/defs.bzl:foo_binary",
             "compatible_with": [],
             "default_target_platform": null,
+            "deprecation": null,
             "mandatory": "m2",
             "optional": "some_default",
             "other_optional": "o1",
@@ -247,6 +249,64 @@ fn udr_rejects_invalid_parameters() {
     );
 }
 
+#[test]
+fn custom_attr_coerces_user_values() -> SharedResult<()> {
+    let prefix = indoc!(
+        r#"
+        def impl(ctx):
+            pass
+
+        def _version(v):
+            if len(v.split(".")) != 3:
+                fail("expected `major.minor.patch`, got `{}`".format(v))
+            return v.removeprefix("v")
+
+        version_attr = attrs.custom(attrs.string(default="0.0.0"), coerce=_version)
+        foo_library = rule(impl=impl, attrs={"version": version_attr})
+
+        def test():
+        "#
+    );
+
+    let mut tester = rule_tester();
+    let result = tester.run_starlark_test(&format!(
+        "{}\n    {}\n    {}\n    {}",
+        prefix,
+        r#"foo_library(name="plain", version="v1.2.3")"#,
+        r#"foo_library(name="selected", version=select({"//config:a": "v2.0.0", "DEFAULT": "1.0.0"}))"#,
+        r#"foo_library(name="defaulted")"#,
+    ))?;
+    let actual = targets_to_json(
+        &result,
+        Tester::build_file_path().package(),
+        AttrInspectOptions::All,
+    )?;
+    assert_eq!(json!("1.2.3"), actual["plain"]["version"]);
+    assert_eq!(
+        json!({
+            "__type": "selector",
+            "entries": {"root//config:a": "2.0.0", "DEFAULT": "1.0.0"},
+        }),
+        actual["selected"]["version"]
+    );
+    assert_eq!(json!("0.0.0"), actual["defaulted"]["version"]);
+
+    let mut tester = rule_tester();
+    tester.run_starlark_test_expecting_error(
+        &format!(
+            "{}\n{}",
+            prefix, r#"    foo_library(name="bad", version="1.2")"#
+        ),
+        "expected `major.minor.patch`, got `1.2`",
+    );
+    let mut tester = rule_tester();
+    tester.run_starlark_test_expecting_error(
+        "def test():\n attrs.list(attrs.custom(attrs.string(), coerce=str))",
+        "custom_attribute",
+    );
+    Ok(())
+}
+
 #[test]
 fn option_allows_none() -> anyhow::Result<()> {
     let mut tester = rule_tester();
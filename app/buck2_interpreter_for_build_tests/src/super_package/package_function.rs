@@ -10,27 +10,33 @@
 use buck2_core::cells::build_file_cell::BuildFileCell;
 use buck2_core::fs::project::ProjectRootTemp;
 use buck2_core::package::PackageLabel;
+use buck2_core::target::label::TargetLabel;
+use buck2_core::target::name::TargetNameRef;
 use buck2_interpreter::starlark_profiler::StarlarkProfilerOrInstrumentation;
 use buck2_interpreter_for_build::interpreter::dice_calculation_delegate::HasCalculationDelegate;
+use buck2_node::attrs::display::AttrDisplayWithContextExt;
+use buck2_node::attrs::inspect_options::AttrInspectOptions;
+use buck2_node::dependency_rules::DependencyRuleKind;
+use buck2_node::dependency_rules::DirectDependencyRuleKind;
+use buck2_node::deprecation::DeprecationLevel;
+use buck2_node::nodes::eval_result::EvaluationResult;
 
 use crate::tests::calculation;
 use crate::tests::root_cell;
 
-#[tokio::test]
-async fn test_package() {
-    let fs = ProjectRootTemp::new().unwrap();
+/// Write `files` to a new project and evaluate the build file of `package`.
+async fn eval_package(files: &[(&str, &str)], package: &str) -> EvaluationResult {
+    try_eval_package(files, package).await.unwrap()
+}
 
-    fs.write_file(
-        "juxtaposition/PACKAGE",
-        r#"
-package(
-    visibility = ["//aaa/..."],
-    within_view = ["//bbb/..."],
-    inherit = True,
-)
-"#,
-    );
-    fs.write_file("juxtaposition/BUCK", "");
+async fn try_eval_package(
+    files: &[(&str, &str)],
+    package: &str,
+) -> anyhow::Result<EvaluationResult> {
+    let fs = ProjectRootTemp::new().unwrap();
+    for (path, content) in files {
+        fs.write_file(path, content);
+    }
 
     let ctx = calculation(&fs).await;
 
@@ -39,11 +45,337 @@ package(
         .await
         .unwrap();
 
-    let _result = interpreter
+    interpreter
         .eval_build_file(
-            PackageLabel::testing_parse("root//juxtaposition"),
+            PackageLabel::testing_parse(package),
             &mut StarlarkProfilerOrInstrumentation::disabled(),
         )
         .await
-        .unwrap();
+}
+
+/// A rule with no attributes of its own.
+const RULES_BZL: (&str, &str) = (
+    "rules.bzl",
+    "rrr = rule(impl = lambda ctx: DefaultInfo(), attrs = {})",
+);
+
+#[tokio::test]
+async fn test_package() {
+    eval_package(
+        &[
+            (
+                "juxtaposition/PACKAGE",
+                r#"
+package(
+    visibility = ["//aaa/..."],
+    within_view = ["//bbb/..."],
+    inherit = True,
+)
+"#,
+            ),
+            ("juxtaposition/BUCK", ""),
+        ],
+        "root//juxtaposition",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_package_deprecation_inherited() {
+    let result = eval_package(
+        &[
+            RULES_BZL,
+            (
+                "legacy/PACKAGE",
+                r#"
+package(
+    deprecation = "use //modern:lib instead",
+    deprecation_allowlist = ["//old_app/..."],
+    deprecation_error = True,
+)
+"#,
+            ),
+            (
+                "legacy/lib/BUCK",
+                r#"
+load("//:rules.bzl", "rrr")
+rrr(name = "lib")
+"#,
+            ),
+        ],
+        "root//legacy/lib",
+    )
+    .await;
+
+    let target_nodes: Vec<_> = result.targets().values().collect();
+    assert_eq!(1, target_nodes.len());
+    let deprecation = target_nodes[0].deprecation().unwrap();
+    assert_eq!("use //modern:lib instead", deprecation.message);
+    assert_eq!(DeprecationLevel::Error, deprecation.level);
+    assert!(!deprecation.applies_to(
+        target_nodes[0].label(),
+        &TargetLabel::testing_parse("root//old_app/bin:bin"),
+    ));
+    assert!(deprecation.applies_to(
+        target_nodes[0].label(),
+        &TargetLabel::testing_parse("root//new_app:bin"),
+    ));
+}
+
+#[tokio::test]
+async fn test_target_deprecation_attribute() {
+    let deprecation = |result: &EvaluationResult, name: &str| {
+        result
+            .targets()
+            .get(TargetNameRef::new(name).unwrap())
+            .unwrap()
+            .deprecation()
+            .map(|d| (d.message.to_owned(), d.level))
+    };
+
+    let legacy = eval_package(
+        &[
+            RULES_BZL,
+            (
+                "legacy/PACKAGE",
+                r#"
+package(
+    deprecation = "use //modern:lib instead",
+    deprecation_error = True,
+)
+"#,
+            ),
+            (
+                "legacy/BUCK",
+                r#"
+load("//:rules.bzl", "rrr")
+rrr(name = "lib")
+rrr(name = "util", deprecation = "use //modern:util instead")
+"#,
+            ),
+        ],
+        "root//legacy",
+    )
+    .await;
+    assert_eq!(
+        Some((
+            "use //modern:lib instead".to_owned(),
+            DeprecationLevel::Error
+        )),
+        deprecation(&legacy, "lib")
+    );
+    assert_eq!(
+        Some((
+            "use //modern:util instead".to_owned(),
+            DeprecationLevel::Error
+        )),
+        deprecation(&legacy, "util")
+    );
+
+    let modern = eval_package(
+        &[
+            RULES_BZL,
+            (
+                "modern/BUCK",
+                r#"
+load("//:rules.bzl", "rrr")
+rrr(name = "lib")
+rrr(name = "old", deprecation = "use :lib instead")
+"#,
+            ),
+        ],
+        "root//modern",
+    )
+    .await;
+    assert_eq!(None, deprecation(&modern, "lib"));
+    assert_eq!(
+        Some(("use :lib instead".to_owned(), DeprecationLevel::Warn)),
+        deprecation(&modern, "old")
+    );
+}
+
+#[tokio::test]
+async fn test_package_default_attrs() {
+    let result = eval_package(
+        &[
+            (
+                "rules.bzl",
+                r#"
+rrr = rule(
+    impl = lambda ctx: DefaultInfo(),
+    attrs = {"labels": attrs.list(attrs.string(), default = [])},
+)
+"#,
+            ),
+            ("PACKAGE", r#"package(labels = ["root"])"#),
+            (
+                "speaker/PACKAGE",
+                r#"package(labels = ["speaker"], inherit = True)"#,
+            ),
+            (
+                "speaker/BUCK",
+                r#"
+load("//:rules.bzl", "rrr")
+rrr(name = "defaulted")
+rrr(name = "explicit", labels = ["own"])
+"#,
+            ),
+        ],
+        "root//speaker",
+    )
+    .await;
+
+    let labels = |name: &str| {
+        result
+            .targets()
+            .get(TargetNameRef::new(name).unwrap())
+            .unwrap()
+            .attr("labels", AttrInspectOptions::All)
+            .unwrap()
+            .unwrap()
+            .as_display_no_ctx()
+            .to_string()
+    };
+    assert_eq!("[\"root\",\"speaker\"]", labels("defaulted"));
+    assert_eq!("[\"own\"]", labels("explicit"));
+}
+
+#[tokio::test]
+async fn test_package_default_target_compatible_with_relative_to_package_file() {
+    let result = eval_package(
+        &[
+            RULES_BZL,
+            (
+                "PACKAGE",
+                r#"package(default_target_compatible_with = [":linux"])"#,
+            ),
+            (
+                "speaker/BUCK",
+                r#"
+load("//:rules.bzl", "rrr")
+rrr(name = "defaulted")
+rrr(name = "legacy", compatible_with = ["//:mac"])
+"#,
+            ),
+        ],
+        "root//speaker",
+    )
+    .await;
+
+    let target_compatible_with = |name: &str| {
+        result
+            .targets()
+            .get(TargetNameRef::new(name).unwrap())
+            .unwrap()
+            .attr("target_compatible_with", AttrInspectOptions::DefinedOnly)
+            .unwrap()
+            .map(|attr| attr.as_display_no_ctx().to_string())
+    };
+    assert_eq!(
+        Some("[\"root//:linux\"]".to_owned()),
+        target_compatible_with("defaulted")
+    );
+    assert_eq!(None, target_compatible_with("legacy"));
+}
+
+#[tokio::test]
+async fn test_package_default_licenses_in_subpackage() {
+    let result = eval_package(
+        &[
+            (
+                "rules.bzl",
+                r#"
+rrr = rule(
+    impl = lambda ctx: DefaultInfo(),
+    attrs = {"licenses": attrs.list(attrs.source(), default = [])},
+)
+"#,
+            ),
+            ("PACKAGE", r#"package(licenses = [":LICENSE"])"#),
+            (
+                "speaker/BUCK",
+                r#"
+load("//:rules.bzl", "rrr")
+rrr(name = "defaulted")
+"#,
+            ),
+        ],
+        "root//speaker",
+    )
+    .await;
+
+    let licenses = result
+        .targets()
+        .get(TargetNameRef::new("defaulted").unwrap())
+        .unwrap()
+        .attr("licenses", AttrInspectOptions::DefinedOnly)
+        .unwrap()
+        .map(|attr| attr.as_display_no_ctx().to_string());
+    assert_eq!(Some("[\"root//:LICENSE\"]".to_owned()), licenses);
+}
+
+#[tokio::test]
+async fn test_package_license_file_rejected() {
+    let err = try_eval_package(
+        &[
+            RULES_BZL,
+            ("speaker/PACKAGE", r#"package(licenses = ["LICENSE"])"#),
+            (
+                "speaker/BUCK",
+                r#"
+load("//:rules.bzl", "rrr")
+rrr(name = "lib")
+"#,
+            ),
+        ],
+        "root//speaker",
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        format!("{:?}", err).contains("license `LICENSE` is not a target label"),
+        "{:?}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn test_package_dependency_rules_accumulated() {
+    let result = eval_package(
+        &[
+            RULES_BZL,
+            ("PACKAGE", r#"package(forbidden_deps = ["//internal/..."])"#),
+            (
+                "ui/PACKAGE",
+                r#"package(transitive_forbidden_deps = ["//db/..."])"#,
+            ),
+            (
+                "ui/BUCK",
+                r#"
+load("//:rules.bzl", "rrr")
+rrr(name = "ui")
+"#,
+            ),
+        ],
+        "root//ui",
+    )
+    .await;
+
+    let target_nodes: Vec<_> = result.targets().values().collect();
+    assert_eq!(1, target_nodes.len());
+    let rules: Vec<_> = target_nodes[0]
+        .dependency_rules()
+        .iter()
+        .map(|r| (r.kind, r.declared_in.as_str()))
+        .collect();
+    assert_eq!(
+        vec![
+            (
+                DependencyRuleKind::Direct(DirectDependencyRuleKind::Forbidden),
+                "root//PACKAGE"
+            ),
+            (DependencyRuleKind::TransitiveForbidden, "root//ui/PACKAGE"),
+        ],
+        rules
+    );
 }
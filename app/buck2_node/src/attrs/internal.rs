@@ -46,6 +46,8 @@ pub const VISIBILITY_ATTRIBUTE_FIELD: &str = "visibility";
 
 pub const TESTS_ATTRIBUTE_FIELD: &str = "tests";
 
+pub const DEPRECATION_ATTRIBUTE_FIELD: &str = "deprecation";
+
 fn name_attribute() -> Attribute {
     Attribute::new(None, "name of the target", AttrType::string())
 }
@@ -108,6 +110,14 @@ fn tests_attribute() -> Attribute {
     )
 }
 
+fn deprecation_attribute() -> Attribute {
+    Attribute::new(
+        Some(Arc::new(CoercedAttr::Literal(AttrLiteral::None))),
+        "a message marking this target as deprecated, e.g. naming its replacement, shown to the targets of other packages depending on it",
+        AttrType::option(AttrType::string()),
+    )
+}
+
 pub fn internal_attrs() -> &'static OrderedMap<&'static str, Attribute> {
     static ATTRS: Lazy<OrderedMap<&'static str, Attribute>> = Lazy::new(|| {
         OrderedMap::from_iter([
@@ -130,6 +140,7 @@ pub fn internal_attrs() -> &'static OrderedMap<&'static str, Attribute> {
             ),
             (VISIBILITY_ATTRIBUTE_FIELD, visibility_attribute()),
             (TESTS_ATTRIBUTE_FIELD, tests_attribute()),
+            (DEPRECATION_ATTRIBUTE_FIELD, deprecation_attribute()),
         ])
     });
     &ATTRS
@@ -142,6 +153,8 @@ pub fn attr_is_configurable(name: &str) -> AttrIsConfigurable {
         || name == DEFAULT_TARGET_PLATFORM_ATTRIBUTE_FIELD
         // visibility attributes aren't configurable so that we can cache them on targetnodes.
         || name == VISIBILITY_ATTRIBUTE_FIELD
+        // deprecation is read from the unconfigured node of dependencies.
+        || name == DEPRECATION_ATTRIBUTE_FIELD
     {
         AttrIsConfigurable::No
     } else {
@@ -0,0 +1,380 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Architecture rules restricting what targets can depend on, declared in `PACKAGE` files.
+
+use std::fmt;
+use std::fmt::Display;
+use std::iter;
+
+use allocative::Allocative;
+use buck2_core::target::label::ConfiguredTargetLabel;
+use buck2_core::target::label::TargetLabel;
+use buck2_util::arc_str::ThinArcSlice;
+use dupe::Dupe;
+use dupe::IterDupedExt;
+use itertools::Itertools;
+use starlark_map::small_map::SmallMap;
+use thiserror::Error;
+
+use crate::nodes::configured::ConfiguredTargetNode;
+use crate::visibility::VisibilityPattern;
+
+#[derive(Debug, Error)]
+pub enum DependencyRulesError {
+    #[error(
+        "`{0}` violates dependency rules:\n{}",
+        .1.iter().map(|v| format!("  {}", v)).join("\n")
+    )]
+    Violations(ConfiguredTargetLabel, Vec<DependencyRuleViolation>),
+}
+
+/// Kinds of rules checked against the direct dependencies of a target.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Allocative)]
+pub enum DirectDependencyRuleKind {
+    /// Direct dependencies must not match the patterns.
+    Forbidden,
+    /// Direct dependencies must match one of the patterns.
+    AllowedOnly,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Allocative)]
+pub enum DependencyRuleKind {
+    Direct(DirectDependencyRuleKind),
+    /// Transitive target dependencies must not match the patterns.
+    TransitiveForbidden,
+}
+
+impl Display for DependencyRuleKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DependencyRuleKind::Direct(DirectDependencyRuleKind::Forbidden) => {
+                write!(f, "forbidden_deps")
+            }
+            DependencyRuleKind::Direct(DirectDependencyRuleKind::AllowedOnly) => {
+                write!(f, "allowed_deps")
+            }
+            DependencyRuleKind::TransitiveForbidden => write!(f, "transitive_forbidden_deps"),
+        }
+    }
+}
+
+/// A rule from a `PACKAGE` file, applying to targets of the packages below it.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Allocative)]
+pub struct DependencyRule {
+    /// Path of the `PACKAGE` file declaring the rule.
+    pub declared_in: String,
+    pub kind: DependencyRuleKind,
+    pub patterns: ThinArcSlice<VisibilityPattern>,
+}
+
+impl DependencyRule {
+    fn matches(&self, target: &TargetLabel) -> bool {
+        self.patterns.iter().any(|p| p.0.matches(target))
+    }
+}
+
+/// A dependency forbidden by a rule, with the dependency path from the checked target.
+#[derive(Debug, Clone)]
+pub struct DependencyRuleViolation {
+    pub kind: DependencyRuleKind,
+    pub declared_in: String,
+    pub path: Vec<ConfiguredTargetLabel>,
+}
+
+impl Display for DependencyRuleViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` from `{}` forbids `{}`: {}",
+            self.kind,
+            self.declared_in,
+            self.path.last().unwrap().unconfigured(),
+            self.path.iter().join(" -> ")
+        )
+    }
+}
+
+impl DependencyRuleViolation {
+    fn new(rule: &DependencyRule, path: Vec<ConfiguredTargetLabel>) -> Self {
+        DependencyRuleViolation {
+            kind: rule.kind,
+            declared_in: rule.declared_in.clone(),
+            path,
+        }
+    }
+}
+
+/// Rules of the package of `node`.
+fn dependency_rules(node: &ConfiguredTargetNode) -> &[DependencyRule] {
+    if node.forward_target().is_some() {
+        // Checked on the node it forwards to.
+        &[]
+    } else {
+        node.dependency_rules()
+    }
+}
+
+fn check_direct_deps(
+    node: &ConfiguredTargetNode,
+    rule: &DependencyRule,
+    kind: DirectDependencyRuleKind,
+    violations: &mut Vec<DependencyRuleViolation>,
+) {
+    // Exec deps are build tools, not part of the output.
+    for dep in node.target_deps() {
+        // Like visibility, rules do not apply within a package.
+        if dep.label().pkg() == node.label().pkg() {
+            continue;
+        }
+        let matches = rule.matches(dep.label().unconfigured());
+        let forbidden = match kind {
+            DirectDependencyRuleKind::Forbidden => matches,
+            DirectDependencyRuleKind::AllowedOnly => !matches,
+        };
+        if forbidden {
+            violations.push(DependencyRuleViolation::new(
+                rule,
+                vec![node.label().dupe(), dep.label().dupe()],
+            ));
+        }
+    }
+}
+
+/// Returns the direct dependencies of `node` forbidden by the rules of its package.
+pub fn direct_dependency_rule_violations(
+    node: &ConfiguredTargetNode,
+) -> Vec<DependencyRuleViolation> {
+    let mut violations = Vec::new();
+    for rule in dependency_rules(node) {
+        if let DependencyRuleKind::Direct(kind) = rule.kind {
+            check_direct_deps(node, rule, kind, &mut violations);
+        }
+    }
+    violations
+}
+
+/// Returns the `transitive_forbidden_deps` rules of the package of `node`.
+pub fn transitive_forbidden_rules(
+    node: &ConfiguredTargetNode,
+) -> impl Iterator<Item = &DependencyRule> {
+    dependency_rules(node)
+        .iter()
+        .filter(|rule| rule.kind == DependencyRuleKind::TransitiveForbidden)
+}
+
+/// Targets matching a rule which a target reaches through target deps (exec deps are build
+/// tools, not part of the output), each with a shortest path starting at a dep of the target.
+///
+/// Computed for a target from the results of its deps, so each target is visited once per rule
+/// however many targets check the rule.
+#[derive(Debug, Default, Eq, PartialEq, Allocative)]
+pub struct ForbiddenReachable(SmallMap<ConfiguredTargetLabel, Vec<ConfiguredTargetLabel>>);
+
+impl ForbiddenReachable {
+    /// Combines the results of the target deps of a target.
+    pub fn new<'a>(
+        rule: &DependencyRule,
+        deps: impl IntoIterator<Item = (&'a ConfiguredTargetNode, &'a ForbiddenReachable)>,
+    ) -> Self {
+        let mut reachable: SmallMap<ConfiguredTargetLabel, Vec<ConfiguredTargetLabel>> =
+            SmallMap::new();
+        for (dep, dep_reachable) in deps {
+            if rule.matches(dep.label().unconfigured()) {
+                reachable.insert(dep.label().dupe(), vec![dep.label().dupe()]);
+            }
+            for (target, path) in &dep_reachable.0 {
+                if reachable
+                    .get(target)
+                    .map_or(true, |shortest| shortest.len() > path.len() + 1)
+                {
+                    let path = iter::once(dep.label()).chain(path).duped().collect();
+                    reachable.insert(target.dupe(), path);
+                }
+            }
+        }
+        ForbiddenReachable(reachable)
+    }
+
+    /// Violations of `rule` by `node`, given the targets it reaches.
+    pub fn violations<'a>(
+        &'a self,
+        node: &'a ConfiguredTargetNode,
+        rule: &'a DependencyRule,
+    ) -> impl Iterator<Item = DependencyRuleViolation> + 'a {
+        self.0.values().map(move |path| {
+            DependencyRuleViolation::new(
+                rule,
+                iter::once(node.label()).chain(path).duped().collect(),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::configuration::pair::ConfigurationNoExec;
+    use buck2_core::pattern::ParsedPattern;
+    use starlark_map::ordered_map::OrderedMap;
+    use starlark_map::unordered_map::UnorderedMap;
+
+    use super::*;
+    use crate::configuration::execution::ExecutionPlatformResolution;
+    use crate::configuration::resolved::ResolvedConfiguration;
+    use crate::nodes::unconfigured::testing::TargetNodeExt;
+    use crate::nodes::unconfigured::TargetNode;
+    use crate::rule_type::RuleType;
+    use crate::rule_type::StarlarkRuleType;
+
+    fn node(label: &str, deps: &[&ConfiguredTargetNode]) -> ConfiguredTargetNode {
+        let label = ConfiguredTargetLabel::testing_parse(label, ConfigurationData::testing_new());
+        ConfiguredTargetNode::new(
+            label.dupe(),
+            TargetNode::testing_new(
+                label.unconfigured().dupe(),
+                RuleType::Starlark(Arc::new(StarlarkRuleType {
+                    import_path: ImportPath::testing_new("root//:rules.bzl"),
+                    name: "rrr".to_owned(),
+                })),
+                Vec::new(),
+            ),
+            ResolvedConfiguration::new(
+                ConfigurationNoExec::new(label.cfg().dupe()),
+                UnorderedMap::new(),
+            ),
+            OrderedMap::new(),
+            ExecutionPlatformResolution::new(None, Vec::new()),
+            deps.iter().map(|dep| (*dep).dupe()).collect(),
+            Vec::new(),
+            OrderedMap::new(),
+        )
+    }
+
+    fn rule(kind: DependencyRuleKind, patterns: &[&str]) -> DependencyRule {
+        DependencyRule {
+            declared_in: "root//PACKAGE".to_owned(),
+            kind,
+            patterns: ThinArcSlice::from_iter(
+                patterns
+                    .iter()
+                    .map(|p| VisibilityPattern(ParsedPattern::testing_parse(p))),
+            ),
+        }
+    }
+
+    fn path(violation: &DependencyRuleViolation) -> Vec<String> {
+        violation
+            .path
+            .iter()
+            .map(|label| label.unconfigured().to_string())
+            .collect()
+    }
+
+    fn direct(node: &ConfiguredTargetNode, rule: &DependencyRule) -> Vec<Vec<String>> {
+        let mut violations = Vec::new();
+        match rule.kind {
+            DependencyRuleKind::Direct(kind) => {
+                check_direct_deps(node, rule, kind, &mut violations)
+            }
+            DependencyRuleKind::TransitiveForbidden => panic!("not a direct rule"),
+        }
+        violations.iter().map(path).collect()
+    }
+
+    /// Computes `ForbiddenReachable` the way the DICE key does, from the deps' results.
+    fn reachable(node: &ConfiguredTargetNode, rule: &DependencyRule) -> ForbiddenReachable {
+        let deps: Vec<_> = node.target_deps().map(|dep| reachable(dep, rule)).collect();
+        ForbiddenReachable::new(rule, node.target_deps().zip(&deps))
+    }
+
+    fn transitive(node: &ConfiguredTargetNode, rule: &DependencyRule) -> Vec<Vec<String>> {
+        reachable(node, rule)
+            .violations(node, rule)
+            .map(|v| path(&v))
+            .collect()
+    }
+
+    #[test]
+    fn test_forbidden() {
+        let internal = node("root//internal:lib", &[]);
+        let public = node("root//public:lib", &[]);
+        let app = node("root//app:app", &[&internal, &public]);
+        let rule = rule(
+            DependencyRuleKind::Direct(DirectDependencyRuleKind::Forbidden),
+            &["root//internal/..."],
+        );
+        assert_eq!(
+            vec![vec!["root//app:app", "root//internal:lib"]],
+            direct(&app, &rule)
+        );
+        assert!(direct(&public, &rule).is_empty());
+    }
+
+    #[test]
+    fn test_allowed_only() {
+        let internal = node("root//internal:lib", &[]);
+        let public = node("root//public:lib", &[]);
+        let app = node("root//app:app", &[&internal, &public]);
+        let rule = rule(
+            DependencyRuleKind::Direct(DirectDependencyRuleKind::AllowedOnly),
+            &["root//public/..."],
+        );
+        assert_eq!(
+            vec![vec!["root//app:app", "root//internal:lib"]],
+            direct(&app, &rule)
+        );
+    }
+
+    #[test]
+    fn test_same_package_exempt() {
+        let helper = node("root//app:helper", &[]);
+        let app = node("root//app:app", &[&helper]);
+        let forbidden = rule(
+            DependencyRuleKind::Direct(DirectDependencyRuleKind::Forbidden),
+            &["root//app/..."],
+        );
+        let allowed_only = rule(
+            DependencyRuleKind::Direct(DirectDependencyRuleKind::AllowedOnly),
+            &["root//public/..."],
+        );
+        assert!(direct(&app, &forbidden).is_empty());
+        assert!(direct(&app, &allowed_only).is_empty());
+    }
+
+    #[test]
+    fn test_transitive() {
+        let db = node("root//db:db", &[]);
+        let model = node("root//model:model", &[&db]);
+        let ui = node("root//ui:ui", &[&model]);
+        let rule = rule(DependencyRuleKind::TransitiveForbidden, &["root//db/..."]);
+        assert_eq!(
+            vec![vec!["root//ui:ui", "root//model:model", "root//db:db"]],
+            transitive(&ui, &rule)
+        );
+        assert!(transitive(&db, &rule).is_empty());
+    }
+
+    #[test]
+    fn test_transitive_shortest_path() {
+        let db = node("root//db:db", &[]);
+        let storage = node("root//storage:storage", &[&db]);
+        let model = node("root//model:model", &[&storage]);
+        let cache = node("root//cache:cache", &[&db]);
+        let ui = node("root//ui:ui", &[&model, &cache]);
+        let rule = rule(DependencyRuleKind::TransitiveForbidden, &["root//db/..."]);
+        assert_eq!(
+            vec![vec!["root//ui:ui", "root//cache:cache", "root//db:db"]],
+            transitive(&ui, &rule)
+        );
+    }
+}
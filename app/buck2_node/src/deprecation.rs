@@ -0,0 +1,130 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use allocative::Allocative;
+use buck2_core::target::label::TargetLabel;
+use buck2_util::arc_str::ThinArcSlice;
+use thiserror::Error;
+
+use crate::visibility::VisibilityPattern;
+
+#[derive(Debug, Error)]
+pub enum DeprecationError {
+    #[error("`{0}` depends on deprecated target `{1}`: {2}")]
+    DeprecatedDependency(TargetLabel, TargetLabel, String),
+}
+
+/// What happens when a target depends on a deprecated target.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Allocative)]
+pub enum DeprecationLevel {
+    /// Emit a warning event when building the dependent target.
+    Warn,
+    /// Fail configuring the dependent target.
+    Error,
+}
+
+/// Deprecation of the targets of a package, declared with `package(deprecation = ...)`
+/// in a `PACKAGE` file and inherited by the packages below it.
+#[derive(Debug, Eq, PartialEq, Hash, Allocative)]
+pub struct DeprecationSpecification {
+    /// Migration message shown to dependents, e.g. which target to use instead.
+    pub message: String,
+    /// Dependents allowed to depend on the deprecated targets without a warning.
+    pub allowlist: ThinArcSlice<VisibilityPattern>,
+    pub level: DeprecationLevel,
+}
+
+/// Deprecation of a target, from its `deprecation` attribute or from its package.
+#[derive(Debug, Clone, Copy)]
+pub struct TargetDeprecation<'a> {
+    /// The `deprecation` attribute of the target if set, else the message of the package.
+    pub message: &'a str,
+    /// The allowlist of the package, if it is deprecated.
+    pub allowlist: &'a [VisibilityPattern],
+    /// The level of the package if it is deprecated, else a warning.
+    pub level: DeprecationLevel,
+}
+
+impl<'a> TargetDeprecation<'a> {
+    pub fn new(
+        attribute: Option<&'a str>,
+        package: Option<&'a DeprecationSpecification>,
+    ) -> Option<Self> {
+        match (attribute, package) {
+            (None, None) => None,
+            (attribute, Some(package)) => Some(TargetDeprecation {
+                message: attribute.unwrap_or(&package.message),
+                allowlist: &package.allowlist,
+                level: package.level,
+            }),
+            (Some(attribute), None) => Some(TargetDeprecation {
+                message: attribute,
+                allowlist: &[],
+                level: DeprecationLevel::Warn,
+            }),
+        }
+    }
+
+    /// Whether a dependency of `dependent` on the deprecated target should be reported. Targets
+    /// in the same package can always depend on each other.
+    pub fn applies_to(&self, deprecated: &TargetLabel, dependent: &TargetLabel) -> bool {
+        deprecated.pkg() != dependent.pkg()
+            && !self.allowlist.iter().any(|p| p.0.matches(dependent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::pattern::ParsedPattern;
+
+    use super::*;
+
+    #[test]
+    fn test_applies_to() {
+        let package = DeprecationSpecification {
+            message: "use //new:lib".to_owned(),
+            allowlist: ThinArcSlice::new([VisibilityPattern(ParsedPattern::testing_parse(
+                "root//legacy/...",
+            ))]),
+            level: DeprecationLevel::Warn,
+        };
+        let spec = TargetDeprecation::new(None, Some(&package)).unwrap();
+        let deprecated = TargetLabel::testing_parse("root//old:lib");
+
+        assert!(spec.applies_to(&deprecated, &TargetLabel::testing_parse("root//app:bin")));
+        assert!(!spec.applies_to(&deprecated, &TargetLabel::testing_parse("root//old:other")));
+        assert!(!spec.applies_to(
+            &deprecated,
+            &TargetLabel::testing_parse("root//legacy/app:bin")
+        ));
+    }
+
+    #[test]
+    fn test_target_deprecation() {
+        let package = DeprecationSpecification {
+            message: "use //new:lib".to_owned(),
+            allowlist: ThinArcSlice::new([VisibilityPattern(ParsedPattern::testing_parse(
+                "root//legacy/...",
+            ))]),
+            level: DeprecationLevel::Error,
+        };
+
+        assert!(TargetDeprecation::new(None, None).is_none());
+
+        let own = TargetDeprecation::new(Some("use //new:other"), None).unwrap();
+        assert_eq!("use //new:other", own.message);
+        assert!(own.allowlist.is_empty());
+        assert_eq!(DeprecationLevel::Warn, own.level);
+
+        let both = TargetDeprecation::new(Some("use //new:other"), Some(&package)).unwrap();
+        assert_eq!("use //new:other", both.message);
+        assert_eq!(1, both.allowlist.len());
+        assert_eq!(DeprecationLevel::Error, both.level);
+    }
+}
@@ -17,8 +17,11 @@ pub mod attrs;
 pub mod call_stack;
 pub mod configuration;
 pub mod configured_universe;
+pub mod dependency_rules;
+pub mod deprecation;
 pub mod nodes;
 pub mod package;
+pub mod package_default_attrs;
 pub mod provider_id_set;
 pub mod query;
 pub mod rule;
@@ -58,6 +58,8 @@ use crate::attrs::internal::TARGET_COMPATIBLE_WITH_ATTRIBUTE_FIELD;
 use crate::attrs::internal::TESTS_ATTRIBUTE_FIELD;
 use crate::configuration::execution::ExecutionPlatformResolution;
 use crate::configuration::resolved::ResolvedConfiguration;
+use crate::dependency_rules::DependencyRule;
+use crate::deprecation::TargetDeprecation;
 use crate::nodes::attributes::DEPS;
 use crate::nodes::attributes::EXECUTION_PLATFORM;
 use crate::nodes::attributes::ONCALL;
@@ -141,6 +143,20 @@ impl TargetNodeOrForward {
         }
     }
 
+    fn deprecation(&self) -> Option<TargetDeprecation> {
+        match self {
+            TargetNodeOrForward::TargetNode(node) => node.deprecation(),
+            TargetNodeOrForward::Forward(_, forward) => forward.deprecation(),
+        }
+    }
+
+    fn dependency_rules(&self) -> &[DependencyRule] {
+        match self {
+            TargetNodeOrForward::TargetNode(node) => node.dependency_rules(),
+            TargetNodeOrForward::Forward(_, forward) => forward.dependency_rules(),
+        }
+    }
+
     fn attr_or_none<'a>(
         &'a self,
         name: &str,
@@ -513,6 +529,14 @@ impl ConfiguredTargetNode {
         self.0.target_node.oncall()
     }
 
+    pub fn deprecation(&self) -> Option<TargetDeprecation> {
+        self.0.target_node.deprecation()
+    }
+
+    pub fn dependency_rules(&self) -> &[DependencyRule] {
+        self.0.target_node.dependency_rules()
+    }
+
     fn attr_configuration_context(&self) -> AttrConfigurationContextImpl {
         AttrConfigurationContextImpl::new(
             &self.0.resolved_configuration,
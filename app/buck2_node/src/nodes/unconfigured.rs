@@ -19,6 +19,7 @@ use buck2_core::configuration::transition::id::TransitionId;
 use buck2_core::provider::label::ProvidersLabel;
 use buck2_core::target::label::TargetLabel;
 use buck2_util::arc_str::ArcStr;
+use buck2_util::arc_str::ThinArcSlice;
 use dupe::Dupe;
 
 use crate::attrs::attr_type::attr_config::CoercedAttrExtraTypes;
@@ -30,11 +31,14 @@ use crate::attrs::coerced_deps_collector::CoercedDeps;
 use crate::attrs::display::AttrDisplayWithContextExt;
 use crate::attrs::inspect_options::AttrInspectOptions;
 use crate::attrs::internal::DEFAULT_TARGET_PLATFORM_ATTRIBUTE_FIELD;
+use crate::attrs::internal::DEPRECATION_ATTRIBUTE_FIELD;
 use crate::attrs::internal::TESTS_ATTRIBUTE_FIELD;
 use crate::attrs::spec::AttributeSpec;
 use crate::attrs::traversal::CoercedAttrTraversal;
 use crate::attrs::values::AttrValues;
 use crate::call_stack::StarlarkCallStack;
+use crate::dependency_rules::DependencyRule;
+use crate::deprecation::TargetDeprecation;
 use crate::nodes::attributes::CONFIGURATION_DEPS;
 use crate::nodes::attributes::DEPS;
 use crate::nodes::attributes::ONCALL;
@@ -227,6 +231,22 @@ impl TargetNode {
         self.0.package.oncall.as_ref().map(|x| x.as_str())
     }
 
+    /// The deprecation of the target, from its `deprecation` attribute or its package.
+    pub fn deprecation(&self) -> Option<TargetDeprecation> {
+        let attribute = match self
+            .attr_or_none(DEPRECATION_ATTRIBUTE_FIELD, AttrInspectOptions::All)
+            .map(|a| a.value)
+        {
+            Some(CoercedAttr::Literal(AttrLiteral::String(message))) => Some(message.as_str()),
+            _ => None,
+        };
+        TargetDeprecation::new(attribute, self.0.package.deprecation.as_deref())
+    }
+
+    pub fn dependency_rules(&self) -> &[DependencyRule] {
+        &self.0.package.dependency_rules
+    }
+
     fn visibility(&self) -> anyhow::Result<&VisibilitySpecification> {
         match self.0.attributes.get(AttributeSpec::visibility_attr_id()) {
             Some(CoercedAttr::Literal(AttrLiteral::Visibility(v))) => Ok(v),
@@ -508,6 +528,8 @@ pub mod testing {
                     buildfile_path,
                     oncall: None,
                     default_visibility_to_public: false,
+                    deprecation: None,
+                    dependency_rules: ThinArcSlice::empty(),
                 }),
                 label,
                 attributes,
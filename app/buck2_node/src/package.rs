@@ -11,6 +11,10 @@ use std::sync::Arc;
 
 use allocative::Allocative;
 use buck2_core::build_file_path::BuildFilePath;
+use buck2_util::arc_str::ThinArcSlice;
+
+use crate::dependency_rules::DependencyRule;
+use crate::deprecation::DeprecationSpecification;
 
 /// Package-specific data for `TargetNode`.
 ///
@@ -24,4 +28,8 @@ pub struct Package {
     pub oncall: Option<Arc<String>>,
     /// Visibility is public by default.
     pub default_visibility_to_public: bool,
+    /// Deprecation from the `PACKAGE` files containing the build file, if any.
+    pub deprecation: Option<Arc<DeprecationSpecification>>,
+    /// Dependency rules from the `PACKAGE` files containing the build file.
+    pub dependency_rules: ThinArcSlice<DependencyRule>,
 }
@@ -0,0 +1,134 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fmt::Display;
+
+use allocative::Allocative;
+use buck2_core::target::label::TargetLabel;
+
+use crate::attrs::internal::TARGET_COMPATIBLE_WITH_ATTRIBUTE_FIELD;
+
+/// Attribute defaults declared with `package()` in `PACKAGE` files, used for the attributes
+/// of targets which do not set them. Labels are resolved against the package of the `PACKAGE`
+/// file declaring them, not against the package of the targets using them.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash, Allocative)]
+pub struct PackageDefaultAttrs {
+    pub target_compatible_with: Option<Vec<TargetLabel>>,
+    pub labels: Option<Vec<String>>,
+    /// Targets providing the license files: files of the package of the `PACKAGE` file could not
+    /// be used by the targets of its subpackages.
+    pub licenses: Option<Vec<TargetLabel>>,
+}
+
+impl PackageDefaultAttrs {
+    /// Defaults of a `PACKAGE` file given the defaults inherited from its parent: a default set
+    /// by the file replaces the inherited one, or extends it with `inherit`.
+    pub fn merge(&self, child: PackageDefaultAttrs, inherit: bool) -> PackageDefaultAttrs {
+        fn merge<T: Clone>(
+            parent: &Option<Vec<T>>,
+            child: Option<Vec<T>>,
+            inherit: bool,
+        ) -> Option<Vec<T>> {
+            match (parent, child) {
+                (Some(parent), Some(child)) if inherit => {
+                    Some(parent.iter().cloned().chain(child).collect())
+                }
+                (parent, child) => child.or_else(|| parent.clone()),
+            }
+        }
+
+        PackageDefaultAttrs {
+            target_compatible_with: merge(
+                &self.target_compatible_with,
+                child.target_compatible_with,
+                inherit,
+            ),
+            labels: merge(&self.labels, child.labels, inherit),
+            licenses: merge(&self.licenses, child.licenses, inherit),
+        }
+    }
+
+    /// The default of an attribute, as absolute labels which coerce to the same values in any
+    /// package.
+    pub fn attr_default(&self, attr_name: &str) -> Option<Vec<String>> {
+        fn to_strings(labels: &Option<Vec<TargetLabel>>) -> Option<Vec<String>> {
+            labels
+                .as_ref()
+                .map(|labels| labels.iter().map(|l| l.to_string()).collect())
+        }
+
+        match attr_name {
+            TARGET_COMPATIBLE_WITH_ATTRIBUTE_FIELD => to_strings(&self.target_compatible_with),
+            "labels" => self.labels.clone(),
+            "licenses" => to_strings(&self.licenses),
+            _ => None,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        fn to_json<T: Display>(values: &Option<Vec<T>>) -> Option<serde_json::Value> {
+            values.as_ref().map(|values| {
+                serde_json::Value::Array(
+                    values
+                        .iter()
+                        .map(|v| serde_json::Value::String(v.to_string()))
+                        .collect(),
+                )
+            })
+        }
+
+        let mut result = serde_json::Map::new();
+        for (name, values) in [
+            (
+                TARGET_COMPATIBLE_WITH_ATTRIBUTE_FIELD,
+                to_json(&self.target_compatible_with),
+            ),
+            ("labels", to_json(&self.labels)),
+            ("licenses", to_json(&self.licenses)),
+        ] {
+            if let Some(values) = values {
+                result.insert(name.to_owned(), values);
+            }
+        }
+        serde_json::Value::Object(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults(labels: &[&str]) -> PackageDefaultAttrs {
+        PackageDefaultAttrs {
+            labels: Some(labels.iter().map(|l| (*l).to_owned()).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let parent = defaults(&["a"]);
+        assert_eq!(defaults(&["a", "b"]), parent.merge(defaults(&["b"]), true));
+        assert_eq!(defaults(&["b"]), parent.merge(defaults(&["b"]), false));
+        assert_eq!(parent, parent.merge(PackageDefaultAttrs::default(), false));
+    }
+
+    #[test]
+    fn test_attr_default() {
+        let defaults = PackageDefaultAttrs {
+            licenses: Some(vec![TargetLabel::testing_parse("root//legal:apache")]),
+            ..Default::default()
+        };
+        assert_eq!(
+            Some(vec!["root//legal:apache".to_owned()]),
+            defaults.attr_default("licenses")
+        );
+        assert_eq!(None, defaults.attr_default("labels"));
+    }
+}
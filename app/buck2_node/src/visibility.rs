@@ -84,7 +84,7 @@ impl VisibilitySpecification {
         }
     }
 
-    pub(crate) fn to_json(&self) -> serde_json::Value {
+    pub fn to_json(&self) -> serde_json::Value {
         let list = match self {
             VisibilitySpecification::Public => vec![serde_json::Value::String(
                 VisibilityPattern::PUBLIC.to_owned(),
@@ -128,6 +128,18 @@ impl Display for VisibilitySpecification {
 }
 
 impl WithinViewSpecification {
+    pub fn to_json(&self) -> serde_json::Value {
+        let list = match self {
+            WithinViewSpecification::Public => vec![serde_json::Value::String(
+                VisibilityPattern::PUBLIC.to_owned(),
+            )],
+            WithinViewSpecification::VisibleTo(patterns) => {
+                patterns.map(|p| serde_json::Value::String(p.to_string()))
+            }
+        };
+        serde_json::Value::Array(list)
+    }
+
     pub fn extend_with(&self, other: &WithinViewSpecification) -> WithinViewSpecification {
         match (self, other) {
             (WithinViewSpecification::Public, _) | (_, WithinViewSpecification::Public) => {
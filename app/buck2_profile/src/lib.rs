@@ -11,11 +11,13 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::Context;
+use buck2_cli_proto::profile_request::ProfileFormat;
 use buck2_cli_proto::profile_request::ProfileOpts;
 use buck2_cli_proto::profile_request::Profiler;
 use buck2_core::fs::fs_util;
 use buck2_interpreter::dice::starlark_profiler::StarlarkProfilerConfiguration;
 use buck2_interpreter::starlark_profiler::StarlarkProfileDataAndStats;
+use starlark::eval::CoverageFormat;
 use starlark::eval::ProfileMode;
 
 pub fn starlark_profiler_configuration_from_request(
@@ -34,6 +36,7 @@ pub fn starlark_profiler_configuration_from_request(
         Profiler::Bytecode => ProfileMode::Bytecode,
         Profiler::BytecodePairs => ProfileMode::BytecodePairs,
         Profiler::Typecheck => ProfileMode::Typecheck,
+        Profiler::Coverage => ProfileMode::Coverage,
     };
 
     match req.profile_opts.as_ref().expect("Missing profile opts") {
@@ -68,9 +71,21 @@ pub fn get_profile_response(
 ) -> anyhow::Result<buck2_cli_proto::ProfileResponse> {
     let command_profile_mode = buck2_cli_proto::profile_request::Profiler::from_i32(req.profiler)
         .context("Invalid profiler")?;
+    let format = ProfileFormat::from_i32(req.format).context("Invalid profile format")?;
 
-    match command_profile_mode {
-        Profiler::HeapFlameAllocated | Profiler::HeapFlameRetained | Profiler::TimeFlame => {
+    match (format, command_profile_mode) {
+        (ProfileFormat::Pprof, _) => {
+            let profile = profile_data.profile_data.gen_pprof()?;
+            fs_util::write(&output, profile).context("Failed to write profile")?;
+        }
+        (ProfileFormat::ChromeTrace, _) => {
+            let profile = profile_data.profile_data.gen_chrome_trace()?;
+            fs_util::write(&output, profile).context("Failed to write profile")?;
+        }
+        (
+            ProfileFormat::Default,
+            Profiler::HeapFlameAllocated | Profiler::HeapFlameRetained | Profiler::TimeFlame,
+        ) => {
             let mut profile = profile_data.profile_data.gen()?;
             if profile.is_empty() {
                 // inferno does not like empty flamegraphs.
@@ -90,7 +105,25 @@ pub fn get_profile_response(
                 .context("Failed to write profile")?;
             fs_util::write(output.join("flame.svg"), &svg).context("Failed to write profile")?;
         }
-        _ => {
+        (ProfileFormat::Default, Profiler::Coverage) => {
+            fs_util::create_dir_if_not_exists(&output)?;
+
+            fs_util::write(
+                output.join("lcov.info"),
+                profile_data
+                    .profile_data
+                    .gen_coverage(CoverageFormat::Lcov)?,
+            )
+            .context("Failed to write profile")?;
+            fs_util::write(
+                output.join("cobertura.xml"),
+                profile_data
+                    .profile_data
+                    .gen_coverage(CoverageFormat::Cobertura)?,
+            )
+            .context("Failed to write profile")?;
+        }
+        (ProfileFormat::Default, _) => {
             let profile = profile_data.profile_data.gen()?;
             fs_util::write(&output, profile).context("Failed to write profile")?;
         }
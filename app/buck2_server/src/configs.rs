@@ -13,6 +13,7 @@ use anyhow::Context;
 use buck2_cli_proto::config_override::ConfigType;
 use buck2_cli_proto::ConfigOverride;
 use buck2_common::legacy_configs::cells::BuckConfigBasedCells;
+use buck2_common::legacy_configs::external_cells::ExternalCells;
 use buck2_common::legacy_configs::LegacyBuckConfigs;
 use buck2_common::legacy_configs::LegacyConfigCmdArg;
 use buck2_core::cells::CellResolver;
@@ -47,11 +48,13 @@ fn get_legacy_config_args<'a, Iter: IntoIterator<Item = &'a ConfigOverride>>(
         .collect::<anyhow::Result<Vec<LegacyConfigCmdArg>>>()
 }
 
-/// Read the configs, returning the cell resolver and the legacy configs
+/// Read the configs, returning the cell resolver and the legacy configs. External cells which
+/// are not cached in `external_cells_dir` are fetched first.
 pub fn parse_legacy_cells<'a, Iter: IntoIterator<Item = &'a ConfigOverride>>(
     config_overrides: Iter,
     cwd: &ProjectRelativePath,
     fs: &ProjectRoot,
+    external_cells_dir: &ProjectRelativePath,
 ) -> anyhow::Result<(CellResolver, LegacyBuckConfigs, HashSet<AbsNormPathBuf>)> {
     let config_values = get_legacy_config_args(config_overrides)?;
     // TODO: We do not need to reparse _all_ configs, instead we just need to
@@ -59,6 +62,25 @@ pub fn parse_legacy_cells<'a, Iter: IntoIterator<Item = &'a ConfigOverride>>(
     // the base configs derived from the config files. This requires us to
     // store the base configs + overlaid ones separately, so we can cheaply
     // recompose.
-    let res = BuckConfigBasedCells::parse_with_config_args(fs, &config_values, cwd)?;
+    let mut res =
+        BuckConfigBasedCells::parse_with_config_args(fs, &config_values, cwd, external_cells_dir)?;
+
+    let root_config = res
+        .configs_by_name
+        .get(res.cell_resolver.root_cell())
+        .context("No config for root cell")?;
+    let mut external_cells = ExternalCells::load(fs, external_cells_dir)?;
+    let fetched = external_cells.materialize_all(root_config)?;
+    if external_cells.write_lock()? || fetched {
+        // The roots of newly locked cells and the configs of newly fetched cells are only known
+        // now.
+        res = BuckConfigBasedCells::parse_with_config_args(
+            fs,
+            &config_values,
+            cwd,
+            external_cells_dir,
+        )?;
+    }
+
     Ok((res.cell_resolver, res.configs_by_name, res.config_paths))
 }
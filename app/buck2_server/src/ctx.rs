@@ -44,6 +44,7 @@ use buck2_common::executor_config::CommandExecutorConfig;
 use buck2_common::io::trace::TracingIoProvider;
 use buck2_common::io::IoProvider;
 use buck2_common::legacy_configs::dice::HasLegacyConfigs;
+use buck2_common::legacy_configs::external_cells::external_cells_dir;
 use buck2_common::legacy_configs::LegacyBuckConfig;
 use buck2_common::legacy_configs::LegacyBuckConfigs;
 use buck2_common::result::SharedError;
@@ -77,6 +78,7 @@ use buck2_execute::re::client::RemoteExecutionClient;
 use buck2_execute::re::manager::ReConnectionHandle;
 use buck2_execute::re::manager::ReConnectionManager;
 use buck2_execute::re::manager::ReConnectionObserver;
+use buck2_execute_impl::executors::worker::WorkerPool;
 use buck2_execute_impl::low_pass_filter::LowPassFilter;
 use buck2_forkserver::client::ForkserverClient;
 use buck2_interpreter::dice::starlark_debug::SetStarlarkDebugger;
@@ -134,6 +136,8 @@ pub struct BaseServerCommandContext {
     pub _fb: fbinit::FacebookInit,
     /// Absolute path to the project root.
     pub project_root: ProjectRoot,
+    /// The buck-out directory of the isolation dir of the daemon.
+    pub buck_out_dir: ProjectRelativePathBuf,
     /// A reference to the dice graph. Most interesting things are accessible from this (and new interesting things should be
     /// added there rather than as fields here). This has some per-request setup done already (like attaching a per-request
     /// event dispatcher).
@@ -149,6 +153,8 @@ pub struct BaseServerCommandContext {
     pub materializer: Arc<dyn Materializer>,
     /// Forkserver connection, if any was started
     pub forkserver: Option<ForkserverClient>,
+    /// The persistent workers of the daemon.
+    pub worker_pool: Arc<WorkerPool>,
     /// The event dispatcher for this command context.
     pub events: EventDispatcher,
     /// Removes this command from the set of active commands when dropped.
@@ -298,6 +304,7 @@ impl<'a> ServerCommandContext<'a> {
 
         let cell_configs_loader = Arc::new(CellConfigLoader {
             project_root: base_context.project_root.clone(),
+            external_cells_dir: external_cells_dir(&base_context.buck_out_dir),
             working_dir: working_dir_project_relative.to_buf().into(),
             reuse_current_config: client_context.reuse_current_config,
             config_overrides: client_context.config_overrides.clone(),
@@ -375,6 +382,7 @@ impl<'a> ServerCommandContext<'a> {
         let build_signals = self.build_signals.dupe();
 
         let forkserver = self.base_context.forkserver.dupe();
+        let worker_pool = self.base_context.worker_pool.dupe();
 
         let upload_all_actions = self
             .build_options
@@ -395,6 +403,7 @@ impl<'a> ServerCommandContext<'a> {
             re_connection,
             build_signals,
             forkserver,
+            worker_pool,
             upload_all_actions,
             skip_cache_read,
             skip_cache_write,
@@ -437,6 +446,7 @@ impl<'a> ServerCommandContext<'a> {
 
 struct CellConfigLoader {
     project_root: ProjectRoot,
+    external_cells_dir: ProjectRelativePathBuf,
     working_dir: ProjectRelativePathBuf,
     /// Reuses build config from the previous invocation if there is one
     reuse_current_config: bool,
@@ -475,8 +485,23 @@ impl CellConfigLoader {
                         );
                     }
                 }
-                parse_legacy_cells(self.config_overrides.iter(), &self.working_dir, &self.project_root)
-                    .shared_error()
+                // Parsing may fetch external cells.
+                let config_overrides = self.config_overrides.clone();
+                let working_dir = self.working_dir.clone();
+                let project_root = self.project_root.clone();
+                let external_cells_dir = self.external_cells_dir.clone();
+                tokio::task::spawn_blocking(move || {
+                    parse_legacy_cells(
+                        config_overrides.iter(),
+                        &working_dir,
+                        &project_root,
+                        &external_cells_dir,
+                    )
+                })
+                .await
+                .map_err(anyhow::Error::from)
+                .and_then(|res| res)
+                .shared_error()
             })
             .await
             .clone()
@@ -494,6 +519,7 @@ struct DiceCommandDataProvider {
     re_connection: Arc<ReConnectionHandle>,
     build_signals: BuildSignalSender,
     forkserver: Option<ForkserverClient>,
+    worker_pool: Arc<WorkerPool>,
     upload_all_actions: bool,
     run_action_knobs: RunActionKnobs,
     skip_cache_read: bool,
@@ -582,6 +608,7 @@ impl DiceDataProvider for DiceCommandDataProvider {
             executor_global_knobs,
             self.upload_all_actions,
             self.forkserver.dupe(),
+            self.worker_pool.dupe(),
             self.skip_cache_read,
             self.skip_cache_write,
             ctx.global_data()
@@ -38,6 +38,7 @@ use buck2_execute_impl::executors::caching::CachingExecutor;
 use buck2_execute_impl::executors::hybrid::HybridExecutor;
 use buck2_execute_impl::executors::local::LocalExecutor;
 use buck2_execute_impl::executors::re::ReExecutor;
+use buck2_execute_impl::executors::worker::WorkerPool;
 use buck2_execute_impl::low_pass_filter::LowPassFilter;
 use buck2_forkserver::client::ForkserverClient;
 use dupe::Dupe;
@@ -71,6 +72,7 @@ pub struct CommandExecutorFactory {
     pub executor_global_knobs: ExecutorGlobalKnobs,
     pub upload_all_actions: bool,
     pub forkserver: Option<ForkserverClient>,
+    pub worker_pool: Arc<WorkerPool>,
     pub skip_cache_read: bool,
     pub skip_cache_write: bool,
     project_root: ProjectRoot,
@@ -87,6 +89,7 @@ impl CommandExecutorFactory {
         executor_global_knobs: ExecutorGlobalKnobs,
         upload_all_actions: bool,
         forkserver: Option<ForkserverClient>,
+        worker_pool: Arc<WorkerPool>,
         skip_cache_read: bool,
         skip_cache_write: bool,
         project_root: ProjectRoot,
@@ -101,6 +104,7 @@ impl CommandExecutorFactory {
             executor_global_knobs,
             upload_all_actions,
             forkserver,
+            worker_pool,
             skip_cache_read,
             skip_cache_write,
             project_root,
@@ -123,6 +127,7 @@ impl HasCommandExecutor for CommandExecutorFactory {
                 self.project_root.root().to_owned(),
                 self.forkserver.dupe(),
                 self.executor_global_knobs.dupe(),
+                self.worker_pool.dupe(),
             )
         };
 
@@ -43,6 +43,7 @@ pub(crate) fn dice_dump(
         DiceDumpFormat::Tsv => dice_dump_tsv(dice, path),
         DiceDumpFormat::Bincode => dice_dump_bincode(dice, path),
         DiceDumpFormat::JsonPretty => dice_dump_json_pretty(dice, path),
+        DiceDumpFormat::Compact => dice_dump_compact(dice, path),
     }
 }
 
@@ -129,6 +130,18 @@ fn dice_dump_bincode(dice: &Arc<Dice>, path: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn dice_dump_compact(dice: &Arc<Dice>, path: &Path) -> anyhow::Result<()> {
+    let path = path.to_path_buf();
+    std::fs::create_dir_all(path.parent().unwrap()).context("Failed to create directory")?;
+    let out =
+        File::create(&path).context(format!("Failed to open compact DICE dumpfile {:?}", &path))?;
+    let mut out = GzEncoder::new(BufWriter::new(out), Compression::default());
+    dice.serialize_compact(&mut out)?;
+    out.try_finish()
+        .context(format!("Failed to flush compact DICE dump to {:?}", &path))?;
+    Ok(())
+}
+
 fn dice_dump_json_pretty(dice: &Arc<Dice>, path: &Path) -> anyhow::Result<()> {
     let path = path.to_path_buf();
     std::fs::create_dir_all(path.parent().unwrap()).context("Failed to create directory")?;
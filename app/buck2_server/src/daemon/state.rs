@@ -43,6 +43,8 @@ use buck2_execute::execute::blocking::BuckBlockingExecutor;
 use buck2_execute::materialize::materializer::MaterializationMethod;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_execute::re::manager::ReConnectionManager;
+use buck2_execute_impl::executors::worker::WorkerPool;
+use buck2_execute_impl::executors::worker::WorkerPoolConfig;
 use buck2_execute_impl::materializers::deferred::DeferredMaterializer;
 use buck2_execute_impl::materializers::deferred::DeferredMaterializerConfigs;
 use buck2_execute_impl::materializers::deferred::TtlRefreshConfiguration;
@@ -120,6 +122,10 @@ pub struct DaemonStateData {
 
     pub(crate) forkserver: Option<ForkserverClient>,
 
+    /// The persistent workers, kept across commands.
+    #[allocative(skip)]
+    pub(crate) worker_pool: Arc<WorkerPool>,
+
     #[allocative(skip)]
     pub scribe_sink: Option<Arc<dyn EventSink>>,
 
@@ -375,6 +381,21 @@ impl DaemonState {
         let forkserver =
             maybe_launch_forkserver(root_config, &paths.forkserver_state_dir()).await?;
 
+        let worker_pool_config = WorkerPoolConfig {
+            max_workers_per_key: root_config
+                .parse("buck2", "persistent_worker_max_instances")?
+                .unwrap_or(4),
+            max_workers: root_config
+                .parse("buck2", "persistent_worker_max_total")?
+                .unwrap_or(16),
+            idle_timeout: Duration::from_secs(
+                root_config
+                    .parse("buck2", "persistent_worker_idle_timeout_s")?
+                    .unwrap_or(600),
+            ),
+        };
+        let worker_pool = Arc::new(WorkerPool::new(worker_pool_config, paths.workers_dir()));
+
         let dice = init_ctx
             .construct_dice(io.dupe(), digest_config, root_config)
             .await?;
@@ -467,6 +488,7 @@ impl DaemonState {
             blocking_executor,
             materializer,
             forkserver,
+            worker_pool,
             scribe_sink,
             hash_all_commands,
             use_network_action_output_cache,
@@ -644,6 +666,7 @@ impl DaemonState {
         Ok(BaseServerCommandContext {
             _fb: self.fb,
             project_root: self.paths.project_root().clone(),
+            buck_out_dir: self.paths.buck_out_dir(),
             dice_manager: data.dice_manager.dupe(),
             io: data.io.dupe(),
             re_client_manager: data.re_client_manager.dupe(),
@@ -652,6 +675,7 @@ impl DaemonState {
             file_watcher: data.file_watcher.dupe(),
             events: dispatcher,
             forkserver: data.forkserver.dupe(),
+            worker_pool: data.worker_pool.dupe(),
             hash_all_commands: data.hash_all_commands,
             use_network_action_output_cache: data.use_network_action_output_cache,
             _drop_guard: drop_guard,
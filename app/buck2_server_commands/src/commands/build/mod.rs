@@ -21,6 +21,7 @@ use buck2_build_api::build::HasCreateUnhashedSymlinkLock;
 use buck2_build_api::build::MaterializationContext;
 use buck2_build_api::build::ProvidersToBuild;
 use buck2_build_api::calculation::Calculation;
+use buck2_build_api::nodes::deprecation::emit_deprecation_warnings;
 use buck2_build_api::query::cquery::evaluator::universe_from_literals;
 use buck2_build_api::query::dice::get_dice_query_delegate;
 use buck2_cli_proto::build_request::build_providers::Action as BuildProviderAction;
@@ -228,6 +229,7 @@ async fn build(
         ConvertMaterializationContext::from(final_artifact_materializations);
 
     let mut provider_artifacts = Vec::new();
+    let mut built_targets = Vec::new();
     for (k, v) in build_targets(
         &ctx,
         resolved_pattern,
@@ -239,6 +241,7 @@ async fn build(
     .await?
     {
         result_collectors.collect_result(&BuildOwner::Target(&k), &v);
+        built_targets.push(k.target().dupe());
         let mut outputs = v.outputs.into_iter().filter_map(|output| match output {
             Ok(output) => Some(output),
             _ => None,
@@ -246,6 +249,8 @@ async fn build(
         provider_artifacts.extend(&mut outputs);
     }
 
+    emit_deprecation_warnings(&ctx, built_targets.into_iter().unique()).await;
+
     if should_create_unhashed_links.unwrap_or(false) {
         span_async(buck2_data::CreateOutputSymlinksStart {}, async {
             let lock = ctx
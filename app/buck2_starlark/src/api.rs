@@ -0,0 +1,697 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Display;
+use std::io::Write;
+
+use anyhow::Context;
+use async_recursion::async_recursion;
+use async_trait::async_trait;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::path_arg::PathArg;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::dice::data::HasIoProvider;
+use buck2_common::dice::file_ops::HasFileOps;
+use buck2_common::io::IoProvider;
+use buck2_core::bzl::ImportPath;
+use buck2_core::cells::CellResolver;
+use buck2_core::fs::fs_util;
+use buck2_interpreter::file_type::StarlarkFileType;
+use buck2_interpreter::parse_import::parse_import;
+use buck2_interpreter::path::OwnedStarlarkPath;
+use buck2_interpreter::path::StarlarkPath;
+use buck2_interpreter_for_build::interpreter::global_interpreter_state::HasGlobalInterpreterState;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use dupe::Dupe;
+use starlark::environment::LibraryExtension;
+use starlark::syntax::AstModule;
+use starlark::syntax::ExportedSymbolKind;
+use starlark::typing::Interface;
+use starlark::typing::OracleDocs;
+use starlark::typing::OracleStandard;
+use starlark::typing::ParamMode;
+use starlark::typing::Ty;
+use starlark::typing::TypingOracle;
+
+use crate::util::paths::starlark_files;
+use crate::StarlarkCommandCommonOptions;
+use crate::StarlarkOpaqueSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "starlark-api",
+    about = "Print the exported API of `.bzl` files as JSON, or check it against a baseline \
+    previously printed by this command, failing if any symbol was removed or changed \
+    incompatibly."
+)]
+pub struct StarlarkApiCommand {
+    #[clap(flatten)]
+    common_opts: StarlarkCommandCommonOptions,
+
+    #[clap(
+        long,
+        value_name = "FILE",
+        help = "Baseline to check the API against, instead of printing the API"
+    )]
+    baseline: Option<PathArg>,
+
+    #[clap(value_name = "PATH", required = true)]
+    paths: Vec<PathArg>,
+}
+
+/// The exported symbols of each file, by import path.
+type ApiMap = BTreeMap<String, BTreeMap<String, SymbolApi>>;
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ParamModeApi {
+    PosOnly,
+    PosOrName,
+    NameOnly,
+    Args,
+    Kwargs,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct TyApi {
+    #[serde(rename = "ty")]
+    display: String,
+    /// The members of the type if it is a union, empty otherwise.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    alternatives: Vec<String>,
+}
+
+impl TyApi {
+    fn new(ty: &Ty) -> Self {
+        let alternatives = match ty {
+            Ty::Union(union) => union.alternatives().iter().map(Ty::to_string).collect(),
+            _ => Vec::new(),
+        };
+        Self {
+            display: ty.to_string(),
+            alternatives,
+        }
+    }
+
+    fn alternatives(&self) -> Vec<&str> {
+        if self.alternatives.is_empty() {
+            vec![self.display.as_str()]
+        } else {
+            self.alternatives.iter().map(String::as_str).collect()
+        }
+    }
+}
+
+impl Display for TyApi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.display)
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct ParamApi {
+    mode: ParamModeApi,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    optional: bool,
+    #[serde(flatten)]
+    ty: TyApi,
+}
+
+impl ParamApi {
+    fn by_position(&self) -> bool {
+        matches!(self.mode, ParamModeApi::PosOnly | ParamModeApi::PosOrName)
+    }
+
+    fn by_name(&self, name: &str) -> bool {
+        matches!(self.mode, ParamModeApi::PosOrName | ParamModeApi::NameOnly)
+            && self.name.as_deref() == Some(name)
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct SymbolApi {
+    function: bool,
+    #[serde(flatten)]
+    ty: TyApi,
+    /// Only present if the symbol is known to be a function with this signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    params: Option<Vec<ParamApi>>,
+}
+
+impl SymbolApi {
+    fn new(kind: &ExportedSymbolKind, ty: Option<&Ty>) -> Self {
+        let ty = ty.unwrap_or(&Ty::Any);
+        let params = match ty {
+            Ty::Function(f) => Some(
+                f.params
+                    .iter()
+                    .map(|p| {
+                        let (mode, name) = match &p.mode {
+                            ParamMode::PosOnly => (ParamModeApi::PosOnly, None),
+                            ParamMode::PosOrName(name) => {
+                                (ParamModeApi::PosOrName, Some(name.clone()))
+                            }
+                            ParamMode::NameOnly(name) => {
+                                (ParamModeApi::NameOnly, Some(name.clone()))
+                            }
+                            ParamMode::Args => (ParamModeApi::Args, None),
+                            ParamMode::Kwargs => (ParamModeApi::Kwargs, None),
+                        };
+                        ParamApi {
+                            mode,
+                            name,
+                            optional: p.optional,
+                            ty: TyApi::new(&p.ty),
+                        }
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        };
+        Self {
+            function: matches!(kind, ExportedSymbolKind::Function) || params.is_some(),
+            ty: TyApi::new(ty),
+            params,
+        }
+    }
+}
+
+/// Whether all values of type `narrower` are also of type `wider`, comparing the alternatives of
+/// unions. A type we know nothing about is compatible with any other.
+fn accepts(wider: &TyApi, narrower: &TyApi) -> bool {
+    let any = Ty::Any.to_string();
+    let wider = wider.alternatives();
+    let narrower = narrower.alternatives();
+    wider.contains(&any.as_str())
+        || narrower.contains(&any.as_str())
+        || narrower.iter().all(|ty| wider.contains(ty))
+}
+
+/// The ways in which calls that were valid against `old` can fail against `new`.
+fn signature_changes(old: &[ParamApi], new: &[ParamApi]) -> Vec<String> {
+    let mut res = Vec::new();
+    let new_args = new.iter().position(|p| p.mode == ParamModeApi::Args);
+    let new_kwargs = new.iter().position(|p| p.mode == ParamModeApi::Kwargs);
+
+    for (i, p) in old.iter().enumerate() {
+        let display = match &p.name {
+            Some(name) => format!("`{}`", name),
+            None => format!("#{}", i),
+        };
+        let matched = match p.mode {
+            ParamModeApi::Args => match new_args {
+                Some(a) => Some(&new[a]),
+                None => {
+                    res.push("no longer accepts `*args`".to_owned());
+                    None
+                }
+            },
+            ParamModeApi::Kwargs => match new_kwargs {
+                Some(k) => Some(&new[k]),
+                None => {
+                    res.push("no longer accepts `**kwargs`".to_owned());
+                    None
+                }
+            },
+            _ => {
+                let by_position = match new.get(i) {
+                    Some(q) if p.by_position() && q.by_position() => Some(q),
+                    _ => None,
+                };
+                let by_name = p
+                    .name
+                    .as_ref()
+                    .map(|name| new.iter().find(|q| q.by_name(name)));
+                match by_name {
+                    Some(None) if new_kwargs.is_some() => by_position,
+                    Some(None) => {
+                        // Renamed if its position now has a name that is new.
+                        let renamed = by_position
+                            .and_then(|q| q.name.as_deref())
+                            .filter(|name| !old.iter().any(|o| o.by_name(name)));
+                        match renamed {
+                            Some(name) => {
+                                res.push(format!(
+                                    "parameter {} was renamed to `{}`",
+                                    display, name
+                                ));
+                                by_position
+                            }
+                            None => {
+                                res.push(format!("parameter {} was removed", display));
+                                None
+                            }
+                        }
+                    }
+                    by_name => {
+                        let by_name = by_name.flatten();
+                        match by_position {
+                            Some(q) if by_name.is_some() && q.name != p.name => res
+                                .push(format!("parameter {} is at a different position", display)),
+                            None if p.by_position() && new_args.map_or(true, |a| a > i) => res
+                                .push(format!(
+                                    "parameter {} can no longer be passed by position",
+                                    display
+                                )),
+                            _ => {}
+                        }
+                        by_name.or(by_position)
+                    }
+                }
+            }
+        };
+        if let Some(q) = matched {
+            if !accepts(&q.ty, &p.ty) {
+                let how = if accepts(&p.ty, &q.ty) {
+                    "narrowed"
+                } else {
+                    "changed"
+                };
+                res.push(format!(
+                    "type of parameter {} {} from `{}` to `{}`",
+                    display, how, p.ty, q.ty
+                ));
+            }
+        }
+    }
+
+    for (i, q) in new.iter().enumerate() {
+        if q.optional || matches!(q.mode, ParamModeApi::Args | ParamModeApi::Kwargs) {
+            continue;
+        }
+        let before = q
+            .name
+            .as_ref()
+            .and_then(|name| old.iter().find(|p| p.by_name(name)))
+            .or_else(|| old.get(i).filter(|p| p.by_position() && q.by_position()));
+        match (before, &q.name) {
+            (Some(p), _) if !p.optional => {}
+            (Some(_), Some(name)) => res.push(format!("parameter `{}` is now required", name)),
+            (None, Some(name)) => res.push(format!("new required parameter `{}`", name)),
+            (_, None) => res.push(format!("new required parameter #{}", i)),
+        }
+    }
+    res
+}
+
+/// The ways in which uses of a symbol that were valid against `old` can fail against `new`.
+fn symbol_changes(old: &SymbolApi, new: &SymbolApi) -> Vec<String> {
+    if old.function && !new.function {
+        return vec![format!("is no longer a function, but `{}`", new.ty)];
+    }
+    match (&old.params, &new.params) {
+        (Some(old), Some(new)) => signature_changes(old, new),
+        // Values are read by users, so they break if the type is widened.
+        _ if !old.function && !accepts(&old.ty, &new.ty) => {
+            vec![format!("type changed from `{}` to `{}`", old.ty, new.ty)]
+        }
+        _ => Vec::new(),
+    }
+}
+
+struct ApiComputer<'a> {
+    cell_resolver: &'a CellResolver,
+    io: &'a dyn IoProvider,
+    oracle: Vec<Box<dyn TypingOracle + Send + Sync>>,
+    interfaces: HashMap<ImportPath, Interface>,
+}
+
+impl<'a> ApiComputer<'a> {
+    async fn parse(&self, path: &ImportPath) -> anyhow::Result<AstModule> {
+        let starlark_path = StarlarkPath::LoadFile(path);
+        let dialect = starlark_path.file_type().dialect(false);
+        let proj_path = self.cell_resolver.resolve_path(path.path().as_ref())?;
+        let path_str = proj_path.to_string();
+        let content = self
+            .io
+            .read_file_if_exists(proj_path)
+            .await?
+            .with_context(|| format!("File not found: `{}`", path_str))?;
+        AstModule::parse(&path_str, content, &dialect)
+    }
+
+    /// Typecheck a module, using the interfaces of the files it loads.
+    #[async_recursion]
+    async fn typecheck(&mut self, path: &ImportPath, ast: AstModule) -> anyhow::Result<Interface> {
+        let cell_alias_resolver = self.cell_resolver.get(path.cell())?.cell_alias_resolver();
+        let dir = path
+            .path()
+            .parent()
+            .context("loaded file should have parent directory")?
+            .to_owned();
+        let mut loads = HashMap::new();
+        for load in ast.loads() {
+            let cell_path = parse_import(cell_alias_resolver, &dir, load.module_id)?;
+            if cell_path.path().extension() == Some("bxl") {
+                continue;
+            }
+            let import = ImportPath::new(cell_path, path.build_file_cell())?;
+            let interface = self.interface(&import).await?;
+            loads.insert(load.module_id.to_owned(), interface);
+        }
+        // We only care about the inferred types, any type errors are for the linter to report.
+        let (_errors, _types, interface, _approximations) =
+            ast.typecheck(&self.oracle.as_slice(), &loads);
+        Ok(interface)
+    }
+
+    #[async_recursion]
+    async fn interface(&mut self, path: &ImportPath) -> anyhow::Result<Interface> {
+        if let Some(interface) = self.interfaces.get(path) {
+            return Ok(interface.dupe());
+        }
+        // Cyclic loads are an error when evaluating, here we just give up on their types.
+        self.interfaces.insert(path.clone(), Interface::empty());
+        let ast = self.parse(path).await?;
+        let interface = self
+            .typecheck(path, ast)
+            .await
+            .with_context(|| format!("Computing the interface of `{}`", path))?;
+        self.interfaces.insert(path.clone(), interface.dupe());
+        Ok(interface)
+    }
+
+    async fn api(&mut self, path: &ImportPath) -> anyhow::Result<BTreeMap<String, SymbolApi>> {
+        let ast = self.parse(path).await?;
+        // Typechecking consumes the module, so grab the exported symbols first.
+        let exported = ast
+            .exported_symbols()
+            .into_iter()
+            .map(|x| (x.name.to_owned(), x.kind))
+            .collect::<Vec<_>>();
+        let interface = self.interface(path).await?;
+        Ok(exported
+            .into_iter()
+            .map(|(name, kind)| {
+                let api = SymbolApi::new(&kind, interface.get(&name));
+                (name, api)
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl StarlarkOpaqueSubcommand for StarlarkApiCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        _client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let cell_resolver = ctx.get_cell_resolver().await?;
+                let fs = ctx.file_ops();
+                let io = ctx.global_data().get_io_provider();
+                let global_state = ctx.get_global_interpreter_state().await?;
+                let globals = global_state.globals_for_file_type(StarlarkFileType::Bzl);
+
+                let mut computer = ApiComputer {
+                    cell_resolver: &cell_resolver,
+                    io: &*io,
+                    oracle: vec![
+                        Box::new(OracleDocs::new_object(&globals.documentation())),
+                        Box::new(OracleStandard::new(LibraryExtension::all())),
+                    ],
+                    interfaces: HashMap::new(),
+                };
+                let mut api = ApiMap::new();
+                let files =
+                    starlark_files(&self.paths, server_ctx, &cell_resolver, &fs, &*io).await?;
+                for file in &files {
+                    // Only `.bzl` files can be loaded, so only they have an API.
+                    if let OwnedStarlarkPath::LoadFile(path) = file {
+                        api.insert(path.to_string(), computer.api(path).await?);
+                    }
+                }
+
+                let mut stdout = stdout.as_writer();
+                let baseline_path = match &self.baseline {
+                    None => {
+                        writeln!(stdout, "{}", serde_json::to_string_pretty(&api)?)?;
+                        return Ok(());
+                    }
+                    Some(baseline) => baseline.resolve(server_ctx.working_dir_abs()),
+                };
+                let baseline: ApiMap =
+                    serde_json::from_str(&fs_util::read_to_string(&baseline_path)?)
+                        .with_context(|| format!("Parsing baseline `{}`", baseline_path))?;
+
+                let mut change_count = 0;
+                for (file, old_symbols) in &baseline {
+                    let new_symbols = match api.get(file) {
+                        Some(new_symbols) => new_symbols,
+                        None => {
+                            change_count += 1;
+                            writeln!(stdout, "{}: file was removed", file)?;
+                            continue;
+                        }
+                    };
+                    for (name, old) in old_symbols {
+                        let changes = match new_symbols.get(name) {
+                            Some(new) => symbol_changes(old, new),
+                            None => vec!["was removed".to_owned()],
+                        };
+                        change_count += changes.len();
+                        for change in changes {
+                            writeln!(stdout, "{}: `{}` {}", file, name, change)?;
+                        }
+                    }
+                }
+                if change_count > 0 {
+                    Err(anyhow::anyhow!(
+                        "Found {} incompatible API changes",
+                        change_count
+                    ))
+                } else {
+                    writeln!(
+                        server_ctx.stderr()?,
+                        "Found no incompatible API changes in {} files",
+                        baseline.len()
+                    )?;
+                    Ok(())
+                }
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &StarlarkCommandCommonOptions {
+        &self.common_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn param(mode: ParamModeApi, name: Option<&str>, optional: bool, ty: Ty) -> ParamApi {
+        ParamApi {
+            mode,
+            name: name.map(str::to_owned),
+            optional,
+            ty: TyApi::new(&ty),
+        }
+    }
+
+    fn pos_or_name(name: &str) -> ParamApi {
+        param(ParamModeApi::PosOrName, Some(name), false, Ty::string())
+    }
+
+    fn name_only(name: &str) -> ParamApi {
+        param(ParamModeApi::NameOnly, Some(name), false, Ty::string())
+    }
+
+    fn args() -> ParamApi {
+        param(ParamModeApi::Args, None, true, Ty::Any)
+    }
+
+    fn kwargs() -> ParamApi {
+        param(ParamModeApi::Kwargs, None, true, Ty::Any)
+    }
+
+    fn optional(p: ParamApi) -> ParamApi {
+        ParamApi {
+            optional: true,
+            ..p
+        }
+    }
+
+    fn typed(p: ParamApi, ty: Ty) -> ParamApi {
+        ParamApi {
+            ty: TyApi::new(&ty),
+            ..p
+        }
+    }
+
+    #[test]
+    fn test_unchanged() {
+        let sig = || vec![pos_or_name("a"), optional(name_only("b")), args(), kwargs()];
+        assert_eq!(Vec::<String>::new(), signature_changes(&sig(), &sig()));
+    }
+
+    #[test]
+    fn test_removed() {
+        assert_eq!(
+            vec!["parameter `b` was removed"],
+            signature_changes(&[pos_or_name("a"), name_only("b")], &[pos_or_name("a")])
+        );
+        // Absorbed by `**kwargs`.
+        assert_eq!(
+            Vec::<String>::new(),
+            signature_changes(&[name_only("b")], &[kwargs()])
+        );
+    }
+
+    #[test]
+    fn test_renamed() {
+        assert_eq!(
+            vec!["parameter `a` was renamed to `x`"],
+            signature_changes(&[pos_or_name("a")], &[pos_or_name("x")])
+        );
+        assert_eq!(
+            vec![
+                "parameter `a` is at a different position",
+                "parameter `b` is at a different position"
+            ],
+            signature_changes(
+                &[pos_or_name("a"), pos_or_name("b")],
+                &[pos_or_name("b"), pos_or_name("a")]
+            )
+        );
+    }
+
+    #[test]
+    fn test_required() {
+        assert_eq!(
+            vec!["parameter `a` is now required"],
+            signature_changes(&[optional(pos_or_name("a"))], &[pos_or_name("a")])
+        );
+        assert_eq!(
+            vec!["new required parameter `b`"],
+            signature_changes(&[pos_or_name("a")], &[pos_or_name("a"), name_only("b")])
+        );
+        // Making a parameter optional, or adding an optional one, is compatible.
+        assert_eq!(
+            Vec::<String>::new(),
+            signature_changes(
+                &[pos_or_name("a")],
+                &[optional(pos_or_name("a")), optional(name_only("b"))]
+            )
+        );
+    }
+
+    #[test]
+    fn test_keyword_only() {
+        assert_eq!(
+            vec!["parameter `a` can no longer be passed by position"],
+            signature_changes(&[pos_or_name("a")], &[name_only("a")])
+        );
+        // Positional arguments are still accepted by `*args`.
+        assert_eq!(
+            Vec::<String>::new(),
+            signature_changes(&[pos_or_name("a")], &[args(), optional(name_only("a"))])
+        );
+    }
+
+    #[test]
+    fn test_args_kwargs() {
+        assert_eq!(
+            vec!["no longer accepts `*args`"],
+            signature_changes(&[pos_or_name("a"), args()], &[pos_or_name("a")])
+        );
+        assert_eq!(
+            vec!["no longer accepts `**kwargs`"],
+            signature_changes(&[pos_or_name("a"), kwargs()], &[pos_or_name("a")])
+        );
+        assert_eq!(
+            Vec::<String>::new(),
+            signature_changes(&[pos_or_name("a")], &[pos_or_name("a"), args(), kwargs()])
+        );
+    }
+
+    #[test]
+    fn test_type_changes() {
+        let union = || Ty::union2(Ty::string(), Ty::int());
+        // Widening is compatible.
+        assert_eq!(
+            Vec::<String>::new(),
+            signature_changes(&[pos_or_name("a")], &[typed(pos_or_name("a"), union())])
+        );
+        assert_eq!(
+            Vec::<String>::new(),
+            signature_changes(&[pos_or_name("a")], &[typed(pos_or_name("a"), Ty::Any)])
+        );
+        assert_eq!(
+            vec![format!(
+                "type of parameter `a` narrowed from `{}` to `{}`",
+                union(),
+                Ty::string()
+            )],
+            signature_changes(&[typed(pos_or_name("a"), union())], &[pos_or_name("a")])
+        );
+        assert_eq!(
+            vec![format!(
+                "type of parameter `a` changed from `{}` to `{}`",
+                Ty::string(),
+                Ty::list(Ty::string())
+            )],
+            signature_changes(
+                &[pos_or_name("a")],
+                &[typed(pos_or_name("a"), Ty::list(Ty::string()))]
+            )
+        );
+    }
+
+    #[test]
+    fn test_symbol_type_changes() {
+        let value = |ty: Ty| SymbolApi {
+            function: false,
+            ty: TyApi::new(&ty),
+            params: None,
+        };
+        // Values are read, so narrowing is compatible and widening is not.
+        assert!(
+            symbol_changes(
+                &value(Ty::union2(Ty::string(), Ty::int())),
+                &value(Ty::int())
+            )
+            .is_empty()
+        );
+        assert_eq!(
+            1,
+            symbol_changes(
+                &value(Ty::int()),
+                &value(Ty::union2(Ty::string(), Ty::int()))
+            )
+            .len()
+        );
+    }
+
+    #[test]
+    fn test_alternatives() {
+        let union = TyApi::new(&Ty::union2(Ty::int(), Ty::list(Ty::string())));
+        assert_eq!(
+            vec![Ty::int().to_string(), Ty::list(Ty::string()).to_string()],
+            union.alternatives()
+        );
+        let list = TyApi::new(&Ty::list(Ty::union2(Ty::int(), Ty::string())));
+        assert_eq!(vec![list.display.as_str()], list.alternatives());
+        // Baselines only store the alternatives of unions.
+        assert_eq!(
+            r#"{"ty":"int"}"#,
+            serde_json::to_string(&TyApi::new(&Ty::int())).unwrap()
+        );
+    }
+}
@@ -25,9 +25,11 @@ use buck2_client_ctx::streaming::StreamingCommand;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 
+use crate::api::StarlarkApiCommand;
 use crate::debug::StarlarkDebugAttachCommand;
 use crate::lint::StarlarkLintCommand;
 
+mod api;
 mod debug;
 mod lint;
 pub mod server;
@@ -47,6 +49,7 @@ pub enum StarlarkCommand {
 #[derive(Debug, clap::Subcommand, serde::Serialize, serde::Deserialize)]
 pub enum StarlarkOpaqueCommand {
     Lint(StarlarkLintCommand),
+    Api(StarlarkApiCommand),
 }
 
 #[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize, Default)]
@@ -87,6 +90,7 @@ impl StarlarkOpaqueCommand {
     fn as_subcommand(&self) -> &dyn StarlarkOpaqueSubcommand {
         match self {
             Self::Lint(cmd) => cmd,
+            Self::Api(cmd) => cmd,
         }
     }
 }
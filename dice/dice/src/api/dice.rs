@@ -248,6 +248,13 @@ impl Dice {
         self.implementation.serialize_serde(serializer)
     }
 
+    /// Writes the graph in the format read by
+    /// [`CompactGraph::read`](crate::introspection::compact::CompactGraph::read), which is much
+    /// smaller than the other formats and meant for offline analysis.
+    pub fn serialize_compact(&self, out: impl Write) -> anyhow::Result<()> {
+        self.implementation.serialize_compact(out)
+    }
+
     pub fn detect_cycles(&self) -> &DetectCycles {
         self.implementation.detect_cycles()
     }
@@ -274,6 +281,21 @@ impl DiceDataBuilder {
         self.0.set(val);
     }
 
+    /// Evicts the least recently used values once the graph uses more than `bytes` of memory,
+    /// as measured by `allocative`. Only values that can be recomputed and that no other value
+    /// depends on are evicted, and never those of keys with a
+    /// [`LastNPinned`](crate::api::storage_type::StorageType::LastNPinned)
+    /// storage type. Ignored by the legacy implementation.
+    pub fn memory_budget(&mut self, bytes: usize) {
+        self.0.memory_budget(bytes);
+    }
+
+    /// Records how each key was last invalidated, so that `buck2 debug dice explain` can tell
+    /// why it was recomputed. Keeps an entry for every invalidated key.
+    pub fn record_invalidations(&mut self) {
+        self.0.record_invalidations();
+    }
+
     pub fn build(self, detect_cycles: DetectCycles) -> Arc<Dice> {
         self.0.build(detect_cycles)
     }
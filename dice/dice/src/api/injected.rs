@@ -62,6 +62,7 @@ where
 
     fn storage_type() -> StorageType {
         // if we store more than usize max value, we are in trouble.
-        StorageType::LastN(usize::max_value())
+        // Injected values can't be recomputed, so they are never evicted.
+        StorageType::LastNPinned(usize::max_value())
     }
 }
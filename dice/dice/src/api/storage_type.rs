@@ -18,4 +18,24 @@ use gazebo::variants::UnpackVariants;
 #[derive(UnpackVariants, Debug, Clone, Copy, Dupe, Allocative)]
 pub enum StorageType {
     LastN(usize),
+    /// Like `LastN`, but never evicted to keep DICE within its memory budget. For keys that are
+    /// expensive to recompute, or that can't be recomputed at all.
+    LastNPinned(usize),
+}
+
+impl StorageType {
+    /// The number of versions of the entry to keep.
+    pub(crate) fn num_to_keep(self) -> usize {
+        match self {
+            StorageType::LastN(n) | StorageType::LastNPinned(n) => n,
+        }
+    }
+
+    /// Whether the entry may be evicted when DICE exceeds its memory budget.
+    pub(crate) fn evictable(self) -> bool {
+        match self {
+            StorageType::LastN(_) => true,
+            StorageType::LastNPinned(_) => false,
+        }
+    }
 }
@@ -79,6 +79,10 @@ impl VersionedRevDependencies {
         }
     }
 
+    pub(crate) fn remove_rdep(&mut self, dependent: DiceKey) {
+        self.rdeps.remove(&dependent);
+    }
+
     pub(crate) fn rdeps(&self) -> &HashMap<DiceKey, VersionNumber> {
         &self.rdeps
     }
@@ -0,0 +1,184 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Tracks the memory used by the nodes of the graph and how recently they were used, so that the
+//! least recently used ones can be evicted when the graph exceeds its memory budget.
+
+use allocative::Allocative;
+
+use crate::impls::key::DiceKey;
+use crate::HashMap;
+
+#[derive(Allocative)]
+pub(crate) struct EvictionTracker {
+    /// The number of bytes above which nodes are evicted.
+    budget: usize,
+    /// Incremented on every use of a key, to order keys by recency.
+    clock: u64,
+    usages: HashMap<DiceKey, KeyUsage>,
+    /// Keys that changed since their size was last measured.
+    unmeasured: Vec<DiceKey>,
+    /// Total of the last measured sizes.
+    total_bytes: usize,
+    stats: EvictionStats,
+}
+
+#[derive(Allocative)]
+struct KeyUsage {
+    last_used: u64,
+    /// Size of all the stored versions of the key, as last measured by `allocative`.
+    bytes: usize,
+    /// Whether the key is in `unmeasured`.
+    measured: bool,
+    evictable: bool,
+}
+
+/// Cumulative statistics of the evictions.
+#[derive(Allocative, Default, Clone, Copy, Debug)]
+pub(crate) struct EvictionStats {
+    pub(crate) evicted_keys: u64,
+    pub(crate) evicted_bytes: u64,
+}
+
+impl EvictionTracker {
+    pub(crate) fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            clock: 0,
+            usages: HashMap::default(),
+            unmeasured: Vec::new(),
+            total_bytes: 0,
+            stats: EvictionStats::default(),
+        }
+    }
+
+    /// Marks the key as just used, if it is tracked.
+    pub(crate) fn touch(&mut self, key: DiceKey) {
+        if let Some(usage) = self.usages.get_mut(&key) {
+            self.clock += 1;
+            usage.last_used = self.clock;
+        }
+    }
+
+    /// Records that the stored versions of the key changed, marking it as just used. Its size is
+    /// only measured later, by `record`, so that storing nodes stays cheap.
+    pub(crate) fn changed(&mut self, key: DiceKey, evictable: bool) {
+        self.clock += 1;
+        let usage = self.usages.entry(key).or_insert(KeyUsage {
+            last_used: 0,
+            bytes: 0,
+            measured: true,
+            evictable,
+        });
+        if usage.measured {
+            usage.measured = false;
+            self.unmeasured.push(key);
+        }
+        usage.last_used = self.clock;
+        usage.evictable = evictable;
+    }
+
+    /// The keys that changed since they were last measured.
+    pub(crate) fn take_unmeasured(&mut self) -> Vec<DiceKey> {
+        std::mem::take(&mut self.unmeasured)
+    }
+
+    /// Records the measured size of the stored versions of the key, if it is tracked.
+    pub(crate) fn record(&mut self, key: DiceKey, bytes: usize) {
+        if let Some(usage) = self.usages.get_mut(&key) {
+            self.total_bytes = self.total_bytes - usage.bytes + bytes;
+            usage.bytes = bytes;
+            usage.measured = true;
+        }
+    }
+
+    /// Stops tracking the key, recording it as evicted.
+    pub(crate) fn evicted(&mut self, key: DiceKey) {
+        if let Some(usage) = self.usages.remove(&key) {
+            self.total_bytes -= usage.bytes;
+            self.stats.evicted_keys += 1;
+            self.stats.evicted_bytes += usage.bytes as u64;
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.usages.clear();
+        self.unmeasured.clear();
+        self.total_bytes = 0;
+    }
+
+    pub(crate) fn total_bytes(&self) -> usize {
+        self.total_bytes
+    }
+
+    pub(crate) fn over_budget(&self) -> bool {
+        self.total_bytes > self.budget
+    }
+
+    pub(crate) fn stats(&self) -> EvictionStats {
+        self.stats
+    }
+
+    /// The evictable keys, least recently used first.
+    pub(crate) fn eviction_candidates(&self) -> Vec<DiceKey> {
+        let mut candidates: Vec<_> = self
+            .usages
+            .iter()
+            .filter(|(_, usage)| usage.evictable)
+            .map(|(key, usage)| (usage.last_used, *key))
+            .collect();
+        candidates.sort_unstable_by_key(|(last_used, _)| *last_used);
+        candidates.into_iter().map(|(_, key)| key).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::impls::core::graph::eviction::EvictionTracker;
+    use crate::impls::key::DiceKey;
+
+    #[test]
+    fn candidates_are_evictable_keys_least_recently_used_first() {
+        let mut tracker = EvictionTracker::new(50);
+        tracker.changed(DiceKey { index: 0 }, true);
+        tracker.changed(DiceKey { index: 1 }, true);
+        tracker.changed(DiceKey { index: 2 }, false);
+        tracker.touch(DiceKey { index: 0 });
+        assert_eq!(0, tracker.total_bytes());
+        assert_eq!(
+            vec![
+                DiceKey { index: 0 },
+                DiceKey { index: 1 },
+                DiceKey { index: 2 }
+            ],
+            tracker.take_unmeasured()
+        );
+        tracker.record(DiceKey { index: 0 }, 10);
+        tracker.record(DiceKey { index: 1 }, 20);
+        tracker.record(DiceKey { index: 2 }, 30);
+        assert_eq!(60, tracker.total_bytes());
+        assert!(tracker.over_budget());
+        assert_eq!(
+            vec![DiceKey { index: 1 }, DiceKey { index: 0 }],
+            tracker.eviction_candidates()
+        );
+
+        tracker.changed(DiceKey { index: 1 }, true);
+        tracker.changed(DiceKey { index: 1 }, true);
+        assert_eq!(vec![DiceKey { index: 1 }], tracker.take_unmeasured());
+        tracker.record(DiceKey { index: 1 }, 5);
+        assert_eq!(45, tracker.total_bytes());
+        assert!(!tracker.over_budget());
+
+        tracker.evicted(DiceKey { index: 0 });
+        assert_eq!(35, tracker.total_bytes());
+        assert_eq!(1, tracker.stats().evicted_keys);
+        assert_eq!(10, tracker.stats().evicted_bytes);
+    }
+}
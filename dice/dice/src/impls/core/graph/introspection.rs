@@ -18,33 +18,68 @@ use crate::impls::dice::DiceModern;
 use crate::impls::key::DiceKey;
 use crate::impls::key_index::DiceKeyIndex;
 use crate::introspection::graph::AnyKey;
+use crate::introspection::graph::EngineForIntrospection;
 use crate::introspection::graph::GraphNodeKind;
 use crate::introspection::graph::KeyID;
 use crate::introspection::graph::NodeID;
 use crate::introspection::graph::SerializedGraphNode;
 use crate::introspection::graph::SerializedGraphNodesForKey;
+use crate::introspection::graph::SerializedInvalidation;
 use crate::introspection::graph::VersionNumber;
+use crate::legacy::dice_futures::dice_task::DiceTaskStateForDebugging;
 use crate::HashMap;
 use crate::HashSet;
 
 pub struct VersionedGraphIntrospectable {
     nodes: HashMap<AnyKey, SerializedGraphNodesForKey>,
     edges: HashMap<AnyKey, Vec<AnyKey>>,
+    value_bytes: HashMap<KeyID, usize>,
 }
 
-impl VersionedGraphIntrospectable {
-    fn keys<'a>(&'a self) -> impl Iterator<Item = &AnyKey> + 'a {
-        self.nodes.keys()
+impl EngineForIntrospection for VersionedGraphIntrospectable {
+    fn keys<'a>(&'a self) -> Box<dyn Iterator<Item = AnyKey> + 'a> {
+        Box::new(self.nodes.keys().cloned())
     }
-    fn edges<'a>(&'a self) -> impl Iterator<Item = (&AnyKey, &Vec<AnyKey>)> + 'a {
-        self.edges.iter()
+
+    fn edges<'a>(&'a self) -> Box<dyn Iterator<Item = (AnyKey, Vec<AnyKey>)> + 'a> {
+        Box::new(self.edges.iter().map(|(k, deps)| (k.clone(), deps.clone())))
+    }
+
+    fn keys_currently_running<'a>(
+        &'a self,
+    ) -> Vec<(AnyKey, VersionNumber, DiceTaskStateForDebugging)> {
+        Vec::new()
     }
-    fn nodes<'a>(&'a self) -> impl Iterator<Item = &SerializedGraphNodesForKey> + 'a {
-        self.nodes.values()
+
+    fn versions_currently_running<'a>(&'a self) -> Vec<VersionNumber> {
+        Vec::new()
     }
+
+    fn nodes<'a>(
+        &'a self,
+        keys: &'a mut HashMap<AnyKey, KeyID>,
+    ) -> Box<dyn Iterator<Item = SerializedGraphNodesForKey> + 'a> {
+        // The ids of the nodes and their deps are already the indices of their `DiceKey`s.
+        Box::new(self.nodes.iter().map(|(k, node)| {
+            keys.insert(k.clone(), node.id);
+            node.clone()
+        }))
+    }
+
+    fn value_bytes<'a>(
+        &'a self,
+        _keys: &'a mut HashMap<AnyKey, KeyID>,
+    ) -> Box<dyn Iterator<Item = (KeyID, usize)> + 'a> {
+        Box::new(self.value_bytes.iter().map(|(id, bytes)| (*id, *bytes)))
+    }
+
     fn len_for_introspection(&self) -> usize {
         self.nodes.len()
     }
+
+    fn currently_running_key_count(&self) -> usize {
+        0
+    }
 }
 
 impl VersionedGraph {
@@ -54,6 +89,7 @@ impl VersionedGraph {
     ) -> VersionedGraphIntrospectable {
         let mut edges = HashMap::default();
         let mut nodes = HashMap::default();
+        let mut value_bytes = HashMap::default();
 
         fn visit_node(key: DiceKey, node: &VersionedGraphNode) -> Option<SerializedGraphNode> {
             match node {
@@ -84,10 +120,22 @@ impl VersionedGraph {
                         .iter()
                         .map(|(v, node)| (v.to_introspectable(), visit_node(*k, node)))
                         .collect(),
+                    invalidated: self.invalidations.as_ref().and_then(|i| i.get(k)).map(
+                        |invalidation| SerializedInvalidation {
+                            version: invalidation.version.to_introspectable(),
+                            by: invalidation.by.map(|by| by.introspect()),
+                        },
+                    ),
                 },
             );
 
             if let Some(last) = versioned_nodes.iter().last() {
+                if let Some(node) = last.1.unpack_occupied() {
+                    value_bytes.insert(
+                        KeyID(k.index as usize),
+                        allocative::size_of_unique(node.val()),
+                    );
+                }
                 edges.insert(
                     dyn_k.clone(),
                     last.1
@@ -120,6 +168,10 @@ impl VersionedGraph {
 
             res
         }
-        VersionedGraphIntrospectable { nodes, edges }
+        VersionedGraphIntrospectable {
+            nodes,
+            edges,
+            value_bytes,
+        }
     }
 }
@@ -28,6 +28,8 @@ use triomphe::Arc;
 
 use crate::api::storage_type::StorageType;
 use crate::impls::core::graph::dependencies::VersionedDependencies;
+use crate::impls::core::graph::eviction::EvictionStats;
+use crate::impls::core::graph::eviction::EvictionTracker;
 use crate::impls::core::graph::history::CellHistory;
 use crate::impls::core::graph::history::HistoryState;
 use crate::impls::core::graph::nodes::OccupiedGraphNode;
@@ -56,12 +58,150 @@ pub(crate) struct VersionedGraph {
     /// VacantGraphEntries can only be present when no other entries are present for the key at
     /// any version.
     pub(crate) last_n: HashMap<DiceKey, SortedVectorMap<VersionNumber, VersionedGraphNode>>,
+    /// How each key was last invalidated, only recorded when enabled by `record_invalidations`.
+    pub(crate) invalidations: Option<HashMap<DiceKey, Invalidation>>,
+    /// Memory usage of the nodes, only tracked when there is a memory budget.
+    eviction: Option<EvictionTracker>,
 }
 
 impl VersionedGraph {
     pub(crate) fn new() -> Self {
         Self {
             last_n: Default::default(),
+            invalidations: None,
+            eviction: None,
+        }
+    }
+
+    /// A graph that evicts the least recently used nodes when they use more than `budget` bytes.
+    pub(crate) fn with_memory_budget(budget: usize) -> Self {
+        Self {
+            last_n: Default::default(),
+            invalidations: None,
+            eviction: Some(EvictionTracker::new(budget)),
+        }
+    }
+
+    /// Records how each key was last invalidated, to explain recomputations.
+    pub(crate) fn record_invalidations(mut self) -> Self {
+        self.invalidations = Some(HashMap::default());
+        self
+    }
+
+    /// Records that the key was just used, to evict it after keys that weren't used since.
+    pub(crate) fn mark_used(&mut self, key: DiceKey) {
+        if let Some(eviction) = &mut self.eviction {
+            eviction.touch(key);
+        }
+    }
+
+    /// The approximate memory used by the nodes as of the last eviction check, and the statistics
+    /// of the evictions. `None` if there is no memory budget.
+    pub(crate) fn memory_usage(&self) -> Option<(usize, EvictionStats)> {
+        self.eviction
+            .as_ref()
+            .map(|eviction| (eviction.total_bytes(), eviction.stats()))
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.last_n.clear();
+        if let Some(invalidations) = &mut self.invalidations {
+            invalidations.clear();
+        }
+        if let Some(eviction) = &mut self.eviction {
+            eviction.clear();
+        }
+    }
+
+    /// Records that the stored versions of the key changed. They are measured by
+    /// `evict_over_budget`, once per key however many times it changed since.
+    fn record_changed(&mut self, key: DiceKey, storage_type: StorageType) {
+        if let Some(eviction) = &mut self.eviction {
+            eviction.changed(key, storage_type.evictable());
+        }
+    }
+
+    fn measure_changed(&mut self) {
+        if let Some(eviction) = &mut self.eviction {
+            for key in eviction.take_unmeasured() {
+                if let Some(versioned) = self.last_n.get(&key) {
+                    eviction.record(key, allocative::size_of_unique_allocated_data(versioned));
+                }
+            }
+        }
+    }
+
+    /// Evicts the least recently used evictable nodes until the memory used by the nodes is within
+    /// the budget, after measuring the nodes that changed since the last call. Only nodes that no
+    /// other node depends on are evicted, so that invalidations still reach every node that is
+    /// kept. Evicting a node can make its deps evictable in turn.
+    ///
+    /// This must only be called when no transaction is active, since ongoing computations expect
+    /// the nodes they depend on to still be present.
+    pub(crate) fn evict_over_budget(&mut self) {
+        self.measure_changed();
+        let eviction = match &self.eviction {
+            Some(eviction) if eviction.over_budget() => eviction,
+            _ => return,
+        };
+
+        let mut candidates = eviction.eviction_candidates();
+        loop {
+            let mut kept = Vec::new();
+            let mut evicted_any = false;
+            for key in candidates {
+                if !self.eviction.as_ref().map_or(false, |e| e.over_budget()) {
+                    return;
+                }
+                if self.has_live_rdeps(key) {
+                    kept.push(key);
+                } else {
+                    self.evict(key);
+                    evicted_any = true;
+                }
+            }
+            if !evicted_any {
+                return;
+            }
+            candidates = kept;
+        }
+    }
+
+    fn has_live_rdeps(&self, key: DiceKey) -> bool {
+        self.last_n.get(&key).map_or(false, |versioned| {
+            versioned.values().any(|node| match node {
+                VersionedGraphNode::Occupied(occ) => occ
+                    .metadata()
+                    .rdeps
+                    .rdeps()
+                    .keys()
+                    .any(|rdep| self.last_n.contains_key(rdep)),
+                VersionedGraphNode::Vacant(_) => false,
+            })
+        })
+    }
+
+    fn evict(&mut self, key: DiceKey) {
+        if let Some(versioned) = self.last_n.remove(&key) {
+            for (_, node) in versioned {
+                if let VersionedGraphNode::Occupied(occ) = node {
+                    for dep in occ.metadata().deps.deps().iter() {
+                        if let Some(dep_versioned) = self.last_n.get_mut(dep) {
+                            for dep_node in dep_versioned.values_mut() {
+                                if let VersionedGraphNode::Occupied(dep_occ) = dep_node {
+                                    dep_occ.metadata_mut().rdeps.remove_rdep(key);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(invalidations) = &mut self.invalidations {
+            invalidations.remove(&key);
+        }
+        if let Some(eviction) = &mut self.eviction {
+            eviction.evicted(key);
         }
     }
 
@@ -172,7 +312,7 @@ impl VersionedGraph {
         deps: Arc<Vec<DiceKey>>,
         storage_type: StorageType,
     ) -> (DiceComputedValue, bool) {
-        let StorageType::LastN(num_to_keep) = storage_type;
+        let num_to_keep = storage_type.num_to_keep();
         // persistent keys, if any changes, are committed at the moment when the version
         // is increased. therefore, it must be the case that the current update for the
         // persistent key is the largest/newest version. it's also the case that they are
@@ -217,7 +357,7 @@ impl VersionedGraph {
             }
         }
 
-        if let Some(key_of_e) = nearest {
+        let res = if let Some(key_of_e) = nearest {
             self.update_entry(
                 key_of_e,
                 key,
@@ -239,7 +379,11 @@ impl VersionedGraph {
                 ),
                 true,
             )
-        }
+        };
+
+        self.record_changed(key.k, storage_type);
+
+        res
     }
 
     /// find the nearest entry to the given key, preferring the smaller version number when tied
@@ -387,12 +531,13 @@ impl VersionedGraph {
 
                                     rdeps
                                         .iter()
-                                        .map(|(r, v)| (r.dupe(), *v))
+                                        .map(|(r, v)| (r.dupe(), *v, key.k))
                                         .collect::<Vec<_>>()
                                 };
 
                                 queue
                             } else {
+                                self.record_invalidation(key, None);
                                 return true;
                             }
                         } else {
@@ -407,11 +552,13 @@ impl VersionedGraph {
                         entry.mark_invalidated(key.v);
 
                         versioned_map.insert(key.v, entry);
+                        self.record_invalidation(key, None);
 
                         return true;
                     }
                 }
-                InvalidateKind::Update(value, StorageType::LastN(num_to_keep)) => {
+                InvalidateKind::Update(value, storage_type) => {
+                    let num_to_keep = storage_type.num_to_keep();
                     let rdeps = {
                         let entry = self.last_n.get(&key.k).and_then(|versioned_map| {
                             versioned_map
@@ -427,7 +574,7 @@ impl VersionedGraph {
                                         .rdeps
                                         .rdeps()
                                         .iter()
-                                        .map(|(r, v)| (r.dupe(), *v))
+                                        .map(|(r, v)| (r.dupe(), *v, key.k))
                                         .collect::<Vec<_>>()
                                 } else {
                                     return false;
@@ -470,27 +617,38 @@ impl VersionedGraph {
                         ));
 
                         versioned_map.insert(key.v, entry);
+                        self.record_changed(key.k, storage_type);
+                        self.record_invalidation(key, None);
 
                         return true;
                     };
 
                     fixup.fixup(versioned_map);
+                    self.record_changed(key.k, storage_type);
 
                     rdeps
                 }
             }
         };
 
+        self.record_invalidation(key, None);
         self.invalidate_rdeps(key.v, rdeps);
         true
     }
 
+    fn record_invalidation(&mut self, key: VersionedGraphKey, by: Option<DiceKey>) {
+        if let Some(invalidations) = &mut self.invalidations {
+            invalidations.insert(key.k, Invalidation { version: key.v, by });
+        }
+    }
+
+    /// Invalidates the rdeps in the queue, each along with the dep that invalidated it.
     fn invalidate_rdeps(
         &mut self,
         version: VersionNumber,
-        mut queue: Vec<(DiceKey, VersionNumber)>,
+        mut queue: Vec<(DiceKey, VersionNumber, DiceKey)>,
     ) {
-        while let Some((rdep, relevant_version)) = queue.pop() {
+        while let Some((rdep, relevant_version, by)) = queue.pop() {
             if let Some(node) = self.get_internal(VersionedGraphKey::new(relevant_version, rdep)) {
                 if node.mark_invalidated(version) {
                     // since dirty always occurs in increasing order, it must be the case that if
@@ -506,16 +664,25 @@ impl VersionedGraph {
 
                             rdeps
                                 .iter()
-                                .map(|(r, v)| (r.dupe(), *v))
+                                .map(|(r, v)| (r.dupe(), *v, rdep))
                                 .collect::<Vec<_>>()
                         })
                     }
+                    self.record_invalidation(VersionedGraphKey::new(version, rdep), Some(by));
                 }
             }
         }
     }
 }
 
+/// How a key was last invalidated, kept to explain recomputations.
+#[derive(Allocative, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Invalidation {
+    pub(crate) version: VersionNumber,
+    /// The dep whose invalidation reached the key, or `None` if the key itself was changed.
+    pub(crate) by: Option<DiceKey>,
+}
+
 pub(crate) enum InvalidateKind {
     ForceDirty,
     #[allow(unused)]
@@ -657,6 +824,7 @@ mod tests {
     use crate::api::key::Key;
     use crate::impls::core::graph::storage::testing::VersionedCacheResultAssertsExt;
     use crate::impls::core::graph::storage::InvalidateKind;
+    use crate::impls::core::graph::storage::Invalidation;
     use crate::impls::core::graph::storage::StorageType;
     use crate::impls::core::graph::storage::VersionedGraph;
     use crate::impls::core::graph::types::VersionedGraphKey;
@@ -1274,6 +1442,59 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn dirty_records_invalidations() -> anyhow::Result<()> {
+        let mut cache = VersionedGraph::new().record_invalidations();
+        let res = DiceValidValue::testing_new(DiceKeyValue::<K>::new(100));
+
+        for (k, deps) in [(0, vec![]), (1, vec![0]), (2, vec![1]), (3, vec![])] {
+            cache.update(
+                VersionedGraphKey::new(VersionNumber::new(0), DiceKey { index: k }),
+                res.dupe(),
+                Arc::new(deps.into_iter().map(|d| DiceKey { index: d }).collect()),
+                StorageType::LastN(1),
+            );
+        }
+
+        assert!(cache.invalidate(
+            VersionedGraphKey::new(VersionNumber::new(1), DiceKey { index: 0 }),
+            InvalidateKind::ForceDirty
+        ));
+
+        let invalidation = |k| {
+            cache
+                .invalidations
+                .as_ref()
+                .unwrap()
+                .get(&DiceKey { index: k })
+                .copied()
+        };
+        assert_eq!(
+            Some(Invalidation {
+                version: VersionNumber::new(1),
+                by: None
+            }),
+            invalidation(0)
+        );
+        assert_eq!(
+            Some(Invalidation {
+                version: VersionNumber::new(1),
+                by: Some(DiceKey { index: 0 })
+            }),
+            invalidation(1)
+        );
+        assert_eq!(
+            Some(Invalidation {
+                version: VersionNumber::new(1),
+                by: Some(DiceKey { index: 1 })
+            }),
+            invalidation(2)
+        );
+        assert_eq!(None, invalidation(3));
+
+        Ok(())
+    }
+
     #[test]
     fn dirty_same_nodes() -> anyhow::Result<()> {
         let mut cache = VersionedGraph::new();
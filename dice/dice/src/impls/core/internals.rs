@@ -33,10 +33,10 @@ pub(super) struct CoreState {
 }
 
 impl CoreState {
-    pub(super) fn new() -> Self {
+    pub(super) fn new(graph: VersionedGraph) -> Self {
         Self {
             version_tracker: VersionTracker::new(),
-            graph: VersionedGraph::new(),
+            graph,
         }
     }
 
@@ -75,10 +75,17 @@ impl CoreState {
     }
 
     pub(super) fn drop_ctx_at_version(&mut self, v: VersionNumber) {
-        self.version_tracker.drop_at_version(v)
+        self.version_tracker.drop_at_version(v);
+
+        // Evicting while computations are ongoing could remove the deps they are about to
+        // record, so only evict once the last transaction is gone.
+        if self.version_tracker.currently_active().next().is_none() {
+            self.graph.evict_over_budget();
+        }
     }
 
     pub(super) fn lookup_key(&mut self, key: VersionedGraphKey) -> VersionedGraphResult {
+        self.graph.mark_used(key.k);
         self.graph.get(key)
     }
 
@@ -94,7 +101,7 @@ impl CoreState {
 
     pub(super) fn unstable_drop_everything(&mut self) {
         self.version_tracker.write().commit();
-        self.graph.last_n.clear();
+        self.graph.clear();
     }
 
     pub(super) fn metrics(&self) -> Metrics {
@@ -107,10 +114,15 @@ impl CoreState {
             currently_running_key_count += active.1.active_tasks_count();
         }
 
+        let (memory_bytes, eviction_stats) = self.graph.memory_usage().unwrap_or_default();
+
         Metrics {
             key_count: self.graph.last_n.len(),
             currently_active_key_count: currently_running_key_count,
             active_transaction_count: active_transaction_count as u32, // probably won't support more than u32 transactions
+            memory_bytes,
+            evicted_key_count: eviction_stats.evicted_keys,
+            evicted_bytes: eviction_stats.evicted_bytes,
         }
     }
 
@@ -125,6 +137,7 @@ impl CoreState {
 mod tests {
     use triomphe::Arc;
 
+    use crate::impls::core::graph::storage::VersionedGraph;
     use crate::impls::core::internals::CoreState;
     use crate::impls::key::DiceKey;
     use crate::impls::transaction::ChangeType;
@@ -132,7 +145,7 @@ mod tests {
 
     #[test]
     fn update_state_gets_next_version() {
-        let mut core = CoreState::new();
+        let mut core = CoreState::new(VersionedGraph::new());
 
         assert_eq!(
             core.update_state([(DiceKey { index: 0 }, ChangeType::Invalidate)]),
@@ -147,7 +160,7 @@ mod tests {
 
     #[test]
     fn state_ctx_at_version() {
-        let mut core = CoreState::new();
+        let mut core = CoreState::new(VersionedGraph::new());
         let v = VersionNumber::new(0);
 
         let ctx = core.ctx_at_version(v);
@@ -9,6 +9,7 @@
 
 use gazebo::variants::VariantName;
 
+use crate::impls::core::graph::storage::VersionedGraph;
 use crate::impls::core::internals::CoreState;
 use crate::impls::core::state::CoreStateHandle;
 use crate::impls::core::state::StateRequest;
@@ -20,9 +21,9 @@ pub(super) struct StateProcessor {
 }
 
 impl StateProcessor {
-    pub(super) fn spawn() -> CoreStateHandle {
+    pub(super) fn spawn(graph: VersionedGraph) -> CoreStateHandle {
         let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
-        let state = CoreState::new();
+        let state = CoreState::new(graph);
 
         std::thread::spawn(move || StateProcessor { state, rx }.event_loop());
         CoreStateHandle::new(tx)
@@ -15,6 +15,7 @@ use tokio::sync::oneshot::Sender;
 use triomphe::Arc;
 
 use crate::api::storage_type::StorageType;
+use crate::impls::core::graph::storage::VersionedGraph;
 use crate::impls::core::graph::types::VersionedGraphKey;
 use crate::impls::core::graph::types::VersionedGraphResult;
 use crate::impls::core::processor::StateProcessor;
@@ -100,7 +101,7 @@ impl CoreStateHandle {
 
 impl Dupe for CoreStateHandle {}
 
-/// Start processing state
-pub(crate) fn init_state() -> CoreStateHandle {
-    StateProcessor::spawn()
+/// Start processing the state of `graph`
+pub(crate) fn init_state(graph: VersionedGraph) -> CoreStateHandle {
+    StateProcessor::spawn(graph)
 }
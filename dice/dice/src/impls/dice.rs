@@ -17,6 +17,7 @@ use dupe::Dupe;
 use crate::api::cycles::DetectCycles;
 use crate::api::data::DiceData;
 use crate::api::user_data::UserComputationData;
+use crate::impls::core::graph::storage::VersionedGraph;
 use crate::impls::core::state::init_state;
 use crate::impls::core::state::CoreStateHandle;
 use crate::impls::core::state::StateRequest;
@@ -38,25 +39,54 @@ impl Debug for DiceModern {
     }
 }
 
-pub(crate) struct DiceModernDataBuilder(DiceData);
+pub(crate) struct DiceModernDataBuilder {
+    data: DiceData,
+    memory_budget: Option<usize>,
+    record_invalidations: bool,
+}
 
 impl DiceModernDataBuilder {
     pub(crate) fn new() -> Self {
-        Self(DiceData::new())
+        Self {
+            data: DiceData::new(),
+            memory_budget: None,
+            record_invalidations: false,
+        }
     }
 
     pub fn set<K: Send + Sync + 'static>(&mut self, val: K) {
-        self.0.set(val);
+        self.data.set(val);
+    }
+
+    pub fn memory_budget(&mut self, bytes: usize) {
+        self.memory_budget = Some(bytes);
+    }
+
+    pub fn record_invalidations(&mut self) {
+        self.record_invalidations = true;
     }
 
     pub fn build(self, _detect_cycles: DetectCycles) -> Arc<DiceModern> {
-        DiceModern::new(self.0)
+        let graph = match self.memory_budget {
+            Some(budget) => VersionedGraph::with_memory_budget(budget),
+            None => VersionedGraph::new(),
+        };
+        let graph = if self.record_invalidations {
+            graph.record_invalidations()
+        } else {
+            graph
+        };
+        DiceModern::new_with_graph(self.data, graph)
     }
 }
 
 impl DiceModern {
     pub(crate) fn new(global_data: DiceData) -> Arc<Self> {
-        let state_handle = init_state();
+        Self::new_with_graph(global_data, VersionedGraph::new())
+    }
+
+    fn new_with_graph(global_data: DiceData, graph: VersionedGraph) -> Arc<Self> {
+        let state_handle = init_state(graph);
 
         Arc::new(DiceModern {
             key_index: Default::default(),
@@ -0,0 +1,173 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use derive_more::Display;
+use more_futures::cancellation::CancellationContext;
+
+use crate::api::computations::DiceComputations;
+use crate::api::cycles::DetectCycles;
+use crate::api::key::Key;
+use crate::api::storage_type::StorageType;
+use crate::impls::dice::DiceModern;
+use crate::metrics::Metrics;
+
+/// Counts the computations of all keys.
+struct Computations(AtomicUsize);
+
+fn count_computation(ctx: &DiceComputations) {
+    ctx.global_data()
+        .get::<Arc<Computations>>()
+        .unwrap()
+        .0
+        .fetch_add(1, Ordering::SeqCst);
+}
+
+#[derive(Allocative, Clone, Debug, Display, PartialEq, Eq, Hash)]
+#[display(fmt = "{:?}", self)]
+struct Leaf(u32);
+
+#[async_trait]
+impl Key for Leaf {
+    type Value = u32;
+
+    async fn compute(
+        &self,
+        ctx: &DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        count_computation(ctx);
+        self.0
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+#[derive(Allocative, Clone, Debug, Display, PartialEq, Eq, Hash)]
+#[display(fmt = "{:?}", self)]
+struct Root(u32);
+
+#[async_trait]
+impl Key for Root {
+    type Value = u32;
+
+    async fn compute(
+        &self,
+        ctx: &DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        count_computation(ctx);
+        ctx.compute(&Leaf(self.0)).await.unwrap() + 1
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+#[derive(Allocative, Clone, Debug, Display, PartialEq, Eq, Hash)]
+#[display(fmt = "{:?}", self)]
+struct Expensive(u32);
+
+#[async_trait]
+impl Key for Expensive {
+    type Value = u32;
+
+    async fn compute(
+        &self,
+        ctx: &DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        count_computation(ctx);
+        self.0
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+
+    fn storage_type() -> StorageType {
+        StorageType::LastNPinned(1)
+    }
+}
+
+fn new_dice(computations: &Arc<Computations>) -> Arc<DiceModern> {
+    let mut builder = DiceModern::builder();
+    builder.set(computations.clone());
+    // Any graph is over this budget.
+    builder.memory_budget(0);
+    builder.build(DetectCycles::Disabled)
+}
+
+/// The metrics once all transactions are dropped, which may happen after the last user reference
+/// is dropped since spawned computations also hold them.
+async fn idle_metrics(dice: &DiceModern) -> Metrics {
+    loop {
+        let metrics = dice.metrics();
+        if metrics.active_transaction_count == 0 {
+            return metrics;
+        }
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unreferenced_nodes_are_evicted_when_idle() -> anyhow::Result<()> {
+    let computations = Arc::new(Computations(AtomicUsize::new(0)));
+    let dice = new_dice(&computations);
+
+    let ctx = dice.updater().commit().await;
+    assert_eq!(2, ctx.compute(&Root(1)).await?);
+    assert_eq!(5, ctx.compute(&Expensive(5)).await?);
+    assert_eq!(3, computations.0.load(Ordering::SeqCst));
+    assert_eq!(3, dice.metrics().key_count);
+    // Sizes are only measured once idle.
+    assert_eq!(0, dice.metrics().memory_bytes);
+    drop(ctx);
+
+    // `Leaf` is only evicted once `Root`, which depends on it, is.
+    let metrics = idle_metrics(&dice).await;
+    assert_eq!(1, metrics.key_count);
+    assert_eq!(2, metrics.evicted_key_count);
+    assert!(metrics.evicted_bytes > 0);
+    assert!(metrics.memory_bytes > 0);
+
+    let ctx = dice.updater().commit().await;
+    assert_eq!(2, ctx.compute(&Root(1)).await?);
+    assert_eq!(5, ctx.compute(&Expensive(5)).await?);
+    assert_eq!(5, computations.0.load(Ordering::SeqCst));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn nothing_is_evicted_while_a_transaction_is_active() -> anyhow::Result<()> {
+    let computations = Arc::new(Computations(AtomicUsize::new(0)));
+    let dice = new_dice(&computations);
+
+    let ctx = dice.updater().commit().await;
+    assert_eq!(2, ctx.compute(&Root(1)).await?);
+
+    let other = dice.updater().commit().await;
+    drop(other);
+
+    let metrics = dice.metrics();
+    assert_eq!(2, metrics.key_count);
+    assert_eq!(0, metrics.evicted_key_count);
+
+    Ok(())
+}
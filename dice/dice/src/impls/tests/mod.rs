@@ -8,6 +8,7 @@
  */
 
 mod demo;
+mod eviction;
 mod general;
 mod keys;
 mod projections;
@@ -0,0 +1,337 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A compact dump of the graph, small enough to be loaded and queried offline for large graphs.
+//!
+//! Compared to the dense serialized graph, only the latest deps of each key are kept, rdeps are
+//! left out since they can be derived from deps, the history of each key is summarized to the
+//! versions it was last verified and dirtied at, and type names are interned.
+
+use std::io::Read;
+use std::io::Write;
+
+use bincode::Options;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::introspection::graph::GraphIntrospectable;
+use crate::introspection::graph::HistoryState;
+use crate::introspection::graph::KeyID;
+use crate::introspection::graph::SerializedGraphNodesForKey;
+use crate::HashMap;
+
+/// Start of every compact dump, followed by [`FORMAT_VERSION`].
+const MAGIC: &[u8; 8] = b"DICECMPT";
+
+/// Bumped whenever the layout of [`CompactGraph`] changes.
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(thiserror::Error, Debug)]
+enum CompactGraphError {
+    #[error("Not a compact DICE dump, or written by an incompatible version of buck2")]
+    IncompatibleFormat,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CompactGraph {
+    pub type_names: Vec<String>,
+    pub keys: Vec<CompactKey>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CompactKey {
+    /// Index of the type name of the key in [`CompactGraph::type_names`].
+    pub type_name: u32,
+    pub key: String,
+    /// Approximate memory used by the latest value of the key, as measured by `allocative`.
+    pub value_bytes: u64,
+    /// Indexes in [`CompactGraph::keys`] of the deps of the latest value of the key.
+    pub deps: Vec<u32>,
+    pub last_verified: Option<usize>,
+    pub last_dirtied: Option<usize>,
+}
+
+impl CompactKey {
+    /// Whether the key was dirtied after it was last computed or verified, i.e. nothing requested
+    /// it since it was invalidated.
+    pub fn is_dirty(&self) -> bool {
+        match (self.last_dirtied, self.last_verified) {
+            (Some(dirtied), Some(verified)) => dirtied > verified,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+/// The keys of a type, and the memory used by their values.
+pub struct TypeStats<'a> {
+    pub type_name: &'a str,
+    pub count: usize,
+    pub value_bytes: u64,
+}
+
+impl CompactGraph {
+    pub(crate) fn from_introspectable(graph: &GraphIntrospectable) -> Self {
+        let mut reg = HashMap::default();
+        let mut nodes = Vec::new();
+        let mut value_bytes = HashMap::default();
+        for engine in graph.introspectables() {
+            nodes.extend(engine.nodes(&mut reg));
+            value_bytes.extend(engine.value_bytes(&mut reg));
+        }
+        Self::new(&nodes, &value_bytes)
+    }
+
+    pub(crate) fn new(
+        nodes: &[SerializedGraphNodesForKey],
+        value_bytes: &HashMap<KeyID, usize>,
+    ) -> Self {
+        let mut nodes: Vec<&SerializedGraphNodesForKey> = nodes.iter().collect();
+        nodes.sort_by_key(|n| n.id.0);
+        let index: HashMap<KeyID, u32> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.id, i as u32))
+            .collect();
+
+        let mut type_names = Vec::new();
+        let mut type_index: HashMap<&str, u32> = HashMap::default();
+        let keys = nodes
+            .iter()
+            .map(|n| {
+                let type_name = *type_index.entry(&n.type_name).or_insert_with(|| {
+                    type_names.push(n.type_name.clone());
+                    (type_names.len() - 1) as u32
+                });
+
+                let mut deps: Vec<u32> = n
+                    .nodes
+                    .values()
+                    .rev()
+                    .flatten()
+                    .next()
+                    .and_then(|latest| latest.deps.as_ref())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|d| index.get(d).copied())
+                    .collect();
+                deps.sort_unstable();
+
+                let mut last_verified = None;
+                let mut last_dirtied = None;
+                for node in n.nodes.values().flatten() {
+                    for (v, state) in &node.history.history {
+                        let last = match state {
+                            HistoryState::Verified => &mut last_verified,
+                            HistoryState::Dirty | HistoryState::ForceDirty => &mut last_dirtied,
+                        };
+                        *last = std::cmp::max(*last, Some(v.0));
+                    }
+                }
+
+                CompactKey {
+                    type_name,
+                    key: n.key.clone(),
+                    value_bytes: value_bytes.get(&n.id).copied().unwrap_or_default() as u64,
+                    deps,
+                    last_verified,
+                    last_dirtied,
+                }
+            })
+            .collect();
+
+        Self { type_names, keys }
+    }
+
+    fn bincode_options() -> impl Options {
+        // Variable length integers, since most indexes and sizes are small.
+        bincode::DefaultOptions::new()
+    }
+
+    pub fn write(&self, mut out: impl Write) -> anyhow::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        Self::bincode_options().serialize_into(&mut out, self)?;
+        Ok(())
+    }
+
+    pub fn read(mut input: impl Read) -> anyhow::Result<Self> {
+        let mut header = [0; 12];
+        input
+            .read_exact(&mut header)
+            .map_err(|_| CompactGraphError::IncompatibleFormat)?;
+        if header[..8] != MAGIC[..] || header[8..] != FORMAT_VERSION.to_le_bytes() {
+            return Err(CompactGraphError::IncompatibleFormat.into());
+        }
+        Ok(Self::bincode_options().deserialize_from(input)?)
+    }
+
+    pub fn type_name(&self, key: &CompactKey) -> &str {
+        &self.type_names[key.type_name as usize]
+    }
+
+    /// Counts and memory of the keys of each type, in no particular order.
+    pub fn type_stats(&self) -> Vec<TypeStats> {
+        let mut stats: Vec<TypeStats> = self
+            .type_names
+            .iter()
+            .map(|type_name| TypeStats {
+                type_name,
+                count: 0,
+                value_bytes: 0,
+            })
+            .collect();
+        for key in &self.keys {
+            let stats = &mut stats[key.type_name as usize];
+            stats.count += 1;
+            stats.value_bytes += key.value_bytes;
+        }
+        stats
+    }
+
+    /// Indexes of the keys whose display or type name contains `pattern`.
+    pub fn matching<'a>(&'a self, pattern: &'a str) -> impl Iterator<Item = u32> + 'a {
+        self.keys
+            .iter()
+            .enumerate()
+            .filter(move |(_, k)| k.key.contains(pattern) || self.type_name(k).contains(pattern))
+            .map(|(i, _)| i as u32)
+    }
+
+    /// The reverse of the deps of each key, indexed like [`CompactGraph::keys`].
+    pub fn rdeps(&self) -> Vec<Vec<u32>> {
+        let mut rdeps = vec![Vec::new(); self.keys.len()];
+        for (i, key) in self.keys.iter().enumerate() {
+            for dep in &key.deps {
+                rdeps[*dep as usize].push(i as u32);
+            }
+        }
+        rdeps
+    }
+
+    /// Indexes of the keys that transitively depend on any of `roots`, excluding them.
+    pub fn transitive_rdeps(&self, roots: &[u32]) -> Vec<u32> {
+        let rdeps = self.rdeps();
+        let mut visited = vec![false; self.keys.len()];
+        for root in roots {
+            visited[*root as usize] = true;
+        }
+        let mut queue = roots.to_vec();
+        let mut res = Vec::new();
+        while let Some(key) = queue.pop() {
+            for rdep in &rdeps[key as usize] {
+                if !visited[*rdep as usize] {
+                    visited[*rdep as usize] = true;
+                    res.push(*rdep);
+                    queue.push(*rdep);
+                }
+            }
+        }
+        res.sort_unstable();
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::introspection::compact::CompactGraph;
+    use crate::introspection::graph::CellHistory;
+    use crate::introspection::graph::GraphNodeKind;
+    use crate::introspection::graph::KeyID;
+    use crate::introspection::graph::NodeID;
+    use crate::introspection::graph::SerializedGraphNode;
+    use crate::introspection::graph::SerializedGraphNodesForKey;
+    use crate::introspection::graph::VersionNumber;
+    use crate::HashMap;
+
+    fn key(
+        id: usize,
+        name: &str,
+        type_name: &str,
+        verified: &[usize],
+        dirtied: &[usize],
+        deps: &[usize],
+    ) -> SerializedGraphNodesForKey {
+        SerializedGraphNodesForKey {
+            id: KeyID(id),
+            key: name.to_owned(),
+            type_name: type_name.to_owned(),
+            nodes: BTreeMap::from([(
+                VersionNumber(0),
+                Some(SerializedGraphNode {
+                    node_id: NodeID(id),
+                    kind: GraphNodeKind::Occupied,
+                    history: CellHistory::new(
+                        verified.iter().map(|v| VersionNumber(*v)).collect(),
+                        dirtied.iter().map(|v| (VersionNumber(*v), false)).collect(),
+                    ),
+                    deps: Some(deps.iter().map(|d| KeyID(*d)).collect()),
+                    rdeps: None,
+                }),
+            )]),
+            invalidated: None,
+        }
+    }
+
+    fn graph() -> CompactGraph {
+        let nodes = vec![
+            key(0, "file", "File", &[0], &[], &[]),
+            key(1, "parse", "Parse", &[0, 2], &[1], &[0]),
+            key(2, "analysis", "Analysis", &[0], &[1], &[1]),
+            key(3, "other_file", "File", &[0], &[], &[]),
+        ];
+        let value_bytes = HashMap::from_iter([(KeyID(0), 10), (KeyID(1), 100), (KeyID(3), 30)]);
+        CompactGraph::new(&nodes, &value_bytes)
+    }
+
+    #[test]
+    fn test_roundtrip() -> anyhow::Result<()> {
+        let mut out = Vec::new();
+        graph().write(&mut out)?;
+        let read = CompactGraph::read(out.as_slice())?;
+        assert_eq!(4, read.keys.len());
+        assert_eq!(vec![1], read.keys[2].deps);
+
+        assert!(CompactGraph::read(&b"not a dump"[..]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_type_stats() {
+        let graph = graph();
+        let stats: Vec<_> = graph
+            .type_stats()
+            .into_iter()
+            .map(|s| (s.type_name, s.count, s.value_bytes))
+            .collect();
+        assert_eq!(
+            vec![("File", 2, 40), ("Parse", 1, 100), ("Analysis", 1, 0)],
+            stats
+        );
+    }
+
+    #[test]
+    fn test_rdeps_and_dirty() {
+        let graph = graph();
+        let file: Vec<u32> = graph.matching("file").collect();
+        assert_eq!(vec![0, 3], file);
+        assert_eq!(vec![1, 2], graph.transitive_rdeps(&[0]));
+        assert_eq!(vec![vec![1], vec![2], vec![], vec![]], graph.rdeps());
+
+        let dirty: Vec<&str> = graph
+            .keys
+            .iter()
+            .filter(|k| k.is_dirty())
+            .map(|k| k.key.as_str())
+            .collect();
+        assert_eq!(vec!["analysis"], dirty);
+    }
+}
@@ -0,0 +1,283 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Explains why a key was invalidated, from the serialized graph.
+//!
+//! When a key is invalidated, the graph records the version and the dep whose invalidation reached
+//! it, or no dep if the key itself was reported via `DiceTransactionUpdater::changed`. Invalidations
+//! reach a key through any of its deps invalidated at the same version, so the explanation is the
+//! shortest path from a changed key through such deps, found by a breadth-first search.
+//!
+//! Only the latest invalidation of each key is kept, so deps invalidated again since are not
+//! followed, and the path may not reach the changed key.
+
+use std::collections::VecDeque;
+
+use crate::introspection::graph::KeyID;
+use crate::introspection::graph::SerializedGraphNodesForKey;
+use crate::introspection::graph::VersionNumber;
+use crate::HashMap;
+
+/// Index of a serialized graph to explain invalidations.
+pub struct InvalidationGraph<'a> {
+    keys: HashMap<KeyID, &'a SerializedGraphNodesForKey>,
+}
+
+/// Why a key was last invalidated.
+pub struct InvalidationExplanation<'a> {
+    pub key: &'a SerializedGraphNodesForKey,
+    /// Version at which the key was last invalidated. `None` if it never was, e.g. because it was
+    /// only computed since it was first requested.
+    pub version: Option<VersionNumber>,
+    /// Shortest path along which the invalidation reached `key`, from the changed key to `key`.
+    /// Only `key` if no such path was found, and empty if the key was never invalidated.
+    pub path: Vec<&'a SerializedGraphNodesForKey>,
+    /// Whether `path` starts at the changed key. It does not if the deps the invalidation went
+    /// through were invalidated again since, which replaced the records of `version`.
+    pub complete: bool,
+}
+
+impl<'a> InvalidationGraph<'a> {
+    pub fn new(nodes: &'a [SerializedGraphNodesForKey]) -> Self {
+        Self {
+            keys: nodes.iter().map(|n| (n.id, n)).collect(),
+        }
+    }
+
+    /// Keys whose display or type name contains `pattern`.
+    pub fn matching<'p>(
+        &'p self,
+        pattern: &'p str,
+    ) -> impl Iterator<Item = &'a SerializedGraphNodesForKey> + 'p {
+        self.keys
+            .values()
+            .copied()
+            .filter(move |k| k.key.contains(pattern) || k.type_name.contains(pattern))
+    }
+
+    /// Explains the latest invalidation of `key`.
+    pub fn explain(&self, key: &'a SerializedGraphNodesForKey) -> InvalidationExplanation<'a> {
+        let version = match &key.invalidated {
+            Some(invalidated) => invalidated.version,
+            None => {
+                return InvalidationExplanation {
+                    key,
+                    version: None,
+                    path: Vec::new(),
+                    complete: false,
+                };
+            }
+        };
+
+        // Breadth-first search towards a changed key, recording how each key was reached.
+        let mut reached_from: HashMap<KeyID, KeyID> = HashMap::default();
+        let mut queue = VecDeque::from([key]);
+        let mut changed = None;
+        while let Some(current) = queue.pop_front() {
+            if current
+                .invalidated
+                .as_ref()
+                .map_or(false, |i| i.by.is_none())
+            {
+                changed = Some(current);
+                break;
+            }
+            for dep in self.invalidated_deps(current, version) {
+                if dep.id != key.id && !reached_from.contains_key(&dep.id) {
+                    reached_from.insert(dep.id, current.id);
+                    queue.push_back(dep);
+                }
+            }
+        }
+
+        let (path, complete) = match changed {
+            Some(changed) => {
+                let mut path = vec![changed];
+                let mut current = changed.id;
+                while let Some(next) = reached_from.get(&current) {
+                    path.push(self.keys[next]);
+                    current = *next;
+                }
+                (path, true)
+            }
+            None => (vec![key], false),
+        };
+
+        InvalidationExplanation {
+            key,
+            version: Some(version),
+            path,
+            complete,
+        }
+    }
+    /// Deps of `key` invalidated at `version`: the recorded one first, then those of any of its
+    /// nodes, in order of id.
+    fn invalidated_deps(
+        &self,
+        key: &'a SerializedGraphNodesForKey,
+        version: VersionNumber,
+    ) -> impl Iterator<Item = &'a SerializedGraphNodesForKey> + '_ {
+        let mut deps: Vec<KeyID> = key
+            .nodes
+            .values()
+            .flatten()
+            .filter_map(|node| node.deps.as_ref())
+            .flatten()
+            .copied()
+            .collect();
+        deps.sort_unstable_by_key(|d| d.0);
+        key.invalidated
+            .as_ref()
+            .and_then(|i| i.by)
+            .into_iter()
+            .chain(deps)
+            .filter_map(|d| self.keys.get(&d).copied())
+            .filter(move |d| d.invalidated.as_ref().map(|i| i.version) == Some(version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::introspection::explain::InvalidationGraph;
+    use crate::introspection::graph::CellHistory;
+    use crate::introspection::graph::GraphNodeKind;
+    use crate::introspection::graph::KeyID;
+    use crate::introspection::graph::NodeID;
+    use crate::introspection::graph::SerializedGraphNode;
+    use crate::introspection::graph::SerializedGraphNodesForKey;
+    use crate::introspection::graph::SerializedInvalidation;
+    use crate::introspection::graph::VersionNumber;
+
+    fn key(
+        id: usize,
+        name: &str,
+        invalidated: Option<(usize, Option<usize>)>,
+    ) -> SerializedGraphNodesForKey {
+        key_with_deps(id, name, invalidated, &[])
+    }
+
+    fn key_with_deps(
+        id: usize,
+        name: &str,
+        invalidated: Option<(usize, Option<usize>)>,
+        deps: &[usize],
+    ) -> SerializedGraphNodesForKey {
+        let node = SerializedGraphNode {
+            node_id: NodeID(id),
+            kind: GraphNodeKind::Occupied,
+            history: CellHistory {
+                history: BTreeMap::new(),
+            },
+            deps: Some(deps.iter().copied().map(KeyID).collect()),
+            rdeps: None,
+        };
+        SerializedGraphNodesForKey {
+            id: KeyID(id),
+            key: name.to_owned(),
+            type_name: "K".to_owned(),
+            nodes: BTreeMap::from([(VersionNumber(0), Some(node))]),
+            invalidated: invalidated.map(|(v, by)| SerializedInvalidation {
+                version: VersionNumber(v),
+                by: by.map(KeyID),
+            }),
+        }
+    }
+
+    fn names(path: &[&SerializedGraphNodesForKey]) -> Vec<String> {
+        path.iter().map(|k| k.key.clone()).collect()
+    }
+
+    #[test]
+    fn test_path_to_changed_key() {
+        // `file` changed at v1, and the invalidation reached `target` via `parse` and `analysis`.
+        // `config` was never invalidated.
+        let nodes = vec![
+            key(0, "file", Some((1, None))),
+            key(1, "parse", Some((1, Some(0)))),
+            key(2, "analysis", Some((1, Some(1)))),
+            key(3, "config", None),
+            key(4, "target", Some((1, Some(2)))),
+        ];
+        let graph = InvalidationGraph::new(&nodes);
+
+        let target = graph.matching("target").next().unwrap();
+        let explanation = graph.explain(target);
+        assert_eq!(Some(VersionNumber(1)), explanation.version);
+        assert!(explanation.complete);
+        assert_eq!(
+            vec!["file", "parse", "analysis", "target"],
+            names(&explanation.path)
+        );
+
+        let changed = graph.matching("file").next().unwrap();
+        assert_eq!(vec!["file"], names(&graph.explain(changed).path));
+
+        let config = graph.matching("config").next().unwrap();
+        let explanation = graph.explain(config);
+        assert_eq!(None, explanation.version);
+        assert!(explanation.path.is_empty());
+    }
+
+    #[test]
+    fn test_path_stops_at_key_invalidated_since() {
+        // `parse` was invalidated again by `other_file` at v2, after it invalidated `target`.
+        let nodes = vec![
+            key(0, "file", Some((1, None))),
+            key(1, "parse", Some((2, Some(3)))),
+            key(2, "target", Some((1, Some(1)))),
+            key(3, "other_file", Some((2, None))),
+        ];
+        let graph = InvalidationGraph::new(&nodes);
+
+        let target = graph.matching("target").next().unwrap();
+        let explanation = graph.explain(target);
+        assert_eq!(Some(VersionNumber(1)), explanation.version);
+        assert!(!explanation.complete);
+        assert_eq!(vec!["target"], names(&explanation.path));
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        // `file` changed at v1. The recorded invalidation of `target` came via `analysis`, but it
+        // depends on `parse` directly too.
+        let nodes = vec![
+            key(0, "file", Some((1, None))),
+            key_with_deps(1, "parse", Some((1, Some(0))), &[0]),
+            key_with_deps(2, "analysis", Some((1, Some(3))), &[1, 3]),
+            key_with_deps(3, "macro", Some((1, Some(1))), &[1]),
+            key_with_deps(4, "target", Some((1, Some(2))), &[1, 2]),
+        ];
+        let graph = InvalidationGraph::new(&nodes);
+
+        let target = graph.matching("target").next().unwrap();
+        let explanation = graph.explain(target);
+        assert!(explanation.complete);
+        assert_eq!(vec!["file", "parse", "target"], names(&explanation.path));
+    }
+
+    #[test]
+    fn test_path_around_key_invalidated_since() {
+        // `analysis` was invalidated again at v2, but `target` depends on `parse` too.
+        let nodes = vec![
+            key(0, "file", Some((1, None))),
+            key_with_deps(1, "parse", Some((1, Some(0))), &[0]),
+            key_with_deps(2, "analysis", Some((2, Some(3))), &[1]),
+            key(3, "other_file", Some((2, None))),
+            key_with_deps(4, "target", Some((1, Some(2))), &[1, 2]),
+        ];
+        let graph = InvalidationGraph::new(&nodes);
+
+        let target = graph.matching("target").next().unwrap();
+        let explanation = graph.explain(target);
+        assert!(explanation.complete);
+        assert_eq!(vec!["file", "parse", "target"], names(&explanation.path));
+    }
+}
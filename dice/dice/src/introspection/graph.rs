@@ -56,7 +56,9 @@ impl GraphIntrospectable {
             GraphIntrospectable::Legacy { introspectables } => {
                 Either::Left(introspectables.0.iter().map(|e| e.introspect()))
             }
-            GraphIntrospectable::Modern { .. } => Either::Right(std::iter::empty()),
+            GraphIntrospectable::Modern { graph } => {
+                Either::Right(std::iter::once(graph as &dyn EngineForIntrospection))
+            }
         }
     }
 }
@@ -87,6 +89,7 @@ pub struct NodeID(pub usize);
     Copy,
     Ord,
     PartialOrd,
+    Debug,
     derive_more::Display
 )]
 pub struct VersionNumber(pub usize);
@@ -188,12 +191,22 @@ pub struct SerializedGraphNode {
     pub rdeps: Option<BTreeMap<VersionNumber, Vec<NodeID>>>,
 }
 
-#[derive(Serialize, Deserialize)]
+/// How a key was last invalidated, recorded when it was invalidated.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SerializedInvalidation {
+    pub version: VersionNumber,
+    /// The dep whose invalidation reached the key, or `None` if the key itself was changed.
+    pub by: Option<KeyID>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SerializedGraphNodesForKey {
     pub id: KeyID,
     pub key: String,
     pub type_name: String,
     pub nodes: BTreeMap<VersionNumber, Option<SerializedGraphNode>>,
+    /// Only recorded when enabled by `DiceDataBuilder::record_invalidations`.
+    pub invalidated: Option<SerializedInvalidation>,
 }
 
 pub(crate) trait EngineForIntrospection {
@@ -207,6 +220,11 @@ pub(crate) trait EngineForIntrospection {
         &'a self,
         keys: &'a mut HashMap<AnyKey, KeyID>,
     ) -> Box<dyn Iterator<Item = SerializedGraphNodesForKey> + 'a>;
+    /// Approximate memory used by the latest value of each key, as measured by `allocative`.
+    fn value_bytes<'a>(
+        &'a self,
+        keys: &'a mut HashMap<AnyKey, KeyID>,
+    ) -> Box<dyn Iterator<Item = (KeyID, usize)> + 'a>;
     fn len_for_introspection(&self) -> usize;
     fn currently_running_key_count(&self) -> usize;
 }
@@ -16,6 +16,8 @@ use crate::introspection::graph::LegacyIntrospectable;
 use crate::Dice;
 use crate::DiceImplementation;
 
+pub mod compact;
+pub mod explain;
 pub mod graph;
 pub(crate) mod introspect;
 
@@ -27,9 +29,7 @@ impl Dice {
     pub fn to_introspectable(&self) -> GraphIntrospectable {
         match &self.implementation {
             DiceImplementation::Legacy(dice) => dice.to_introspectable(),
-            DiceImplementation::Modern(_) => {
-                unimplemented!("todo")
-            }
+            DiceImplementation::Modern(dice) => dice.to_introspectable(),
         }
     }
 }
@@ -54,8 +54,12 @@ mod tests {
     use crate::api::computations::DiceComputations;
     use crate::api::cycles::DetectCycles;
     use crate::api::key::Key;
+    use crate::introspection::compact::CompactGraph;
+    use crate::introspection::explain::InvalidationGraph;
     use crate::introspection::graph::SerializedGraphNodesForKey;
     use crate::introspection::serialize_graph;
+    use crate::Dice;
+    use crate::DiceDataBuilder;
     use crate::DiceLegacy;
     use crate::HashMap;
 
@@ -170,4 +174,147 @@ mod tests {
         let _out: Vec<SerializedGraphNodesForKey> = bincode::deserialize(&node)?;
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_serialization_compact() -> anyhow::Result<()> {
+        let dice = DiceLegacy::builder().build(DetectCycles::Disabled);
+        let ctx = dice.updater().commit().await;
+        ctx.compute(&KeyA(3)).await?;
+
+        let mut out = Vec::new();
+        CompactGraph::from_introspectable(&dice.to_introspectable()).write(&mut out)?;
+        let graph = CompactGraph::read(out.as_slice())?;
+
+        assert_eq!(5, graph.keys.len());
+        let b = graph.matching("KeyB").collect::<Vec<_>>();
+        assert_eq!(1, b.len());
+        let rdeps_of_b = graph
+            .transitive_rdeps(&b)
+            .into_iter()
+            .map(|k| graph.keys[k as usize].key.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(4, rdeps_of_b.len());
+        assert!(rdeps_of_b.contains(&"KeyA(3)"));
+        assert!(graph.keys.iter().all(|k| !k.is_dirty()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_serialization_compact_modern() -> anyhow::Result<()> {
+        let dice = Dice::modern().build(DetectCycles::Disabled);
+        let ctx = dice.updater().commit().await;
+        ctx.compute(&KeyA(3)).await?;
+
+        // The modern dice blocks on its state thread to introspect.
+        let out = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+            let mut out = Vec::new();
+            dice.serialize_compact(&mut out)?;
+            Ok(out)
+        })
+        .await??;
+        let graph = CompactGraph::read(out.as_slice())?;
+
+        assert_eq!(5, graph.keys.len());
+        let b = graph.matching("KeyB").collect::<Vec<_>>();
+        assert_eq!(1, b.len());
+        let rdeps_of_b = graph
+            .transitive_rdeps(&b)
+            .into_iter()
+            .map(|k| graph.keys[k as usize].key.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(4, rdeps_of_b.len());
+        assert!(rdeps_of_b.contains(&"KeyA(3)"));
+        assert!(graph.keys.iter().all(|k| !k.is_dirty()));
+        assert!(graph.keys.iter().all(|k| k.value_bytes > 0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_serialization_dense_modern() -> anyhow::Result<()> {
+        let dice = Dice::modern().build(DetectCycles::Disabled);
+        let ctx = dice.updater().commit().await;
+        ctx.compute(&KeyA(3)).await?;
+
+        // The modern dice blocks on its state thread to introspect.
+        let node =
+            tokio::task::spawn_blocking(move || bincode::serialize(&dice.to_introspectable()))
+                .await??;
+
+        let out: Vec<SerializedGraphNodesForKey> = bincode::deserialize(&node)?;
+        assert_eq!(5, out.len());
+        let a0 = out
+            .iter()
+            .find(|n| n.key == "KeyA(0)")
+            .context("Missing key")?;
+        let b = out
+            .iter()
+            .find(|n| n.key == "KeyB")
+            .context("Missing key")?;
+        let deps = a0
+            .nodes
+            .values()
+            .flatten()
+            .find_map(|n| n.deps.as_ref())
+            .context("Missing deps")?;
+        assert!(deps.contains(&b.id));
+        Ok(())
+    }
+
+    /// Changes `KeyB` after computing `KeyA(3)`, and dumps the graph.
+    async fn invalidate_and_dump(
+        builder: DiceDataBuilder,
+    ) -> anyhow::Result<Vec<SerializedGraphNodesForKey>> {
+        let dice = builder.build(DetectCycles::Disabled);
+        let ctx = dice.updater().commit().await;
+        ctx.compute(&KeyA(3)).await?;
+        drop(ctx);
+
+        let mut updater = dice.updater();
+        updater.changed([KeyB])?;
+        let _ctx = updater.commit().await;
+
+        let node =
+            tokio::task::spawn_blocking(move || bincode::serialize(&dice.to_introspectable()))
+                .await??;
+        Ok(bincode::deserialize(&node)?)
+    }
+
+    async fn test_explain(mut builder: DiceDataBuilder) -> anyhow::Result<()> {
+        builder.record_invalidations();
+        let nodes = invalidate_and_dump(builder).await?;
+
+        let graph = InvalidationGraph::new(&nodes);
+        let a3 = graph.matching("KeyA(3)").next().context("Missing key")?;
+        let explanation = graph.explain(a3);
+        assert!(explanation.complete);
+        assert_eq!(
+            vec!["KeyB", "KeyA(0)", "KeyA(1)", "KeyA(2)", "KeyA(3)"],
+            explanation
+                .path
+                .iter()
+                .map(|k| k.key.as_str())
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_explain_legacy() -> anyhow::Result<()> {
+        test_explain(Dice::builder()).await
+    }
+
+    #[tokio::test]
+    async fn test_explain_modern() -> anyhow::Result<()> {
+        test_explain(Dice::modern()).await
+    }
+
+    #[tokio::test]
+    async fn test_invalidations_not_recorded_by_default() -> anyhow::Result<()> {
+        for builder in [Dice::builder(), Dice::modern()] {
+            let nodes = invalidate_and_dump(builder).await?;
+            assert_eq!(5, nodes.len());
+            assert!(nodes.iter().all(|n| n.invalidated.is_none()));
+        }
+        Ok(())
+    }
 }
@@ -20,6 +20,7 @@ use crate::introspection::graph::KeyID;
 use crate::introspection::graph::NodeID;
 use crate::introspection::graph::SerializedGraphNode;
 use crate::introspection::graph::SerializedGraphNodesForKey;
+use crate::introspection::graph::SerializedInvalidation;
 use crate::legacy::dice_futures::dice_task::DiceTask;
 use crate::legacy::dice_futures::dice_task::DiceTaskStateForDebugging;
 use crate::legacy::incremental::graph::dependencies::VersionedDependencies;
@@ -160,6 +161,12 @@ where
         }
         Box::new(self.versioned_cache.iter().map(move |e| {
             let k = AnyKey::new(e.key().clone());
+            let invalidated =
+                self.invalidation(e.key())
+                    .map(|invalidation| SerializedInvalidation {
+                        version: invalidation.version.to_introspectable(),
+                        by: invalidation.by.map(&mut map_id),
+                    });
             SerializedGraphNodesForKey {
                 id: map_id(k.clone()),
                 key: k.to_string(),
@@ -169,10 +176,25 @@ where
                     .iter()
                     .map(|(v, node)| (v.to_introspectable(), visit_node(node, &mut map_id)))
                     .collect(),
+                invalidated,
             }
         }))
     }
 
+    fn value_bytes<'a>(
+        &'a self,
+        keys: &'a mut HashMap<AnyKey, KeyID>,
+    ) -> Box<dyn Iterator<Item = (KeyID, usize)> + 'a> {
+        Box::new(self.versioned_cache.iter().filter_map(move |e| {
+            let node = e.value().iter().last()?.1.unpack_graph_value()?;
+            let num_keys = keys.len();
+            let id = *keys
+                .entry(AnyKey::new(e.key().clone()))
+                .or_insert_with(|| KeyID(num_keys));
+            Some((id, allocative::size_of_unique(node.val())))
+        }))
+    }
+
     fn len_for_introspection(&self) -> usize {
         self.versioned_cache.len()
     }
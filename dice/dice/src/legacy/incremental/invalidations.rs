@@ -0,0 +1,50 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Records of how keys were last invalidated, kept to explain recomputations.
+//!
+//! Invalidations propagate across the engines of all the key types, so the records are shared by
+//! all the engines of a DICE, and keyed by `AnyKey`.
+
+use allocative::Allocative;
+use parking_lot::Mutex;
+
+use crate::introspection::graph::AnyKey;
+use crate::versions::VersionNumber;
+use crate::HashMap;
+
+/// How a key was last invalidated.
+#[derive(Clone)]
+pub(crate) struct Invalidation {
+    pub(crate) version: VersionNumber,
+    /// The dep whose invalidation reached the key, or `None` if the key itself was changed.
+    pub(crate) by: Option<AnyKey>,
+}
+
+#[derive(Allocative, Default)]
+pub(crate) struct Invalidations {
+    #[allocative(skip)]
+    records: Mutex<HashMap<AnyKey, Invalidation>>,
+}
+
+impl Invalidations {
+    pub(crate) fn record(&self, key: AnyKey, version: VersionNumber, by: Option<AnyKey>) {
+        self.records
+            .lock()
+            .insert(key, Invalidation { version, by });
+    }
+
+    pub(crate) fn get(&self, key: &AnyKey) -> Option<Invalidation> {
+        self.records.lock().get(key).cloned()
+    }
+
+    pub(crate) fn clear(&self) {
+        self.records.lock().clear();
+    }
+}
@@ -18,6 +18,7 @@ pub(crate) mod dep_trackers;
 pub(crate) mod evaluator;
 pub(crate) mod graph;
 pub(crate) mod introspection;
+pub(crate) mod invalidations;
 pub(crate) mod transaction_ctx;
 pub(crate) mod versions;
 
@@ -55,6 +56,7 @@ use crate::api::projection::DiceProjectionComputations;
 use crate::api::projection::ProjectionKey;
 use crate::api::user_data::UserComputationData;
 use crate::impls::core::graph::history::CellHistory;
+use crate::introspection::graph::AnyKey;
 use crate::introspection::graph::EngineForIntrospection;
 use crate::legacy::ctx::ComputationData;
 use crate::legacy::dice_futures::dice_future::DiceFuture;
@@ -67,11 +69,14 @@ pub(crate) use crate::legacy::incremental::graph::dependencies::ComputedDependen
 pub(crate) use crate::legacy::incremental::graph::dependencies::Dependency;
 use crate::legacy::incremental::graph::storage_properties::StorageProperties;
 use crate::legacy::incremental::graph::GraphNode;
+use crate::legacy::incremental::graph::GraphNodeDyn;
 use crate::legacy::incremental::graph::VersionedGraph;
 use crate::legacy::incremental::graph::VersionedGraphKey;
 use crate::legacy::incremental::graph::VersionedGraphKeyRef;
 use crate::legacy::incremental::graph::VersionedGraphResult;
 use crate::legacy::incremental::graph::VersionedGraphResultMismatch;
+use crate::legacy::incremental::invalidations::Invalidation;
+use crate::legacy::incremental::invalidations::Invalidations;
 use crate::legacy::incremental::transaction_ctx::TransactionCtx;
 use crate::legacy::opaque::OpaqueValueImplLegacy;
 use crate::legacy::projection::ProjectionKeyAsKey;
@@ -127,6 +132,8 @@ pub(crate) struct IncrementalEngine<K: IncrementalComputeProperties> {
     /// Tracks the last scheduled task. We use this when deleting from the currently_running map,
     /// since it's possible to overwrite an existing entry while both futures are running.
     epoch: AtomicU64,
+    /// Where to record how keys were invalidated, if enabled.
+    invalidations: Option<Arc<Invalidations>>,
 }
 
 impl<K: IncrementalComputeProperties> Debug for IncrementalEngine<K> {
@@ -171,13 +178,27 @@ where
     K: IncrementalComputeProperties,
 {
     pub(crate) fn new(evaluator: K) -> Arc<Self> {
+        Self::with_invalidations(evaluator, None)
+    }
+
+    /// An engine recording how its keys are invalidated in `invalidations`, if any.
+    pub(crate) fn with_invalidations(
+        evaluator: K,
+        invalidations: Option<Arc<Invalidations>>,
+    ) -> Arc<Self> {
         Arc::new(Self {
             versioned_cache: VersionedGraph::new(evaluator),
             currently_running: RwLock::new(HashMap::default()),
             epoch: AtomicU64::new(0),
+            invalidations,
         })
     }
 
+    /// How the key was last invalidated, if recorded.
+    pub(crate) fn invalidation(&self, k: &K::Key) -> Option<Invalidation> {
+        self.invalidations.as_ref()?.get(&AnyKey::new(k.clone()))
+    }
+
     fn next_epoch(&self) -> Epoch {
         Epoch(self.epoch.fetch_add(1, Ordering::Relaxed))
     }
@@ -195,6 +216,9 @@ where
         // So, when marking the an invalidated node as dirty first ensures that any new nodes will
         // either read the invalidated node's history, or be invalidated via the rdeps traversal, or
         // both
+        if let Some(invalidations) = &self.invalidations {
+            invalidations.record(AnyKey::new(k.clone()), version, None);
+        }
         let node = self
             .versioned_cache
             .entry(VersionedGraphKey::new(version, k));
@@ -209,24 +233,27 @@ where
             // if we actually did something, invalidate the rdeps of occupied entries
             if let Some(node) = node.unpack_occupied() {
                 debug!("dirtying rdeps");
-                Self::invalidate_rdeps(version, GraphNode::occupied(node.dupe()))
+                self.invalidate_rdeps(version, GraphNode::occupied(node.dupe()))
             }
         }
     }
 
-    fn invalidate_rdeps(version: VersionNumber, invalidated: GraphNode<K>) {
+    fn invalidate_rdeps(&self, version: VersionNumber, invalidated: GraphNode<K>) {
+        let invalidations = self.invalidations.as_deref();
+        // Only keep track of the dep invalidating each rdep when recording invalidations.
         let mut queue = {
+            let by = invalidations.map(|_| AnyKey::new(invalidated.key().clone()));
             let metadata = invalidated.read_meta();
             let rdeps = metadata.rdeps.rdeps();
 
             rdeps
                 .rdeps
                 .iter()
-                .map(|(r, v)| (r.dupe(), *v))
+                .map(|(r, v)| (r.dupe(), *v, by.clone()))
                 .collect::<Vec<_>>()
         };
 
-        while let Some((rdep, relevant_version)) = queue.pop() {
+        while let Some((rdep, relevant_version, by)) = queue.pop() {
             if let Some(node) = rdep.0.upgrade() {
                 let mut metadata = node.writable();
 
@@ -242,15 +269,19 @@ where
                     // the version it was dirtied at, it may no longer depend on the current node
                     // so we skip marking it as dirty, and rely on delayed propagation of dirty
                     if metadata.hist.mark_invalidated(version) {
+                        let key = invalidations.map(|_| node.key());
                         queue.extend({
                             let rdeps = metadata.rdeps.rdeps();
 
                             rdeps
                                 .rdeps
                                 .iter()
-                                .map(|(r, v)| (r.dupe(), *v))
+                                .map(|(r, v)| (r.dupe(), *v, key.clone()))
                                 .collect::<Vec<_>>()
-                        })
+                        });
+                        if let (Some(invalidations), Some(key)) = (invalidations, key) {
+                            invalidations.record(key, version, by);
+                        }
                     }
                 }
             }
@@ -282,6 +313,9 @@ where
     ) -> bool {
         // It is crucial that we `dirty` first before updating the `rdeps`.
         // See `IncrementalEngine::dirty` below for details.
+        if let Some(invalidations) = &self.invalidations {
+            invalidations.record(AnyKey::new(k.clone()), version, None);
+        }
         let node = self
             .versioned_cache
             .entry(VersionedGraphKey::new(version, k.clone()));
@@ -294,7 +328,7 @@ where
 
         if let Some(invalidated) = invalidated {
             debug!("dirtying rdeps");
-            Self::invalidate_rdeps(version, invalidated)
+            self.invalidate_rdeps(version, invalidated)
         }
 
         let is_changed = new.get_history().latest_verified_before(version) == Some(version);
@@ -21,6 +21,7 @@ use futures::StreamExt;
 use gazebo::prelude::*;
 use incremental::evaluator::Evaluator;
 use incremental::graph::GraphNode;
+use incremental::invalidations::Invalidations;
 use incremental::transaction_ctx::TransactionCtx;
 use incremental::versions::VersionTracker;
 use incremental::IncrementalComputeProperties;
@@ -72,6 +73,8 @@ pub(crate) struct DiceLegacy {
     pub(crate) active_transaction_count: AtomicU32,
     #[allocative(skip)]
     active_versions_observer: watch::Receiver<usize>,
+    /// How keys were last invalidated, shared by all the engines, if recorded.
+    invalidations: Option<Arc<Invalidations>>,
 }
 
 impl Debug for DiceLegacy {
@@ -82,19 +85,29 @@ impl Debug for DiceLegacy {
     }
 }
 
-pub(crate) struct DiceLegacyDataBuilder(DiceData);
+pub(crate) struct DiceLegacyDataBuilder {
+    data: DiceData,
+    record_invalidations: bool,
+}
 
 impl DiceLegacyDataBuilder {
     pub(crate) fn new() -> Self {
-        Self(DiceData::new())
+        Self {
+            data: DiceData::new(),
+            record_invalidations: false,
+        }
     }
 
     pub fn set<K: Send + Sync + 'static>(&mut self, val: K) {
-        self.0.set(val);
+        self.data.set(val);
+    }
+
+    pub fn record_invalidations(&mut self) {
+        self.record_invalidations = true;
     }
 
     pub fn build(self, detect_cycles: DetectCycles) -> Arc<DiceLegacy> {
-        DiceLegacy::new(self.0, detect_cycles)
+        DiceLegacy::new(self.data, detect_cycles, self.record_invalidations)
     }
 }
 
@@ -104,7 +117,11 @@ impl DiceLegacy {
         DiceLegacyDataBuilder::new()
     }
 
-    pub(crate) fn new(data: DiceData, detect_cycles: DetectCycles) -> Arc<Self> {
+    pub(crate) fn new(
+        data: DiceData,
+        detect_cycles: DetectCycles,
+        record_invalidations: bool,
+    ) -> Arc<Self> {
         let map = Arc::new(RwLock::new(DiceMap::new()));
         let weak_map = Arc::downgrade(&map);
         let (active_versions_sender, active_versions_observer) = watch::channel(0);
@@ -130,6 +147,7 @@ impl DiceLegacy {
             detect_cycles,
             active_transaction_count: AtomicU32::new(0),
             active_versions_observer,
+            invalidations: record_invalidations.then(|| Arc::new(Invalidations::default())),
         })
     }
 
@@ -172,9 +190,12 @@ impl DiceLegacy {
             return cache;
         }
 
-        self.map
-            .write()
-            .find_cache(|| IncrementalEngine::new(StoragePropertiesForKey::<K>::new(self)))
+        self.map.write().find_cache(|| {
+            IncrementalEngine::with_invalidations(
+                StoragePropertiesForKey::<K>::new(self),
+                self.invalidations.dupe(),
+            )
+        })
     }
 
     pub(crate) fn find_projection_cache<P: ProjectionKey>(
@@ -191,14 +212,20 @@ impl DiceLegacy {
             return cache;
         }
 
-        self.map
-            .write()
-            .find_cache(|| IncrementalEngine::new(ProjectionKeyProperties::<P>::new(self)))
+        self.map.write().find_cache(|| {
+            IncrementalEngine::with_invalidations(
+                ProjectionKeyProperties::<P>::new(self),
+                self.invalidations.dupe(),
+            )
+        })
     }
 
     pub(crate) fn unstable_take(self: &Arc<DiceLegacy>) -> DiceMap {
         debug!(msg = "clearing all Dice state");
         let mut map = self.map.write();
+        if let Some(invalidations) = &self.invalidations {
+            invalidations.clear();
+        }
         std::mem::replace(&mut map, DiceMap::new())
     }
 
@@ -214,6 +241,9 @@ impl DiceLegacy {
             active_transaction_count: self
                 .active_transaction_count
                 .load(std::sync::atomic::Ordering::SeqCst),
+            memory_bytes: 0,
+            evicted_key_count: 0,
+            evicted_bytes: 0,
         }
     }
 
@@ -955,7 +955,11 @@ async fn compute_and_update_uses_proper_version_numbers() -> anyhow::Result<()>
 
 #[test]
 fn test_active_transaction_count() {
-    let dice = Arc::new(DiceLegacy::new(DiceData::new(), DetectCycles::Enabled));
+    let dice = Arc::new(DiceLegacy::new(
+        DiceData::new(),
+        DetectCycles::Enabled,
+        false,
+    ));
     assert_eq!(0, dice.metrics().active_transaction_count);
     let ctx = dice.updater().commit();
     assert_eq!(1, dice.metrics().active_transaction_count);
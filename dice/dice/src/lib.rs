@@ -255,6 +255,7 @@ pub use crate::api::user_data::UserCycleDetectorGuard;
 pub use crate::api::which::WhichDice;
 use crate::impls::dice::DiceModern;
 use crate::impls::dice::DiceModernDataBuilder;
+use crate::introspection::compact::CompactGraph;
 use crate::introspection::graph::GraphIntrospectable;
 use crate::introspection::serialize_dense_graph;
 use crate::introspection::serialize_graph;
@@ -310,6 +311,10 @@ impl DiceImplementation {
         Ok(())
     }
 
+    pub fn serialize_compact(&self, out: impl Write) -> anyhow::Result<()> {
+        CompactGraph::from_introspectable(&self.to_introspectable()).write(out)
+    }
+
     fn to_introspectable(&self) -> GraphIntrospectable {
         match self {
             DiceImplementation::Legacy(dice) => dice.to_introspectable(),
@@ -369,6 +374,20 @@ impl DiceDataBuilderImpl {
         }
     }
 
+    pub fn memory_budget(&mut self, bytes: usize) {
+        match self {
+            DiceDataBuilderImpl::Legacy(_) => {}
+            DiceDataBuilderImpl::Modern(d) => d.memory_budget(bytes),
+        }
+    }
+
+    pub fn record_invalidations(&mut self) {
+        match self {
+            DiceDataBuilderImpl::Legacy(d) => d.record_invalidations(),
+            DiceDataBuilderImpl::Modern(d) => d.record_invalidations(),
+        }
+    }
+
     pub fn build(self, detect_cycles: DetectCycles) -> Arc<Dice> {
         Dice::new(match self {
             DiceDataBuilderImpl::Legacy(d) => DiceImplementation::Legacy(d.build(detect_cycles)),
@@ -14,4 +14,11 @@ pub struct Metrics {
     /// The number of keys currently active in the per transaction cache
     pub currently_active_key_count: usize,
     pub active_transaction_count: u32,
+    /// The approximate memory used by the values and edges of the graph. Only measured when a
+    /// memory budget is set.
+    pub memory_bytes: usize,
+    /// The number of keys evicted to stay within the memory budget, since DICE was built
+    pub evicted_key_count: u64,
+    /// The memory freed by evicting keys, since DICE was built
+    pub evicted_bytes: u64,
 }
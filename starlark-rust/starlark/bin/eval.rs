@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fs;
@@ -34,7 +35,10 @@ use starlark::environment::FrozenModule;
 use starlark::environment::Globals;
 use starlark::environment::Module;
 use starlark::errors::EvalMessage;
+use starlark::eval::CoverageFormat;
 use starlark::eval::Evaluator;
+use starlark::eval::ProfileData;
+use starlark::eval::ProfileMode;
 use starlark::lsp::server::LspContext;
 use starlark::lsp::server::LspEvalResult;
 use starlark::lsp::server::LspUrl;
@@ -66,6 +70,8 @@ pub(crate) struct Context {
     pub(crate) module: Option<Module>,
     pub(crate) builtin_docs: HashMap<LspUrl, String>,
     pub(crate) builtin_symbols: HashMap<String, LspUrl>,
+    /// Coverage profiles of evaluated files, if coverage is enabled.
+    pub(crate) coverage: Option<RefCell<Vec<ProfileData>>>,
 }
 
 /// The outcome of evaluating (checking, parsing or running) given starlark code.
@@ -134,9 +140,30 @@ impl Context {
             module,
             builtin_docs,
             builtin_symbols,
+            coverage: None,
         })
     }
 
+    /// Collect code coverage of all files evaluated by this context.
+    pub(crate) fn enable_coverage(&mut self) {
+        self.coverage = Some(RefCell::new(Vec::new()));
+    }
+
+    /// Write coverage aggregated across all evaluated files.
+    pub(crate) fn write_coverage(&self, path: &Path, format: CoverageFormat) -> anyhow::Result<()> {
+        let profiles = match &self.coverage {
+            Some(profiles) => profiles.borrow(),
+            None => return Err(anyhow::anyhow!("Coverage is not enabled")),
+        };
+        let report = if profiles.is_empty() {
+            String::new()
+        } else {
+            ProfileData::merge(profiles.iter())?.gen_coverage(format)?
+        };
+        fs::write(path, report)?;
+        Ok(())
+    }
+
     fn url_for_doc(doc: &Doc) -> LspUrl {
         let url = match &doc.item {
             DocItem::Module(_) => Url::parse("starlark:/native/builtins.bzl").unwrap(),
@@ -238,18 +265,25 @@ impl Context {
         let mut eval = Evaluator::new(module);
         eval.enable_terminal_breakpoint_console();
         let globals = globals();
-        Self::err(
-            file,
-            eval.eval_module(ast, &globals).map(|v| {
-                if self.print_non_none && !v.is_none() {
-                    println!("{}", v);
-                }
-                EvalResult {
-                    messages: iter::empty(),
-                    ast: None,
-                }
-            }),
-        )
+        let go = || -> anyhow::Result<_> {
+            if self.coverage.is_some() {
+                eval.enable_profile(&ProfileMode::Coverage)?;
+            }
+            let res = eval.eval_module(ast, &globals);
+            // Record the coverage even if evaluation failed, it shows how far it got.
+            if let Some(coverage) = &self.coverage {
+                coverage.borrow_mut().push(eval.gen_profile()?);
+            }
+            let v = res?;
+            if self.print_non_none && !v.is_none() {
+                println!("{}", v);
+            }
+            Ok(EvalResult {
+                messages: iter::empty(),
+                ast: None,
+            })
+        };
+        Self::err(file, go())
     }
 
     fn check(&self, module: &AstModule) -> impl Iterator<Item = EvalMessage> {
@@ -354,3 +388,19 @@ pub(crate) fn globals() -> Globals {
 pub(crate) fn dialect() -> Dialect {
     Dialect::Extended
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage_of_failing_file() -> anyhow::Result<()> {
+        let mut ctx = Context::new(ContextMode::Run, false, &[], false)?;
+        ctx.enable_coverage();
+        let EvalResult { messages, .. } =
+            ctx.file_with_contents("fail.star", "x = 1\nfail(\"boom\")\n".to_owned());
+        assert_eq!(1, messages.count());
+        assert_eq!(1, ctx.coverage.as_ref().unwrap().borrow().len());
+        Ok(())
+    }
+}
@@ -44,8 +44,8 @@ use starlark::docs::RenderMarkdown;
 use starlark::environment::Globals;
 use starlark::errors::EvalMessage;
 use starlark::errors::EvalSeverity;
+use starlark::eval::CoverageFormat;
 use starlark::lsp;
-use starlark::read_line::ReadLine;
 use walkdir::WalkDir;
 
 use crate::eval::ContextMode;
@@ -53,6 +53,7 @@ use crate::types::LintMessage;
 
 mod dap;
 mod eval;
+mod repl;
 mod types;
 
 #[derive(Debug, Parser)]
@@ -130,6 +131,22 @@ struct Args {
     )]
     evaluate: Vec<String>,
 
+    #[arg(
+        long = "coverage",
+        value_name = "PATH",
+        help = "Write code coverage of all evaluated files to a file.",
+        conflicts_with_all = &["lsp", "dap", "check"],
+    )]
+    coverage: Option<PathBuf>,
+
+    #[arg(
+        long = "coverage-format",
+        help = "Format of the coverage report.",
+        requires = "coverage",
+        default_value = "lcov"
+    )]
+    coverage_format: ArgsCoverageFormat,
+
     #[arg(
         id = "files",
         value_name = "FILE",
@@ -146,6 +163,21 @@ enum ArgsDoc {
     Code,
 }
 
+#[derive(ValueEnum, Copy, Clone, Dupe, Debug, PartialEq, Eq)]
+enum ArgsCoverageFormat {
+    Lcov,
+    Cobertura,
+}
+
+impl ArgsCoverageFormat {
+    fn to_coverage_format(self) -> CoverageFormat {
+        match self {
+            ArgsCoverageFormat::Lcov => CoverageFormat::Lcov,
+            ArgsCoverageFormat::Cobertura => CoverageFormat::Cobertura,
+        }
+    }
+}
+
 // Treat directories as things to recursively walk for .<extension> files,
 // and everything else as normal files.
 fn expand_dirs(extension: &str, xs: Vec<PathBuf>) -> impl Iterator<Item = PathBuf> {
@@ -217,20 +249,6 @@ fn drain(xs: impl Iterator<Item = EvalMessage>, json: bool, stats: &mut Stats) {
     }
 }
 
-fn interactive(ctx: &Context) -> anyhow::Result<()> {
-    let mut rl = ReadLine::new("STARLARK_RUST_HISTFILE")?;
-    loop {
-        match rl.read_line("$> ")? {
-            Some(line) => {
-                let mut stats = Stats::default();
-                drain(ctx.expression(line).messages, false, &mut stats);
-            }
-            // User pressed EOF - disconnected terminal, or similar
-            None => return Ok(()),
-        }
-    }
-}
-
 /// starlark-rust does not support panic.
 /// Terminate on panic even if compiled without `-Cpanic=abort`.
 fn terminate_on_panic() {
@@ -265,6 +283,9 @@ fn main() -> anyhow::Result<()> {
             &expand_dirs(ext, args.prelude).collect::<Vec<_>>(),
             is_interactive,
         )?;
+        if args.coverage.is_some() {
+            ctx.enable_coverage();
+        }
 
         if args.lsp {
             ctx.mode = ContextMode::Check;
@@ -295,7 +316,10 @@ fn main() -> anyhow::Result<()> {
                 ArgsDoc::Code => println!("{}", render_docs_as_code(&builtin)),
             };
         } else if is_interactive {
-            interactive(&ctx)?;
+            repl::interactive(&ctx)?;
+            if let Some(coverage) = &args.coverage {
+                ctx.write_coverage(coverage, args.coverage_format.to_coverage_format())?;
+            }
         } else {
             let mut stats = Stats::default();
             for e in args.evaluate.clone() {
@@ -308,6 +332,10 @@ fn main() -> anyhow::Result<()> {
                 drain(ctx.file(&file).messages, args.json, &mut stats);
             }
 
+            if let Some(coverage) = &args.coverage {
+                ctx.write_coverage(coverage, args.coverage_format.to_coverage_format())?;
+            }
+
             if !args.json {
                 println!("{}", stats);
                 if stats.error > 0 {
@@ -0,0 +1,217 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Interactive read-eval-print loop.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use starlark::docs::get_registered_starlark_docs;
+use starlark::docs::Doc;
+use starlark::docs::DocItem;
+use starlark::docs::DocProperty;
+use starlark::docs::DocType;
+use starlark::docs::Identifier;
+use starlark::docs::MarkdownFlavor;
+use starlark::docs::RenderMarkdown;
+use starlark::environment::LibraryExtension;
+use starlark::read_line::ReadLine;
+use starlark::syntax::AstModule;
+use starlark::typing::OracleDocs;
+use starlark::typing::OracleStandard;
+use starlark::typing::TypingOracle;
+
+use crate::drain;
+use crate::eval::dialect;
+use crate::eval::globals;
+use crate::eval::Context;
+use crate::Stats;
+
+#[derive(Debug, thiserror::Error)]
+enum ReplError {
+    #[error("Unknown command `:{0}`, try `:help`")]
+    UnknownCommand(String),
+    #[error("Command `:{0}` requires an argument")]
+    MissingArgument(&'static str),
+    #[error("No documentation for `{0}`")]
+    NoDocs(String),
+}
+
+/// Meta-commands, their argument and help text.
+const COMMANDS: &[(&str, &str, &str)] = &[
+    ("help", "", "Show this help message"),
+    ("type", "EXPR", "Show the inferred type of an expression"),
+    (
+        "doc",
+        "NAME",
+        "Show the documentation of a variable or builtin",
+    ),
+    (
+        "load",
+        "FILE",
+        "Evaluate a file, adding its definitions to the session",
+    ),
+];
+
+const KEYWORDS: &[&str] = &[
+    "and", "break", "continue", "def", "elif", "else", "for", "if", "in", "lambda", "load", "not",
+    "or", "pass", "return",
+];
+
+/// Run the REPL until EOF, evaluating into the module of `ctx`.
+pub(crate) fn interactive(ctx: &Context) -> anyhow::Result<()> {
+    let mut rl = ReadLine::new("STARLARK_RUST_HISTFILE")?;
+    loop {
+        // Variables may have been defined by the previous line.
+        rl.set_completions(completions(ctx));
+        match rl.read_line("$> ")? {
+            Some(line) => match line.trim().strip_prefix(':') {
+                Some(command) => {
+                    if let Err(e) = meta_command(ctx, command) {
+                        eprintln!("{:#}", e);
+                    }
+                }
+                None => {
+                    let mut stats = Stats::default();
+                    drain(ctx.expression(line).messages, false, &mut stats);
+                }
+            },
+            // User pressed EOF - disconnected terminal, or similar
+            None => return Ok(()),
+        }
+    }
+}
+
+fn completions(ctx: &Context) -> Vec<String> {
+    let mut res: Vec<String> = globals()
+        .names()
+        .map(|name| name.as_str().to_owned())
+        .collect();
+    if let Some(module) = &ctx.module {
+        res.extend(
+            module
+                .variables()
+                .into_iter()
+                .map(|(name, _)| name.as_str().to_owned()),
+        );
+    }
+    res.extend(KEYWORDS.iter().map(|x| (*x).to_owned()));
+    res.extend(COMMANDS.iter().map(|(name, _, _)| format!(":{}", name)));
+    res
+}
+
+fn meta_command(ctx: &Context, command: &str) -> anyhow::Result<()> {
+    let (name, arg) = match command.split_once(char::is_whitespace) {
+        Some((name, arg)) => (name, arg.trim()),
+        None => (command, ""),
+    };
+    let require_arg = |name| {
+        if arg.is_empty() {
+            Err(ReplError::MissingArgument(name))
+        } else {
+            Ok(arg)
+        }
+    };
+    match name {
+        "help" | "?" => {
+            for (name, arg, help) in COMMANDS {
+                println!("* :{} {}, {}", name, arg, help);
+            }
+        }
+        "type" => println!("{}", type_of(ctx, require_arg("type")?)?),
+        "doc" => println!("{}", doc_of(ctx, require_arg("doc")?)?),
+        "load" => {
+            let mut stats = Stats::default();
+            drain(
+                ctx.file(Path::new(require_arg("load")?)).messages,
+                false,
+                &mut stats,
+            );
+        }
+        _ => return Err(ReplError::UnknownCommand(name.to_owned()).into()),
+    }
+    Ok(())
+}
+
+/// Infer the type of an expression, given the types of the values in the session.
+fn type_of(ctx: &Context, expr: &str) -> anyhow::Result<String> {
+    let mut variables = Vec::new();
+    if let Some(module) = &ctx.module {
+        for (name, value) in module.variables() {
+            let item = match value.documentation() {
+                Some(item @ (DocItem::Function(_) | DocItem::Property(_))) => item,
+                _ => DocItem::Property(DocProperty {
+                    docs: None,
+                    typ: Some(DocType {
+                        raw_type: value.get_type_starlark_repr(),
+                    }),
+                }),
+            };
+            variables.push(Doc {
+                id: Identifier {
+                    name: name.as_str().to_owned(),
+                    location: None,
+                },
+                item,
+                custom_attrs: HashMap::new(),
+            });
+        }
+    }
+    let oracle: Vec<Box<dyn TypingOracle>> = vec![
+        Box::new(OracleDocs::new(&variables)),
+        Box::new(OracleStandard::new(LibraryExtension::all())),
+    ];
+
+    let ast = AstModule::parse("type", format!("it = ({})\n", expr), &dialect())?;
+    let (errors, _, interface, _) = ast.typecheck(&oracle.as_slice(), &HashMap::new());
+    if let Some(error) = errors.into_iter().next() {
+        return Err(error);
+    }
+    Ok(interface
+        .get("it")
+        .map_or_else(|| "Any".to_owned(), |ty| ty.to_string()))
+}
+
+/// Render the documentation of a session variable, a global, or a builtin type.
+fn doc_of(ctx: &Context, name: &str) -> anyhow::Result<String> {
+    let variable = ctx
+        .module
+        .as_ref()
+        .and_then(|module| module.get(name))
+        .and_then(|value| value.documentation());
+    let item = variable
+        .or_else(|| match globals().documentation() {
+            DocItem::Module(module) => module.members.get(name).cloned().map(|x| x.to_doc_item()),
+            _ => None,
+        })
+        .or_else(|| {
+            get_registered_starlark_docs()
+                .into_iter()
+                .find(|doc| doc.id.name == name)
+                .map(|doc| doc.item)
+        })
+        .ok_or_else(|| ReplError::NoDocs(name.to_owned()))?;
+    let doc = Doc {
+        id: Identifier {
+            name: name.to_owned(),
+            location: None,
+        },
+        item,
+        custom_attrs: HashMap::new(),
+    };
+    Ok(doc.render_markdown(MarkdownFlavor::DocFile))
+}
@@ -27,6 +27,7 @@ use std::ptr;
 
 pub use starlark_derive::Coerce;
 use starlark_map::small_map::SmallMap;
+use starlark_map::small_set::SmallSet;
 
 /// A marker trait such that the existence of `From: Coerce<To>` implies
 /// that `From` can be treat as `To` without any data manipulation.
@@ -144,6 +145,9 @@ where
 {
 }
 
+// `SmallSet` is a `repr(transparent)` wrapper of `SmallMap<T, ()>`.
+unsafe impl<From, To> Coerce<SmallSet<To>> for SmallSet<From> where From: CoerceKey<To> {}
+
 /// Safely convert between types which have a `Coerce` relationship.
 /// Often the second type argument will need to be given explicitly,
 /// e.g. `coerce::<_, ToType>(x)`.
@@ -212,6 +216,16 @@ mod tests {
         assert_eq!(10, *new.x.0);
     }
 
+    #[test]
+    fn test_coerce_small_set() {
+        fn f<'v>(x: SmallSet<&'static str>) -> SmallSet<&'v str> {
+            coerce(x)
+        }
+
+        let set = f(SmallSet::from_iter(["a", "b"]));
+        assert_eq!(vec!["a", "b"], set.into_iter().collect::<Vec<_>>());
+    }
+
     #[test]
     fn test_coerce_is_unsound() {
         // TODO(nga): fix it.
@@ -354,6 +354,16 @@ impl Module {
             })
     }
 
+    /// The exported variables which have been assigned, with their values.
+    pub fn variables<'v>(&'v self) -> Vec<(FrozenStringValue, Value<'v>)> {
+        self.names
+            .all_names_and_visibilities()
+            .into_iter()
+            .filter(|(_, vis)| *vis == Visibility::Public)
+            .filter_map(|(name, _)| Some((name, self.get(name.as_str())?)))
+            .collect()
+    }
+
     /// Freeze the environment, all its value will become immutable afterwards.
     pub fn freeze(self) -> anyhow::Result<FrozenModule> {
         let Module {
@@ -549,4 +559,35 @@ x = f(1)
         assert!(profile_info.unused_capacity.get() > 0);
         assert!(heap_summary.contains("\"x.star.f\""), "{:?}", heap_summary);
     }
+
+    #[test]
+    fn test_variables() {
+        let module = Module::new();
+        {
+            let mut eval = Evaluator::new(&module);
+            eval.eval_module(
+                AstModule::parse(
+                    "x.star",
+                    "x = 1\n_y = 2\nz = [x]\n".to_owned(),
+                    &Dialect::Extended,
+                )
+                .unwrap(),
+                &Globals::standard(),
+            )
+            .unwrap();
+        }
+        let mut variables: Vec<(String, String)> = module
+            .variables()
+            .into_iter()
+            .map(|(name, value)| (name.as_str().to_owned(), value.to_string()))
+            .collect();
+        variables.sort();
+        assert_eq!(
+            vec![
+                ("x".to_owned(), "1".to_owned()),
+                ("z".to_owned(), "[1]".to_owned())
+            ],
+            variables
+        );
+    }
 }
@@ -73,6 +73,7 @@ pub(crate) struct Scope<'a> {
     unscopes: Vec<Unscope>,
     codemap: FrozenRef<'static, CodeMap>,
     globals: FrozenRef<'static, Globals>,
+    dialect: &'a Dialect,
     pub(crate) errors: Vec<anyhow::Error>,
 }
 
@@ -222,7 +223,7 @@ impl<'a> Scope<'a> {
         code: &mut CstStmt,
         globals: FrozenRef<'static, Globals>,
         codemap: FrozenRef<'static, CodeMap>,
-        dialect: &Dialect,
+        dialect: &'a Dialect,
     ) -> Self {
         // Not really important, sanity check
         assert_eq!(scope_id, ScopeId::module());
@@ -267,6 +268,7 @@ impl<'a> Scope<'a> {
             unscopes: Vec::new(),
             codemap,
             globals,
+            dialect,
             errors: Vec::new(),
         };
         scope.resolve_idents(code);
@@ -500,7 +502,13 @@ impl<'a> Scope<'a> {
                             self.errors.push(self.variable_not_found_err(ident));
                             return;
                         }
-                        Some(v) => ResolvedIdent::Global(v),
+                        Some(v) => {
+                            if let Err(e) = self.dialect.check_global(&self.codemap, ident) {
+                                self.errors.push(e);
+                                return;
+                            }
+                            ResolvedIdent::Global(v)
+                        }
                     }
                 }
                 Some(slot) => ResolvedIdent::Slot(slot),
@@ -94,10 +94,6 @@ pub(crate) enum EvaluatorError {
     ProfileOrInstrumentationAlreadyEnabled,
     #[error("Top frame is not def (internal error)")]
     TopFrameNotDef,
-    #[error(
-        "Coverage profile generation not implemented (but can be obtained with `.coverage()` function)"
-    )]
-    CoverageNotImplemented,
     #[error("Coverage not enabled")]
     CoverageNotEnabled,
 }
@@ -334,7 +330,7 @@ impl<'v, 'a> Evaluator<'v, 'a> {
                 Err(EvaluatorError::RetainedMemoryProfilingCannotBeObtainedFromEvaluator.into())
             }
             ProfileMode::Statement => self.stmt_profile.gen(),
-            ProfileMode::Coverage => Err(EvaluatorError::CoverageNotImplemented.into()),
+            ProfileMode::Coverage => self.stmt_profile.gen_coverage(),
             ProfileMode::Bytecode => self.bc_profile.gen_bc_profile(),
             ProfileMode::BytecodePairs => self.bc_profile.gen_bc_pairs_profile(),
             ProfileMode::TimeFlame => self.flame_profile.gen(),
@@ -0,0 +1,118 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Write flame graph data in
+//! [Chrome trace event](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU)
+//! JSON format, viewable in `chrome://tracing` or Perfetto.
+//!
+//! Flame graph data is aggregated, so the trace is a flame chart:
+//! each node is an event which spans its children, children are laid out
+//! one after another starting at the parent's start.
+
+use serde_json::json;
+
+use crate::eval::runtime::profile::flamegraph::FlameGraphData;
+use crate::eval::runtime::profile::flamegraph::FlameGraphNode;
+
+fn write_node(
+    name: &str,
+    node: &FlameGraphNode,
+    start: u64,
+    scale: u64,
+    events: &mut Vec<serde_json::Value>,
+) {
+    let total = node.total();
+    if total == 0 {
+        return;
+    }
+    events.push(json!({
+        "name": name,
+        "ph": "X",
+        "ts": start * scale,
+        "dur": total * scale,
+        "pid": 0,
+        "tid": 0,
+    }));
+    let mut child_start = start;
+    for (child_name, child) in node.children() {
+        write_node(child_name, child, child_start, scale, events);
+        child_start += child.total();
+    }
+}
+
+/// Write flame graph as Chrome trace.
+///
+/// Trace timestamps are in microseconds, node values are multiplied by `scale`
+/// to obtain them (e.g. `1000` for values in milliseconds).
+pub(crate) fn write_chrome_trace(data: &FlameGraphData, scale: u64) -> String {
+    let root = data.root_node();
+    let mut events = Vec::new();
+    let mut start = 0;
+    if let Some(value) = root.value() {
+        events.push(json!({
+            "name": "(unknown)",
+            "ph": "X",
+            "ts": 0,
+            "dur": value * scale,
+            "pid": 0,
+            "tid": 0,
+        }));
+        start += value;
+    }
+    for (name, child) in root.children() {
+        write_node(name, child, start, scale, &mut events);
+        start += child.total();
+    }
+    serde_json::to_string(&json!({
+        "traceEvents": events,
+        "displayTimeUnit": "ms",
+    }))
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::runtime::profile::chrome_trace::write_chrome_trace;
+    use crate::eval::runtime::profile::flamegraph::FlameGraphData;
+
+    #[test]
+    fn test_write_chrome_trace() {
+        let mut data = FlameGraphData::default();
+        data.root().child("a".into()).add(10);
+        data.root().child("a".into()).child("b".into()).add(20);
+        data.root().child("c".into()).add(5);
+
+        let trace: serde_json::Value =
+            serde_json::from_str(&write_chrome_trace(&data, 1000)).unwrap();
+        let events: Vec<(&str, u64, u64)> = trace["traceEvents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| {
+                (
+                    e["name"].as_str().unwrap(),
+                    e["ts"].as_u64().unwrap(),
+                    e["dur"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![("a", 0, 30000), ("b", 0, 20000), ("c", 30000, 5000)],
+            events
+        );
+    }
+}
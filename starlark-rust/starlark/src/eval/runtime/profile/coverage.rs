@@ -0,0 +1,338 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Line coverage data, and its export to LCOV and Cobertura formats.
+
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use dupe::Dupe;
+
+use crate::codemap::CodeMap;
+use crate::codemap::FileSpan;
+use crate::syntax::ast::AstExpr;
+use crate::syntax::ast::AstLiteral;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::ExprP;
+use crate::syntax::ast::StmtP;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+/// Output format for coverage profile.
+#[derive(Debug, Copy, Clone, Dupe, PartialEq, Eq)]
+pub enum CoverageFormat {
+    /// [LCOV](https://github.com/linux-test-project/lcov) tracefile.
+    Lcov,
+    /// [Cobertura](https://cobertura.github.io/cobertura/) XML report.
+    Cobertura,
+}
+
+/// Hit count per line, per file.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CoverageData {
+    /// File name to 1-based line number to hit count.
+    ///
+    /// Executable lines which were never executed are present with zero count.
+    files: BTreeMap<String, BTreeMap<u32, u64>>,
+}
+
+impl CoverageData {
+    /// Record all lines with statements in the given file as executable.
+    ///
+    /// The profiler only observes the statements which were executed,
+    /// so to report lines which were not executed, we parse the file again.
+    /// If the file cannot be parsed, only executed lines are reported.
+    pub(crate) fn add_file(&mut self, codemap: &CodeMap) {
+        let lines = self.files.entry(codemap.filename().to_owned()).or_default();
+        let module = match AstModule::parse(
+            codemap.filename(),
+            codemap.source().to_owned(),
+            &Dialect::Extended,
+        ) {
+            Ok(module) => module,
+            Err(_) => return,
+        };
+
+        fn visit(stmt: &AstStmt, codemap: &CodeMap, lines: &mut BTreeMap<u32, u64>) {
+            match &stmt.node {
+                // Not compiled into anything, or not instrumented.
+                StmtP::Statements(_) | StmtP::Pass | StmtP::Load(_) => {}
+                // Docstrings are removed by the compiler.
+                StmtP::Expression(e) if is_string_literal(e) => {}
+                _ => {
+                    let line = codemap.find_line(stmt.span.begin()) as u32 + 1;
+                    lines.entry(line).or_insert(0);
+                }
+            }
+            stmt.visit_stmt(|stmt| visit(stmt, codemap, lines));
+        }
+
+        visit(&module.statement, &module.codemap, lines);
+    }
+
+    /// Record a statement executed `count` times.
+    pub(crate) fn add_hits(&mut self, span: &FileSpan, count: u64) {
+        let line = span.file.find_line(span.span.begin()) as u32 + 1;
+        let hits = self
+            .files
+            .entry(span.file.filename().to_owned())
+            .or_default()
+            .entry(line)
+            .or_insert(0);
+        // Multiple statements on the same line (e.g. `if x: y`) are counted once.
+        *hits = (*hits).max(count);
+    }
+
+    /// Sum hit counts from several profiles.
+    pub(crate) fn merge<'a>(datas: impl IntoIterator<Item = &'a CoverageData>) -> CoverageData {
+        let mut result = CoverageData::default();
+        for data in datas {
+            for (file, lines) in &data.files {
+                let result_lines = result.files.entry(file.clone()).or_default();
+                for (line, hits) in lines {
+                    match result_lines.entry(*line) {
+                        Entry::Occupied(mut e) => *e.get_mut() += hits,
+                        Entry::Vacant(e) => {
+                            e.insert(*hits);
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    pub(crate) fn gen(&self, format: CoverageFormat) -> String {
+        match format {
+            CoverageFormat::Lcov => self.gen_lcov(),
+            CoverageFormat::Cobertura => self.gen_cobertura(),
+        }
+    }
+
+    fn gen_lcov(&self) -> String {
+        let mut r = String::new();
+        for (file, lines) in &self.files {
+            writeln!(r, "TN:").unwrap();
+            writeln!(r, "SF:{}", file).unwrap();
+            for (line, hits) in lines {
+                writeln!(r, "DA:{},{}", line, hits).unwrap();
+            }
+            writeln!(r, "LF:{}", lines.len()).unwrap();
+            writeln!(r, "LH:{}", lines_hit(lines)).unwrap();
+            writeln!(r, "end_of_record").unwrap();
+        }
+        r
+    }
+
+    fn gen_cobertura(&self) -> String {
+        // Files are grouped into packages by directory.
+        let mut packages: BTreeMap<&str, Vec<(&str, &BTreeMap<u32, u64>)>> = BTreeMap::new();
+        for (file, lines) in &self.files {
+            let package = match file.rfind('/') {
+                Some(i) => &file[..i],
+                None => ".",
+            };
+            packages.entry(package).or_default().push((file, lines));
+        }
+
+        let total_valid: usize = self.files.values().map(|lines| lines.len()).sum();
+        let total_covered: usize = self.files.values().map(lines_hit).sum();
+
+        let mut r = String::new();
+        writeln!(r, r#"<?xml version="1.0" ?>"#).unwrap();
+        writeln!(
+            r,
+            r#"<!DOCTYPE coverage SYSTEM "http://cobertura.sourceforge.net/xml/coverage-04.dtd">"#
+        )
+        .unwrap();
+        writeln!(
+            r,
+            r#"<coverage line-rate="{}" branch-rate="0" lines-covered="{}" lines-valid="{}" branches-covered="0" branches-valid="0" complexity="0" version="0" timestamp="0">"#,
+            line_rate(total_covered, total_valid),
+            total_covered,
+            total_valid,
+        )
+        .unwrap();
+        writeln!(r, "  <sources>").unwrap();
+        writeln!(r, "    <source>.</source>").unwrap();
+        writeln!(r, "  </sources>").unwrap();
+        writeln!(r, "  <packages>").unwrap();
+        for (package, files) in packages {
+            let valid: usize = files.iter().map(|(_, lines)| lines.len()).sum();
+            let covered: usize = files.iter().map(|(_, lines)| lines_hit(lines)).sum();
+            writeln!(
+                r,
+                r#"    <package name="{}" line-rate="{}" branch-rate="0" complexity="0">"#,
+                xml_escape(package),
+                line_rate(covered, valid),
+            )
+            .unwrap();
+            writeln!(r, "      <classes>").unwrap();
+            for (file, lines) in files {
+                writeln!(
+                    r,
+                    r#"        <class name="{}" filename="{}" line-rate="{}" branch-rate="0" complexity="0">"#,
+                    xml_escape(file),
+                    xml_escape(file),
+                    line_rate(lines_hit(lines), lines.len()),
+                )
+                .unwrap();
+                writeln!(r, "          <methods/>").unwrap();
+                writeln!(r, "          <lines>").unwrap();
+                for (line, hits) in lines {
+                    writeln!(
+                        r,
+                        r#"            <line number="{}" hits="{}"/>"#,
+                        line, hits
+                    )
+                    .unwrap();
+                }
+                writeln!(r, "          </lines>").unwrap();
+                writeln!(r, "        </class>").unwrap();
+            }
+            writeln!(r, "      </classes>").unwrap();
+            writeln!(r, "    </package>").unwrap();
+        }
+        writeln!(r, "  </packages>").unwrap();
+        writeln!(r, "</coverage>").unwrap();
+        r
+    }
+}
+
+fn is_string_literal(expr: &AstExpr) -> bool {
+    matches!(expr.node, ExprP::Literal(AstLiteral::String(_)))
+}
+
+fn lines_hit(lines: &BTreeMap<u32, u64>) -> usize {
+    lines.values().filter(|hits| **hits != 0).count()
+}
+
+fn line_rate(covered: usize, valid: usize) -> f64 {
+    if valid == 0 {
+        1.0
+    } else {
+        covered as f64 / valid as f64
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut r = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => r.push_str("&amp;"),
+            '<' => r.push_str("&lt;"),
+            '>' => r.push_str("&gt;"),
+            '"' => r.push_str("&quot;"),
+            '\'' => r.push_str("&apos;"),
+            c => r.push(c),
+        }
+    }
+    r
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert::test_functions;
+    use crate::environment::GlobalsBuilder;
+    use crate::environment::Module;
+    use crate::eval::runtime::profile::coverage::xml_escape;
+    use crate::eval::CoverageFormat;
+    use crate::eval::Evaluator;
+    use crate::eval::ProfileData;
+    use crate::eval::ProfileMode;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    fn coverage_profile() -> ProfileData {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+
+        let module = AstModule::parse(
+            "cov.star",
+            r#"
+def xx(x):
+    if x:
+        return noop(x)
+    return None
+
+xx(*[1])
+xx(*[2])
+"#
+            .to_owned(),
+            &Dialect::Extended,
+        )
+        .unwrap();
+        eval.enable_profile(&ProfileMode::Coverage).unwrap();
+        let mut globals = GlobalsBuilder::standard();
+        test_functions(&mut globals);
+        eval.eval_module(module, &globals.build()).unwrap();
+        eval.gen_profile().unwrap()
+    }
+
+    #[test]
+    fn test_lcov() {
+        let profile = coverage_profile();
+        assert_eq!(
+            "\
+TN:
+SF:cov.star
+DA:2,1
+DA:3,2
+DA:4,2
+DA:5,0
+DA:7,1
+DA:8,1
+LF:6
+LH:5
+end_of_record
+",
+            profile.gen_coverage(CoverageFormat::Lcov).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_lcov_merge() {
+        let profile = coverage_profile();
+        let merged = ProfileData::merge([&profile, &profile]).unwrap();
+        let lcov = merged.gen_coverage(CoverageFormat::Lcov).unwrap();
+        assert!(lcov.contains("DA:3,4\n"), "{}", lcov);
+        assert!(lcov.contains("DA:5,0\n"), "{}", lcov);
+    }
+
+    #[test]
+    fn test_cobertura() {
+        let profile = coverage_profile();
+        let xml = profile.gen_coverage(CoverageFormat::Cobertura).unwrap();
+        assert!(
+            xml.contains(r#"lines-covered="5" lines-valid="6""#),
+            "{}",
+            xml
+        );
+        assert!(
+            xml.contains(r#"<class name="cov.star" filename="cov.star""#),
+            "{}",
+            xml
+        );
+        assert!(xml.contains(r#"<line number="5" hits="0"/>"#), "{}", xml);
+    }
+
+    #[test]
+    fn test_xml_escape() {
+        assert_eq!("a&lt;b&amp;&quot;c&quot;", xml_escape("a<b&\"c\""));
+    }
+}
@@ -23,7 +23,11 @@ use dupe::Dupe;
 
 use crate::eval::runtime::profile::bc::BcPairsProfileData;
 use crate::eval::runtime::profile::bc::BcProfileData;
+use crate::eval::runtime::profile::chrome_trace::write_chrome_trace;
+use crate::eval::runtime::profile::coverage::CoverageData;
+use crate::eval::runtime::profile::coverage::CoverageFormat;
 use crate::eval::runtime::profile::flamegraph::FlameGraphData;
+use crate::eval::runtime::profile::pprof::write_pprof;
 use crate::eval::ProfileMode;
 use crate::slice_vec_ext::SliceExt;
 use crate::values::AggregateHeapProfileInfo;
@@ -38,6 +42,10 @@ enum ProfileDataError {
     DifferentProfileModes,
     #[error("Merge of profile data for profile mode `{0}` is not implemented")]
     MergeNotImplemented(ProfileMode),
+    #[error("Profile mode `{0}` is not coverage profile")]
+    NotCoverage(ProfileMode),
+    #[error("Profile mode `{0}` cannot be written in {1} format")]
+    UnsupportedFormat(ProfileMode, &'static str),
 }
 
 #[derive(Clone, Debug)]
@@ -47,6 +55,7 @@ pub(crate) enum ProfileDataImpl {
     AggregateHeapProfileInfo(Box<AggregateHeapProfileInfo>),
     /// Flame graph data is in milliseconds.
     TimeFlameProfile(FlameGraphData),
+    Coverage(Box<CoverageData>),
     Other(String),
 }
 
@@ -87,9 +96,61 @@ impl ProfileData {
             (ProfileDataImpl::TimeFlameProfile(_), _) => {
                 Err(ProfileDataError::ProfileDataNotConsistent.into())
             }
+            (ProfileDataImpl::Coverage(data), ProfileMode::Coverage) => {
+                Ok(data.gen(CoverageFormat::Lcov))
+            }
+            (ProfileDataImpl::Coverage(_), _) => {
+                Err(ProfileDataError::ProfileDataNotConsistent.into())
+            }
+        }
+    }
+
+    /// Generate coverage report in given format.
+    /// Only valid for [`ProfileMode::Coverage`] profile.
+    pub fn gen_coverage(&self, format: CoverageFormat) -> anyhow::Result<String> {
+        match &self.profile {
+            ProfileDataImpl::Coverage(data) => Ok(data.gen(format)),
+            _ => Err(ProfileDataError::NotCoverage(self.profile_mode.dupe()).into()),
+        }
+    }
+
+    /// Flame graph data with pprof sample type, unit,
+    /// and multiplier to convert the unit to microseconds for Chrome trace.
+    fn flame_graph_data(
+        &self,
+        format: &'static str,
+    ) -> anyhow::Result<(FlameGraphData, &'static str, &'static str, u64)> {
+        match (&self.profile, &self.profile_mode) {
+            (ProfileDataImpl::TimeFlameProfile(data), ProfileMode::TimeFlame) => {
+                Ok((data.clone(), "time", "milliseconds", 1000))
+            }
+            (
+                ProfileDataImpl::AggregateHeapProfileInfo(profile),
+                ProfileMode::HeapFlameAllocated,
+            ) => Ok((profile.gen_flame_graph_data(), "alloc_space", "bytes", 1)),
+            (
+                ProfileDataImpl::AggregateHeapProfileInfo(profile),
+                ProfileMode::HeapFlameRetained,
+            ) => Ok((profile.gen_flame_graph_data(), "inuse_space", "bytes", 1)),
+            _ => Err(ProfileDataError::UnsupportedFormat(self.profile_mode.dupe(), format).into()),
         }
     }
 
+    /// Generate profile in [pprof](https://github.com/google/pprof) protobuf format.
+    /// Only valid for time flame and heap flame profiles.
+    pub fn gen_pprof(&self) -> anyhow::Result<Vec<u8>> {
+        let (data, sample_type, unit, _) = self.flame_graph_data("pprof")?;
+        Ok(write_pprof(&data, sample_type, unit))
+    }
+
+    /// Generate profile as Chrome trace JSON (flame chart of aggregated data).
+    /// Only valid for time flame and heap flame profiles.
+    /// For heap profiles, one byte is represented as one microsecond.
+    pub fn gen_chrome_trace(&self) -> anyhow::Result<String> {
+        let (data, _, _, scale) = self.flame_graph_data("Chrome trace")?;
+        Ok(write_chrome_trace(&data, scale))
+    }
+
     /// Write to a file.
     pub fn write(&self, path: &Path) -> anyhow::Result<()> {
         fs::write(path, self.gen()?).with_context(|| {
@@ -152,6 +213,14 @@ impl ProfileData {
                 let profile = FlameGraphData::merge(profiles);
                 ProfileDataImpl::TimeFlameProfile(profile)
             }
+            ProfileMode::Coverage => {
+                let profiles = profiles.try_map(|p| match &p.profile {
+                    ProfileDataImpl::Coverage(data) => Ok(&**data),
+                    _ => Err(ProfileDataError::ProfileDataNotConsistent),
+                })?;
+                let profile = CoverageData::merge(profiles);
+                ProfileDataImpl::Coverage(Box::new(profile))
+            }
             profile_mode => {
                 return Err(ProfileDataError::MergeNotImplemented(profile_mode.dupe()).into());
             }
@@ -219,4 +288,39 @@ mod tests {
         // Smoke.
         ProfileData::merge([&profile, &profile]).unwrap();
     }
+
+    #[test]
+    fn merge_coverage() {
+        let profile = ProfileData {
+            profile_mode: ProfileMode::Coverage,
+            profile: ProfileDataImpl::Coverage(Box::default()),
+        };
+        // Smoke.
+        ProfileData::merge([&profile, &profile]).unwrap();
+    }
+
+    #[test]
+    fn gen_pprof_and_chrome_trace() {
+        let profile = ProfileData {
+            profile_mode: ProfileMode::TimeFlame,
+            profile: ProfileDataImpl::TimeFlameProfile(FlameGraphData::default()),
+        };
+        // Smoke.
+        profile.gen_pprof().unwrap();
+        profile.gen_chrome_trace().unwrap();
+
+        let profile = ProfileData {
+            profile_mode: ProfileMode::HeapFlameAllocated,
+            profile: ProfileDataImpl::AggregateHeapProfileInfo(Box::default()),
+        };
+        // Smoke.
+        profile.gen_pprof().unwrap();
+        profile.gen_chrome_trace().unwrap();
+
+        let profile = ProfileData {
+            profile_mode: ProfileMode::Bytecode,
+            profile: ProfileDataImpl::Bc(Box::default()),
+        };
+        assert!(profile.gen_pprof().is_err());
+    }
 }
@@ -73,6 +73,19 @@ impl FlameGraphNode {
     pub(crate) fn child(&mut self, name: ArcStr) -> &mut FlameGraphNode {
         self.children.entry(name).or_default()
     }
+
+    pub(crate) fn value(&self) -> Option<u64> {
+        self.value
+    }
+
+    pub(crate) fn children(&self) -> impl Iterator<Item = (&str, &FlameGraphNode)> {
+        self.children.iter().map(|(k, v)| (&**k, v))
+    }
+
+    /// Value of this node plus values of all descendants.
+    pub(crate) fn total(&self) -> u64 {
+        self.value.unwrap_or(0) + self.children.values().map(|c| c.total()).sum::<u64>()
+    }
 }
 
 impl FlameGraphData {
@@ -88,6 +101,10 @@ impl FlameGraphData {
         &mut self.root
     }
 
+    pub(crate) fn root_node(&self) -> &FlameGraphNode {
+        &self.root
+    }
+
     pub(crate) fn merge<'a>(
         graphs: impl IntoIterator<Item = &'a FlameGraphData>,
     ) -> FlameGraphData {
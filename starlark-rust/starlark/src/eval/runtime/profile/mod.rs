@@ -22,11 +22,14 @@ use allocative::Allocative;
 use dupe::Dupe;
 
 pub(crate) mod bc;
+pub(crate) mod chrome_trace;
+pub(crate) mod coverage;
 pub(crate) mod csv;
 pub(crate) mod data;
 pub(crate) mod flamegraph;
 pub(crate) mod heap;
 pub(crate) mod or_instrumentation;
+pub(crate) mod pprof;
 pub(crate) mod stmt;
 pub(crate) mod time_flame;
 pub(crate) mod typecheck;
@@ -48,7 +51,8 @@ pub enum ProfileMode {
     HeapFlameRetained,
     /// The statement profile mode provides information about time spent in each statement.
     Statement,
-    /// Code coverage.
+    /// Code coverage. Profile is written in LCOV format,
+    /// use [`ProfileData::gen_coverage`](crate::eval::ProfileData::gen_coverage) for other formats.
     Coverage,
     /// The bytecode profile mode provides information about bytecode instructions.
     Bytecode,
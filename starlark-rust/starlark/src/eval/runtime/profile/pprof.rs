@@ -0,0 +1,227 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Write flame graph data in
+//! [pprof](https://github.com/google/pprof/blob/main/proto/profile.proto) format.
+//!
+//! Output is uncompressed protobuf, which `pprof` tools accept as is.
+
+use starlark_map::small_map::SmallMap;
+
+use crate::eval::runtime::profile::flamegraph::FlameGraphData;
+use crate::eval::runtime::profile::flamegraph::FlameGraphNode;
+
+/// Minimal protobuf encoder, sufficient to write pprof profile.
+#[derive(Default)]
+struct ProtoWriter {
+    buf: Vec<u8>,
+}
+
+impl ProtoWriter {
+    const VARINT: u64 = 0;
+    const LEN: u64 = 2;
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u64) {
+        self.varint(((field as u64) << 3) | wire_type);
+    }
+
+    /// Write `uint64` or `int64` field. Zero (default) values are omitted.
+    fn uint64(&mut self, field: u32, value: u64) {
+        if value != 0 {
+            self.key(field, Self::VARINT);
+            self.varint(value);
+        }
+    }
+
+    fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.key(field, Self::LEN);
+        self.varint(value.len() as u64);
+        self.buf.extend_from_slice(value);
+    }
+
+    fn message(&mut self, field: u32, write: impl FnOnce(&mut ProtoWriter)) {
+        let mut message = ProtoWriter::default();
+        write(&mut message);
+        self.bytes(field, &message.buf);
+    }
+
+    fn packed_uint64(&mut self, field: u32, values: &[u64]) {
+        let mut packed = ProtoWriter::default();
+        for value in values {
+            packed.varint(*value);
+        }
+        self.bytes(field, &packed.buf);
+    }
+}
+
+/// Collect samples and functions from flame graph tree.
+struct PprofBuilder<'a> {
+    /// Function name to function id (which is also used as location id).
+    functions: SmallMap<&'a str, u64>,
+    /// Location ids (leaf first) and value.
+    samples: Vec<(Vec<u64>, u64)>,
+}
+
+impl<'a> PprofBuilder<'a> {
+    fn function_id(&mut self, name: &'a str) -> u64 {
+        // Zero id is reserved in pprof.
+        let next_id = self.functions.len() as u64 + 1;
+        *self.functions.entry(name).or_insert(next_id)
+    }
+
+    fn visit(&mut self, node: &'a FlameGraphNode, stack: &mut Vec<u64>) {
+        if let Some(value) = node.value() {
+            let mut locations = stack.clone();
+            if locations.is_empty() {
+                locations.push(self.function_id("(unknown)"));
+            }
+            locations.reverse();
+            self.samples.push((locations, value));
+        }
+        for (name, child) in node.children() {
+            let id = self.function_id(name);
+            stack.push(id);
+            self.visit(child, stack);
+            stack.pop().unwrap();
+        }
+    }
+}
+
+/// Strings are referenced by index in pprof.
+struct StringTable<'a> {
+    strings: SmallMap<&'a str, u64>,
+}
+
+impl<'a> StringTable<'a> {
+    fn new() -> StringTable<'a> {
+        let mut strings = SmallMap::new();
+        // First string must be empty.
+        strings.insert("", 0);
+        StringTable { strings }
+    }
+
+    fn index(&mut self, s: &'a str) -> u64 {
+        let next_index = self.strings.len() as u64;
+        *self.strings.entry(s).or_insert(next_index)
+    }
+}
+
+/// Write flame graph as pprof profile with single sample type.
+pub(crate) fn write_pprof(data: &FlameGraphData, sample_type: &str, unit: &str) -> Vec<u8> {
+    let mut builder = PprofBuilder {
+        functions: SmallMap::new(),
+        samples: Vec::new(),
+    };
+    let mut stack = Vec::new();
+    builder.visit(data.root_node(), &mut stack);
+    assert!(stack.is_empty());
+
+    let mut strings = StringTable::new();
+    let sample_type_index = strings.index(sample_type);
+    let unit_index = strings.index(unit);
+    let function_names: Vec<(u64, u64)> = builder
+        .functions
+        .iter()
+        .map(|(name, id)| (*id, strings.index(name)))
+        .collect();
+
+    let mut profile = ProtoWriter::default();
+    // `Profile.sample_type`.
+    profile.message(1, |w| {
+        w.uint64(1, sample_type_index);
+        w.uint64(2, unit_index);
+    });
+    // `Profile.sample`.
+    for (locations, value) in &builder.samples {
+        profile.message(2, |w| {
+            w.packed_uint64(1, locations);
+            w.packed_uint64(2, &[*value]);
+        });
+    }
+    // `Profile.location`, one per function.
+    for (id, _) in &function_names {
+        profile.message(4, |w| {
+            w.uint64(1, *id);
+            w.message(4, |w| w.uint64(1, *id));
+        });
+    }
+    // `Profile.function`.
+    for (id, name_index) in &function_names {
+        profile.message(5, |w| {
+            w.uint64(1, *id);
+            w.uint64(2, *name_index);
+            w.uint64(3, *name_index);
+        });
+    }
+    // `Profile.string_table`.
+    for s in strings.strings.keys() {
+        profile.bytes(6, s.as_bytes());
+    }
+    profile.buf
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::runtime::profile::flamegraph::FlameGraphData;
+    use crate::eval::runtime::profile::pprof::write_pprof;
+    use crate::eval::runtime::profile::pprof::ProtoWriter;
+
+    #[test]
+    fn test_varint() {
+        let mut w = ProtoWriter::default();
+        w.varint(1);
+        w.varint(300);
+        assert_eq!(vec![0x01, 0xac, 0x02], w.buf);
+    }
+
+    #[test]
+    fn test_write_pprof() {
+        let mut data = FlameGraphData::default();
+        data.root().child("a".into()).child("b".into()).add(20);
+
+        let pprof = write_pprof(&data, "time", "ms");
+        let expected: &[u8] = &[
+            // sample_type { type: 1, unit: 2 }
+            0x0a, 0x04, 0x08, 0x01, 0x10, 0x02, //
+            // sample { location_id: [2, 1], value: [20] }
+            0x12, 0x07, 0x0a, 0x02, 0x02, 0x01, 0x12, 0x01, 0x14, //
+            // location { id: 1, line { function_id: 1 } }
+            0x22, 0x06, 0x08, 0x01, 0x22, 0x02, 0x08, 0x01, //
+            // location { id: 2, line { function_id: 2 } }
+            0x22, 0x06, 0x08, 0x02, 0x22, 0x02, 0x08, 0x02, //
+            // function { id: 1, name: 3, system_name: 3 }
+            0x2a, 0x06, 0x08, 0x01, 0x10, 0x03, 0x18, 0x03, //
+            // function { id: 2, name: 4, system_name: 4 }
+            0x2a, 0x06, 0x08, 0x02, 0x10, 0x04, 0x18, 0x04, //
+            // string_table: ["", "time", "ms", "a", "b"]
+            0x32, 0x00, //
+            0x32, 0x04, b't', b'i', b'm', b'e', //
+            0x32, 0x02, b'm', b's', //
+            0x32, 0x01, b'a', //
+            0x32, 0x01, b'b', //
+        ];
+        assert_eq!(expected, pprof.as_slice());
+    }
+}
@@ -29,8 +29,10 @@ use crate::codemap::FileSpan;
 use crate::codemap::FileSpanRef;
 use crate::codemap::ResolvedFileSpan;
 use crate::codemap::Span;
+use crate::eval::runtime::profile::coverage::CoverageData;
 use crate::eval::runtime::profile::csv::CsvWriter;
 use crate::eval::runtime::profile::data::ProfileData;
+use crate::eval::runtime::profile::data::ProfileDataImpl;
 use crate::eval::runtime::small_duration::SmallDuration;
 use crate::eval::ProfileMode;
 
@@ -151,6 +153,23 @@ impl StmtProfileData {
         csv.finish()
     }
 
+    fn coverage_data(&self, now: Instant) -> CoverageData {
+        let mut data = self.clone();
+        data.add_last(now);
+
+        let mut coverage = CoverageData::default();
+        for codemap in data.files.values() {
+            coverage.add_file(codemap);
+        }
+        for ((file, span), (count, _time)) in data.stmts {
+            // EMPTY represents the first time special-case
+            if file != CodeMapId::EMPTY {
+                coverage.add_hits(&data.files[&file].file_span(span), count as u64);
+            }
+        }
+        coverage
+    }
+
     fn coverage(&self) -> HashSet<ResolvedFileSpan> {
         self.stmts
             .keys()
@@ -194,6 +213,17 @@ impl StmtProfile {
         }
     }
 
+    pub(crate) fn gen_coverage(&self) -> anyhow::Result<ProfileData> {
+        let now = Instant::now();
+        match &self.0 {
+            Some(data) => Ok(ProfileData {
+                profile_mode: ProfileMode::Coverage,
+                profile: ProfileDataImpl::Coverage(Box::new(data.coverage_data(now))),
+            }),
+            None => Err(StmtProfileError::NotEnabled.into()),
+        }
+    }
+
     pub(crate) fn coverage(&self) -> anyhow::Result<HashSet<ResolvedFileSpan>> {
         Ok(self
             .0
@@ -0,0 +1,420 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Caches of parsed documents for the LSP server, so that unchanged documents
+//! are not re-parsed and re-checked, and of which documents load which.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Instant;
+
+use dupe::Dupe;
+use lsp_types::Diagnostic;
+
+use crate::analysis::definition::LspModule;
+use crate::lsp::server::LspUrl;
+
+/// A document which is open in the editor, and the results of its last validation.
+pub(crate) struct OpenDocument {
+    /// The version of the document reported by the client.
+    pub(crate) version: Option<i64>,
+    /// The contents the diagnostics were computed from.
+    pub(crate) contents: String,
+    /// The diagnostics reported by [`LspContext`](crate::lsp::server::LspContext).
+    pub(crate) diagnostics: Vec<Diagnostic>,
+    /// The files this document loads, from its last valid parse.
+    pub(crate) loads: Vec<LspUrl>,
+}
+
+/// A validation which was requested, but is delayed until the client stops typing.
+struct PendingValidation {
+    due: Instant,
+    version: Option<i64>,
+    /// The new contents, or `None` to validate the contents last validated again, whichever
+    /// they are once the validation is due.
+    contents: Option<String>,
+    /// Validate even if contents did not change, because a loaded file did.
+    force: bool,
+}
+
+/// A validation which is due.
+pub(crate) struct DueValidation {
+    pub(crate) uri: LspUrl,
+    pub(crate) version: Option<i64>,
+    pub(crate) contents: String,
+    pub(crate) force: bool,
+}
+
+#[derive(Default)]
+pub(crate) struct DocumentCache {
+    /// Documents open in the editor.
+    open: HashMap<LspUrl, OpenDocument>,
+    /// Parses of files which are not open but loaded by open documents, with the contents they
+    /// were parsed from. Used when resolving definitions in loaded files.
+    loaded: HashMap<LspUrl, (String, Option<Arc<LspModule>>)>,
+    /// For each file, the open documents which load it.
+    dependents: HashMap<LspUrl, HashSet<LspUrl>>,
+    /// Validations to run once they are due.
+    pending: HashMap<LspUrl, PendingValidation>,
+}
+
+impl DocumentCache {
+    /// The document is open and was last validated with exactly these contents.
+    pub(crate) fn is_unchanged(&self, uri: &LspUrl, contents: &str) -> bool {
+        self.open
+            .get(uri)
+            .map_or(false, |doc| doc.contents == contents)
+    }
+
+    /// Update the version of an unchanged document, returning its diagnostics.
+    pub(crate) fn set_version(
+        &mut self,
+        uri: &LspUrl,
+        version: Option<i64>,
+    ) -> Option<Vec<Diagnostic>> {
+        let doc = self.open.get_mut(uri)?;
+        doc.version = version;
+        Some(doc.diagnostics.clone())
+    }
+
+    /// The files an open document loads.
+    pub(crate) fn loads(&self, uri: &LspUrl) -> Vec<LspUrl> {
+        self.open
+            .get(uri)
+            .map(|doc| doc.loads.clone())
+            .unwrap_or_default()
+    }
+
+    /// Record the result of validating an open document.
+    ///
+    /// Returns whether what the documents loading it may depend on changed: its contents, or the
+    /// diagnostics computed from them.
+    pub(crate) fn insert_open(&mut self, uri: LspUrl, doc: OpenDocument) -> bool {
+        let old = self.unlink(&uri);
+        for load in &doc.loads {
+            self.dependents
+                .entry(load.clone())
+                .or_default()
+                .insert(uri.clone());
+        }
+        let changed = match old {
+            Some(old) => {
+                self.evict_unloaded(&old.loads);
+                old.contents != doc.contents || old.diagnostics != doc.diagnostics
+            }
+            None => true,
+        };
+        // The open document shadows any parse from disk.
+        self.loaded.remove(&uri);
+        self.open.insert(uri, doc);
+        changed
+    }
+
+    /// Forget an open document, e.g. when it is closed.
+    pub(crate) fn remove_open(&mut self, uri: &LspUrl) -> Option<OpenDocument> {
+        self.pending.remove(uri);
+        let doc = self.unlink(uri)?;
+        self.evict_unloaded(&doc.loads);
+        Some(doc)
+    }
+
+    /// Remove an open document and its edges from the dependency map.
+    fn unlink(&mut self, uri: &LspUrl) -> Option<OpenDocument> {
+        let doc = self.open.remove(uri)?;
+        for load in &doc.loads {
+            if let Some(dependents) = self.dependents.get_mut(load) {
+                dependents.remove(uri);
+                if dependents.is_empty() {
+                    self.dependents.remove(load);
+                }
+            }
+        }
+        Some(doc)
+    }
+
+    /// Evict the parses of these files if no open document loads them anymore.
+    fn evict_unloaded(&mut self, loads: &[LspUrl]) {
+        for load in loads {
+            if !self.dependents.contains_key(load) {
+                self.loaded.remove(load);
+            }
+        }
+    }
+
+    /// Open documents which load the given file.
+    pub(crate) fn dependents(&self, uri: &LspUrl) -> Vec<LspUrl> {
+        let mut dependents: Vec<LspUrl> = self
+            .dependents
+            .get(uri)
+            .map(|x| x.iter().cloned().collect())
+            .unwrap_or_default();
+        // Make the order of revalidation deterministic.
+        dependents.sort_by_key(|x| x.to_string());
+        dependents
+    }
+
+    /// Get the parse of a file which is not open, if it was parsed from these contents.
+    /// A parse of other contents is evicted, since the file changed.
+    pub(crate) fn get_loaded(
+        &mut self,
+        uri: &LspUrl,
+        contents: &str,
+    ) -> Option<Option<Arc<LspModule>>> {
+        match self.loaded.get(uri) {
+            Some((cached, module)) if cached == contents => Some(module.dupe()),
+            Some(_) => {
+                self.loaded.remove(uri);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Cache the parse of a file which is not open, if an open document loads it.
+    pub(crate) fn insert_loaded(
+        &mut self,
+        uri: LspUrl,
+        contents: String,
+        module: Option<Arc<LspModule>>,
+    ) {
+        if self.dependents.contains_key(&uri) && !self.open.contains_key(&uri) {
+            self.loaded.insert(uri, (contents, module));
+        }
+    }
+
+    /// Validate the document at `due`, replacing any earlier request for the same document.
+    pub(crate) fn schedule(
+        &mut self,
+        uri: LspUrl,
+        version: Option<i64>,
+        contents: String,
+        due: Instant,
+    ) {
+        let force = self.pending.get(&uri).map_or(false, |p| p.force);
+        self.pending.insert(
+            uri,
+            PendingValidation {
+                due,
+                version,
+                contents: Some(contents),
+                force,
+            },
+        );
+    }
+
+    /// Revalidate an open document at `due` even if it did not change,
+    /// because a file it loads did.
+    pub(crate) fn schedule_dependent(&mut self, uri: &LspUrl, due: Instant) {
+        if let Some(pending) = self.pending.get_mut(uri) {
+            pending.force = true;
+            return;
+        }
+        if let Some(doc) = self.open.get(uri) {
+            self.pending.insert(
+                uri.clone(),
+                PendingValidation {
+                    due,
+                    version: doc.version,
+                    contents: None,
+                    force: true,
+                },
+            );
+        }
+    }
+
+    /// Cancel a pending validation, e.g. because the document is validated right away.
+    pub(crate) fn cancel(&mut self, uri: &LspUrl) {
+        self.pending.remove(uri);
+    }
+
+    /// When the next pending validation is due.
+    pub(crate) fn next_due(&self) -> Option<Instant> {
+        self.pending.values().map(|p| p.due).min()
+    }
+
+    /// Remove and return the validations due at `now`, or all of them if `now` is `None`.
+    pub(crate) fn take_due(&mut self, now: Option<Instant>) -> Vec<DueValidation> {
+        let mut due: Vec<LspUrl> = self
+            .pending
+            .iter()
+            .filter(|(_, p)| now.map_or(true, |now| p.due <= now))
+            .map(|(uri, _)| uri.clone())
+            .collect();
+        due.sort_by_key(|x| x.to_string());
+        due.into_iter()
+            .filter_map(|uri| {
+                let p = self.pending.remove(&uri).unwrap();
+                let (version, contents) = match p.contents {
+                    Some(contents) => (p.version, contents),
+                    None => {
+                        let doc = self.open.get(&uri)?;
+                        (doc.version, doc.contents.clone())
+                    }
+                };
+                Some(DueValidation {
+                    uri,
+                    version,
+                    contents,
+                    force: p.force,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use lsp_types::Diagnostic;
+
+    use crate::lsp::cache::DocumentCache;
+    use crate::lsp::cache::OpenDocument;
+    use crate::lsp::server::LspUrl;
+
+    fn url(path: &str) -> LspUrl {
+        LspUrl::File(PathBuf::from(path))
+    }
+
+    fn doc(contents: &str, loads: Vec<LspUrl>) -> OpenDocument {
+        OpenDocument {
+            version: Some(1),
+            contents: contents.to_owned(),
+            diagnostics: Vec::new(),
+            loads,
+        }
+    }
+
+    #[test]
+    fn test_dependents() {
+        let mut cache = DocumentCache::default();
+        cache.insert_open(url("/a.bzl"), doc("", vec![url("/c.bzl")]));
+        cache.insert_open(url("/b.bzl"), doc("", vec![url("/c.bzl")]));
+        assert_eq!(
+            vec![url("/a.bzl"), url("/b.bzl")],
+            cache.dependents(&url("/c.bzl"))
+        );
+
+        // Reparse drops the old loads.
+        cache.insert_open(url("/a.bzl"), doc("", Vec::new()));
+        assert_eq!(vec![url("/b.bzl")], cache.dependents(&url("/c.bzl")));
+
+        cache.remove_open(&url("/b.bzl"));
+        assert!(cache.dependents(&url("/c.bzl")).is_empty());
+    }
+
+    #[test]
+    fn test_is_unchanged() {
+        let mut cache = DocumentCache::default();
+        assert!(!cache.is_unchanged(&url("/a.bzl"), "x = 1"));
+        cache.insert_open(url("/a.bzl"), doc("x = 1", Vec::new()));
+        assert!(cache.is_unchanged(&url("/a.bzl"), "x = 1"));
+        assert!(!cache.is_unchanged(&url("/a.bzl"), "x = 2"));
+    }
+
+    #[test]
+    fn test_debounce() {
+        let mut cache = DocumentCache::default();
+        let now = Instant::now();
+        let later = now + Duration::from_secs(1);
+        cache.insert_open(url("/a.bzl"), doc("x = 1", Vec::new()));
+        cache.schedule(url("/a.bzl"), Some(2), "x = 2".to_owned(), now);
+        // Later change replaces the earlier one.
+        cache.schedule(url("/a.bzl"), Some(3), "x = 3".to_owned(), later);
+        assert_eq!(Some(later), cache.next_due());
+        assert!(cache.take_due(Some(now)).is_empty());
+
+        let due = cache.take_due(Some(later));
+        assert_eq!(1, due.len());
+        assert_eq!(Some(3), due[0].version);
+        assert_eq!("x = 3", due[0].contents);
+        assert!(!due[0].force);
+        assert_eq!(None, cache.next_due());
+    }
+
+    #[test]
+    fn test_schedule_dependent() {
+        let mut cache = DocumentCache::default();
+        let now = Instant::now();
+        cache.insert_open(url("/a.bzl"), doc("x = 1", Vec::new()));
+        cache.schedule_dependent(&url("/a.bzl"), now);
+        // Not open, so nothing to revalidate.
+        cache.schedule_dependent(&url("/b.bzl"), now);
+        let due = cache.take_due(None);
+        assert_eq!(1, due.len());
+        assert_eq!(url("/a.bzl"), due[0].uri);
+        assert_eq!("x = 1", due[0].contents);
+        assert!(due[0].force);
+    }
+
+    #[test]
+    fn test_insert_open_changed() {
+        let mut cache = DocumentCache::default();
+        assert!(cache.insert_open(url("/a.bzl"), doc("x = 1", Vec::new())));
+        assert!(!cache.insert_open(url("/a.bzl"), doc("x = 1", Vec::new())));
+        assert!(cache.insert_open(url("/a.bzl"), doc("x = 2", Vec::new())));
+
+        // Same contents, but a file it loads changed its diagnostics.
+        let mut with_error = doc("x = 2", Vec::new());
+        with_error.diagnostics.push(Diagnostic {
+            message: "error".to_owned(),
+            ..Diagnostic::default()
+        });
+        assert!(cache.insert_open(url("/a.bzl"), with_error));
+    }
+
+    #[test]
+    fn test_schedule_dependent_uses_latest_contents() {
+        let mut cache = DocumentCache::default();
+        let now = Instant::now();
+        cache.insert_open(url("/a.bzl"), doc("x = 1", Vec::new()));
+        cache.schedule_dependent(&url("/a.bzl"), now);
+        // Validated with new contents before the revalidation is due.
+        cache.insert_open(url("/a.bzl"), doc("x = 2", Vec::new()));
+        let due = cache.take_due(None);
+        assert_eq!(1, due.len());
+        assert_eq!("x = 2", due[0].contents);
+        assert!(due[0].force);
+    }
+
+    #[test]
+    fn test_loaded_eviction() {
+        let mut cache = DocumentCache::default();
+        // Not loaded by any open document, so not cached.
+        cache.insert_loaded(url("/c.bzl"), "x = 1".to_owned(), None);
+        assert!(cache.get_loaded(&url("/c.bzl"), "x = 1").is_none());
+
+        cache.insert_open(url("/a.bzl"), doc("", vec![url("/c.bzl")]));
+        cache.insert_loaded(url("/c.bzl"), "x = 1".to_owned(), None);
+        assert!(cache.get_loaded(&url("/c.bzl"), "x = 1").is_some());
+
+        // The file changed, so the parse of its old contents is evicted.
+        assert!(cache.get_loaded(&url("/c.bzl"), "x = 2").is_none());
+        assert!(cache.get_loaded(&url("/c.bzl"), "x = 1").is_none());
+
+        // Kept while an open document loads it.
+        cache.insert_loaded(url("/c.bzl"), "x = 2".to_owned(), None);
+        cache.insert_open(url("/a.bzl"), doc("y = 1", vec![url("/c.bzl")]));
+        assert!(cache.get_loaded(&url("/c.bzl"), "x = 2").is_some());
+
+        cache.remove_open(&url("/a.bzl"));
+        assert!(cache.get_loaded(&url("/c.bzl"), "x = 2").is_none());
+    }
+}
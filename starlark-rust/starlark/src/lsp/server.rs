@@ -22,7 +22,10 @@ use std::fmt::Debug;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::sync::RwLock;
+use std::time::Duration;
+use std::time::Instant;
 
 use derivative::Derivative;
 use derive_more::Display;
@@ -71,6 +74,8 @@ use crate::analysis::definition::DottedDefinition;
 use crate::analysis::definition::IdentifierDefinition;
 use crate::analysis::definition::LspModule;
 use crate::codemap::ResolvedSpan;
+use crate::lsp::cache::DocumentCache;
+use crate::lsp::cache::OpenDocument;
 use crate::lsp::server::LoadContentsError::WrongScheme;
 use crate::syntax::AstModule;
 
@@ -311,12 +316,18 @@ pub(crate) enum LoadContentsError {
     WrongScheme(String, LspUrl),
 }
 
+/// How long to wait after a change before validating a document, so that a burst of
+/// keystrokes results in a single parse.
+const VALIDATE_DELAY: Duration = Duration::from_millis(200);
+
 struct Backend<T: LspContext> {
     connection: Connection,
     context: T,
     /// The `AstModule` from the last time that a file was opened / changed and parsed successfully.
     /// Entries are evicted when the file is closed.
     last_valid_parse: RwLock<HashMap<LspUrl, Arc<LspModule>>>,
+    /// Validation results of open documents, parses of loaded files, and pending validations.
+    documents: Mutex<DocumentCache>,
 }
 
 /// The logic implementations of stuff
@@ -341,51 +352,140 @@ impl<T: LspContext> Backend<T> {
         last_valid_parse.get(uri).duped()
     }
 
+    /// Get the parse of a file which is open, or else of its contents on disk.
+    ///
+    /// Parses of files on disk are cached, and reused as long as the contents are the same.
     fn get_ast_or_load_from_disk(&self, uri: &LspUrl) -> anyhow::Result<Option<Arc<LspModule>>> {
-        let module = match self.get_ast(uri) {
-            Some(result) => Some(result),
-            None => self
-                .context
-                .parse_file(uri)?
-                .and_then(|eval_result| eval_result.ast.map(|ast| Arc::new(LspModule::new(ast)))),
+        if let Some(module) = self.get_ast(uri) {
+            return Ok(Some(module));
+        }
+        let contents = match self.context.get_load_contents(uri)? {
+            Some(contents) => contents,
+            None => return Ok(None),
         };
+        if let Some(module) = self.documents.lock().unwrap().get_loaded(uri, &contents) {
+            return Ok(module);
+        }
+        let module = self
+            .context
+            .parse_file_with_contents(uri, contents.clone())
+            .ast
+            .map(|ast| Arc::new(LspModule::new(ast)));
+        self.documents
+            .lock()
+            .unwrap()
+            .insert_loaded(uri.clone(), contents, module.dupe());
         Ok(module)
     }
 
-    fn validate(&self, uri: Url, version: Option<i64>, text: String) -> anyhow::Result<()> {
-        let uri = uri.try_into()?;
-        let eval_result = self.context.parse_file_with_contents(&uri, text);
+    /// Parse and check a document, and publish its diagnostics.
+    ///
+    /// If the document was already validated with the same contents, the previous diagnostics
+    /// are published again, unless `force` is set (e.g. because a file it loads has changed).
+    ///
+    /// Returns whether the documents loading this one may be affected, i.e. whether it was
+    /// reparsed and its contents or diagnostics changed.
+    fn validate(
+        &self,
+        uri: LspUrl,
+        version: Option<i64>,
+        text: String,
+        force: bool,
+    ) -> anyhow::Result<bool> {
+        if !force {
+            let mut documents = self.documents.lock().unwrap();
+            if documents.is_unchanged(&uri, &text) {
+                let diagnostics = documents.set_version(&uri, version).unwrap_or_default();
+                drop(documents);
+                self.publish_diagnostics((&uri).try_into()?, diagnostics, version);
+                return Ok(false);
+            }
+        }
+
+        let eval_result = self.context.parse_file_with_contents(&uri, text.clone());
+        let loads = match &eval_result.ast {
+            Some(ast) => ast
+                .loads()
+                .iter()
+                .filter_map(|load| self.resolve_load_path(load.module_id, &uri).ok())
+                .collect(),
+            // Keep the dependencies of the last valid parse.
+            None => self.documents.lock().unwrap().loads(&uri),
+        };
         if let Some(ast) = eval_result.ast {
             let module = Arc::new(LspModule::new(ast));
             let mut last_valid_parse = self.last_valid_parse.write().unwrap();
             last_valid_parse.insert(uri.clone(), module);
         }
-        self.publish_diagnostics(uri.try_into()?, eval_result.diagnostics, version);
+        let changed = self.documents.lock().unwrap().insert_open(
+            uri.clone(),
+            OpenDocument {
+                version,
+                contents: text,
+                diagnostics: eval_result.diagnostics.clone(),
+                loads,
+            },
+        );
+        self.publish_diagnostics((&uri).try_into()?, eval_result.diagnostics, version);
+        Ok(changed)
+    }
+
+    /// Run the pending validations which are due at `now`, or all of them if `now` is `None`.
+    ///
+    /// Documents which load a document whose contents or diagnostics changed are scheduled to be
+    /// validated again, transitively. Revalidating a document with the same inputs gives the same
+    /// results, so this stops, even along load cycles.
+    fn run_pending_validations(&self, now: Option<Instant>) -> anyhow::Result<()> {
+        let due = self.documents.lock().unwrap().take_due(now);
+        for validation in due {
+            let changed = self.validate(
+                validation.uri.clone(),
+                validation.version,
+                validation.contents,
+                validation.force,
+            )?;
+            if changed {
+                let mut documents = self.documents.lock().unwrap();
+                let due = Instant::now() + VALIDATE_DELAY;
+                for dependent in documents.dependents(&validation.uri) {
+                    documents.schedule_dependent(&dependent, due);
+                }
+            }
+        }
         Ok(())
     }
 
     fn did_open(&self, params: DidOpenTextDocumentParams) -> anyhow::Result<()> {
+        let uri: LspUrl = params.text_document.uri.try_into()?;
+        // Opening a document should show its diagnostics right away.
+        self.documents.lock().unwrap().cancel(&uri);
         self.validate(
-            params.text_document.uri,
+            uri,
             Some(params.text_document.version as i64),
             params.text_document.text,
-        )
+            false,
+        )?;
+        Ok(())
     }
 
     fn did_change(&self, params: DidChangeTextDocumentParams) -> anyhow::Result<()> {
         // We asked for Sync full, so can just grab all the text from params
         let change = params.content_changes.into_iter().next().unwrap();
-        self.validate(
-            params.text_document.uri,
+        self.documents.lock().unwrap().schedule(
+            params.text_document.uri.try_into()?,
             Some(params.text_document.version as i64),
             change.text,
-        )
+            Instant::now() + VALIDATE_DELAY,
+        );
+        Ok(())
     }
 
     fn did_close(&self, params: DidCloseTextDocumentParams) -> anyhow::Result<()> {
         {
+            let uri = params.text_document.uri.clone().try_into()?;
             let mut last_valid_parse = self.last_valid_parse.write().unwrap();
-            last_valid_parse.remove(&params.text_document.uri.clone().try_into()?);
+            last_valid_parse.remove(&uri);
+            self.documents.lock().unwrap().remove_open(&uri);
         }
         self.publish_diagnostics(params.text_document.uri, Vec::new(), None);
         Ok(())
@@ -617,9 +717,30 @@ impl<T: LspContext> Backend<T> {
 
     fn main_loop(&self, _params: InitializeParams) -> anyhow::Result<()> {
         self.log_message(MessageType::INFO, "Starlark server initialised");
-        for msg in &self.connection.receiver {
+        loop {
+            // Wake up when the next debounced validation is due.
+            let next_due = self.documents.lock().unwrap().next_due();
+            let msg = match next_due {
+                Some(due) => {
+                    let timeout = due.saturating_duration_since(Instant::now());
+                    match self.connection.receiver.recv_timeout(timeout) {
+                        Ok(msg) => msg,
+                        Err(e) if e.is_timeout() => {
+                            self.run_pending_validations(Some(Instant::now()))?;
+                            continue;
+                        }
+                        Err(_) => break,
+                    }
+                }
+                None => match self.connection.receiver.recv() {
+                    Ok(msg) => msg,
+                    Err(_) => break,
+                },
+            };
             match msg {
                 Message::Request(req) => {
+                    // Requests must see the latest contents of every document.
+                    self.run_pending_validations(None)?;
                     // TODO(nmj): Also implement DocumentSymbols so that some logic can
                     //            be handled client side.
                     if let Some(params) = as_request::<GotoDefinition>(&req) {
@@ -689,6 +810,7 @@ pub fn server_with_connection<T: LspContext>(
         connection,
         context,
         last_valid_parse: RwLock::default(),
+        documents: Mutex::default(),
     }
     .main_loop(initialization_params)?;
 
@@ -774,6 +896,7 @@ mod test {
     use anyhow::Context;
     use lsp_server::Request;
     use lsp_server::RequestId;
+    use lsp_types::notification::PublishDiagnostics;
     use lsp_types::request::GotoDefinition;
     use lsp_types::GotoDefinitionParams;
     use lsp_types::GotoDefinitionResponse;
@@ -1809,4 +1932,58 @@ mod test {
         }
         Ok(())
     }
+
+    #[test]
+    fn revalidates_dependents_after_change() -> anyhow::Result<()> {
+        let foo_uri = temp_file_uri("foo.star");
+        let bar_uri = temp_file_uri("bar.star");
+
+        let mut server = TestServer::new()?;
+        server.open_file(bar_uri.clone(), "baz = 1\n".to_owned())?;
+        server.open_file(
+            foo_uri.clone(),
+            format!("load(\"{}\", \"baz\")\nbaz\n", bar_uri.path()),
+        )?;
+
+        // Unchanged contents are not revalidated, so dependents are not either.
+        server.change_file(bar_uri.clone(), "baz = 1\n".to_owned())?;
+        let unchanged = server.get_notification::<PublishDiagnostics>()?;
+        assert_eq!(bar_uri, unchanged.uri);
+
+        server.change_file(bar_uri.clone(), "baz = 2\n".to_owned())?;
+        let changed = server.get_notification::<PublishDiagnostics>()?;
+        assert_eq!(bar_uri, changed.uri);
+        assert_eq!(Some(4), changed.version);
+        let dependent = server.get_notification::<PublishDiagnostics>()?;
+        assert_eq!(foo_uri, dependent.uri);
+        assert_eq!(Some(2), dependent.version);
+        Ok(())
+    }
+
+    #[test]
+    fn revalidation_stops_on_load_cycles() -> anyhow::Result<()> {
+        let foo_uri = temp_file_uri("foo.star");
+        let bar_uri = temp_file_uri("bar.star");
+        let foo = |x: i32| format!("load(\"{}\", \"y\")\ny\nx = {}\n", bar_uri.path(), x);
+        let bar = |y: i32| format!("load(\"{}\", \"x\")\nx\ny = {}\n", foo_uri.path(), y);
+
+        let mut server = TestServer::new()?;
+        server.open_file(foo_uri.clone(), foo(1))?;
+        server.open_file(bar_uri.clone(), bar(1))?;
+
+        server.change_file(foo_uri.clone(), foo(2))?;
+        let changed = server.get_notification::<PublishDiagnostics>()?;
+        assert_eq!(foo_uri, changed.uri);
+        let dependent = server.get_notification::<PublishDiagnostics>()?;
+        assert_eq!(bar_uri, dependent.uri);
+
+        // The revalidation of `bar.star` changed nothing, so `foo.star` is not revalidated again,
+        // and the next diagnostics are for the next change.
+        server.change_file(bar_uri.clone(), bar(2))?;
+        let changed = server.get_notification::<PublishDiagnostics>()?;
+        assert_eq!(bar_uri, changed.uri);
+        let dependent = server.get_notification::<PublishDiagnostics>()?;
+        assert_eq!(foo_uri, dependent.uri);
+        Ok(())
+    }
 }
@@ -21,19 +21,159 @@
 use std::env;
 use std::io;
 
+use rustyline::completion::Completer;
 use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
 use rustyline::history::DefaultHistory;
+use rustyline::validate::ValidationContext;
+use rustyline::validate::ValidationResult;
+use rustyline::validate::Validator;
+use rustyline::Context;
 use rustyline::Editor;
+use rustyline::Helper;
+
+/// Completes names, and asks for more lines while the input is an incomplete statement.
+#[derive(Default)]
+struct ReadLineHelper {
+    /// Sorted names to complete.
+    completions: Vec<String>,
+}
+
+impl Helper for ReadLineHelper {}
+
+impl Hinter for ReadLineHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReadLineHelper {}
+
+impl Completer for ReadLineHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(complete_word(&line[..pos], &self.completions))
+    }
+}
+
+impl Validator for ReadLineHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        if is_incomplete(ctx.input()) {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+/// Find the completions of the word ending at the end of `line`.
+/// Returns the start of the word, and the candidates to replace it with.
+fn complete_word(line: &str, completions: &[String]) -> (usize, Vec<String>) {
+    let mut start = line
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .map_or(0, |i| i + 1);
+    // Meta-commands like `:type` are only completed at the start of the line.
+    if start == 1 && line.starts_with(':') {
+        start = 0;
+    }
+    let word = &line[start..];
+    let candidates = completions
+        .iter()
+        .filter(|c| c.starts_with(word) && (start == 0 || !c.starts_with(':')))
+        .cloned()
+        .collect();
+    (start, candidates)
+}
+
+/// Is the input the start of a statement which continues on the next line?
+///
+/// That is the case when brackets or a triple-quoted string are not closed,
+/// the last line ends with a backslash, or the input contains a block
+/// (a line ending with `:`) which was not yet terminated with an empty line.
+fn is_incomplete(input: &str) -> bool {
+    if input.starts_with(':') {
+        // Meta-command.
+        return false;
+    }
+
+    let mut depth = 0;
+    // Quote character and whether the string is triple-quoted.
+    let mut string: Option<(char, bool)> = None;
+    let mut block = false;
+    let mut last = ' ';
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match string {
+            Some((quote, triple)) => match c {
+                '\\' => {
+                    chars.next();
+                }
+                '\n' if !triple => string = None,
+                c if c == quote => {
+                    if !triple {
+                        string = None;
+                    } else if chars.next_if_eq(&quote).is_some()
+                        && chars.next_if_eq(&quote).is_some()
+                    {
+                        string = None;
+                    }
+                }
+                _ => {}
+            },
+            None => match c {
+                '"' | '\'' => {
+                    let triple = chars.next_if_eq(&c).is_some();
+                    if triple && chars.next_if_eq(&c).is_none() {
+                        // An empty string.
+                    } else {
+                        string = Some((c, triple));
+                    }
+                    last = c;
+                }
+                '#' => while chars.next_if(|c| *c != '\n').is_some() {},
+                '(' | '[' | '{' => {
+                    depth += 1;
+                    last = c;
+                }
+                ')' | ']' | '}' => {
+                    depth -= 1;
+                    last = c;
+                }
+                '\n' => {
+                    if depth <= 0 && last == ':' {
+                        block = true;
+                    }
+                    last = ' ';
+                }
+                c if c.is_whitespace() => {}
+                c => last = c,
+            },
+        }
+    }
+
+    let last_line = input.rsplit('\n').next().unwrap_or_default();
+    matches!(string, Some((_, true)))
+        || depth > 0
+        || last == '\\'
+        || (depth <= 0 && last == ':')
+        || (block && !last_line.trim().is_empty())
+}
 
 /// Wrapper for the readline library, whichever we are using at the moment.
 pub struct ReadLine {
-    editor: Editor<(), DefaultHistory>,
+    editor: Editor<ReadLineHelper, DefaultHistory>,
     histfile: Option<String>,
 }
 
 impl ReadLine {
     pub fn new(histfile_env: &str) -> anyhow::Result<ReadLine> {
         let mut editor = Editor::new()?;
+        editor.set_helper(Some(ReadLineHelper::default()));
         let histfile = if let Ok(histfile) = env::var(histfile_env) {
             if let Err(e) = editor.load_history(&histfile) {
                 match e {
@@ -48,6 +188,15 @@ impl ReadLine {
         Ok(ReadLine { editor, histfile })
     }
 
+    /// Set the names offered for tab completion, e.g. variables in scope.
+    pub fn set_completions(&mut self, mut completions: Vec<String>) {
+        completions.sort();
+        completions.dedup();
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.completions = completions;
+        }
+    }
+
     /// Read line. Return `None` on EOF or interrupt.
     pub fn read_line(&mut self, prompt: &str) -> anyhow::Result<Option<String>> {
         match self.editor.readline(prompt) {
@@ -66,3 +215,53 @@ impl ReadLine {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::read_line::complete_word;
+    use crate::read_line::is_incomplete;
+
+    #[test]
+    fn test_is_incomplete() {
+        assert!(!is_incomplete("x = 1"));
+        assert!(!is_incomplete(":type x"));
+        assert!(!is_incomplete("x = \"(\" # ["));
+        assert!(is_incomplete("x = [1,"));
+        assert!(is_incomplete("x = 1 + \\"));
+        assert!(is_incomplete("x = '''abc"));
+        assert!(!is_incomplete("x = '''abc\n'''"));
+        assert!(!is_incomplete("x = ''"));
+        assert!(is_incomplete("def f():"));
+        assert!(is_incomplete("def f():\n    return 1"));
+        assert!(!is_incomplete("def f():\n    return 1\n"));
+        assert!(is_incomplete("if True: # comment"));
+        assert!(!is_incomplete("x = {1: 2}"));
+    }
+
+    #[test]
+    fn test_complete_word() {
+        let completions = vec![
+            ":doc".to_owned(),
+            ":type".to_owned(),
+            "len".to_owned(),
+            "list".to_owned(),
+            "tuple".to_owned(),
+        ];
+        assert_eq!(
+            (4, vec!["len".to_owned(), "list".to_owned()]),
+            complete_word("x = l", &completions)
+        );
+        assert_eq!(
+            (0, vec![":type".to_owned()]),
+            complete_word(":t", &completions)
+        );
+        assert_eq!(
+            (4, Vec::<String>::new()),
+            complete_word("x = :t", &completions)
+        );
+        assert_eq!(
+            (6, vec!["tuple".to_owned()]),
+            complete_word(":type t", &completions)
+        );
+    }
+}
@@ -32,6 +32,7 @@ pub(crate) mod partial;
 
 pub(crate) mod list;
 pub(crate) mod record;
+pub(crate) mod set;
 pub(crate) mod string;
 pub(crate) mod structs;
 pub(crate) mod util;
@@ -56,6 +57,9 @@ pub enum LibraryExtension {
     RecordType,
     /// Definitions to support the `enum` type, the `enum()` constructor.
     EnumType,
+    /// Definitions to support the `set` type, the `set()` constructor.
+    /// Only usable in dialects with [`enable_set`](crate::syntax::Dialect::enable_set).
+    SetType,
     /// A function `map(f, xs)` which applies `f` to each element of `xs` and returns the result.
     Map,
     /// A function `filter(f, xs)` which applies `f` to each element of `xs` and returns those for which `f` returns `True`.
@@ -90,6 +94,7 @@ impl LibraryExtension {
             StructType,
             RecordType,
             EnumType,
+            SetType,
             Map,
             Filter,
             Partial,
@@ -110,6 +115,7 @@ impl LibraryExtension {
             StructType => structs::global(builder),
             RecordType => record::global(builder),
             EnumType => enumeration::global(builder),
+            SetType => set::global(builder),
             Map => extra::map(builder),
             Filter => extra::filter(builder),
             Partial => partial::partial(builder),
@@ -0,0 +1,353 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `set()` constructor and methods for the `set` type.
+
+use starlark_derive::starlark_module;
+use starlark_map::small_set::SmallSet;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::environment::MethodsBuilder;
+use crate::values::none::NoneType;
+use crate::values::set::Set;
+use crate::values::set::SetMut;
+use crate::values::set::SetRef;
+use crate::values::Heap;
+use crate::values::Value;
+
+/// Collect the elements of a set or other iterable into a new set.
+fn to_set<'v>(x: Value<'v>, heap: &'v Heap) -> anyhow::Result<Set<'v>> {
+    if let Some(x) = SetRef::from_value(x) {
+        return Ok((*x).clone());
+    }
+    let it = x.iterate(heap)?;
+    let mut content = SmallSet::with_capacity(it.size_hint().0);
+    for x in it {
+        content.insert_hashed(x.get_hashed()?);
+    }
+    Ok(Set::new(content))
+}
+
+#[starlark_module]
+pub fn global(builder: &mut GlobalsBuilder) {
+    /// Create a set.
+    ///
+    /// `set()` returns an empty set, `set(x)` returns a set containing the elements
+    /// of the iterable `x`. Elements must be hashable. Iteration order of a set
+    /// is the order in which elements were first inserted.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// # (
+    /// len(set()) == 0
+    /// # and
+    /// list(set([3, 1, 3, 2])) == [3, 1, 2]
+    /// # and
+    /// set("a b a".split(" ")) == set(["b", "a"])
+    /// # )"#);
+    /// ```
+    #[starlark(type = Set::TYPE, speculative_exec_safe)]
+    fn set<'v>(
+        #[starlark(require = pos, type = "iter(\"\")")] a: Option<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        match a {
+            None => Ok(Set::default()),
+            Some(a) => to_set(a, heap),
+        }
+    }
+}
+
+#[starlark_module]
+pub(crate) fn set_methods(registry: &mut MethodsBuilder) {
+    /// `S.add(x)` inserts `x` into the set if it is not already present.
+    ///
+    /// `add` fails if `x` is unhashable, or the set is frozen or has active iterators.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1])
+    /// x.add(2)
+    /// x.add(1)
+    /// list(x) == [1, 2]
+    /// # "#);
+    /// ```
+    fn add<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] value: Value<'v>,
+    ) -> anyhow::Result<NoneType> {
+        let value = value.get_hashed()?;
+        SetMut::from_value(this)?.insert_hashed(value);
+        Ok(NoneType)
+    }
+
+    /// `S.clear()` removes all the elements of the set.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.clear()
+    /// x == set()
+    /// # "#);
+    /// ```
+    fn clear(this: Value) -> anyhow::Result<NoneType> {
+        SetMut::from_value(this)?.clear();
+        Ok(NoneType)
+    }
+
+    /// `S.discard(x)` removes `x` from the set if it is present.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.discard(2)
+    /// x.discard(3)
+    /// x == set([1])
+    /// # "#);
+    /// ```
+    fn discard<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] value: Value<'v>,
+    ) -> anyhow::Result<NoneType> {
+        let value = value.get_hashed()?;
+        SetMut::from_value(this)?.remove_hashed(value);
+        Ok(NoneType)
+    }
+
+    /// `S.remove(x)` removes `x` from the set, failing if it is not present.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.remove(2)
+    /// x == set([1])
+    /// # "#);
+    /// ```
+    ///
+    /// ```
+    /// # starlark::assert::fail(r#"
+    /// set([1]).remove(2) # error: not found
+    /// # "#, "not found");
+    /// ```
+    fn remove<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] value: Value<'v>,
+    ) -> anyhow::Result<NoneType> {
+        let hashed = value.get_hashed()?;
+        if SetMut::from_value(this)?.remove_hashed(hashed) {
+            Ok(NoneType)
+        } else {
+            Err(anyhow::anyhow!(
+                "Value `{}` not found in set `{}`",
+                value.to_repr(),
+                this.to_repr()
+            ))
+        }
+    }
+
+    /// `S.pop()` removes and returns the most recently inserted element of the set.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// # (
+    /// x.pop() == 2
+    /// # and
+    /// x == set([1])
+    /// # )"#);
+    /// ```
+    fn pop<'v>(this: Value<'v>) -> anyhow::Result<Value<'v>> {
+        match SetMut::from_value(this)?.pop() {
+            Some(x) => Ok(x),
+            None => Err(anyhow::anyhow!("Cannot .pop() on an empty set")),
+        }
+    }
+
+    /// `S.copy()` returns a new set with the same elements.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// y = x.copy()
+    /// y.add(3)
+    /// x == set([1, 2])
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn copy<'v>(this: SetRef<'v>) -> anyhow::Result<Set<'v>> {
+        Ok((*this).clone())
+    }
+
+    /// `S.union(*others)` returns a new set with the elements of `S` and of all
+    /// the `others`, which may be any iterables.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// list(set([1, 2]).union([2, 3], set([4]))) == [1, 2, 3, 4]
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn union<'v>(
+        this: SetRef<'v>,
+        #[starlark(args)] others: Vec<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        let mut res = (*this).clone();
+        for other in others {
+            res = res.union(&to_set(other, heap)?);
+        }
+        Ok(res)
+    }
+
+    /// `S.intersection(*others)` returns a new set with the elements of `S`
+    /// which are in all the `others`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// list(set([1, 2, 3]).intersection([2, 3, 4], set([3, 2]))) == [2, 3]
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn intersection<'v>(
+        this: SetRef<'v>,
+        #[starlark(args)] others: Vec<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        let mut res = (*this).clone();
+        for other in others {
+            res = res.intersection(&to_set(other, heap)?);
+        }
+        Ok(res)
+    }
+
+    /// `S.difference(*others)` returns a new set with the elements of `S`
+    /// which are in none of the `others`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// list(set([1, 2, 3]).difference([2], set([3]))) == [1]
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn difference<'v>(
+        this: SetRef<'v>,
+        #[starlark(args)] others: Vec<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        let mut res = (*this).clone();
+        for other in others {
+            res = res.difference(&to_set(other, heap)?);
+        }
+        Ok(res)
+    }
+
+    /// `S.symmetric_difference(x)` returns a new set with the elements which
+    /// are in exactly one of `S` and `x`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// list(set([1, 2]).symmetric_difference([2, 3])) == [1, 3]
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn symmetric_difference<'v>(
+        this: SetRef<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        Ok(this.symmetric_difference(&to_set(other, heap)?))
+    }
+
+    /// `S.update(*others)` inserts the elements of all the `others` into `S`.
+    ///
+    /// `update` fails if the set is frozen or has active iterators.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1])
+    /// x.update([2, 1], set([3]))
+    /// list(x) == [1, 2, 3]
+    /// # "#);
+    /// ```
+    fn update<'v>(
+        this: Value<'v>,
+        #[starlark(args)] others: Vec<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<NoneType> {
+        // Collect first: `others` may contain `this` itself.
+        let others = others
+            .into_iter()
+            .map(|x| to_set(x, heap))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let mut this = SetMut::from_value(this)?;
+        for other in others {
+            for x in other.iter_hashed() {
+                this.insert_hashed(x);
+            }
+        }
+        Ok(NoneType)
+    }
+
+    /// `S.issubset(x)` returns `True` if every element of `S` is in the iterable `x`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1]).issubset([1, 2]) and not set([1, 3]).issubset(set([1, 2]))
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn issubset<'v>(
+        this: SetRef<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<bool> {
+        Ok(this.is_subset(&to_set(other, heap)?))
+    }
+
+    /// `S.issuperset(x)` returns `True` if every element of the iterable `x` is in `S`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2]).issuperset([1]) and not set([1]).issuperset(set([1, 2]))
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn issuperset<'v>(
+        this: SetRef<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<bool> {
+        Ok(to_set(other, heap)?.is_subset(&this))
+    }
+
+    /// `S.isdisjoint(x)` returns `True` if `S` and the iterable `x` have no elements in common.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1]).isdisjoint([2]) and not set([1, 2]).isdisjoint(set([2]))
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn isdisjoint<'v>(
+        this: SetRef<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<bool> {
+        Ok(this.intersection(&to_set(other, heap)?).is_empty())
+    }
+}
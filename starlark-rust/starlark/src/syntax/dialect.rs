@@ -23,6 +23,7 @@ use crate::codemap::Pos;
 use crate::codemap::Span;
 use crate::codemap::Spanned;
 use crate::errors::Diagnostic;
+use crate::syntax::ast::AstString;
 use crate::syntax::ast::Visibility;
 
 #[derive(Error, Debug)]
@@ -37,6 +38,8 @@ pub(crate) enum DialectError {
     KeywordOnlyArguments,
     #[error("type annotations are not allowed in this dialect")]
     Types,
+    #[error("`set` is not allowed in this dialect")]
+    Set,
 }
 
 /// How to handle type annotations in Starlark.
@@ -75,6 +78,10 @@ pub struct Dialect {
     /// Are `for`, `if` and other statements allowed at the top level.
     /// Only enabled in [`Extended`](Dialect::Extended).
     pub enable_top_level_stmt: bool,
+    /// Can the `set` type be used, via the `set()` constructor defined by
+    /// [`LibraryExtension::SetType`](crate::stdlib::LibraryExtension::SetType).
+    /// Only enabled in [`Extended`](Dialect::Extended).
+    pub enable_set: bool,
     /// Like `#[non_exhaustive]`, but allows struct expression.
     ///
     /// [Explanation](https://github.com/rust-lang/rust-clippy/issues/6559).
@@ -102,6 +109,7 @@ impl Dialect {
         enable_types: DialectTypes::Disable,
         enable_load_reexport: true, // But they plan to change it
         enable_top_level_stmt: false,
+        enable_set: false,
         _non_exhaustive: (),
     };
 
@@ -114,6 +122,7 @@ impl Dialect {
         enable_types: DialectTypes::Enable,
         enable_load_reexport: true,
         enable_top_level_stmt: true,
+        enable_set: true,
         _non_exhaustive: (),
     };
 }
@@ -174,6 +183,15 @@ impl Dialect {
         }
     }
 
+    /// Check a reference to a global is allowed in this dialect.
+    pub(crate) fn check_global(&self, codemap: &CodeMap, ident: &AstString) -> anyhow::Result<()> {
+        if ident.node == "set" && !self.enable_set {
+            err(codemap, ident.span, DialectError::Set)
+        } else {
+            Ok(())
+        }
+    }
+
     pub(crate) fn load_visibility(&self) -> Visibility {
         if self.enable_load_reexport {
             Visibility::Public
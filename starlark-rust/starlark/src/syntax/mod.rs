@@ -21,6 +21,12 @@ pub use ast::AstModule;
 pub use dialect::Dialect;
 pub use dialect::DialectTypes;
 pub use parser::AstLoad;
+pub use top_level_calls::AstCallArgument;
+pub use top_level_calls::AstCallValue;
+pub use top_level_calls::AstTopLevelCall;
+
+pub use crate::analysis::exported::ExportedSymbol;
+pub use crate::analysis::exported::ExportedSymbolKind;
 
 #[cfg(test)]
 mod grammar_tests;
@@ -50,4 +56,5 @@ mod grammar {
 }
 
 pub(crate) mod parser;
+mod top_level_calls;
 pub(crate) mod uniplate;
@@ -0,0 +1,188 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::ops::Range;
+
+use crate::codemap::Span;
+use crate::syntax::ast::Argument;
+use crate::syntax::ast::AstExpr;
+use crate::syntax::ast::AstLiteral;
+use crate::syntax::ast::Expr;
+use crate::syntax::ast::Stmt;
+use crate::syntax::AstModule;
+
+/// A function call written as a statement at the top level of a module, like a target
+/// declaration in a build file.
+///
+/// All the positions are byte offsets in [`AstModule::source`], so that tools can rewrite
+/// parts of the call while keeping the rest of the source, including comments, untouched.
+#[derive(Debug)]
+pub struct AstTopLevelCall<'a> {
+    /// The called function, e.g. `cxx_library` or `native.cxx_library`.
+    pub function: String,
+    /// The whole call, from the function to the closing parenthesis.
+    pub span: Range<usize>,
+    pub arguments: Vec<AstCallArgument<'a>>,
+}
+
+/// An argument of an [`AstTopLevelCall`].
+#[derive(Debug)]
+pub struct AstCallArgument<'a> {
+    /// The name of a named argument, `None` for other arguments.
+    pub name: Option<&'a str>,
+    /// The whole argument, including `name =` for named arguments.
+    pub span: Range<usize>,
+    pub value: AstCallValue<'a>,
+}
+
+/// The value of an [`AstCallArgument`], or an element of a list value.
+#[derive(Debug)]
+pub struct AstCallValue<'a> {
+    pub span: Range<usize>,
+    /// The value if it is a string literal.
+    pub string: Option<&'a str>,
+    /// The elements if the value is a list literal.
+    pub list: Option<Vec<AstCallValue<'a>>>,
+}
+
+fn range(span: Span) -> Range<usize> {
+    span.begin().get() as usize..span.end().get() as usize
+}
+
+impl<'a> AstCallValue<'a> {
+    fn new(expr: &'a AstExpr) -> Self {
+        AstCallValue {
+            span: range(expr.span),
+            string: match &expr.node {
+                Expr::Literal(AstLiteral::String(s)) => Some(&s.node),
+                _ => None,
+            },
+            list: match &expr.node {
+                Expr::List(xs) => Some(xs.iter().map(AstCallValue::new).collect()),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl<'a> AstTopLevelCall<'a> {
+    fn new(expr: &'a AstExpr) -> Option<Self> {
+        fn function_name(expr: &AstExpr) -> Option<String> {
+            match &expr.node {
+                Expr::Identifier(name, _) => Some(name.node.clone()),
+                Expr::Dot(object, attr) => {
+                    Some(format!("{}.{}", function_name(object)?, attr.node))
+                }
+                _ => None,
+            }
+        }
+
+        match &expr.node {
+            Expr::Call(function, arguments) => Some(AstTopLevelCall {
+                function: function_name(function)?,
+                span: range(expr.span),
+                arguments: arguments
+                    .iter()
+                    .map(|argument| {
+                        let (name, value) = match &argument.node {
+                            Argument::Named(name, value) => (Some(name.node.as_str()), value),
+                            Argument::Positional(value)
+                            | Argument::Args(value)
+                            | Argument::KwArgs(value) => (None, value),
+                        };
+                        AstCallArgument {
+                            name,
+                            span: range(argument.span),
+                            value: AstCallValue::new(value),
+                        }
+                    })
+                    .collect(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The value of the named argument `name`, if there is one.
+    pub fn argument(&self, name: &str) -> Option<&AstCallArgument<'a>> {
+        self.arguments.iter().find(|a| a.name == Some(name))
+    }
+}
+
+impl AstModule {
+    /// The function calls written as statements at the top level of the module, in order.
+    /// Calls nested in other statements, like `if` or `def`, are not included.
+    pub fn top_level_calls(&self) -> Vec<AstTopLevelCall> {
+        self.top_level_statements()
+            .into_iter()
+            .filter_map(|stmt| match &stmt.node {
+                Stmt::Expression(expr) => AstTopLevelCall::new(expr),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The source code of the module.
+    pub fn source(&self) -> &str {
+        self.codemap.source()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    #[test]
+    fn test_top_level_calls() {
+        let contents = r#"
+load(":defs.bzl", "foo")
+
+# A comment.
+foo(
+    name = "a",
+    srcs = ["x.c", y],
+)
+
+native.bar("b", *args)
+
+def f():
+    foo(name = "not top level")
+"#;
+        let module = AstModule::parse("BUCK", contents.to_owned(), &Dialect::Extended).unwrap();
+        let calls = module.top_level_calls();
+        assert_eq!(2, calls.len());
+
+        let foo = &calls[0];
+        assert_eq!("foo", foo.function);
+        assert!(module.source()[foo.span.clone()].starts_with("foo(\n"));
+        assert!(module.source()[foo.span.clone()].ends_with(",\n)"));
+        assert_eq!(Some("a"), foo.argument("name").unwrap().value.string);
+        let srcs = foo.argument("srcs").unwrap();
+        assert_eq!(r#"srcs = ["x.c", y]"#, &module.source()[srcs.span.clone()]);
+        let items = srcs.value.list.as_ref().unwrap();
+        assert_eq!(Some("x.c"), items[0].string);
+        assert_eq!("y", &module.source()[items[1].span.clone()]);
+
+        let bar = &calls[1];
+        assert_eq!("native.bar", bar.function);
+        assert_eq!(
+            vec![None, None],
+            bar.arguments.iter().map(|a| a.name).collect::<Vec<_>>()
+        );
+        assert!(bar.argument("name").is_none());
+    }
+}
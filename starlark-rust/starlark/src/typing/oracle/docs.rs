@@ -105,6 +105,7 @@ impl TypingOracle for OracleDocs {
             Ty::List(_) => "list",
             Ty::Tuple(_) => "tuple",
             Ty::Dict(_) => "dict",
+            Ty::Set(_) => "set",
             Ty::Struct { .. } => "struct",
             _ => return None,
         };
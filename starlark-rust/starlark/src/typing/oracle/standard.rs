@@ -70,6 +70,9 @@ impl OracleStandard {
         add::<crate::values::list::value::ListGen<crate::values::list::value::FrozenListData>>(
             &mut fallback,
         );
+        add::<crate::values::set::value::SetGen<crate::values::set::value::FrozenSetData>>(
+            &mut fallback,
+        );
         add::<crate::values::string::StarlarkStr>(&mut fallback);
         add::<crate::values::structs::value::FrozenStruct>(&mut fallback);
         add::<crate::values::tuple::value::FrozenTuple>(&mut fallback);
@@ -101,6 +101,20 @@ hash(1)
     );
 }
 
+#[test]
+fn test_set() {
+    let (errs, _, interface, _) = typecheck(
+        r#"
+def foo(x: "set") -> "set":
+    return x.union([1])
+y = foo(set([1]))
+   "#,
+        &HashMap::new(),
+    );
+    assert!(errs.is_empty());
+    assert_eq!(interface.get("y").unwrap(), &Ty::set(Ty::Any));
+}
+
 #[test]
 fn test_load() {
     let (errs, _, interface, approx) = typecheck(
@@ -211,6 +211,8 @@ pub enum Ty {
     Tuple(Vec<Ty>),
     /// A dictionary, with key and value types
     Dict(Box<(Ty, Ty)>),
+    /// A set.
+    Set(Box<Ty>),
     /// A `struct`.
     Struct {
         /// The fields that are definitely present in the struct, with their types.
@@ -316,6 +318,7 @@ impl Ty {
         match name {
             "list" => Self::List(Box::new(Ty::Any)),
             "dict" => Self::Dict(Box::new((Ty::Any, Ty::Any))),
+            "set" => Self::Set(Box::new(Ty::Any)),
             "NoneType" => Self::None,
             "function" => {
                 Self::function(vec![Param::args(Ty::Any), Param::kwargs(Ty::Any)], Ty::Any)
@@ -358,6 +361,11 @@ impl Ty {
         Ty::Dict(Box::new((key, value)))
     }
 
+    /// Create a set type.
+    pub fn set(inner: Ty) -> Self {
+        Ty::Set(Box::new(inner))
+    }
+
     /// Create a tuple of two elements
     pub fn tuple2(a: Ty, b: Ty) -> Self {
         Ty::Tuple(vec![a, b])
@@ -427,6 +435,7 @@ impl Ty {
             (Ty::Dict(x), Ty::Dict(y)) => {
                 Either::Left(Ty::dict(Ty::union2(x.0, y.0), Ty::union2(x.1, y.1)))
             }
+            (Ty::Set(x), Ty::Set(y)) => Either::Left(Ty::set(Ty::union2(*x, *y))),
             (
                 Ty::Struct { fields, extra },
                 Ty::Struct {
@@ -549,6 +558,7 @@ impl Ty {
                     (Ty::Dict(x), Ty::Dict(y)) => {
                         x.0.intersects(&y.0, ctx) && x.1.intersects(&y.1, ctx)
                     }
+                    (Ty::Set(x), Ty::Set(y)) => x.intersects(y, ctx),
                     (Ty::Tuple(_), t) | (Ty::Tuple(_), t) if t.is_name("tuple") => true,
                     (Ty::Tuple(xs), Ty::Tuple(ys)) if xs.len() == ys.len() => {
                         std::iter::zip(xs, ys).all(|(x, y)| x.intersects(y, ctx))
@@ -725,6 +735,7 @@ impl Display for Ty {
                 write!(f, ")")
             }
             Ty::Dict(k_v) => write!(f, "{{{}: {}}}", k_v.0, k_v.1),
+            Ty::Set(x) => write!(f, "set({})", x),
             Ty::Struct { fields, extra } => {
                 write!(f, "struct(")?;
                 for (k, v) in fields {
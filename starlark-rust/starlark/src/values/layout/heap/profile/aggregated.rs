@@ -392,12 +392,16 @@ impl AggregateHeapProfileInfo {
 
     /// Write this out recursively to a file.
     pub fn gen_flame_graph(&self) -> String {
+        self.gen_flame_graph_data().write()
+    }
+
+    pub(crate) fn gen_flame_graph_data(&self) -> FlameGraphData {
         let mut data = FlameGraphData::default();
         self.root().write_flame_graph(data.root());
         data.root()
             .child(ArcStr::new_static("unused_capacity"))
             .add(self.unused_capacity.get() as u64);
-        data.write()
+        data
     }
 
     /// Write per-function summary in CSV format.
@@ -82,6 +82,7 @@ pub use crate::values::types::none;
 pub use crate::values::types::range;
 pub use crate::values::types::record;
 pub use crate::values::types::regex;
+pub use crate::values::types::set;
 pub use crate::values::types::string;
 pub use crate::values::types::structs;
 pub use crate::values::types::tuple;
@@ -0,0 +1,493 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cell::Ref;
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::mem;
+use std::ops::Deref;
+
+use allocative::Allocative;
+use display_container::fmt_container;
+use serde::Serialize;
+use starlark_derive::StarlarkDocs;
+use starlark_map::small_set::SmallSet;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::coerce::coerce;
+use crate::coerce::Coerce;
+use crate::collections::Hashed;
+use crate::environment::Methods;
+use crate::environment::MethodsStatic;
+use crate::starlark_type;
+use crate::values::dict::refcell::unleak_borrow;
+use crate::values::error::ValueError;
+use crate::values::set::SetRef;
+use crate::values::type_repr::StarlarkTypeRepr;
+use crate::values::AllocFrozenValue;
+use crate::values::AllocValue;
+use crate::values::Freeze;
+use crate::values::Freezer;
+use crate::values::FrozenHeap;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Trace;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+#[derive(
+    Clone,
+    Default,
+    Trace,
+    Debug,
+    ProvidesStaticType,
+    StarlarkDocs,
+    Allocative
+)]
+#[starlark_docs(builtin = "extension")]
+pub(crate) struct SetGen<T>(pub(crate) T);
+
+impl<'v, T: SetLike<'v>> Display for SetGen<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_set(f, &self.0.content())
+    }
+}
+
+impl<'v> Display for Set<'v> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_set(f, &self.content)
+    }
+}
+
+fn fmt_set(f: &mut fmt::Formatter<'_>, content: &SmallSet<Value>) -> fmt::Result {
+    if content.is_empty() {
+        write!(f, "set()")
+    } else {
+        fmt_container(f, "set([", "])", content.iter())
+    }
+}
+
+/// Define the set type.
+#[derive(Clone, Default, Trace, Debug, ProvidesStaticType, Allocative)]
+#[repr(transparent)]
+pub struct Set<'v> {
+    /// The data stored by the set. The values must all be hashable.
+    content: SmallSet<Value<'v>>,
+}
+
+impl<'v> StarlarkTypeRepr for Set<'v> {
+    fn starlark_type_repr() -> String {
+        Set::TYPE.to_owned()
+    }
+}
+
+#[derive(Clone, Default, Debug, ProvidesStaticType, Allocative)]
+#[repr(transparent)]
+pub(crate) struct FrozenSetData {
+    /// The data stored by the set. The values must all be hashable.
+    content: SmallSet<FrozenValue>,
+}
+
+/// Alias is used in `StarlarkDocs` derive.
+type FrozenSet = SetGen<FrozenSetData>;
+
+unsafe impl<'v> Coerce<Set<'v>> for FrozenSetData {}
+unsafe impl<'v> Coerce<Set<'v>> for SmallSet<Value<'v>> {}
+
+impl<'v> AllocValue<'v> for Set<'v> {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc_complex(SetGen(RefCell::new(self)))
+    }
+}
+
+impl AllocFrozenValue for FrozenSetData {
+    fn alloc_frozen_value(self, heap: &FrozenHeap) -> FrozenValue {
+        heap.alloc_simple(SetGen(self))
+    }
+}
+
+impl<'v> Set<'v> {
+    /// The result of calling `type()` on sets.
+    pub const TYPE: &'static str = "set";
+
+    /// Create a new [`Set`].
+    pub fn new(content: SmallSet<Value<'v>>) -> Self {
+        Self { content }
+    }
+
+    /// Number of elements in the set.
+    pub fn len(&self) -> usize {
+        self.content.len()
+    }
+
+    /// Is the set empty?
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty()
+    }
+
+    /// Iterate through the values in the set, in insertion order.
+    pub fn iter<'a>(&'a self) -> impl ExactSizeIterator<Item = Value<'v>> + 'a {
+        self.content.iter().copied()
+    }
+
+    /// Iterate through the values in the set, retaining their hashes.
+    pub fn iter_hashed<'a>(&'a self) -> impl ExactSizeIterator<Item = Hashed<Value<'v>>> + 'a
+    where
+        'v: 'a,
+    {
+        self.content.iter_hashed().map(|x| x.copied())
+    }
+
+    /// Is the value in the set? Will be [`Err`] if the value is not hashable.
+    pub fn contains(&self, value: Value<'v>) -> anyhow::Result<bool> {
+        Ok(self.contains_hashed(value.get_hashed()?))
+    }
+
+    /// Is the prehashed value in the set?
+    pub fn contains_hashed(&self, value: Hashed<Value<'v>>) -> bool {
+        self.content.contains_hashed(value.as_ref())
+    }
+
+    /// Insert a value into the set, return `true` if it was not present.
+    pub fn insert_hashed(&mut self, value: Hashed<Value<'v>>) -> bool {
+        self.content.insert_hashed(value)
+    }
+
+    /// Remove a value from the set, return `true` if it was present.
+    pub fn remove_hashed(&mut self, value: Hashed<Value<'v>>) -> bool {
+        self.content.remove_hashed(value.as_ref())
+    }
+
+    /// Remove and return the last inserted value.
+    pub fn pop(&mut self) -> Option<Value<'v>> {
+        self.content.pop()
+    }
+
+    /// Remove all elements from the set.
+    pub fn clear(&mut self) {
+        self.content.clear();
+    }
+
+    /// Elements of `self` followed by elements of `other` which are not in `self`.
+    pub fn union(&self, other: &Set<'v>) -> Set<'v> {
+        let mut content = self.content.clone();
+        for x in other.iter_hashed() {
+            content.insert_hashed(x);
+        }
+        Set { content }
+    }
+
+    /// Elements of `self` which are also in `other`.
+    pub fn intersection(&self, other: &Set<'v>) -> Set<'v> {
+        let mut content = SmallSet::new();
+        for x in self.iter_hashed() {
+            if other.contains_hashed(x) {
+                content.insert_hashed(x);
+            }
+        }
+        Set { content }
+    }
+
+    /// Elements of `self` which are not in `other`.
+    pub fn difference(&self, other: &Set<'v>) -> Set<'v> {
+        let mut content = SmallSet::new();
+        for x in self.iter_hashed() {
+            if !other.contains_hashed(x) {
+                content.insert_hashed(x);
+            }
+        }
+        Set { content }
+    }
+
+    /// Elements which are in exactly one of `self` and `other`.
+    pub fn symmetric_difference(&self, other: &Set<'v>) -> Set<'v> {
+        let mut content = self.difference(other).content;
+        for x in other.iter_hashed() {
+            if !self.contains_hashed(x) {
+                content.insert_hashed(x);
+            }
+        }
+        Set { content }
+    }
+
+    /// Is every element of `self` in `other`?
+    pub fn is_subset(&self, other: &Set<'v>) -> bool {
+        self.len() <= other.len() && self.iter_hashed().all(|x| other.contains_hashed(x))
+    }
+}
+
+impl<'v> Freeze for SetGen<RefCell<Set<'v>>> {
+    type Frozen = SetGen<FrozenSetData>;
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<Self::Frozen> {
+        let content = self.0.into_inner().content.freeze(freezer)?;
+        Ok(SetGen(FrozenSetData { content }))
+    }
+}
+
+pub(crate) trait SetLike<'v>: Debug + Allocative {
+    type ContentRef<'a>: Deref<Target = SmallSet<Value<'v>>>
+    where
+        Self: 'a,
+        'v: 'a;
+    fn content<'a>(&'a self) -> Self::ContentRef<'a>;
+    // These functions are unsafe for the same reason
+    // `StarlarkValue` iterator functions are unsafe.
+    unsafe fn iter_start(&self);
+    unsafe fn content_unchecked(&self) -> &SmallSet<Value<'v>>;
+    unsafe fn iter_stop(&self);
+}
+
+impl<'v> SetLike<'v> for RefCell<Set<'v>> {
+    type ContentRef<'a> = Ref<'a, SmallSet<Value<'v>>> where Self: 'a, 'v: 'a;
+
+    fn content<'a>(&'a self) -> Ref<'a, SmallSet<Value<'v>>> {
+        Ref::map(self.borrow(), |x| &x.content)
+    }
+
+    #[inline]
+    unsafe fn iter_start(&self) {
+        mem::forget(self.borrow());
+    }
+
+    #[inline]
+    unsafe fn iter_stop(&self) {
+        unleak_borrow(self);
+    }
+
+    #[inline]
+    unsafe fn content_unchecked(&self) -> &SmallSet<Value<'v>> {
+        // SAFETY: this function contract is, caller must ensure that the value is borrowed.
+        &self.try_borrow_unguarded().ok().unwrap_unchecked().content
+    }
+}
+
+impl<'v> SetLike<'v> for FrozenSetData {
+    type ContentRef<'a> = &'a SmallSet<Value<'v>> where Self: 'a, 'v: 'a;
+
+    fn content<'a>(&'a self) -> &'a SmallSet<Value<'v>> {
+        coerce(&self.content)
+    }
+
+    unsafe fn iter_start(&self) {}
+
+    unsafe fn iter_stop(&self) {}
+
+    unsafe fn content_unchecked(&self) -> &SmallSet<Value<'v>> {
+        coerce(&self.content)
+    }
+}
+
+pub(crate) fn set_methods() -> Option<&'static Methods> {
+    static RES: MethodsStatic = MethodsStatic::new();
+    RES.methods(crate::stdlib::set::set_methods)
+}
+
+impl<'v, T: SetLike<'v> + 'v> SetGen<T>
+where
+    Self: ProvidesStaticType,
+{
+    fn binary_op(
+        &self,
+        op: &str,
+        rhs: Value<'v>,
+        heap: &'v Heap,
+        f: impl FnOnce(&Set<'v>, &Set<'v>) -> Set<'v>,
+    ) -> anyhow::Result<Value<'v>> {
+        let rhs = SetRef::from_value(rhs)
+            .map_or_else(|| ValueError::unsupported_with(self, op, rhs), Ok)?;
+        let content = self.0.content();
+        let lhs: &Set<'v> = coerce(&*content);
+        Ok(heap.alloc(f(lhs, &rhs)))
+    }
+}
+
+impl<'v, T: SetLike<'v> + 'v> StarlarkValue<'v> for SetGen<T>
+where
+    Self: ProvidesStaticType,
+{
+    starlark_type!(Set::TYPE);
+
+    fn get_methods() -> Option<&'static Methods> {
+        set_methods()
+    }
+
+    fn collect_repr(&self, r: &mut String) {
+        let content = self.0.content();
+        if content.is_empty() {
+            r.push_str("set()");
+            return;
+        }
+        r.push_str("set([");
+        for (i, x) in content.iter().enumerate() {
+            if i != 0 {
+                r.push_str(", ");
+            }
+            x.collect_repr(r);
+        }
+        r.push_str("])");
+    }
+
+    fn collect_repr_cycle(&self, collector: &mut String) {
+        collector.push_str("set(...)");
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.0.content().is_empty()
+    }
+
+    fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        match SetRef::from_value(other) {
+            None => Ok(false),
+            Some(other) => {
+                let content = self.0.content();
+                if content.len() != other.len() {
+                    return Ok(false);
+                }
+                Ok(content
+                    .iter_hashed()
+                    .all(|x| other.contains_hashed(x.copied())))
+            }
+        }
+    }
+
+    fn length(&self) -> anyhow::Result<i32> {
+        Ok(self.0.content().len() as i32)
+    }
+
+    fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        Ok(self
+            .0
+            .content()
+            .contains_hashed(other.get_hashed()?.as_ref()))
+    }
+
+    unsafe fn iterate(&self, me: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.0.iter_start();
+        Ok(me)
+    }
+
+    unsafe fn iter_size_hint(&self, index: usize) -> (usize, Option<usize>) {
+        debug_assert!(index <= self.0.content().len());
+        let rem = self.0.content().len() - index;
+        (rem, Some(rem))
+    }
+
+    unsafe fn iter_next(&self, index: usize, _heap: &'v Heap) -> Option<Value<'v>> {
+        self.0.content_unchecked().get_index(index).copied()
+    }
+
+    unsafe fn iter_stop(&self) {
+        self.0.iter_stop();
+    }
+
+    fn bit_or(&self, rhs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.binary_op("|", rhs, heap, Set::union)
+    }
+
+    fn bit_and(&self, rhs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.binary_op("&", rhs, heap, Set::intersection)
+    }
+
+    fn bit_xor(&self, rhs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.binary_op("^", rhs, heap, Set::symmetric_difference)
+    }
+
+    fn sub(&self, rhs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.binary_op("-", rhs, heap, Set::difference)
+    }
+}
+
+impl<'v, T: SetLike<'v>> Serialize for SetGen<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.0.content().iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+    use crate::assert::Assert;
+
+    #[test]
+    fn test_set_operators() {
+        assert::is_true(
+            r#"
+a = set([1, 2, 3])
+b = set([3, 4])
+(
+    list(a | b) == [1, 2, 3, 4] and
+    list(a & b) == [3] and
+    list(a - b) == [1, 2] and
+    list(a ^ b) == [1, 2, 4] and
+    a == set([3, 2, 1]) and
+    a != b and
+    2 in a and
+    5 not in a and
+    len(a) == 3
+)
+"#,
+        );
+    }
+
+    #[test]
+    fn test_set_repr() {
+        assert::eq("repr(set())", "'set()'");
+        assert::eq("str(set([1, 'x']))", "'set([1, \"x\"])'");
+    }
+
+    #[test]
+    fn test_set_frozen() {
+        let mut a = Assert::new();
+        a.module("x", "frozen_set = set([1, 2])");
+        a.fail("load('x','frozen_set')\nfrozen_set.add(3)", "Immutable");
+        a.is_true("load('x','frozen_set')\nlist(frozen_set | set([3])) == [1, 2, 3]");
+    }
+
+    #[test]
+    fn test_set_mutate_during_iteration() {
+        assert::fail(
+            r#"
+s = set([1, 2])
+def f():
+    for x in s:
+        s.add(x + 10)
+f()
+"#,
+            "mutate an iterable for an iterator",
+        );
+    }
+
+    #[test]
+    fn test_set_dialect() {
+        let mut a = Assert::new();
+        a.dialect_set(|d| d.enable_set = false);
+        a.fail("set([1])", "`set` is not allowed in this dialect");
+        a.is_true("def set(x):\n    return x\nset(1) == 1");
+    }
+
+    #[test]
+    fn test_set_operator_requires_set() {
+        assert::fail("set([1]) | [2]", "not supported");
+    }
+}
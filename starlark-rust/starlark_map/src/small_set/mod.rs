@@ -39,6 +39,8 @@ pub use crate::small_set::iter::IterHashed;
 pub use crate::small_set::iter::IterMutUnchecked;
 
 /// An memory-efficient set with deterministic order, based on [`SmallMap`].
+// `repr(transparent)` so that `SmallSet` can be coerced like the map it wraps.
+#[repr(transparent)]
 #[derive(Clone, Allocative)]
 pub struct SmallSet<T>(SmallMap<T, ()>);
 
@@ -244,6 +246,18 @@ impl<T> SmallSet<T> {
         self.0.remove(key).is_some()
     }
 
+    /// Remove the element from the set if it is present.
+    ///
+    /// Time complexity of this operation is *O(N)* where *N* is the number of entries in the set.
+    #[inline]
+    pub fn remove_hashed<Q>(&mut self, key: Hashed<&Q>) -> bool
+    where
+        Q: ?Sized + Equivalent<T>,
+        T: Eq,
+    {
+        self.0.remove_hashed(key).is_some()
+    }
+
     /// Insert entry if it doesn't exist.
     ///
     /// Return the resulting entry in the map.